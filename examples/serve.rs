@@ -0,0 +1,20 @@
+//! Serves a brain of bytes until stdin closes, e.g. for the Node client's
+//! tests.
+//!
+//! ```text
+//! cargo run --example serve --features server -- 127.0.0.1:7878
+//! ```
+
+use memory::{Brain, NumericalDuration, Server, ServerConfig};
+use std::io::Read;
+
+fn main() -> std::io::Result<()> {
+    let address = std::env::args().nth(1);
+    let address = address.as_deref().unwrap_or("127.0.0.1:0");
+    let server = Server::bind(address, Brain::new(1.minutes()), ServerConfig::new())?;
+    println!("listening on {}", server.local_addr());
+    std::io::stdin().read_to_end(&mut Vec::new())?;
+    server
+        .shutdown()
+        .map_err(|_| std::io::Error::other("server panicked"))
+}
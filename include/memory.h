@@ -0,0 +1,99 @@
+/* C ABI of the memory crate, built with the `ffi` feature. */
+
+#ifndef MEMORY_H
+#define MEMORY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returned by functions which succeeded. */
+#define BRAIN_OK 0
+/* Returned for a null brain or out parameter, or a key which isn't UTF-8. */
+#define BRAIN_INVALID -1
+/* Returned by brain_retrieve_bytes for a miss. */
+#define BRAIN_MISSING 1
+
+/* A brain of bytes, shared between threads, resolving keys through its
+ * aliases and rules first. */
+typedef struct Brain Brain;
+
+/* Counters of a brain's operations since its creation. */
+typedef struct BrainStats {
+    uint64_t hits;
+    uint64_t misses;
+    uint64_t insertions;
+    uint64_t evictions;
+    uint64_t capacity_evictions;
+    uint64_t expired_on_read;
+} BrainStats;
+
+/* Creates a brain keeping values for the retention in milliseconds, to be
+ * freed with brain_free. */
+Brain *brain_new(uint64_t retention_millis);
+
+/* Frees a brain, doing nothing for NULL. */
+void brain_free(Brain *brain);
+
+/* Memoizes a copy of the value under the UTF-8 key, giving BRAIN_OK or
+ * BRAIN_INVALID. */
+int32_t brain_memoize_bytes(const Brain *brain, const uint8_t *key, size_t key_len,
+                            const uint8_t *value, size_t value_len);
+
+/* Memoizes a copy of the value with its own retention in milliseconds,
+ * giving BRAIN_OK or BRAIN_INVALID. */
+int32_t brain_memoize_bytes_for(const Brain *brain, const uint8_t *key, size_t key_len,
+                                const uint8_t *value, size_t value_len,
+                                uint64_t retention_millis);
+
+/* Retrieves a copy of the value and its length, giving BRAIN_OK,
+ * BRAIN_MISSING with NULL and 0 for a miss, or BRAIN_INVALID for a NULL
+ * brain, value or length, or a key which isn't UTF-8. The copy is to be
+ * freed with brain_bytes_free, even if empty. */
+int32_t brain_retrieve_bytes(const Brain *brain, const uint8_t *key, size_t key_len,
+                             uint8_t **value, size_t *value_len);
+
+/* Frees a copy from brain_retrieve_bytes or brain_canonical_key, doing
+ * nothing for NULL. */
+void brain_bytes_free(uint8_t *value, size_t value_len);
+
+/* Forgets the expired values, doing nothing for NULL. */
+void brain_forget(const Brain *brain);
+
+/* Writes the brain's counters, giving BRAIN_OK or BRAIN_INVALID. */
+int32_t brain_stats(const Brain *brain, BrainStats *stats);
+
+/* Makes the alias stand for the key, giving BRAIN_OK or BRAIN_INVALID. */
+int32_t brain_add_alias(const Brain *brain, const uint8_t *alias, size_t alias_len,
+                        const uint8_t *key, size_t key_len);
+
+/* Makes the alias stand for itself again, giving BRAIN_OK, BRAIN_MISSING if
+ * it wasn't an alias, or BRAIN_INVALID. */
+int32_t brain_remove_alias(const Brain *brain, const uint8_t *alias, size_t alias_len);
+
+/* Rewrites keys starting with `from` to start with `to` instead, after the
+ * rules added before, giving BRAIN_OK or BRAIN_INVALID. */
+int32_t brain_add_prefix_rule(const Brain *brain, const uint8_t *from, size_t from_len,
+                              const uint8_t *to, size_t to_len);
+
+/* Rewrites keys matching the pattern, where `*` stands for any text, into
+ * the replacement, where `$1`, `$2`, ... stand for the text of each `*` and
+ * `$$` for `$`, after the rules added before. Gives BRAIN_OK or
+ * BRAIN_INVALID. */
+int32_t brain_add_pattern_rule(const Brain *brain, const uint8_t *pattern, size_t pattern_len,
+                               const uint8_t *replacement, size_t replacement_len);
+
+/* Writes a copy of the key the alias stands for, by its alias or rule, and
+ * its length, giving BRAIN_OK or BRAIN_INVALID as brain_retrieve_bytes does.
+ * The copy is to be freed with brain_bytes_free. */
+int32_t brain_canonical_key(const Brain *brain, const uint8_t *alias, size_t alias_len,
+                            uint8_t **key, size_t *key_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,268 @@
+'use strict';
+
+// A Node client for brains served by the memory crate's `server` feature,
+// speaking its length-prefixed protocol, see `Server` in the crate docs.
+//
+// Values are Buffers. The JSON methods encode values as the crate's
+// `JsonCodec` does, so they're shared with a `RemoteBrain` using that codec.
+// `AliasedBrain` resolves keys through aliases and rules as the crate's
+// `AliasedBrain` does.
+
+const net = require('node:net');
+
+const GET = 1;
+const SET = 2;
+const DEL = 3;
+const TTL = 4;
+const FORGET = 5;
+const LEASE = 7;
+const RELEASE = 8;
+const AUTH = 9;
+
+const FOUND = 0;
+const MISSING = 1;
+
+function field(bytes) {
+  const len = Buffer.alloc(4);
+  len.writeUInt32LE(bytes.length);
+  return [len, bytes];
+}
+
+function millis(ms) {
+  const bytes = Buffer.alloc(8);
+  bytes.writeBigUInt64LE(BigInt(Math.max(0, Math.round(ms))));
+  return bytes;
+}
+
+// A connection to a server, answering requests in the order they were sent.
+class RemoteBrain {
+  // Connects to the server, presenting the token first if given.
+  static connect(port, host = '127.0.0.1', { token } = {}) {
+    return new Promise((resolve, reject) => {
+      const socket = net.connect(port, host);
+      socket.once('error', reject);
+      socket.once('connect', () => {
+        socket.off('error', reject);
+        const brain = new RemoteBrain(socket);
+        if (token === undefined) {
+          resolve(brain);
+          return;
+        }
+        brain.request(AUTH, token).then(([status]) => {
+          if (status === FOUND) {
+            resolve(brain);
+          } else {
+            brain.close();
+            reject(new Error('unauthorized'));
+          }
+        }, reject);
+      });
+    });
+  }
+
+  constructor(socket) {
+    this.socket = socket;
+    this.pending = [];
+    this.received = Buffer.alloc(0);
+    socket.setNoDelay(true);
+    socket.on('data', (data) => this.receive(data));
+    const fail = (error) => {
+      for (const { reject } of this.pending.splice(0)) {
+        reject(error ?? new Error('connection closed'));
+      }
+    };
+    socket.on('error', fail);
+    socket.on('close', () => fail());
+  }
+
+  // The value, or null for a miss.
+  async get(key) {
+    const [status, body] = await this.request(GET, key);
+    return status === FOUND ? body : null;
+  }
+  async getJSON(key) {
+    const value = await this.get(key);
+    return value === null ? null : JSON.parse(value.toString('utf8'));
+  }
+  // Memoizes the value, with a retention in milliseconds if given.
+  async set(key, value, { ttl = 0 } = {}) {
+    await this.request(SET, key, ...field(Buffer.from(value)), millis(ttl));
+  }
+  async setJSON(key, value, options) {
+    await this.set(key, Buffer.from(JSON.stringify(value), 'utf8'), options);
+  }
+  // Removes the value, telling whether there was one.
+  async del(key) {
+    const [status] = await this.request(DEL, key);
+    return status === FOUND;
+  }
+  // Milliseconds left until the value expires, or null for a miss.
+  async ttl(key) {
+    const [status, body] = await this.request(TTL, key);
+    return status === FOUND ? Number(body.readBigUInt64LE()) : null;
+  }
+  // Forgets the expired values of the brain.
+  async forget() {
+    await this.request(FORGET, '');
+  }
+  // Takes or renews the lease for the ttl in milliseconds, giving null if
+  // granted and else the holder.
+  async lease(key, holder, ttl) {
+    const [status, body] = await this.request(LEASE, key, ...field(Buffer.from(holder)), millis(ttl));
+    return status === FOUND ? null : body.toString('utf8');
+  }
+  // Lets go of the lease, telling whether the holder held it.
+  async release(key, holder) {
+    const [status] = await this.request(RELEASE, key, ...field(Buffer.from(holder)));
+    return status === FOUND;
+  }
+  close() {
+    this.socket.end();
+  }
+
+  // Sends a request, resolving to its status and body, or rejecting errors
+  // the server answers.
+  request(operation, key, ...fields) {
+    const frame = Buffer.concat([Buffer.from([operation]), ...field(Buffer.from(key, 'utf8')), ...fields]);
+    const len = Buffer.alloc(4);
+    len.writeUInt32LE(frame.length);
+    return new Promise((resolve, reject) => {
+      this.pending.push({ resolve, reject });
+      this.socket.write(Buffer.concat([len, frame]));
+    });
+  }
+
+  receive(data) {
+    this.received = Buffer.concat([this.received, data]);
+    while (this.received.length >= 4) {
+      const len = this.received.readUInt32LE(0);
+      if (this.received.length < 4 + len) {
+        return;
+      }
+      const frame = this.received.subarray(4, 4 + len);
+      this.received = this.received.subarray(4 + len);
+      const { resolve, reject } = this.pending.shift() ?? {};
+      const [status] = frame;
+      if (status === FOUND || status === MISSING) {
+        resolve?.([status, frame.subarray(1)]);
+      } else {
+        reject?.(new Error(frame.subarray(1).toString('utf8')));
+      }
+    }
+  }
+}
+
+// Resolves keys through aliases, and else the first rule rewriting them,
+// before passing them to the brain.
+class AliasedBrain {
+  constructor(brain, aliases = {}) {
+    this.brain = brain;
+    this.aliases = new Map(Object.entries(aliases));
+    this.rules = [];
+  }
+
+  // Makes the alias stand for the key, giving back the key it stood for.
+  addAlias(alias, key) {
+    const previous = this.aliases.get(alias) ?? null;
+    this.aliases.set(alias, key);
+    return previous;
+  }
+  // Makes the alias stand for itself again, giving back the key it stood for.
+  removeAlias(alias) {
+    const previous = this.aliases.get(alias) ?? null;
+    this.aliases.delete(alias);
+    return previous;
+  }
+  // Replaces the prefix of keys starting with it, after the rules added before.
+  addPrefixRule(from, to) {
+    this.addRule((key) => (key.startsWith(from) ? to + key.slice(from.length) : null));
+  }
+  // Rewrites keys matching the pattern, where `*` stands for any text, into
+  // the replacement, where `$1`, `$2`, ... stand for the text of the first,
+  // second, ... `*` and `$$` for `$`.
+  addPatternRule(pattern, replacement) {
+    this.addRule((key) => {
+      const found = captures(pattern, key);
+      return found === null ? null : substitute(replacement, found);
+    });
+  }
+  // Rewrites keys with a function giving null for those it leaves alone.
+  addRule(rewrite) {
+    this.rules.push(rewrite);
+  }
+  // The key of the brain the alias stands for, by its alias or rule.
+  canonicalKey(alias) {
+    if (this.aliases.has(alias)) {
+      return this.aliases.get(alias);
+    }
+    for (const rewrite of this.rules) {
+      const key = rewrite(alias);
+      if (key !== null && key !== undefined) {
+        return key;
+      }
+    }
+    return alias;
+  }
+
+  get(key) {
+    return this.brain.get(this.canonicalKey(key));
+  }
+  getJSON(key) {
+    return this.brain.getJSON(this.canonicalKey(key));
+  }
+  set(key, value, options) {
+    return this.brain.set(this.canonicalKey(key), value, options);
+  }
+  setJSON(key, value, options) {
+    return this.brain.setJSON(this.canonicalKey(key), value, options);
+  }
+  del(key) {
+    return this.brain.del(this.canonicalKey(key));
+  }
+  ttl(key) {
+    return this.brain.ttl(this.canonicalKey(key));
+  }
+  forget() {
+    return this.brain.forget();
+  }
+  close() {
+    this.brain.close();
+  }
+}
+
+// The texts standing in for the `*` of the pattern, each as short as the
+// rest of the pattern allows, or null unless the key matches it.
+function captures(pattern, key) {
+  const literals = pattern.split('*');
+  const first = literals.shift();
+  const last = literals.pop();
+  if (last === undefined) {
+    return key === first ? [] : null;
+  }
+  if (!key.startsWith(first)) {
+    return null;
+  }
+  let rest = key.slice(first.length);
+  const found = [];
+  for (const literal of literals) {
+    const at = rest.indexOf(literal);
+    if (at < 0) {
+      return null;
+    }
+    found.push(rest.slice(0, at));
+    rest = rest.slice(at + literal.length);
+  }
+  if (!rest.endsWith(last)) {
+    return null;
+  }
+  found.push(rest.slice(0, rest.length - last.length));
+  return found;
+}
+
+function substitute(replacement, found) {
+  return replacement.replace(/\$(\d+|\$)?/g, (_, capture) =>
+    capture === undefined || capture === '$' ? '$' : found[Number(capture) - 1] ?? '',
+  );
+}
+
+module.exports = { AliasedBrain, RemoteBrain };
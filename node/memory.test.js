@@ -0,0 +1,62 @@
+'use strict';
+
+// Smoke tests of the client against a server, run with `npm test` after
+// building it with
+//
+//     cargo build --example serve --features server
+//
+// or pointing `MEMORY_SERVER` at a binary printing the address it listens on.
+
+const assert = require('node:assert');
+const { spawn } = require('node:child_process');
+const fs = require('node:fs');
+const path = require('node:path');
+const test = require('node:test');
+const { AliasedBrain, RemoteBrain } = require('./memory');
+
+const SERVER =
+  process.env.MEMORY_SERVER ?? path.join(__dirname, '..', 'target', 'debug', 'examples', 'serve');
+
+// Starts the server, resolving to its process and port.
+function serve() {
+  return new Promise((resolve, reject) => {
+    const server = spawn(SERVER, [], { stdio: ['pipe', 'pipe', 'inherit'] });
+    server.once('error', reject);
+    let output = '';
+    server.stdout.on('data', (data) => {
+      output += data;
+      const listening = /listening on .*:(\d+)/.exec(output);
+      if (listening) {
+        resolve({ server, port: Number(listening[1]) });
+      }
+    });
+  });
+}
+
+test('remote brain', { skip: !fs.existsSync(SERVER) && `no server at ${SERVER}` }, async (t) => {
+  const { server, port } = await serve();
+  t.after(() => server.stdin.end());
+  const brain = await RemoteBrain.connect(port);
+  t.after(() => brain.close());
+
+  await brain.set('a', Buffer.from([0, 1, 2]));
+  assert.deepStrictEqual(await brain.get('a'), Buffer.from([0, 1, 2]));
+  assert.strictEqual(await brain.get('missing'), null);
+  await brain.setJSON('b', { n: 1 }, { ttl: 1 });
+  await new Promise((resolve) => setTimeout(resolve, 5));
+  await brain.forget();
+  assert.strictEqual(await brain.getJSON('b'), null);
+  assert.ok((await brain.ttl('a')) > 0);
+  assert.strictEqual(await brain.del('a'), true);
+  assert.strictEqual(await brain.get('a'), null);
+
+  const aliased = new AliasedBrain(brain, { first: 'a' });
+  aliased.addPrefixRule('v1:', '');
+  aliased.addPatternRule('user:*:name:*', 'name:$2:user:$1:$$');
+  await aliased.set('first', Buffer.from('one'));
+  assert.deepStrictEqual(await aliased.get('v1:a'), Buffer.from('one'));
+  await aliased.setJSON('user:42:name:ü', 'ada');
+  assert.strictEqual(await brain.getJSON('name:ü:user:42:$'), 'ada');
+  assert.strictEqual(aliased.removeAlias('first'), 'a');
+  assert.strictEqual(await aliased.get('first'), null);
+});
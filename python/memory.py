@@ -0,0 +1,173 @@
+"""Python bindings to the `ffi` feature of the memory crate, over ctypes.
+
+Build the library with e.g.
+
+    cargo rustc --release --features ffi --crate-type cdylib
+
+and point `Brain` at `target/release/libmemory.so` (`.dylib` on macOS,
+`memory.dll` on Windows). ctypes releases the GIL while calling into the
+library, so threads waiting on a brain's locks don't stall the interpreter.
+"""
+
+import ctypes
+
+BRAIN_OK = 0
+BRAIN_MISSING = 1
+
+
+class BrainStats(ctypes.Structure):
+    """Counters of a brain's operations since its creation."""
+
+    _fields_ = [
+        ("hits", ctypes.c_uint64),
+        ("misses", ctypes.c_uint64),
+        ("insertions", ctypes.c_uint64),
+        ("evictions", ctypes.c_uint64),
+        ("capacity_evictions", ctypes.c_uint64),
+        ("expired_on_read", ctypes.c_uint64),
+    ]
+
+    def hit_ratio(self):
+        retrievals = self.hits + self.misses
+        return self.hits / retrievals if retrievals else 0.0
+
+
+def _load(path):
+    library = ctypes.CDLL(path)
+    brain = ctypes.c_void_p
+    data = ctypes.POINTER(ctypes.c_uint8)
+    size = ctypes.c_size_t
+    library.brain_new.argtypes = [ctypes.c_uint64]
+    library.brain_new.restype = brain
+    library.brain_free.argtypes = [brain]
+    library.brain_free.restype = None
+    library.brain_memoize_bytes.argtypes = [brain, ctypes.c_char_p, size, ctypes.c_char_p, size]
+    library.brain_memoize_bytes.restype = ctypes.c_int32
+    library.brain_memoize_bytes_for.argtypes = [
+        brain, ctypes.c_char_p, size, ctypes.c_char_p, size, ctypes.c_uint64,
+    ]
+    library.brain_memoize_bytes_for.restype = ctypes.c_int32
+    library.brain_retrieve_bytes.argtypes = [
+        brain, ctypes.c_char_p, size, ctypes.POINTER(data), ctypes.POINTER(size),
+    ]
+    library.brain_retrieve_bytes.restype = ctypes.c_int32
+    library.brain_bytes_free.argtypes = [data, size]
+    library.brain_bytes_free.restype = None
+    library.brain_forget.argtypes = [brain]
+    library.brain_forget.restype = None
+    library.brain_stats.argtypes = [brain, ctypes.POINTER(BrainStats)]
+    library.brain_stats.restype = ctypes.c_int32
+    for rewrite in ("brain_add_alias", "brain_add_prefix_rule", "brain_add_pattern_rule"):
+        function = getattr(library, rewrite)
+        function.argtypes = [brain, ctypes.c_char_p, size, ctypes.c_char_p, size]
+        function.restype = ctypes.c_int32
+    library.brain_remove_alias.argtypes = [brain, ctypes.c_char_p, size]
+    library.brain_remove_alias.restype = ctypes.c_int32
+    library.brain_canonical_key.argtypes = [
+        brain, ctypes.c_char_p, size, ctypes.POINTER(data), ctypes.POINTER(size),
+    ]
+    library.brain_canonical_key.restype = ctypes.c_int32
+    return library
+
+
+class Brain:
+    """A brain of bytes keeping values for a retention in seconds.
+
+    Keys are strings, values bytes. Like the Rust brain, expired values are
+    retrieved until `forget` forgets them. Keys are resolved through the
+    aliases and rules first, as an `AliasedBrain` does.
+    """
+
+    def __init__(self, retention, library):
+        self._library = _load(library) if isinstance(library, str) else library
+        self._brain = self._library.brain_new(_millis(retention))
+
+    def memoize(self, key, value, retention=None):
+        """Memoizes a copy of the value, with its own retention if given."""
+        key = key.encode()
+        value = bytes(value)
+        if retention is None:
+            status = self._library.brain_memoize_bytes(self._brain, key, len(key), value, len(value))
+        else:
+            status = self._library.brain_memoize_bytes_for(
+                self._brain, key, len(key), value, len(value), _millis(retention)
+            )
+        if status != BRAIN_OK:
+            raise ValueError("brain closed")
+
+    def retrieve(self, key):
+        """A copy of the value, or None."""
+        return self._copy(self._library.brain_retrieve_bytes, key)
+
+    def add_alias(self, alias, key):
+        """Makes the alias stand for the key."""
+        self._rewrite(self._library.brain_add_alias, alias, key)
+
+    def remove_alias(self, alias):
+        """Makes the alias stand for itself again, telling whether it was one."""
+        alias = alias.encode()
+        status = self._library.brain_remove_alias(self._brain, alias, len(alias))
+        if status not in (BRAIN_OK, BRAIN_MISSING):
+            raise ValueError("brain closed")
+        return status == BRAIN_OK
+
+    def add_prefix_rule(self, prefix, replacement):
+        """Rewrites keys starting with the prefix to start with the replacement."""
+        self._rewrite(self._library.brain_add_prefix_rule, prefix, replacement)
+
+    def add_pattern_rule(self, pattern, replacement):
+        """Rewrites keys matching the pattern, `*` standing for any text, into
+        the replacement, `$1`, `$2`, ... standing for the text of each `*`."""
+        self._rewrite(self._library.brain_add_pattern_rule, pattern, replacement)
+
+    def canonical_key(self, alias):
+        """The key the alias stands for, by its alias or rule."""
+        return self._copy(self._library.brain_canonical_key, alias).decode()
+
+    def forget(self):
+        """Forgets the expired values."""
+        self._library.brain_forget(self._brain)
+
+    def stats(self):
+        stats = BrainStats()
+        if self._library.brain_stats(self._brain, ctypes.byref(stats)) != BRAIN_OK:
+            raise ValueError("brain closed")
+        return stats
+
+    def _rewrite(self, function, source, target):
+        source, target = source.encode(), target.encode()
+        if function(self._brain, source, len(source), target, len(target)) != BRAIN_OK:
+            raise ValueError("brain closed")
+
+    def _copy(self, function, key):
+        key = key.encode()
+        copy = ctypes.POINTER(ctypes.c_uint8)()
+        length = ctypes.c_size_t()
+        status = function(self._brain, key, len(key), ctypes.byref(copy), ctypes.byref(length))
+        if status == BRAIN_MISSING:
+            return None
+        if status != BRAIN_OK:
+            raise ValueError("brain closed")
+        try:
+            return ctypes.string_at(copy, length.value)
+        finally:
+            self._library.brain_bytes_free(copy, length.value)
+
+    def close(self):
+        """Frees the brain, which can't be used afterwards."""
+        if self._brain:
+            self._library.brain_free(self._brain)
+            self._brain = None
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *_):
+        self.close()
+
+    def __del__(self):
+        self.close()
+
+
+def _millis(seconds):
+    return max(0, round(seconds * 1000))
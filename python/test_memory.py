@@ -0,0 +1,57 @@
+"""Smoke tests of the bindings against the built library.
+
+Build it with
+
+    cargo rustc --release --features ffi --crate-type cdylib
+
+and run `python -m unittest` from this directory, or point `MEMORY_LIBRARY`
+at the library elsewhere.
+"""
+
+import os
+import sys
+import time
+import unittest
+
+from memory import Brain
+
+_NAMES = {"darwin": "libmemory.dylib", "win32": "memory.dll"}
+LIBRARY = os.environ.get("MEMORY_LIBRARY") or os.path.join(
+    os.path.dirname(os.path.abspath(__file__)),
+    "..", "target", "release", _NAMES.get(sys.platform, "libmemory.so"),
+)
+
+
+@unittest.skipUnless(os.path.exists(LIBRARY), "library not built at " + LIBRARY)
+class BrainTest(unittest.TestCase):
+    def test_memoize_retrieve_forget(self):
+        with Brain(60, LIBRARY) as brain:
+            brain.memoize("a", b"\x00one")
+            brain.memoize("b", b"two", retention=0.001)
+            self.assertEqual(brain.retrieve("a"), b"\x00one")
+            self.assertIsNone(brain.retrieve("missing"))
+            time.sleep(0.005)
+            brain.forget()
+            self.assertIsNone(brain.retrieve("b"))
+            stats = brain.stats()
+            self.assertEqual((stats.hits, stats.misses, stats.evictions), (1, 2, 1))
+        with self.assertRaises(ValueError):
+            brain.retrieve("a")
+
+    def test_aliases(self):
+        with Brain(60, LIBRARY) as brain:
+            brain.add_alias("first", "a")
+            brain.add_prefix_rule("v1:", "")
+            brain.add_pattern_rule("user:*:name", "name:$1")
+            brain.memoize("first", b"1")
+            brain.memoize("user:42:name", b"ada")
+            self.assertEqual(brain.retrieve("v1:a"), b"1")
+            self.assertEqual(brain.retrieve("name:42"), b"ada")
+            self.assertEqual(brain.canonical_key("user:7:name"), "name:7")
+            self.assertTrue(brain.remove_alias("first"))
+            self.assertFalse(brain.remove_alias("first"))
+            self.assertIsNone(brain.retrieve("first"))
+
+
+if __name__ == "__main__":
+    unittest.main()
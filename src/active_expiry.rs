@@ -0,0 +1,118 @@
+use crate::wall_clock::{self, Timestamp};
+use crate::{Brain, EvictionCause, StorageBackend};
+use parking_lot::Mutex;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Expiries of the engrams memoized, soonest first, for writes to forget a
+/// few expired engrams each, see [`BrainBuilder::expire_on_write`].
+///
+/// Entries go stale when their engram is memoized over or removed, and are
+/// dropped once due.
+///
+/// [`BrainBuilder::expire_on_write`]: crate::BrainBuilder::expire_on_write
+pub(crate) struct ExpiryQueue {
+    per_write: usize,
+    due: Mutex<BinaryHeap<Reverse<(Timestamp, u64, String)>>>,
+}
+impl ExpiryQueue {
+    pub(crate) fn new(per_write: usize) -> Self {
+        Self {
+            per_write,
+            due: Mutex::default(),
+        }
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Queues the expiry of the engram memoized at the version.
+    pub(crate) fn queue_expiry(&self, key: &str, expires: Timestamp, version: u64) {
+        if let Some(queue) = &self.expiry_queue {
+            queue
+                .due
+                .lock()
+                .push(Reverse((expires, version, key.to_string())));
+        }
+    }
+    /// Forgets up to the configured number of engrams due to expire, telling
+    /// how many.
+    pub(crate) fn expire_due(&self) -> usize {
+        let Some(queue) = &self.expiry_queue else {
+            return 0;
+        };
+        let now = wall_clock::now();
+        let due = {
+            let mut due = queue.due.lock();
+            let mut expiring = Vec::new();
+            while expiring.len() < queue.per_write {
+                match due.peek() {
+                    Some(Reverse((expires, ..))) if *expires < now => {}
+                    _ => break,
+                }
+                let Some(Reverse((_, version, key))) = due.pop() else {
+                    break;
+                };
+                expiring.push((version, key));
+            }
+            expiring
+        };
+        let mut expired = 0;
+        for (version, key) in due {
+            let (removed, extended) = {
+                let mut memory = self.memory.write();
+                match memory.get(&key) {
+                    Some(engram) if engram.version == version => {
+                        if engram.is_expired(self.retention, now) {
+                            (memory.remove(&key), None)
+                        } else {
+                            // rehearsed since
+                            let retention = engram.retention.unwrap_or(self.retention);
+                            (None, Some(engram.memoized + retention))
+                        }
+                    }
+                    _ => (None, None),
+                }
+            };
+            if let Some(expires) = extended {
+                self.queue_expiry(&key, expires, version);
+            }
+            let Some((key, engram)) = removed else {
+                continue;
+            };
+            expired += 1;
+            #[cfg(feature = "serde")]
+            self.dirty.touch(&key);
+            self.discard(&key, EvictionCause::Expired);
+            if let Some(hooks) = self.hooks() {
+                hooks.on_evict(&key, &engram.value, EvictionCause::Expired);
+            }
+        }
+        self.stats.expired(expired);
+        expired
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn expire_on_write() {
+        let brain = Brain::builder()
+            .retention(1.milliseconds())
+            .expire_on_write(2)
+            .build();
+        for key in ["a", "b", "c"] {
+            brain.memoize(key, 0);
+        }
+        brain.memoize_for("kept", 0, 1.minutes());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.memoize_for("d", 0, 1.minutes());
+        assert_eq!(brain.memory.read().len(), 3);
+        brain.memoize_for("d", 1, 1.minutes());
+        assert_eq!(brain.memory.read().len(), 2);
+        assert!(brain.is_cached("kept"));
+        assert_eq!(brain.stats().evictions, 3);
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::sync::Arc;
+
+type Rewrite = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A rule of an [`AliasedBrain`] rewriting keys, rather than an alias of
+/// a single key.
+///
+/// [`AliasedBrain`]: crate::AliasedBrain
+#[derive(Clone)]
+pub enum AliasRule {
+    /// Replaces the prefix of keys starting with it.
+    Prefix { from: String, to: String },
+    /// Matches keys against a pattern where `*` stands for any text, and
+    /// rewrites them into the replacement, where `$1`, `$2`, ... stand for
+    /// the text of the first, second, ... `*` and `$$` for `$`.
+    Pattern {
+        pattern: String,
+        replacement: String,
+    },
+    /// Rewrites keys with a function giving `None` for those it leaves alone,
+    /// e.g. to match them with a regex.
+    Custom(Rewrite),
+}
+impl AliasRule {
+    pub fn prefix(from: &str, to: &str) -> Self {
+        Self::Prefix {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+    pub fn pattern(pattern: &str, replacement: &str) -> Self {
+        Self::Pattern {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+    pub fn custom(rewrite: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(rewrite))
+    }
+    /// The key rewritten, `None` unless the rule applies to it.
+    pub fn rewrite(&self, key: &str) -> Option<String> {
+        match self {
+            Self::Prefix { from, to } => key
+                .strip_prefix(from.as_str())
+                .map(|rest| to.clone() + rest),
+            Self::Pattern {
+                pattern,
+                replacement,
+            } => captures(pattern, key).map(|captures| substitute(replacement, &captures)),
+            Self::Custom(rewrite) => rewrite(key),
+        }
+    }
+}
+impl fmt::Debug for AliasRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefix { from, to } => write!(f, "Prefix({from:?} -> {to:?})"),
+            Self::Pattern {
+                pattern,
+                replacement,
+            } => write!(f, "Pattern({pattern:?} -> {replacement:?})"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// The texts standing in for the `*` of the pattern, each as short as the
+/// rest of the pattern allows.
+fn captures<'a>(pattern: &str, key: &'a str) -> Option<Vec<&'a str>> {
+    let mut literals = pattern.split('*');
+    let first = literals.next().unwrap_or_default();
+    let mut literals = literals.collect::<Vec<_>>();
+    let Some(last) = literals.pop() else {
+        return (key == first).then(Vec::new);
+    };
+    let mut rest = key.strip_prefix(first)?;
+    let mut captures = Vec::with_capacity(literals.len() + 1);
+    for literal in literals {
+        let at = rest.find(literal)?;
+        captures.push(&rest[..at]);
+        rest = &rest[at + literal.len()..];
+    }
+    captures.push(rest.strip_suffix(last)?);
+    Some(captures)
+}
+
+fn substitute(replacement: &str, captures: &[&str]) -> String {
+    let mut rewritten = String::with_capacity(replacement.len());
+    let mut rest = replacement;
+    while let Some(at) = rest.find('$') {
+        rewritten.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits > 0 {
+            let index = rest[..digits].parse::<usize>().unwrap_or(0);
+            let capture = index.checked_sub(1).and_then(|index| captures.get(index));
+            rewritten.push_str(capture.copied().unwrap_or_default());
+            rest = &rest[digits..];
+        } else {
+            rewritten.push('$');
+            rest = rest.strip_prefix('$').unwrap_or(rest);
+        }
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let prefix = AliasRule::prefix("legacy:", "v2:");
+        assert_eq!(prefix.rewrite("legacy:a").as_deref(), Some("v2:a"));
+        assert_eq!(prefix.rewrite("a"), None);
+
+        let pattern = AliasRule::pattern("user:*:name:*", "name:$2:user:$1:$$");
+        assert_eq!(
+            pattern.rewrite("user:42:name:ü").as_deref(),
+            Some("name:ü:user:42:$")
+        );
+        assert_eq!(pattern.rewrite("user:42:age:3"), None);
+        assert_eq!(
+            AliasRule::pattern("a", "b").rewrite("a").as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            AliasRule::pattern("*", "$1$3").rewrite("x").as_deref(),
+            Some("x")
+        );
+        // the pattern is anchored at both ends
+        assert_eq!(
+            AliasRule::pattern("a*b", "$1").rewrite("ab b"),
+            Some("b ".into())
+        );
+        assert_eq!(
+            AliasRule::pattern("a*b", "$1").rewrite("ab"),
+            Some("".into())
+        );
+        assert_eq!(AliasRule::pattern("ab*b", "$1").rewrite("ab"), None);
+
+        let custom = AliasRule::custom(|key| key.parse::<u32>().ok().map(|n| format!("#{n}")));
+        assert_eq!(custom.rewrite("7").as_deref(), Some("#7"));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{Brain, Duration, Memory};
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A brain of some value type, whatever it is.
+trait TypedBrain: Send + Sync {
+    fn forget(&self);
+    fn as_any(&self) -> &(dyn Any + Send + Sync);
+}
+impl<T: Clone + Send + Sync + 'static> TypedBrain for Brain<T> {
+    fn forget(&self) {
+        Memory::forget(self);
+    }
+    fn as_any(&self) -> &(dyn Any + Send + Sync) {
+        self
+    }
+}
+
+/// A memory of values of any types, keyed by their type and key, e.g. one
+/// cache shared by parts of an app keeping values of their own types.
+///
+/// Values of each type are kept in a brain of their own, created when the
+/// type is first memoized, so the same key may hold a value of each type.
+#[derive(Clone)]
+pub struct AnyBrain {
+    retention: Duration,
+    brains: Arc<RwLock<HashMap<TypeId, Arc<dyn TypedBrain>>>>,
+}
+impl AnyBrain {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            brains: Default::default(),
+        }
+    }
+    /// The brain keeping the values of the type.
+    pub fn brain<T: Clone + Send + Sync + 'static>(&self) -> Brain<T> {
+        let id = TypeId::of::<T>();
+        if let Some(brain) = self.typed::<T>() {
+            return brain;
+        }
+        let mut brains = self.brains.write();
+        let brain = brains
+            .entry(id)
+            .or_insert_with(|| Arc::new(Brain::<T>::new(self.retention)));
+        Self::downcast(brain.as_ref())
+    }
+    pub fn memoize<T: Clone + Send + Sync + 'static>(&self, key: &str, value: T) {
+        self.brain::<T>().memoize(key, value);
+    }
+    /// Retrieves the value of the type memoized under the key.
+    pub fn retrieve<T: Clone + Send + Sync + 'static>(&self, key: &str) -> Option<T> {
+        self.typed::<T>()?.retrieve(key)
+    }
+    /// Removes the value of the type memoized under the key, leaving those
+    /// of other types.
+    pub fn remove<T: Clone + Send + Sync + 'static>(&self, key: &str) -> Option<T> {
+        self.typed::<T>()?.remove(key)
+    }
+    /// Forgets the expired values of all types.
+    pub fn forget(&self) {
+        let brains = self.brains.read().values().cloned().collect::<Vec<_>>();
+        for brain in brains {
+            brain.forget();
+        }
+    }
+    fn typed<T: Clone + Send + Sync + 'static>(&self) -> Option<Brain<T>> {
+        let brains = self.brains.read();
+        Some(Self::downcast(brains.get(&TypeId::of::<T>())?.as_ref()))
+    }
+    fn downcast<T: Clone + Send + Sync + 'static>(brain: &dyn TypedBrain) -> Brain<T> {
+        brain
+            .as_any()
+            .downcast_ref::<Brain<T>>()
+            .expect("brains are keyed by their value type")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn any_brain() {
+        let brain = AnyBrain::new(1.milliseconds());
+        brain.memoize("a", 1u32);
+        brain.memoize("a", "one".to_string());
+        assert_eq!(brain.retrieve::<u32>("a"), Some(1));
+        assert_eq!(brain.retrieve::<String>("a").as_deref(), Some("one"));
+        assert_eq!(brain.retrieve::<u64>("a"), None);
+
+        assert_eq!(brain.remove::<u32>("a"), Some(1));
+        assert_eq!(brain.retrieve::<String>("a").as_deref(), Some("one"));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.forget();
+        assert!(!brain.brain::<String>().is_cached("a"));
+    }
+}
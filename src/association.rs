@@ -0,0 +1,168 @@
+use crate::{Brain, Memory, StorageBackend};
+use parking_lot::RwLock;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Links between the keys of a brain, see [`Brain::associate`].
+#[derive(Default)]
+pub(crate) struct Associations(RwLock<HashMap<String, BTreeSet<String>>>);
+impl Associations {
+    /// Drops the links of a key no longer memoized.
+    pub(crate) fn unlink(&self, key: &str) {
+        if !self.0.read().contains_key(key) {
+            return;
+        }
+        let mut links = self.0.write();
+        for other in links.remove(key).unwrap_or_default() {
+            if let Some(others) = links.get_mut(&other) {
+                others.remove(key);
+                if others.is_empty() {
+                    links.remove(&other);
+                }
+            }
+        }
+    }
+}
+
+impl Associations {
+    /// Checks that links go both ways, and that keys have no empty links
+    /// nor links to themselves.
+    #[cfg(feature = "testing")]
+    pub(crate) fn check(&self) -> Result<(), crate::testing::InvariantViolation> {
+        let links = self.0.read();
+        for (key, others) in links.iter() {
+            if others.is_empty() {
+                return Err(crate::testing::InvariantViolation(format!(
+                    "{key:?} kept without links"
+                )));
+            }
+            for other in others {
+                if other == key || !links.get(other).is_some_and(|back| back.contains(key)) {
+                    return Err(crate::testing::InvariantViolation(format!(
+                        "{key:?} links to {other:?} one way"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Links two memoized keys, e.g. an entity and a view derived from it,
+    /// telling whether both are memoized.
+    ///
+    /// Links go both ways, and are dropped when either key is forgotten.
+    pub fn associate(&self, a: &str, b: &str) -> bool {
+        if a == b || !self.is_cached(a) || !self.is_cached(b) {
+            return false;
+        }
+        let mut links = self.associations.0.write();
+        links
+            .entry(a.to_string())
+            .or_default()
+            .insert(b.to_string());
+        links
+            .entry(b.to_string())
+            .or_default()
+            .insert(a.to_string());
+        true
+    }
+    /// Unlinks two keys, telling whether they were linked.
+    pub fn dissociate(&self, a: &str, b: &str) -> bool {
+        let mut links = self.associations.0.write();
+        let mut unlink = |from: &str, to: &str| {
+            let others = links.get_mut(from)?;
+            let unlinked = others.remove(to);
+            if others.is_empty() {
+                links.remove(from);
+            }
+            Some(unlinked)
+        };
+        let unlinked = unlink(a, b).unwrap_or(false);
+        unlink(b, a);
+        unlinked
+    }
+    /// The keys linked to the key, sorted.
+    pub fn associated(&self, key: &str) -> Vec<String> {
+        let links = self.associations.0.read();
+        links
+            .get(key)
+            .map(|others| others.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// The keys reachable from the key over up to `depth` links, nearest
+    /// first, without the key itself.
+    pub fn related_keys(&self, key: &str, depth: usize) -> Vec<String> {
+        let links = self.associations.0.read();
+        let mut seen = HashSet::from([key]);
+        let mut queue = VecDeque::from([(key, 0)]);
+        let mut related = Vec::new();
+        while let Some((key, hops)) = queue.pop_front() {
+            if hops == depth {
+                continue;
+            }
+            for other in links.get(key).into_iter().flatten() {
+                if seen.insert(other) {
+                    related.push(other.clone());
+                    queue.push_back((other, hops + 1));
+                }
+            }
+        }
+        related
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the values of the keys reachable from the key over up to
+    /// `depth` links, nearest first, e.g. to warm up everything connected
+    /// to an entity.
+    pub fn retrieve_related(&self, key: &str, depth: usize) -> Vec<(String, T)> {
+        self.related_keys(key, depth)
+            .into_iter()
+            .filter_map(|key| Some((key.clone(), self.retrieve(&key)?)))
+            .collect()
+    }
+    /// Forgets the key and those reachable from it over up to `depth`
+    /// links, e.g. to invalidate views derived from an entity, telling how
+    /// many were memoized.
+    pub fn forget_related(&self, key: &str, depth: usize) -> usize {
+        let related = self.related_keys(key, depth);
+        [key.to_string()]
+            .into_iter()
+            .chain(related)
+            .filter(|key| self.remove(key).is_some())
+            .count()
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration};
+
+    #[test]
+    fn associations() {
+        let brain = Brain::new(1.minutes());
+        for (key, value) in [("user", 1), ("profile", 2), ("avatar", 3), ("feed", 4)] {
+            brain.memoize(key, value);
+        }
+        assert!(brain.associate("user", "profile"));
+        assert!(brain.associate("profile", "avatar"));
+        assert!(brain.associate("user", "feed"));
+        assert!(!brain.associate("user", "missing"));
+        assert_eq!(brain.associated("user"), ["feed", "profile"]);
+        assert_eq!(brain.related_keys("user", 1).len(), 2);
+        let related = brain.retrieve_related("user", 2);
+        assert_eq!(related.len(), 3);
+        assert_eq!(related.last().unwrap(), &("avatar".to_string(), 3));
+
+        // links are dropped with either key
+        brain.remove("profile");
+        assert_eq!(brain.associated("user"), ["feed"]);
+        assert!(brain.associated("avatar").is_empty());
+
+        assert!(brain.dissociate("feed", "user"));
+        assert!(!brain.dissociate("feed", "user"));
+        brain.associate("user", "feed");
+        assert_eq!(brain.forget_related("user", 1), 2);
+        assert!(!brain.is_cached("feed") && brain.is_cached("avatar"));
+    }
+}
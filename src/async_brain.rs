@@ -0,0 +1,300 @@
+use crate::{Brain, Duration, Memory};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait AsyncMemory<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()>;
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>>;
+    fn forget(&self) -> BoxFuture<'_, ()>;
+}
+
+/// An async memory chosen at runtime, shared between tasks.
+pub type SharedAsyncMemory<T> = Arc<dyn AsyncMemory<T> + Send + Sync>;
+
+macro_rules! forward_async_memory {
+    ($($pointer:ty),*) => {$(
+        impl<T, M: AsyncMemory<T> + ?Sized> AsyncMemory<T> for $pointer {
+            fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+                (**self).memoize(key, value)
+            }
+            fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+                (**self).retrieve(key)
+            }
+            fn forget(&self) -> BoxFuture<'_, ()> {
+                (**self).forget()
+            }
+        }
+    )*};
+}
+forward_async_memory!(&M, Box<M>, Arc<M>);
+
+/// A [`Brain`] for async executors.
+///
+/// Callers wait for their turn asynchronously, so the underlying lock is
+/// never contended and no executor thread blocks on it.
+#[derive(Clone, Default)]
+pub struct AsyncBrain<T> {
+    brain: Brain<T>,
+    gate: Arc<Gate>,
+}
+impl<T> AsyncBrain<T> {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            brain: Brain::new(retention),
+            gate: Default::default(),
+        }
+    }
+    /// Tells whether a value is memoized for the key, whatever the value is.
+    pub async fn is_cached(&self, key: &str) -> bool {
+        let _permit = self.gate.read().await;
+        self.brain.is_cached(key)
+    }
+}
+impl<T: Clone> AsyncBrain<T> {
+    /// Memoizes a value with its own retention instead of the brain's.
+    pub async fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        let _permit = self.gate.write().await;
+        self.brain.memoize_for(key, value, retention);
+    }
+}
+impl<T: Clone + Send + Sync + 'static> AsyncBrain<T> {
+    /// Like [`Brain::get_or_load_many`], awaiting `load` with the missing
+    /// keys without holding the brain meanwhile.
+    pub async fn get_or_load_many<'k, F: Future<Output = HashMap<String, T>>>(
+        &self,
+        keys: impl IntoIterator<Item = &'k str>,
+        load: impl FnOnce(Vec<String>) -> F,
+    ) -> HashMap<String, T> {
+        let (mut values, missing) = {
+            let _permit = self.gate.read().await;
+            self.brain.found_and_missing(keys)
+        };
+        if missing.is_empty() {
+            return values;
+        }
+        let loaded = load(missing.iter().map(|key| key.to_string()).collect()).await;
+        let _permit = self.gate.write().await;
+        self.brain.merge_loaded(&missing, loaded, &mut values);
+        values
+    }
+}
+impl<T: Clone + Send + Sync + 'static> AsyncMemory<T> for AsyncBrain<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let _permit = self.gate.write().await;
+            self.brain.memoize(key, value);
+        })
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        Box::pin(async move {
+            let _permit = self.gate.read().await;
+            self.brain.retrieve(key)
+        })
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let _permit = self.gate.write().await;
+            self.brain.forget();
+        })
+    }
+}
+
+/// Admits either many readers or one writer, waiting writers go first.
+#[derive(Default)]
+struct Gate {
+    state: Mutex<GateState>,
+}
+#[derive(Default)]
+struct GateState {
+    readers: usize,
+    writing: bool,
+    writers_waiting: usize,
+    wakers: Vec<Waker>,
+}
+impl Gate {
+    fn read(&self) -> Admission<'_> {
+        Admission {
+            gate: self,
+            write: false,
+            waiting: false,
+        }
+    }
+    fn write(&self) -> Admission<'_> {
+        Admission {
+            gate: self,
+            write: true,
+            waiting: false,
+        }
+    }
+    fn wake(state: &mut GateState) {
+        std::mem::take(&mut state.wakers)
+            .into_iter()
+            .for_each(Waker::wake);
+    }
+}
+
+struct Admission<'gate> {
+    gate: &'gate Gate,
+    write: bool,
+    waiting: bool,
+}
+impl<'gate> Future for Admission<'gate> {
+    type Output = Permit<'gate>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let gate = self.gate;
+        let mut state = gate.state.lock();
+        let admitted = if self.write {
+            !state.writing && state.readers == 0
+        } else {
+            !state.writing && state.writers_waiting == 0
+        };
+        if admitted {
+            if self.write {
+                state.writing = true;
+                if self.waiting {
+                    state.writers_waiting -= 1;
+                }
+            } else {
+                state.readers += 1;
+            }
+            self.waiting = false;
+            Poll::Ready(Permit {
+                gate,
+                write: self.write,
+            })
+        } else {
+            if !self.waiting && self.write {
+                state.writers_waiting += 1;
+            }
+            self.waiting = true;
+            state.wakers.push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if self.waiting && self.write {
+            let mut state = self.gate.state.lock();
+            state.writers_waiting -= 1;
+            Gate::wake(&mut state);
+        }
+    }
+}
+
+struct Permit<'gate> {
+    gate: &'gate Gate,
+    write: bool,
+}
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.gate.state.lock();
+        if self.write {
+            state.writing = false;
+        } else {
+            state.readers -= 1;
+        }
+        Gate::wake(&mut state);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::collections::HashMap;
+    use std::task::Wake;
+
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        struct Unpark(std::thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            std::thread::park();
+        }
+    }
+
+    #[test]
+    fn memory() {
+        block_on(async {
+            let memory = AsyncBrain::new(3.milliseconds());
+
+            memory.memoize("a", 3).await;
+            memory.memoize_for("b", 6, 1.minutes()).await;
+            assert_eq!(memory.retrieve("a").await, Some(3));
+
+            std::thread::sleep(std::time::Duration::from_millis(4));
+            memory.forget().await;
+
+            assert_eq!(memory.retrieve("a").await, None);
+            assert!(!memory.is_cached("a").await);
+            assert_eq!(memory.retrieve("b").await, Some(6));
+        });
+    }
+
+    #[test]
+    fn shared() {
+        let memory: SharedAsyncMemory<i32> = Arc::new(AsyncBrain::new(1.minutes()));
+        let boxed: Box<dyn AsyncMemory<i32> + Send + Sync> = Box::new(memory.clone());
+        block_on(async {
+            boxed.memoize("a", 1).await;
+            assert_eq!(memory.retrieve("a").await, Some(1));
+        });
+    }
+
+    #[test]
+    fn get_or_load_many() {
+        let brain = AsyncBrain::new(1.minutes());
+        block_on(async {
+            brain.memoize("a", 1).await;
+            let values = brain
+                .get_or_load_many(["a", "b"], |missing| async move {
+                    assert_eq!(missing, ["b"]);
+                    HashMap::from([("b".to_string(), 2)])
+                })
+                .await;
+            assert_eq!(values, HashMap::from([("a".into(), 1), ("b".into(), 2)]));
+            assert_eq!(brain.retrieve("b").await, Some(2));
+        });
+    }
+
+    #[test]
+    fn gate() {
+        let gate = Gate::default();
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+
+        let reading = block_on(gate.read());
+        let mut writer = std::pin::pin!(gate.write());
+        assert!(writer.as_mut().poll(&mut cx).is_pending());
+
+        // the waiting writer goes before new readers
+        let mut reader = std::pin::pin!(gate.read());
+        assert!(reader.as_mut().poll(&mut cx).is_pending());
+
+        drop(reading);
+        let writing = match writer.as_mut().poll(&mut cx) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("writer not admitted"),
+        };
+        assert!(reader.as_mut().poll(&mut cx).is_pending());
+
+        drop(writing);
+        assert!(reader.as_mut().poll(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::wall_clock;
+use crate::{Brain, Engram, StorageBackend};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Metadata attached to an engram, see [`Brain::memoize_with_attributes`].
+pub type Attributes = BTreeMap<String, Attribute>;
+
+type Multiplier = Arc<dyn Fn(&Attributes) -> Option<f64> + Send + Sync>;
+
+/// The value of an attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Attribute {
+    Text(String),
+    Number(f64),
+}
+impl From<&str> for Attribute {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+impl From<String> for Attribute {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+impl From<f64> for Attribute {
+    fn from(number: f64) -> Self {
+        Self::Number(number)
+    }
+}
+impl From<i64> for Attribute {
+    fn from(number: i64) -> Self {
+        Self::Number(number as f64)
+    }
+}
+
+/// A rule of a brain multiplying the retention of values by their
+/// attributes, see [`BrainBuilder::retention_rule`].
+///
+/// [`BrainBuilder::retention_rule`]: crate::BrainBuilder::retention_rule
+#[derive(Clone)]
+pub enum RetentionRule {
+    /// Multiplies the retention of values whose attribute equals the value.
+    Equals {
+        attribute: String,
+        value: Attribute,
+        multiplier: f64,
+    },
+    /// Multiplies the retention by what the function gives, `None` for
+    /// values it leaves alone.
+    Custom(Multiplier),
+}
+impl RetentionRule {
+    pub fn equals(attribute: &str, value: impl Into<Attribute>, multiplier: f64) -> Self {
+        Self::Equals {
+            attribute: attribute.to_string(),
+            value: value.into(),
+            multiplier,
+        }
+    }
+    pub fn custom(multiplier: impl Fn(&Attributes) -> Option<f64> + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(multiplier))
+    }
+    /// The multiplier for the attributes, `None` unless the rule applies.
+    pub fn multiplier(&self, attributes: &Attributes) -> Option<f64> {
+        match self {
+            Self::Equals {
+                attribute,
+                value,
+                multiplier,
+            } => (attributes.get(attribute) == Some(value)).then_some(*multiplier),
+            Self::Custom(multiplier) => multiplier(attributes),
+        }
+    }
+}
+impl fmt::Debug for RetentionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equals {
+                attribute,
+                value,
+                multiplier,
+            } => write!(f, "Equals({attribute:?} = {value:?} -> {multiplier}x)"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Memoizes a value with attributes, e.g. `importance = "high"`, which
+    /// the brain's retention rules multiply its retention by.
+    ///
+    /// The multipliers of all rules applying are multiplied. Memoizing the
+    /// key again replaces its attributes.
+    pub fn memoize_with_attributes<K: Into<String>, A: Into<Attribute>>(
+        &self,
+        key: &str,
+        value: T,
+        attributes: impl IntoIterator<Item = (K, A)>,
+    ) {
+        let attributes = attributes
+            .into_iter()
+            .map(|(name, attribute)| (name.into(), attribute.into()))
+            .collect::<Attributes>();
+        let multiplier = self
+            .retention_rules
+            .iter()
+            .filter_map(|rule| rule.multiplier(&attributes))
+            .product::<f64>();
+        let engram = Engram {
+            attributes: Some(Arc::new(attributes)),
+            ..Engram::new(value, Some(wall_clock::scaled(self.retention, multiplier)))
+        };
+        self.store_if(key, engram, |_| true);
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn retention_rules() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .retention_rule(RetentionRule::equals("importance", "high", 3.0))
+            .retention_rule(RetentionRule::custom(|attributes| {
+                match attributes.get("size") {
+                    Some(Attribute::Number(size)) if *size > 100.0 => Some(0.5),
+                    _ => None,
+                }
+            }))
+            .build();
+        brain.memoize_with_attributes("a", 1, [("importance", "high")]);
+        brain.memoize_with_attributes(
+            "b",
+            2,
+            [
+                ("importance", Attribute::from("high")),
+                ("size", 1000.0.into()),
+            ],
+        );
+        brain.memoize_with_attributes("c", 3, [("importance", "low")]);
+        brain.memoize("d", 4);
+
+        let retention = |key| brain.memory.read().get(key).unwrap().retention();
+        assert_eq!(retention("a"), Some(3.minutes()));
+        assert_eq!(retention("b"), Some(90.seconds()));
+        assert_eq!(retention("c"), Some(1.minutes()));
+        assert_eq!(retention("d"), None);
+        let engram = brain
+            .memory
+            .read()
+            .get("b")
+            .map(|engram| engram.attribute("size").cloned());
+        assert_eq!(engram, Some(Some(Attribute::Number(1000.0))));
+        assert_eq!(brain.retrieve("b"), Some(2));
+    }
+}
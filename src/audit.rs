@@ -0,0 +1,147 @@
+use crate::wall_clock::{self, Timestamp};
+use crate::{Brain, EvictionCause, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+#[cfg(feature = "time")]
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// What happened to an entry, as recorded by an audit log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOperation {
+    Memoized,
+    /// The entry left the brain, for the cause.
+    Evicted(EvictionCause),
+}
+
+/// An operation recorded by the audit log of a brain, see
+/// [`BrainBuilder::audit`].
+///
+/// [`BrainBuilder::audit`]: crate::BrainBuilder::audit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub at: Timestamp,
+    /// Who did it, see [`Brain::acting_as`], `None` for the brain itself
+    /// or callers not telling.
+    pub actor: Option<Arc<str>>,
+    pub key: String,
+    pub operation: AuditOperation,
+}
+
+/// The last operations on a brain's entries.
+pub(crate) struct AuditLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+impl AuditLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Default::default(),
+        }
+    }
+    pub(crate) fn record(&self, actor: Option<Arc<str>>, key: &str, operation: AuditOperation) {
+        let entry = AuditEntry {
+            at: wall_clock::now(),
+            actor,
+            key: key.to_string(),
+            operation,
+        };
+        let mut entries = self.entries.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+impl<T, S> Brain<T, S> {
+    /// A clone of the brain whose operations the audit log attributes to
+    /// the actor, e.g. a user or a deployment.
+    pub fn acting_as(&self, actor: &str) -> Self {
+        Self {
+            audit_actor: Some(actor.into()),
+            ..self.clone()
+        }
+    }
+    pub(crate) fn audit_record(&self, key: &str, operation: AuditOperation) {
+        if let Some(audit) = &self.audit {
+            audit.record(self.audit_actor.clone(), key, operation);
+        }
+    }
+}
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// The recorded operations on the key, oldest first, empty unless the
+    /// brain keeps an audit log.
+    pub fn audit(&self, key: &str) -> Vec<AuditEntry> {
+        self.audit_log()
+            .into_iter()
+            .filter(|entry| entry.key == key)
+            .collect()
+    }
+    /// All recorded operations, oldest first.
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        let Some(audit) = &self.audit else {
+            return Vec::new();
+        };
+        audit.entries.lock().iter().cloned().collect()
+    }
+    /// Writes the recorded operations, oldest first, a line each with the
+    /// time in nanoseconds since the Unix epoch, the actor or `-`, the
+    /// operation and the key, separated by tabs.
+    #[cfg(feature = "time")]
+    pub fn export_audit(&self, mut writer: impl Write) -> io::Result<()> {
+        for entry in self.audit_log() {
+            let at = entry.at.unix_timestamp_nanos();
+            let operation = match entry.operation {
+                AuditOperation::Memoized => "memoized",
+                AuditOperation::Evicted(EvictionCause::Expired) => "expired",
+                AuditOperation::Evicted(EvictionCause::Capacity) => "evicted",
+                AuditOperation::Evicted(EvictionCause::Removed) => "removed",
+            };
+            let actor = entry.actor.as_deref().unwrap_or("-");
+            writeln!(writer, "{at}\t{actor}\t{operation}\t{}", entry.key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn audit() {
+        let brain = Brain::builder()
+            .retention(1.milliseconds())
+            .audit(3)
+            .build();
+        brain.acting_as("deploy").memoize("config", 1);
+        brain.memoize("derived", 2);
+        brain.acting_as("ops").remove("config");
+        let config = brain.audit("config");
+        assert_eq!(config.len(), 2);
+        assert_eq!(config[0].actor.as_deref(), Some("deploy"));
+        assert_eq!(config[0].operation, AuditOperation::Memoized);
+        assert_eq!(
+            config[1].operation,
+            AuditOperation::Evicted(EvictionCause::Removed)
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.forget();
+        let log = brain.audit_log();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].key, "derived");
+        let mut exported = Vec::new();
+        brain.export_audit(&mut exported).unwrap();
+        let exported = String::from_utf8(exported).unwrap();
+        assert!(exported
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("\t-\texpired\tderived"));
+        assert!(Brain::<u8>::new(1.minutes()).audit_log().is_empty());
+    }
+}
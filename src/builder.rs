@@ -0,0 +1,514 @@
+use crate::decay::Decay;
+use crate::wall_clock::{self, Instant};
+use crate::{Brain, Duration, Engram, Memory, RetentionRule, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Which entry a full brain forgets to make room for a new one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Eviction {
+    /// The entry memoized first.
+    #[default]
+    Oldest,
+    /// The entry retrieved least often since it was memoized, the oldest
+    /// of those on ties.
+    LeastHits,
+    /// The entry closest to the end of its retention.
+    SoonestExpiring,
+}
+#[cfg(feature = "time")]
+impl Eviction {
+    pub(crate) const ALL: [Eviction; 3] = [Self::Oldest, Self::LeastHits, Self::SoonestExpiring];
+
+    /// Name of the policy in config files and the environment.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Oldest => "oldest",
+            Self::LeastHits => "least_hits",
+            Self::SoonestExpiring => "soonest_expiring",
+        }
+    }
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|eviction| eviction.name() == name)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Capacity {
+    pub(crate) max_entries: usize,
+    eviction: Eviction,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Rehearsal {
+    increment: Duration,
+    max_retention: Duration,
+}
+
+pub(crate) struct Sweeping {
+    interval: std::time::Duration,
+    last: Mutex<Instant>,
+}
+
+/// Configures a brain before creating it, see [`Brain::builder`].
+pub struct BrainBuilder<T, S = HashMap<String, Engram<T>>> {
+    retention: Duration,
+    storage: S,
+    max_entries: Option<usize>,
+    eviction: Eviction,
+    sweep_interval: Option<Duration>,
+    rehearsal: Option<Rehearsal>,
+    decay: Option<Decay>,
+    retention_rules: Vec<RetentionRule>,
+    history: Option<usize>,
+    hot_keys: Option<usize>,
+    key_filter: Option<usize>,
+    expire_on_write: Option<usize>,
+    soft_limit: Option<usize>,
+    hard_limit: Option<usize>,
+    audit: Option<usize>,
+    actor: u64,
+    #[cfg(feature = "serde")]
+    pub(crate) durability: crate::Durability,
+    #[cfg(feature = "serde")]
+    pub(crate) on_drop: Option<OnBuild<T, S>>,
+    value: PhantomData<T>,
+}
+/// Finishes a brain once built, e.g. to save it on drop.
+#[cfg(feature = "serde")]
+pub(crate) type OnBuild<T, S> = Box<dyn FnOnce(Brain<T, S>) -> Brain<T, S> + Send + Sync>;
+impl<T, S> BrainBuilder<T, S> {
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+    /// Keeps the engrams in the storage instead of a `HashMap`.
+    pub fn storage<B>(self, storage: B) -> BrainBuilder<T, B> {
+        BrainBuilder {
+            retention: self.retention,
+            storage,
+            max_entries: self.max_entries,
+            eviction: self.eviction,
+            sweep_interval: self.sweep_interval,
+            rehearsal: self.rehearsal,
+            decay: self.decay,
+            retention_rules: self.retention_rules,
+            history: self.history,
+            hot_keys: self.hot_keys,
+            key_filter: self.key_filter,
+            expire_on_write: self.expire_on_write,
+            soft_limit: self.soft_limit,
+            hard_limit: self.hard_limit,
+            audit: self.audit,
+            actor: self.actor,
+            #[cfg(feature = "serde")]
+            durability: self.durability,
+            #[cfg(feature = "serde")]
+            on_drop: None,
+            value: PhantomData,
+        }
+    }
+    /// Limits the number of entries, evicting one whenever the brain is full.
+    ///
+    /// The entry just memoized is never evicted. Finding the entry to evict
+    /// scans the brain.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+    /// Chooses the entries evicted from a full brain, the oldest by default.
+    pub fn eviction(mut self, eviction: Eviction) -> Self {
+        self.eviction = eviction;
+        self
+    }
+    /// Forgets expired engrams while memoizing, at most once per interval.
+    ///
+    /// For sweeps on a background thread, see [`Brain::start_maintenance`].
+    pub fn sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = Some(interval);
+        self
+    }
+    /// Extends the retention of a value by the increment whenever it's
+    /// retrieved before it expired, up to the max retention in all.
+    ///
+    /// Values retrieved regularly stay for up to the max retention, while
+    /// one retrieved once is gone an increment after its retention.
+    /// Memoizing a value anew starts it over.
+    pub fn rehearsal(mut self, increment: Duration, max_retention: Duration) -> Self {
+        self.rehearsal = Some(Rehearsal {
+            increment,
+            max_retention,
+        });
+        self
+    }
+    /// Forgets values by a forgetting curve rather than only once their
+    /// retention passed: [`Memory::forget`] also forgets those whose
+    /// [`Brain::strength`] fell below the threshold.
+    ///
+    /// The strength halves every half-life since a value was memoized, and
+    /// every retrieval stretches its half-life by another one, so values
+    /// retrieved often are kept longer. The retention still bounds how long
+    /// values are kept, set it long to leave forgetting to the curve.
+    pub fn decay(mut self, half_life: Duration, threshold: f64) -> Self {
+        self.decay = Some(Decay {
+            half_life,
+            threshold,
+        });
+        self
+    }
+    /// Multiplies the retention of values memoized with attributes the
+    /// rule applies to, see [`Brain::memoize_with_attributes`].
+    ///
+    /// Rules add up, the multipliers of all applying being multiplied.
+    pub fn retention_rule(mut self, rule: RetentionRule) -> Self {
+        self.retention_rules.push(rule);
+        self
+    }
+    /// Keeps the last values memoized per key, up to the depth, see
+    /// [`Brain::history`], e.g. to tell what a key held before an incident.
+    ///
+    /// The history of a key goes with its value.
+    pub fn history(mut self, depth: usize) -> Self {
+        self.history = Some(depth);
+        self
+    }
+    /// Forgets the expired engrams as soon as the brain holds more entries
+    /// than the soft limit, rather than at the next sweep.
+    ///
+    /// Beyond the limit, memoizing forgets them again whenever the brain
+    /// grew by a sixteenth of the limit. Unlike [`max_entries`], values
+    /// which didn't expire are kept.
+    ///
+    /// [`max_entries`]: Self::max_entries
+    pub fn soft_limit(mut self, entries: usize) -> Self {
+        self.soft_limit = Some(entries);
+        self
+    }
+    /// Refuses to memoize new keys while the brain holds as many entries,
+    /// so it sheds writes rather than growing further: [`TryMemoize`]
+    /// fails, memoizing drops the value, and [`Brain::shed_writes`] counts
+    /// them. Keys already memoized are still memoized over.
+    ///
+    /// [`TryMemoize`]: crate::TryMemoize
+    pub fn hard_limit(mut self, entries: usize) -> Self {
+        self.hard_limit = Some(entries);
+        self
+    }
+    /// Forgets up to `per_write` expired engrams whenever memoizing, the
+    /// soonest expired first, e.g. on targets without threads for
+    /// [`Brain::start_maintenance`].
+    ///
+    /// Expiries are queued in a heap as engrams are memoized, which keeps
+    /// an entry per write until it's due. Engrams restored from snapshots
+    /// or peers are left to sweeps.
+    pub fn expire_on_write(mut self, per_write: usize) -> Self {
+        self.expire_on_write = Some(per_write);
+        self
+    }
+    /// Estimates how often each key is retrieved, keeping the `top` keys
+    /// retrieved the most, see [`Brain::hottest_keys`].
+    ///
+    /// Counts take a kilobyte per key kept, at least 16, in a count-min
+    /// sketch, and cost every retrieval a few atomic additions.
+    pub fn track_hot_keys(mut self, top: usize) -> Self {
+        self.hot_keys = Some(top);
+        self
+    }
+    /// Keeps a Bloom filter of the keys memoized, sized for the expected
+    /// number of keys, so retrieving keys the brain never held mostly
+    /// misses without locking it.
+    ///
+    /// The filter takes 10 bits per expected key and rules out about 99%
+    /// of missing keys while that many are held, fewer beyond. It's
+    /// rebuilt whenever a sweep removes entries.
+    pub fn key_filter(mut self, expected_keys: usize) -> Self {
+        self.key_filter = Some(expected_keys);
+        self
+    }
+    /// Records who memoized or removed which key and when, keeping the last
+    /// operations up to the capacity, see [`Brain::audit`].
+    ///
+    /// Evictions and expiries are recorded too, operations through
+    /// [`Brain::acting_as`] with the actor.
+    pub fn audit(mut self, capacity: usize) -> Self {
+        self.audit = Some(capacity);
+        self
+    }
+    /// Id stamped on every engram the brain memoizes, zero by default.
+    ///
+    /// Brains accepting writes independently need distinct ids, so
+    /// [`Brain::merge_crdt`] can order writes made at the same time.
+    pub fn actor(mut self, actor: u64) -> Self {
+        self.actor = actor;
+        self
+    }
+    /// Applies all settings of the config.
+    #[cfg(feature = "time")]
+    pub fn config(self, config: &crate::BrainConfig) -> Self {
+        let mut builder = self.retention(config.retention).eviction(config.eviction);
+        builder.max_entries = config.max_entries;
+        builder.sweep_interval = config.sweep_interval;
+        builder
+    }
+}
+impl<T, S: StorageBackend<String, T>> BrainBuilder<T, S> {
+    pub fn build(self) -> Brain<T, S> {
+        let brain = Brain {
+            capacity: self.max_entries.map(|max_entries| Capacity {
+                max_entries,
+                eviction: self.eviction,
+            }),
+            sweeping: self.sweep_interval.map(|interval| {
+                Arc::new(Sweeping {
+                    interval: wall_clock::unsigned(interval),
+                    last: Mutex::new(Instant::now()),
+                })
+            }),
+            rehearsal: self.rehearsal,
+            decay: self.decay,
+            retention_rules: self.retention_rules.into(),
+            history: self
+                .history
+                .map(|depth| Arc::new(crate::history::History::new(depth))),
+            hot_keys: self
+                .hot_keys
+                .map(|top| Arc::new(crate::hot_keys::HotKeys::new(top))),
+            key_filter: self
+                .key_filter
+                .map(|expected_keys| Arc::new(crate::key_filter::KeyFilter::new(expected_keys))),
+            expiry_queue: self
+                .expire_on_write
+                .map(|per_write| Arc::new(crate::active_expiry::ExpiryQueue::new(per_write))),
+            limits: (self.soft_limit.is_some() || self.hard_limit.is_some())
+                .then(|| Arc::new(crate::limits::Limits::new(self.soft_limit, self.hard_limit))),
+            audit: self
+                .audit
+                .map(|capacity| Arc::new(crate::audit::AuditLog::new(capacity))),
+            actor: self.actor,
+            #[cfg(feature = "serde")]
+            durability: self.durability,
+            ..Brain::with_storage(self.retention, self.storage)
+        };
+        brain.refilter(&brain.memory.read());
+        #[cfg(feature = "serde")]
+        if let Some(on_drop) = self.on_drop {
+            return on_drop(brain);
+        }
+        brain
+    }
+}
+
+impl<T> Brain<T> {
+    /// Starts configuring a brain, for more than a retention.
+    ///
+    /// ```
+    /// # use memory::{Brain, Duration, Eviction, Memory};
+    /// let brain = Brain::builder()
+    ///     .retention(Duration::new(60, 0))
+    ///     .max_entries(1)
+    ///     .eviction(Eviction::Oldest)
+    ///     .sweep_interval(Duration::new(10, 0))
+    ///     .build();
+    /// brain.memoize("a", 1);
+    /// brain.memoize("b", 2);
+    /// assert_eq!(brain.retrieve("a"), None);
+    /// ```
+    pub fn builder() -> BrainBuilder<T> {
+        BrainBuilder {
+            retention: Duration::default(),
+            storage: HashMap::new(),
+            max_entries: None,
+            eviction: Eviction::default(),
+            sweep_interval: None,
+            rehearsal: None,
+            decay: None,
+            retention_rules: Vec::new(),
+            history: None,
+            hot_keys: None,
+            key_filter: None,
+            expire_on_write: None,
+            soft_limit: None,
+            hard_limit: None,
+            audit: None,
+            actor: 0,
+            #[cfg(feature = "serde")]
+            durability: Default::default(),
+            #[cfg(feature = "serde")]
+            on_drop: None,
+            value: PhantomData,
+        }
+    }
+}
+impl<T> BrainBuilder<T> {
+    /// Hashes keys with the hasher instead of SipHash.
+    ///
+    /// The default SipHash is keyed at random per brain, so whoever picks
+    /// the keys, e.g. from request paths, can't pick many colliding ones to
+    /// slow the brain down. Faster hashers such as [`FxBuildHasher`] or
+    /// ahash are worth it for keys your own code makes.
+    ///
+    /// [`FxBuildHasher`]: crate::FxBuildHasher
+    pub fn hasher<H: BuildHasher>(
+        self,
+        hasher: H,
+    ) -> BrainBuilder<T, HashMap<String, Engram<T>, H>> {
+        self.storage(HashMap::with_hasher(hasher))
+    }
+}
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Evicts entries other than the key while the brain holds too many.
+    pub(crate) fn make_room(&self, memory: &mut S, key: &str) -> Vec<(String, Engram<T>)> {
+        let Some(capacity) = self.capacity else {
+            return Vec::new();
+        };
+        let expiry =
+            |engram: &Engram<T>| engram.memoized + engram.retention.unwrap_or(self.retention);
+        let mut evicted = Vec::new();
+        while memory.len() > capacity.max_entries {
+            let victim = memory
+                .scan()
+                .filter(|(candidate, _)| candidate.as_str() != key)
+                .min_by(|(_, a), (_, b)| {
+                    a.salience
+                        .total_cmp(&b.salience)
+                        .then_with(|| match capacity.eviction {
+                            Eviction::Oldest => a.memoized.cmp(&b.memoized),
+                            Eviction::LeastHits => a
+                                .hits
+                                .load(Ordering::Relaxed)
+                                .cmp(&b.hits.load(Ordering::Relaxed))
+                                .then(a.memoized.cmp(&b.memoized)),
+                            Eviction::SoonestExpiring => expiry(a).cmp(&expiry(b)),
+                        })
+                })
+                .map(|(victim, _)| victim.clone());
+            match victim.and_then(|victim| memory.remove(&victim)) {
+                Some(entry) => evicted.push(entry),
+                None => break,
+            }
+        }
+        evicted
+    }
+    /// Extends the retention of the engram for the key, if rehearsing.
+    pub(crate) fn rehearse(&self, key: &str) {
+        let Some(rehearsal) = self.rehearsal else {
+            return;
+        };
+        let rehearsed = self.memory.write().update(key, &mut |engram| {
+            let retention = engram.retention.unwrap_or(self.retention);
+            if retention < rehearsal.max_retention {
+                engram.retention =
+                    Some((retention + rehearsal.increment).min(rehearsal.max_retention));
+            }
+        });
+        if rehearsed {
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+        }
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Forgets expired engrams if the sweep interval passed since the last time.
+    pub(crate) fn sweep_if_due(&self) {
+        let Some(sweeping) = &self.sweeping else {
+            return;
+        };
+        {
+            let mut last = sweeping.last.lock();
+            if last.elapsed() < sweeping.interval {
+                return;
+            }
+            *last = Instant::now();
+        }
+        self.forget();
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn max_entries() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(2)
+            .eviction(Eviction::LeastHits)
+            .build();
+        brain.memoize("a", 1);
+        brain.memoize("b", 2);
+        brain.retrieve("a");
+        brain.memoize("c", 3);
+        assert_eq!(brain.retrieve("b"), None);
+        assert_eq!(brain.retrieve("a"), Some(1));
+        assert_eq!(brain.retrieve("c"), Some(3));
+        assert_eq!(brain.stats().capacity_evictions, 1);
+
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(1)
+            .eviction(Eviction::SoonestExpiring)
+            .build();
+        brain.memoize_for("a", 1, 1.hours());
+        brain.memoize("b", 2);
+        assert_eq!(brain.retrieve("b"), Some(2));
+        assert_eq!(brain.retrieve("a"), None);
+    }
+
+    #[test]
+    fn salience() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(2)
+            .build();
+        brain.memoize_with_salience("config", 1, 0.9);
+        brain.memoize("derived", 2);
+        brain.memoize("more", 3);
+        assert!(brain.is_cached("config") && !brain.is_cached("derived"));
+        brain.memoize_with_salience("critical", 4, 2.0);
+        assert!(brain.is_cached("config") && !brain.is_cached("more"));
+        assert_eq!(brain.memory.read()["critical"].salience(), 1.0);
+    }
+
+    #[test]
+    fn sweep_interval() {
+        let brain = Brain::builder()
+            .retention(1.milliseconds())
+            .sweep_interval(2.milliseconds())
+            .build();
+        brain.memoize("a", 1);
+        std::thread::sleep(std::time::Duration::from_millis(3));
+        brain.memoize("b", 2);
+        assert!(!brain.is_cached("a"));
+        assert!(brain.is_cached("b"));
+    }
+
+    #[test]
+    fn rehearsal() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .rehearsal(1.minutes(), 150.seconds())
+            .build();
+        brain.memoize("a", 1);
+        brain.memoize_for("b", 2, 1.milliseconds());
+        brain.retrieve("a");
+        assert!(brain.time_to_live("a").unwrap() > 119.seconds());
+        brain.retrieve("a");
+        brain.retrieve("a");
+        assert!(brain.time_to_live("a").unwrap() <= 150.seconds());
+        assert!(brain.time_to_live("a").unwrap() > 149.seconds());
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        // expired values aren't rehearsed
+        brain.retrieve("b");
+        assert_eq!(brain.time_to_live("b"), Some(Duration::ZERO));
+    }
+}
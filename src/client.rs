@@ -0,0 +1,740 @@
+use crate::protocol::{
+    put_field, read_frame, request, write_frame, AUTH, DEL, FAILED, FORGET, FOUND, GET, LEASE,
+    MISSING, RELEASE, SET, TTL,
+};
+use crate::transport::{Duplex, Stream, Wrap};
+use crate::{
+    AsyncMemory, BinaryCodec, BoxFuture, Duration, Held, Leasing, Memory, TryMemoize, WireCodec,
+};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// Pipelined requests sent before reading their responses, so neither side
+/// fills its buffers while the other still writes.
+const PIPELINE_WINDOW: usize = 64;
+
+/// Status and body of a response.
+type Response = (u8, Vec<u8>);
+
+/// How a [`RemoteBrain`] talks to its server.
+#[derive(Clone)]
+pub struct ClientConfig {
+    pool_size: usize,
+    timeout: Duration,
+    retries: u32,
+    pub(crate) backoff: Duration,
+    token: Option<Arc<str>>,
+    wrap: Option<Wrap>,
+}
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientConfig")
+            .field("pool_size", &self.pool_size)
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("backoff", &self.backoff)
+            .field("token", &self.token.as_ref().map(|_| ".."))
+            .field("wrap", &self.wrap.is_some())
+            .finish()
+    }
+}
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 4,
+            timeout: Duration::seconds(5),
+            retries: 3,
+            backoff: Duration::milliseconds(50),
+            token: None,
+            wrap: None,
+        }
+    }
+}
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Idle connections kept for later requests, 4 by default.
+    pub fn pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+    /// Time to connect, send a request or receive a response, 5 seconds
+    /// by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Times a failed request is sent again on a new connection, 3 by default.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+    /// Wait before the first retry, doubled for every further one, 50
+    /// milliseconds by default.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    /// Presents the token on every new connection, for servers which
+    /// [authorize](crate::ServerConfig::authorize) them, and Redis with
+    /// `AUTH`. memcached's text protocol has no authentication.
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+    /// Talks to the server through the stream the hook makes of every new
+    /// TCP connection, such as a TLS session for a server which
+    /// [wraps](crate::ServerConfig::wrap) its own. Unix domain sockets
+    /// aren't wrapped.
+    pub fn wrap(
+        mut self,
+        wrap: impl Fn(TcpStream) -> io::Result<Box<dyn Duplex>> + Send + Sync + 'static,
+    ) -> Self {
+        self.wrap = Some(Arc::new(wrap));
+        self
+    }
+}
+
+/// Presents a token on a new connection, in the protocol of the server.
+pub(crate) type Handshake = fn(&mut Stream, &str) -> io::Result<()>;
+
+/// Pooled connections to a server, retried with a backoff.
+pub(crate) struct Client {
+    target: Target,
+    config: ClientConfig,
+    handshake: Handshake,
+    idle: Mutex<Vec<Stream>>,
+    workers: Workers,
+}
+enum Target {
+    Tcp(Vec<SocketAddr>),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+impl Client {
+    /// Resolves the address, failing unless the server is reachable.
+    pub(crate) fn new(address: impl ToSocketAddrs, config: ClientConfig) -> io::Result<Self> {
+        Self::speaking(address, config, authenticate)
+    }
+    /// Like [`Client::new`], for a server of another protocol, presenting
+    /// tokens with the handshake.
+    pub(crate) fn speaking(
+        address: impl ToSocketAddrs,
+        config: ClientConfig,
+        handshake: Handshake,
+    ) -> io::Result<Self> {
+        let target = Target::Tcp(address.to_socket_addrs()?.collect());
+        Self::with_target(target, config, handshake)
+    }
+    /// Connects to a Unix domain socket, failing unless the server is reachable.
+    #[cfg(unix)]
+    pub(crate) fn unix(path: &Path, config: ClientConfig) -> io::Result<Self> {
+        Self::with_target(Target::Unix(path.to_path_buf()), config, authenticate)
+    }
+    fn with_target(target: Target, config: ClientConfig, handshake: Handshake) -> io::Result<Self> {
+        let client = Client {
+            target,
+            workers: Workers::new(config.pool_size),
+            config,
+            handshake,
+            idle: Mutex::new(Vec::new()),
+        };
+        let stream = client.connect()?;
+        client.idle.lock().push(stream);
+        Ok(client)
+    }
+    fn connect(&self) -> io::Result<Stream> {
+        let timeout = Some(self.config.timeout.unsigned_abs()).filter(|timeout| !timeout.is_zero());
+        let stream = match &self.target {
+            Target::Tcp(addresses) => {
+                let mut connected = Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no address to connect to",
+                ));
+                for address in addresses {
+                    connected = match timeout {
+                        Some(timeout) => TcpStream::connect_timeout(address, timeout),
+                        None => TcpStream::connect(address),
+                    };
+                    if connected.is_ok() {
+                        break;
+                    }
+                }
+                Stream::Tcp(connected?)
+            }
+            #[cfg(unix)]
+            Target::Unix(path) => Stream::Unix(UnixStream::connect(path)?),
+        };
+        stream.set_nodelay()?;
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        let mut stream = match &self.config.wrap {
+            Some(wrap) => stream.wrap(wrap)?,
+            None => stream,
+        };
+        if let Some(token) = &self.config.token {
+            (self.handshake)(&mut stream, token)?;
+        }
+        Ok(stream)
+    }
+    /// Exchanges a request and its response on a connection, retrying on
+    /// new connections while the exchange fails.
+    ///
+    /// The inner result is the server's answer, an error there isn't retried.
+    pub(crate) fn call<R>(
+        &self,
+        exchange: impl Fn(&mut Stream) -> io::Result<io::Result<R>>,
+    ) -> io::Result<R> {
+        let mut backoff = self.config.backoff.unsigned_abs();
+        let mut retries = self.config.retries;
+        loop {
+            let pooled = self.idle.lock().pop();
+            let exchanged = pooled
+                .map_or_else(|| self.connect(), Ok)
+                .and_then(|mut stream| Ok((exchange(&mut stream)?, stream)));
+            match exchanged {
+                Ok((answer, stream)) => {
+                    let mut idle = self.idle.lock();
+                    if idle.len() < self.config.pool_size {
+                        idle.push(stream);
+                    }
+                    return answer;
+                }
+                Err(_) if retries > 0 => {
+                    retries -= 1;
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    /// Sends a framed request, giving the status and body of the response.
+    pub(crate) fn request(&self, request: &[u8]) -> io::Result<(u8, Vec<u8>)> {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, request)?;
+        self.call(|stream| {
+            stream.write_all(&frame)?;
+            read_response(stream)
+        })
+    }
+    /// Sends the requests before reading their responses, a window at a time,
+    /// giving each response or the server's error for it.
+    ///
+    /// A window failing on a connection is sent again as a whole.
+    pub(crate) fn pipeline(&self, requests: &[Vec<u8>]) -> io::Result<Vec<io::Result<Response>>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for window in requests.chunks(PIPELINE_WINDOW) {
+            let mut frames = Vec::new();
+            for request in window {
+                write_frame(&mut frames, request)?;
+            }
+            responses.extend(self.call(|stream| {
+                stream.write_all(&frames)?;
+                let responses = window.iter().map(|_| read_response(stream));
+                responses.collect::<io::Result<Vec<_>>>().map(Ok)
+            })?);
+        }
+        Ok(responses)
+    }
+    /// Runs the task on a worker of the client, completing with its output.
+    pub(crate) fn offload<R: Send + 'static>(
+        &self,
+        task: impl FnOnce() -> R + Send + 'static,
+    ) -> Offloaded<R> {
+        let state = Arc::new(Mutex::new((None, None::<Waker>)));
+        let done = state.clone();
+        self.workers.run(Box::new(move || {
+            let output = task();
+            let mut done = done.lock();
+            done.0 = Some(output);
+            if let Some(waker) = done.1.take() {
+                waker.wake();
+            }
+        }));
+        Offloaded { state }
+    }
+}
+
+/// Presents the token to a [`Server`](crate::Server) with `AUTH`.
+fn authenticate(stream: &mut Stream, token: &str) -> io::Result<()> {
+    let mut frame = Vec::new();
+    write_frame(&mut frame, &request(AUTH, token))?;
+    stream.write_all(&frame)?;
+    read_response(stream)?
+        .map(drop)
+        .map_err(|error| io::Error::new(io::ErrorKind::PermissionDenied, error.to_string()))
+}
+
+/// Reads a response, the inner result being an error of the server.
+fn read_response(stream: &mut Stream) -> io::Result<io::Result<Response>> {
+    let response = read_frame(stream)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))?;
+    let (&status, body) = response
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty response"))?;
+    Ok(match status {
+        FOUND | MISSING => Ok((status, body.to_vec())),
+        FAILED => Err(io::Error::other(String::from_utf8_lossy(body))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unknown response status",
+        )),
+    })
+}
+
+/// A brain served by a [`Server`], usable like a local one.
+///
+/// Values are sent in a compact binary format, or encoded by the codec it
+/// was given. Connections are pooled, and requests failing on a connection
+/// are sent again on a new one after a backoff. [`Memory`] drops errors, so
+/// a failed memoize is lost and a failed retrieval is a miss, the `try_`
+/// methods report them.
+///
+/// [`Server`]: crate::Server
+pub struct RemoteBrain<T> {
+    client: Arc<Client>,
+    codec: Arc<dyn WireCodec<T>>,
+}
+impl<T> Clone for RemoteBrain<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            codec: self.codec.clone(),
+        }
+    }
+}
+impl<T: Serialize + DeserializeOwned + 'static> RemoteBrain<T> {
+    /// Connects to a server, failing unless it is reachable.
+    pub fn connect(address: impl ToSocketAddrs, config: ClientConfig) -> io::Result<Self> {
+        Self::connect_with(address, config, BinaryCodec)
+    }
+    /// Connects to a [`UnixServer`] at the path, failing unless it is reachable.
+    ///
+    /// [`UnixServer`]: crate::UnixServer
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<Path>, config: ClientConfig) -> io::Result<Self> {
+        Ok(Self {
+            client: Arc::new(Client::unix(path.as_ref(), config)?),
+            codec: Arc::new(BinaryCodec),
+        })
+    }
+}
+impl<T> RemoteBrain<T> {
+    /// Connects to a server, encoding values with the codec, which needn't
+    /// rely on serde.
+    pub fn connect_with(
+        address: impl ToSocketAddrs,
+        config: ClientConfig,
+        codec: impl WireCodec<T> + 'static,
+    ) -> io::Result<Self> {
+        Self::connect_shared(address, config, Arc::new(codec))
+    }
+    pub(crate) fn connect_shared(
+        address: impl ToSocketAddrs,
+        config: ClientConfig,
+        codec: Arc<dyn WireCodec<T>>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            client: Arc::new(Client::new(address, config)?),
+            codec,
+        })
+    }
+    /// Encodes values with the codec from now on.
+    pub fn codec(mut self, codec: impl WireCodec<T> + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.set(key, value, 0)
+    }
+    /// Memoizes a value with its own retention, rounded to milliseconds.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        let milliseconds = retention.whole_milliseconds().clamp(1, u64::MAX.into()) as u64;
+        self.set(key, value, milliseconds)
+    }
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        match self.client.request(&request(GET, key))? {
+            (FOUND, value) => self.codec.decode(&value).map(Some),
+            _ => Ok(None),
+        }
+    }
+    /// Removes the value for the key, telling whether there was one.
+    pub fn try_remove(&self, key: &str) -> io::Result<bool> {
+        Ok(self.client.request(&request(DEL, key))?.0 == FOUND)
+    }
+    /// Time left until the value for the key expires.
+    pub fn try_time_to_live(&self, key: &str) -> io::Result<Option<Duration>> {
+        match self.client.request(&request(TTL, key))? {
+            (FOUND, left) => {
+                let left = left.first_chunk::<8>().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "truncated time to live")
+                })?;
+                let left = i64::try_from(u64::from_le_bytes(*left)).unwrap_or(i64::MAX);
+                Ok(Some(Duration::milliseconds(left)))
+            }
+            _ => Ok(None),
+        }
+    }
+    pub fn try_forget(&self) -> io::Result<()> {
+        self.client.request(&request(FORGET, "")).map(drop)
+    }
+    /// Retrieves the values for the keys, in as few round trips as the
+    /// pipeline allows, giving a result per key.
+    pub fn try_retrieve_many(&self, keys: &[&str]) -> io::Result<Vec<io::Result<Option<T>>>> {
+        let requests = keys.iter().map(|key| request(GET, key)).collect::<Vec<_>>();
+        let responses = self.client.pipeline(&requests)?;
+        let values = responses.into_iter().map(|response| match response? {
+            (FOUND, value) => self.codec.decode(&value).map(Some),
+            _ => Ok(None),
+        });
+        Ok(values.collect())
+    }
+    /// Memoizes the values, in as few round trips as the pipeline allows,
+    /// giving a result per value.
+    ///
+    /// Values failing to encode aren't sent.
+    pub fn try_memoize_many(&self, entries: &[(&str, T)]) -> io::Result<Vec<io::Result<()>>> {
+        let mut requests = Vec::with_capacity(entries.len());
+        let encoded = entries
+            .iter()
+            .map(|(key, value)| {
+                self.set_frame(key, value, 0)
+                    .map(|frame| requests.push(frame))
+            })
+            .collect::<Vec<io::Result<()>>>();
+        let mut responses = self.client.pipeline(&requests)?.into_iter();
+        let results = encoded.into_iter().map(|encoded| {
+            encoded?;
+            responses
+                .next()
+                .unwrap_or_else(|| Err(io::ErrorKind::UnexpectedEof.into()))
+                .map(drop)
+        });
+        Ok(results.collect())
+    }
+    fn set(&self, key: &str, value: T, milliseconds: u64) -> io::Result<()> {
+        let frame = self.set_frame(key, &value, milliseconds)?;
+        self.client.request(&frame).map(drop)
+    }
+    fn set_frame(&self, key: &str, value: &T, milliseconds: u64) -> io::Result<Vec<u8>> {
+        let value = self.codec.encode(value)?;
+        let mut frame = request(SET, key);
+        put_field(&mut frame, &value);
+        frame.extend(milliseconds.to_le_bytes());
+        Ok(frame)
+    }
+}
+/// Leases are taken by the server, so they hold for all its clients.
+impl<T> Leasing for RemoteBrain<T> {
+    fn try_hold(&self, key: &str, holder: &str, ttl: Duration) -> Result<(), Held> {
+        let milliseconds = ttl.whole_milliseconds().clamp(1, u64::MAX.into()) as u64;
+        let mut frame = request(LEASE, key);
+        put_field(&mut frame, holder.as_bytes());
+        frame.extend(milliseconds.to_le_bytes());
+        match self.client.request(&frame)? {
+            (FOUND, _) => Ok(()),
+            (_, holder) => Err(Held::By(String::from_utf8_lossy(&holder).into_owned())),
+        }
+    }
+    fn try_release(&self, key: &str, holder: &str) -> io::Result<bool> {
+        let mut frame = request(RELEASE, key);
+        put_field(&mut frame, holder.as_bytes());
+        Ok(self.client.request(&frame)?.0 == FOUND)
+    }
+}
+impl<T> TryMemoize<T> for RemoteBrain<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+impl<T> Memory<T> for RemoteBrain<T> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    fn forget(&self) {
+        let _ = self.try_forget();
+    }
+}
+/// Requests run on worker threads of the client, as many as the pooled
+/// connections, so they don't block the executor.
+impl<T: Send + 'static> AsyncMemory<T> for RemoteBrain<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        let (brain, key) = (self.clone(), key.to_string());
+        Box::pin(
+            self.client
+                .offload(move || Memory::memoize(&brain, &key, value)),
+        )
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        let (brain, key) = (self.clone(), key.to_string());
+        Box::pin(self.client.offload(move || Memory::retrieve(&brain, &key)))
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        let brain = self.clone();
+        Box::pin(self.client.offload(move || Memory::forget(&brain)))
+    }
+}
+
+/// Completes once a task running on another thread finished.
+pub(crate) struct Offloaded<R> {
+    state: Arc<Mutex<(Option<R>, Option<Waker>)>>,
+}
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads running the blocking requests of async calls, started on the
+/// first one, as many as the connections pooled: further calls wait for a
+/// thread in a queue rather than each taking one of their own.
+struct Workers {
+    size: usize,
+    jobs: Mutex<Option<mpsc::Sender<Job>>>,
+}
+impl Workers {
+    fn new(size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            jobs: Mutex::new(None),
+        }
+    }
+    /// Runs the job on a worker, the workers ending with the pool.
+    fn run(&self, job: Job) {
+        let mut jobs = self.jobs.lock();
+        let jobs = jobs.get_or_insert_with(|| {
+            let (jobs, queue) = mpsc::channel::<Job>();
+            let queue = Arc::new(Mutex::new(queue));
+            for _ in 0..self.size {
+                let queue = queue.clone();
+                thread::spawn(move || loop {
+                    let job = queue.lock().recv();
+                    match job {
+                        // a panicking job leaves its future pending, not the
+                        // pool short of a worker
+                        Ok(job) => drop(panic::catch_unwind(AssertUnwindSafe(job))),
+                        Err(_) => break,
+                    }
+                });
+            }
+            jobs
+        });
+        let _ = jobs.send(job);
+    }
+}
+impl<R> Future for Offloaded<R> {
+    type Output = R;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let mut state = self.state.lock();
+        match state.0.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::async_brain::tests::block_on;
+    use crate::{Brain, NumericalDuration, Server, ServerConfig};
+    use std::net::Shutdown;
+
+    #[test]
+    fn remote() {
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new());
+        let server = server.unwrap();
+        let remote = RemoteBrain::connect(server.local_addr(), ClientConfig::new()).unwrap();
+
+        Memory::memoize(&remote, "a", vec![(1, "one".to_string())]);
+        let retrieved = Memory::retrieve(&remote, "a");
+        assert_eq!(retrieved, Some(vec![(1, "one".to_string())]));
+        remote
+            .try_memoize_for("b", vec![], 1.milliseconds())
+            .unwrap();
+        assert!(remote.try_time_to_live("b").unwrap().unwrap() <= 1.milliseconds());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        Memory::forget(&remote);
+        assert_eq!(Memory::retrieve(&remote, "b"), None);
+        assert!(remote.try_remove("a").unwrap());
+        assert!(!remote.try_remove("a").unwrap());
+        block_on(async {
+            AsyncMemory::memoize(&remote, "c", vec![]).await;
+            assert_eq!(AsyncMemory::retrieve(&remote, "c").await, Some(vec![]));
+        });
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn workers() {
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new());
+        let server = server.unwrap();
+        let config = ClientConfig::new().pool_size(2);
+        let remote = RemoteBrain::connect(server.local_addr(), config).unwrap();
+
+        let keys = (0..32).map(|n| n.to_string()).collect::<Vec<_>>();
+        let memoizing = keys
+            .iter()
+            .map(|key| AsyncMemory::memoize(&remote, key, key.len()))
+            .collect::<Vec<_>>();
+        let threads = (0..32)
+            .map(|_| remote.client.offload(|| thread::current().id()))
+            .collect::<Vec<_>>();
+        let threads = block_on(async {
+            for memoized in memoizing {
+                memoized.await;
+            }
+            let mut ids = std::collections::HashSet::new();
+            for thread in threads {
+                ids.insert(thread.await);
+            }
+            ids
+        });
+        assert!(threads.len() <= 2);
+        assert_eq!(Memory::retrieve(&remote, "31"), Some(2));
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn pipeline() {
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new());
+        let server = server.unwrap();
+        let remote = RemoteBrain::connect(server.local_addr(), ClientConfig::new()).unwrap();
+
+        let keys = (0..150).map(|key| key.to_string()).collect::<Vec<_>>();
+        let entries = keys
+            .iter()
+            .map(|key| (key.as_str(), key.len()))
+            .collect::<Vec<_>>();
+        let memoized = remote.try_memoize_many(&entries).unwrap();
+        assert_eq!(memoized.len(), 150);
+        assert!(memoized.iter().all(Result::is_ok));
+
+        let mut keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+        keys.push("missing");
+        let values = remote.try_retrieve_many(&keys).unwrap();
+        assert_eq!(values.len(), 151);
+        assert_eq!(*values[9].as_ref().unwrap(), Some(1));
+        assert_eq!(*values[149].as_ref().unwrap(), Some(3));
+        assert_eq!(*values[150].as_ref().unwrap(), None);
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn token() {
+        let config = ServerConfig::new().authorize(|token| token == "secret");
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), config).unwrap();
+        let connect = |config| RemoteBrain::<i32>::connect(server.local_addr(), config);
+
+        let anonymous = connect(ClientConfig::new()).unwrap();
+        let refused = anonymous.try_memoize("a", 1).unwrap_err();
+        assert_eq!(refused.to_string(), "unauthorized");
+        let denied = connect(ClientConfig::new().token("guess")).err().unwrap();
+        assert_eq!(denied.kind(), io::ErrorKind::PermissionDenied);
+
+        let remote = connect(ClientConfig::new().token("secret")).unwrap();
+        remote.try_memoize("a", 1).unwrap();
+        assert_eq!(remote.try_retrieve("a").unwrap(), Some(1));
+        server.shutdown().unwrap();
+    }
+
+    /// Scrambles every byte, standing in for TLS.
+    struct Scrambled(TcpStream);
+    impl io::Read for Scrambled {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.0.read(buf)?;
+            buf[..read].iter_mut().for_each(|byte| *byte ^= 0x5a);
+            Ok(read)
+        }
+    }
+    impl Write for Scrambled {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let scrambled = buf.iter().map(|byte| byte ^ 0x5a).collect::<Vec<_>>();
+            self.0.write(&scrambled)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+    fn scramble(stream: TcpStream) -> io::Result<Box<dyn Duplex>> {
+        Ok(Box::new(Scrambled(stream)))
+    }
+
+    #[test]
+    fn wrapped() {
+        let config = ServerConfig::new().wrap(scramble);
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), config).unwrap();
+        let connect = |config| RemoteBrain::<i32>::connect(server.local_addr(), config);
+
+        let remote = connect(ClientConfig::new().wrap(scramble)).unwrap();
+        remote.try_memoize("a", 1).unwrap();
+        assert_eq!(remote.try_retrieve("a").unwrap(), Some(1));
+        let plain = connect(ClientConfig::new().retries(0)).unwrap();
+        assert!(plain.try_retrieve("a").is_err());
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn lease() {
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new());
+        let server = server.unwrap();
+        let remote = RemoteBrain::<()>::connect(server.local_addr(), ClientConfig::new()).unwrap();
+        let other = RemoteBrain::<()>::connect(server.local_addr(), ClientConfig::new()).unwrap();
+
+        let lease = remote.acquire_lease("refresh", "a", 1.minutes()).unwrap();
+        match other.acquire_lease("refresh", "b", 1.minutes()) {
+            Err(Held::By(holder)) => assert_eq!(holder, "a"),
+            _ => panic!("lease granted twice"),
+        }
+        assert!(lease.release().unwrap());
+        assert!(other.acquire_lease("refresh", "b", 1.minutes()).is_ok());
+        server.shutdown().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        let path = std::env::temp_dir().join(format!("brain-{}.sock", std::process::id()));
+        let brain = Brain::new(1.minutes());
+        let server = crate::UnixServer::bind(&path, brain, ServerConfig::new()).unwrap();
+        let remote = RemoteBrain::connect_unix(server.path(), ClientConfig::new()).unwrap();
+
+        remote.try_memoize("a", 1).unwrap();
+        assert_eq!(remote.try_retrieve("a").unwrap(), Some(1));
+        server.shutdown().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reconnect() {
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new());
+        let server = server.unwrap();
+        let config = ClientConfig::new().backoff(1.milliseconds());
+        let remote = RemoteBrain::connect(server.local_addr(), config).unwrap();
+        remote.try_memoize("a", 1).unwrap();
+
+        for stream in remote.client.idle.lock().iter() {
+            stream.shutdown(Shutdown::Both).unwrap();
+        }
+        assert_eq!(remote.try_retrieve("a").unwrap(), Some(1));
+        server.shutdown().unwrap();
+    }
+}
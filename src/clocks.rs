@@ -0,0 +1,218 @@
+use crate::{Brain, Duration, Engram, Memory, StorageBackend};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Counts the writes of every actor a value descends from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VectorClock(pub(crate) BTreeMap<u64, u64>);
+impl VectorClock {
+    /// Writes of the actor this clock has seen.
+    pub fn get(&self, actor: u64) -> u64 {
+        self.0.get(&actor).copied().unwrap_or(0)
+    }
+    fn increment(&mut self, actor: u64) {
+        *self.0.entry(actor).or_default() += 1;
+    }
+    fn merge(&mut self, other: &VectorClock) {
+        for (actor, writes) in &other.0 {
+            let ours = self.0.entry(*actor).or_default();
+            *ours = (*ours).max(*writes);
+        }
+    }
+}
+/// A clock is less than another which saw all its writes and more, clocks
+/// which saw writes of each other's are concurrent and not ordered.
+impl PartialOrd for VectorClock {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let actors = self.0.keys().chain(other.0.keys());
+        let (mut less, mut greater) = (false, false);
+        for actor in actors {
+            match self.get(*actor).cmp(&other.get(*actor)) {
+                Ordering::Less => less = true,
+                Ordering::Greater => greater = true,
+                Ordering::Equal => {}
+            }
+        }
+        match (less, greater) {
+            (false, false) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (true, true) => None,
+        }
+    }
+}
+
+/// Concurrent values of a key, each with the clock of its write.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Siblings<T>(pub(crate) Vec<(VectorClock, T)>);
+impl<T> Siblings<T> {
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().map(|(_, value)| value)
+    }
+    pub fn clocks(&self) -> impl Iterator<Item = &VectorClock> {
+        self.0.iter().map(|(clock, _)| clock)
+    }
+    pub fn is_conflicted(&self) -> bool {
+        self.0.len() > 1
+    }
+    /// Keeps the siblings no other one descends from, once each, telling
+    /// whether the value was added.
+    fn add(&mut self, clock: VectorClock, value: T) -> bool {
+        if self.clocks().any(|ours| clock <= *ours) {
+            return false;
+        }
+        self.0
+            .retain(|(ours, _)| ours.partial_cmp(&clock) != Some(Ordering::Less));
+        self.0.push((clock, value));
+        true
+    }
+}
+
+/// A brain tracking the causality of writes with vector clocks, so writes
+/// made independently by several brains surface as conflicts instead of
+/// overwriting each other like [`Brain::merge_crdt`] does.
+///
+/// A write descends from all values of the key it replaces. Merging another
+/// brain keeps the values neither brain has seen replaced, so a key written
+/// on both sides since the last merge holds both values until the
+/// application resolves the conflict by memoizing the result. [`Memory`]
+/// retrieves one value of a conflict, the same on every brain, see
+/// [`ClockedBrain::retrieve_conflicted`] for all of them.
+pub struct ClockedBrain<T> {
+    brain: Brain<Siblings<T>>,
+    actor: u64,
+}
+impl<T: Clone> ClockedBrain<T> {
+    /// Wraps a brain, the actor must be unique among the brains merged.
+    pub fn new(brain: Brain<Siblings<T>>, actor: u64) -> Self {
+        Self { brain, actor }
+    }
+    /// The brain holding the siblings, e.g. to save it.
+    pub fn brain(&self) -> &Brain<Siblings<T>> {
+        &self.brain
+    }
+    pub fn actor(&self) -> u64 {
+        self.actor
+    }
+    /// All concurrent values of the key, more than one if they conflict.
+    pub fn retrieve_conflicted(&self, key: &str) -> Vec<T> {
+        self.brain
+            .retrieve(key)
+            .map(|siblings| siblings.values().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// Memoizes a value with its own retention instead of the brain's.
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        self.brain
+            .memoize_for(key, self.write(key, value), retention);
+    }
+    /// Takes the values of another brain which this one hasn't seen replaced,
+    /// returning the number of keys changed.
+    ///
+    /// Keys keep the later expiry of both brains.
+    pub fn merge<B: StorageBackend<String, Siblings<T>>>(
+        &self,
+        other: &Brain<Siblings<T>, B>,
+    ) -> usize {
+        let theirs = other
+            .memory
+            .read()
+            .scan()
+            .map(|(key, engram)| {
+                let retention = engram.retention.unwrap_or(other.retention);
+                (
+                    key.clone(),
+                    engram.value.clone(),
+                    engram.memoized,
+                    retention,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut memory = self.brain.memory.write();
+        let mut changed = 0;
+        for (key, theirs, memoized, retention) in theirs {
+            let (mut siblings, memoized, retention) = match memory.get(&key) {
+                Some(ours) => {
+                    let expires = (memoized + retention)
+                        .max(ours.memoized + ours.retention.unwrap_or(self.brain.retention));
+                    (ours.value.clone(), ours.memoized, expires - ours.memoized)
+                }
+                None => (Siblings(Vec::new()), memoized, retention),
+            };
+            let mut added = false;
+            for (clock, value) in theirs.0 {
+                added |= siblings.add(clock, value);
+            }
+            if added {
+                let engram = Engram {
+                    memoized,
+                    retention: Some(retention),
+                    ..Engram::new(siblings, None)
+                };
+                self.brain.filter_key(&key);
+                memory.put(key, engram);
+                changed += 1;
+            }
+        }
+        changed
+    }
+    /// Clocks the value as descending from all values of the key.
+    fn write(&self, key: &str, value: T) -> Siblings<T> {
+        let mut clock = VectorClock::default();
+        if let Some(siblings) = self.brain.memory.read().get(key) {
+            siblings.value.clocks().for_each(|seen| clock.merge(seen));
+        }
+        clock.increment(self.actor);
+        Siblings(vec![(clock, value)])
+    }
+}
+impl<T: Clone> Memory<T> for ClockedBrain<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.brain.memoize(key, self.write(key, value));
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        let siblings = self.brain.retrieve(key)?;
+        let chosen = siblings
+            .0
+            .into_iter()
+            .max_by(|(a, _), (b, _)| a.0.iter().cmp(b.0.iter()));
+        chosen.map(|(_, value)| value)
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn conflicts() {
+        let east = ClockedBrain::new(Brain::new(1.minutes()), 1);
+        let west = ClockedBrain::new(Brain::new(1.minutes()), 2);
+        east.memoize("total", 1);
+        assert_eq!(west.merge(east.brain()), 1);
+        assert_eq!(west.merge(east.brain()), 0);
+
+        // concurrent writes conflict
+        east.memoize("total", 2);
+        west.memoize("total", 3);
+        east.merge(west.brain());
+        west.merge(east.brain());
+        let mut conflicted = east.retrieve_conflicted("total");
+        conflicted.sort();
+        assert_eq!(conflicted, vec![2, 3]);
+        assert_eq!(west.retrieve_conflicted("total").len(), 2);
+        assert_eq!(east.retrieve("total"), west.retrieve("total"));
+
+        // resolving descends from both
+        west.memoize("total", 5);
+        east.merge(west.brain());
+        assert_eq!(east.retrieve_conflicted("total"), vec![5]);
+        let resolved = east.brain().retrieve("total").unwrap();
+        let clock = resolved.clocks().next().unwrap();
+        assert_eq!((clock.get(1), clock.get(2)), (2, 2));
+    }
+}
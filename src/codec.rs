@@ -0,0 +1,100 @@
+use crate::Memory;
+use std::io;
+use std::marker::PhantomData;
+
+/// Encodes values to bytes and back, for values kept as bytes: over the
+/// wire, in spill files or in memories of bytes such as a
+/// [`CompressedMemory`].
+///
+/// The `serde` feature adds [`BinaryCodec`] and [`JsonCodec`]; types which
+/// can't implement serde get a codec of their own, e.g. with protobuf to
+/// share schemas with other languages or a zero-copy format.
+///
+/// [`CompressedMemory`]: crate::CompressedMemory
+/// [`BinaryCodec`]: crate::BinaryCodec
+/// [`JsonCodec`]: crate::JsonCodec
+pub trait ValueCodec<T>: Send + Sync {
+    fn encode(&self, value: &T) -> io::Result<Vec<u8>>;
+    fn decode(&self, bytes: &[u8]) -> io::Result<T>;
+}
+
+/// A shared codec, e.g. one handed to several clients.
+impl<T, C: ValueCodec<T> + ?Sized> ValueCodec<T> for std::sync::Arc<C> {
+    fn encode(&self, value: &T) -> io::Result<Vec<u8>> {
+        (**self).encode(value)
+    }
+    fn decode(&self, bytes: &[u8]) -> io::Result<T> {
+        (**self).decode(bytes)
+    }
+}
+
+/// Memoizes values into a memory of bytes, encoded by the codec.
+///
+/// Values which don't encode aren't memoized, and those which don't decode
+/// are retrieved as misses.
+pub struct CodecMemory<T, M, C> {
+    memory: M,
+    codec: C,
+    value: PhantomData<fn(T) -> T>,
+}
+impl<T, M, C> CodecMemory<T, M, C> {
+    pub fn new(memory: M, codec: C) -> Self {
+        Self {
+            memory,
+            codec,
+            value: PhantomData,
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+}
+impl<T, M: Memory<Vec<u8>>, C: ValueCodec<T>> Memory<T> for CodecMemory<T, M, C> {
+    fn memoize(&self, key: &str, value: T) {
+        if let Ok(bytes) = self.codec.encode(&value) {
+            self.memory.memoize(key, bytes);
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.codec.decode(&self.memory.retrieve(key)?).ok()
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    /// A point without serde, as two little-endian words.
+    #[derive(Debug, PartialEq)]
+    struct Point(u32, u32);
+    struct PointCodec;
+    impl ValueCodec<Point> for PointCodec {
+        fn encode(&self, point: &Point) -> io::Result<Vec<u8>> {
+            Ok([point.0.to_le_bytes(), point.1.to_le_bytes()].concat())
+        }
+        fn decode(&self, bytes: &[u8]) -> io::Result<Point> {
+            let word = |at: usize| {
+                bytes
+                    .get(at..at + 4)
+                    .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+                    .ok_or(io::ErrorKind::InvalidData)
+            };
+            Ok(Point(word(0)?, word(4)?))
+        }
+    }
+
+    #[test]
+    fn codec_memory() {
+        let memory = CodecMemory::new(Brain::new(1.minutes()), PointCodec);
+        memory.memoize("a", Point(1, 2));
+        assert_eq!(memory.memory().retrieve("a").unwrap().len(), 8);
+        assert_eq!(memory.retrieve("a"), Some(Point(1, 2)));
+
+        memory.memory().memoize("b", vec![1, 2, 3]);
+        assert_eq!(memory.retrieve("b"), None);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::Memory;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Compresses the values of a [`CompressedMemory`].
+pub trait Compression: Send + Sync {
+    fn compress(&self, value: &[u8]) -> Vec<u8>;
+    /// Decompresses a value of the given length, `None` if it's corrupt.
+    fn decompress(&self, compressed: &[u8], len: usize) -> Option<Vec<u8>>;
+}
+
+/// The LZ4 block format, fast rather than compact.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lz4;
+
+/// Counters of a [`CompressedMemory`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Values memoized compressed.
+    pub compressed: u64,
+    /// Values memoized as they are, being small or incompressible.
+    pub stored: u64,
+    /// Bytes of the values memoized compressed, before compression.
+    pub original_bytes: u64,
+    /// Bytes of the values memoized compressed, after compression.
+    pub compressed_bytes: u64,
+}
+impl CompressionStats {
+    pub fn saved_bytes(&self) -> u64 {
+        self.original_bytes.saturating_sub(self.compressed_bytes)
+    }
+}
+
+/// Compresses values from a size on before memoizing them into a memory of
+/// bytes, and decompresses them when retrieved.
+///
+/// Values are stored behind a byte telling whether they are compressed,
+/// and compressed ones behind their length. Values which don't decompress
+/// are retrieved as misses.
+pub struct CompressedMemory<M, C = Lz4> {
+    memory: M,
+    compression: C,
+    threshold: usize,
+    compressed: AtomicU64,
+    stored: AtomicU64,
+    original_bytes: AtomicU64,
+    compressed_bytes: AtomicU64,
+}
+const STORED: u8 = 0;
+const COMPRESSED: u8 = 1;
+
+impl<M> CompressedMemory<M> {
+    /// Compresses values of at least `threshold` bytes with LZ4.
+    pub fn new(memory: M, threshold: usize) -> Self {
+        Self::with_compression(memory, threshold, Lz4)
+    }
+}
+impl<M, C> CompressedMemory<M, C> {
+    pub fn with_compression(memory: M, threshold: usize, compression: C) -> Self {
+        Self {
+            memory,
+            compression,
+            threshold,
+            compressed: AtomicU64::new(0),
+            stored: AtomicU64::new(0),
+            original_bytes: AtomicU64::new(0),
+            compressed_bytes: AtomicU64::new(0),
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    pub fn stats(&self) -> CompressionStats {
+        CompressionStats {
+            compressed: self.compressed.load(Ordering::Relaxed),
+            stored: self.stored.load(Ordering::Relaxed),
+            original_bytes: self.original_bytes.load(Ordering::Relaxed),
+            compressed_bytes: self.compressed_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+impl<M: Memory<Vec<u8>>, C: Compression> Memory<Vec<u8>> for CompressedMemory<M, C> {
+    fn memoize(&self, key: &str, value: Vec<u8>) {
+        if value.len() >= self.threshold {
+            let compressed = self.compression.compress(&value);
+            if compressed.len() + 9 < value.len() {
+                self.compressed.fetch_add(1, Ordering::Relaxed);
+                self.original_bytes
+                    .fetch_add(value.len() as u64, Ordering::Relaxed);
+                self.compressed_bytes
+                    .fetch_add(compressed.len() as u64 + 9, Ordering::Relaxed);
+                let mut stored = Vec::with_capacity(compressed.len() + 9);
+                stored.push(COMPRESSED);
+                stored.extend_from_slice(&(value.len() as u64).to_be_bytes());
+                stored.extend_from_slice(&compressed);
+                return self.memory.memoize(key, stored);
+            }
+        }
+        self.stored.fetch_add(1, Ordering::Relaxed);
+        let mut stored = Vec::with_capacity(value.len() + 1);
+        stored.push(STORED);
+        stored.extend_from_slice(&value);
+        self.memory.memoize(key, stored);
+    }
+    fn retrieve(&self, key: &str) -> Option<Vec<u8>> {
+        let stored = self.memory.retrieve(key)?;
+        match stored.split_first()? {
+            (&STORED, value) => Some(value.to_vec()),
+            (&COMPRESSED, rest) if rest.len() >= 8 => {
+                let (len, compressed) = rest.split_at(8);
+                let len = usize::try_from(u64::from_be_bytes(len.try_into().ok()?)).ok()?;
+                self.compression.decompress(compressed, len)
+            }
+            _ => None,
+        }
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+const MIN_MATCH: usize = 4;
+/// Bytes at the end of a block which are always literals.
+const LAST_LITERALS: usize = 5;
+/// Bytes at the end of a block no match may start in.
+const MATCH_LIMIT: usize = 12;
+const HASH_BITS: u32 = 12;
+
+impl Compression for Lz4 {
+    fn compress(&self, value: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::with_capacity(value.len() / 2 + 16);
+        let mut table = vec![usize::MAX; 1 << HASH_BITS];
+        let (mut anchor, mut at) = (0, 0);
+        while at + MATCH_LIMIT <= value.len() {
+            let sequence = read_u32(value, at);
+            let slot = (sequence.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+            let candidate = std::mem::replace(&mut table[slot], at);
+            if candidate == usize::MAX
+                || at - candidate > u16::MAX as usize
+                || read_u32(value, candidate) != sequence
+            {
+                at += 1;
+                continue;
+            }
+            let end = value.len() - LAST_LITERALS;
+            let mut len = MIN_MATCH;
+            while at + len < end && value[candidate + len] == value[at + len] {
+                len += 1;
+            }
+            let literals = &value[anchor..at];
+            compressed
+                .push(((literals.len().min(15) as u8) << 4) | (len - MIN_MATCH).min(15) as u8);
+            write_length(&mut compressed, literals.len());
+            compressed.extend_from_slice(literals);
+            compressed.extend_from_slice(&((at - candidate) as u16).to_le_bytes());
+            write_length(&mut compressed, len - MIN_MATCH);
+            at += len;
+            anchor = at;
+        }
+        let literals = &value[anchor..];
+        compressed.push((literals.len().min(15) as u8) << 4);
+        write_length(&mut compressed, literals.len());
+        compressed.extend_from_slice(literals);
+        compressed
+    }
+    fn decompress(&self, compressed: &[u8], len: usize) -> Option<Vec<u8>> {
+        let mut value = Vec::with_capacity(len);
+        let mut at = 0;
+        loop {
+            let token = *compressed.get(at)?;
+            at += 1;
+            let literals = read_length(compressed, &mut at, (token >> 4) as usize)?;
+            value.extend_from_slice(compressed.get(at..at.checked_add(literals)?)?);
+            at += literals;
+            if value.len() > len {
+                return None;
+            }
+            if at == compressed.len() {
+                break;
+            }
+            let offset = u16::from_le_bytes(compressed.get(at..at + 2)?.try_into().ok()?) as usize;
+            at += 2;
+            let matched = read_length(compressed, &mut at, (token & 15) as usize)? + MIN_MATCH;
+            if offset == 0 || offset > value.len() || value.len() + matched > len {
+                return None;
+            }
+            let start = value.len() - offset;
+            for i in start..start + matched {
+                value.push(value[i]);
+            }
+        }
+        (value.len() == len).then_some(value)
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Writes what a length exceeds the 15 of its token by, if it does.
+fn write_length(compressed: &mut Vec<u8>, len: usize) {
+    let Some(mut rest) = len.checked_sub(15) else {
+        return;
+    };
+    while rest >= 255 {
+        compressed.push(255);
+        rest -= 255;
+    }
+    compressed.push(rest as u8);
+}
+
+fn read_length(compressed: &[u8], at: &mut usize, token: usize) -> Option<usize> {
+    let mut len = token;
+    if token == 15 {
+        loop {
+            let byte = *compressed.get(*at)?;
+            *at += 1;
+            len += byte as usize;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn lz4() {
+        let json = br#"{"id":1,"name":"a","tags":["x","y"]},"#.repeat(100);
+        let cases: [&[u8]; 5] = [b"", b"abc", b"abcdabcdabcdabcdabcd", &[7; 1000], &json];
+        for value in cases {
+            let compressed = Lz4.compress(value);
+            assert_eq!(
+                Lz4.decompress(&compressed, value.len()).as_deref(),
+                Some(value)
+            );
+        }
+        assert!(Lz4.compress(&json).len() < json.len() / 8);
+        // a block as written by the reference implementation
+        assert_eq!(
+            Lz4.decompress(b"\x1fa\x01\x00\x01\x50aaaaa", 26).unwrap(),
+            b"a".repeat(26)
+        );
+        assert_eq!(Lz4.decompress(b"\x10a\x02\x00", 8), None);
+    }
+
+    #[test]
+    fn compressed() {
+        let brain = Brain::<Vec<u8>>::new(1.minutes());
+        let memory = CompressedMemory::new(brain.clone(), 64);
+        let blob = b"{\"field\":\"value\"}".repeat(64);
+        memory.memoize("blob", blob.clone());
+        memory.memoize("small", b"tiny".to_vec());
+        assert_eq!(memory.retrieve("blob"), Some(blob.clone()));
+        assert_eq!(memory.retrieve("small"), Some(b"tiny".to_vec()));
+        assert!(brain.retrieve("blob").unwrap().len() < blob.len() / 8);
+
+        let stats = memory.stats();
+        assert_eq!((stats.compressed, stats.stored), (1, 1));
+        assert_eq!(stats.original_bytes, blob.len() as u64);
+        assert!(stats.saved_bytes() > 0);
+        brain.memoize("corrupt", vec![COMPRESSED, 0, 0]);
+        assert_eq!(memory.retrieve("corrupt"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{Brain, Duration, Eviction, MaintenanceConfig};
+use std::io;
+
+/// Settings of a brain, e.g. read from a config file or the environment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BrainConfig {
+    pub retention: Duration,
+    /// Interval of the sweeps forgetting expired engrams, `None` for no sweeps.
+    pub sweep_interval: Option<Duration>,
+    /// Most entries the brain holds, `None` for no limit.
+    pub max_entries: Option<usize>,
+    pub eviction: Eviction,
+}
+impl BrainConfig {
+    /// Reads `<PREFIX>_RETENTION_SECONDS`, `<PREFIX>_SWEEP_INTERVAL_SECONDS`,
+    /// `<PREFIX>_MAX_ENTRIES` and `<PREFIX>_EVICTION`, the eviction being one
+    /// of `oldest`, `least_hits` or `soonest_expiring`.
+    ///
+    /// Unset variables keep their defaults.
+    pub fn from_env(prefix: &str) -> io::Result<Self> {
+        fn var<V>(
+            name: String,
+            expected: &str,
+            parse: impl FnOnce(&str) -> Option<V>,
+        ) -> io::Result<Option<V>> {
+            match std::env::var(&name) {
+                Ok(value) => parse(value.trim()).map(Some).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{name} is not {expected}"),
+                    )
+                }),
+                Err(std::env::VarError::NotPresent) => Ok(None),
+                Err(error) => Err(io::Error::new(io::ErrorKind::InvalidInput, error)),
+            }
+        }
+        let duration = |name: &str| {
+            var(format!("{prefix}_{name}"), "a number of seconds", |value| {
+                value.parse().ok().and_then(seconds)
+            })
+        };
+        Ok(Self {
+            retention: duration("RETENTION_SECONDS")?.unwrap_or_default(),
+            sweep_interval: duration("SWEEP_INTERVAL_SECONDS")?,
+            max_entries: var(format!("{prefix}_MAX_ENTRIES"), "a number", |value| {
+                value.parse().ok()
+            })?,
+            eviction: var(
+                format!("{prefix}_EVICTION"),
+                "an eviction policy",
+                Eviction::from_name,
+            )?
+            .unwrap_or_default(),
+        })
+    }
+    /// Maintenance doing the configured sweeps, see [`Brain::start_maintenance`].
+    pub fn maintenance<T: Clone + 'static>(&self) -> MaintenanceConfig<T> {
+        match self.sweep_interval {
+            Some(interval) => MaintenanceConfig::new().sweep_every(interval),
+            None => MaintenanceConfig::new(),
+        }
+    }
+}
+
+/// Converts a non-negative number of seconds.
+pub(crate) fn seconds(seconds: f64) -> Option<Duration> {
+    Some(seconds)
+        .filter(|seconds| *seconds >= 0.0)
+        .and_then(Duration::checked_seconds_f64)
+}
+
+impl<T> Brain<T> {
+    /// Creates a brain with the config, see [`BrainBuilder::config`].
+    ///
+    /// [`BrainBuilder::config`]: crate::BrainBuilder::config
+    pub fn from_config(config: &BrainConfig) -> Self {
+        Self::builder().config(config).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn from_env() {
+        std::env::set_var("CONFIG_TEST_RETENTION_SECONDS", "90");
+        std::env::set_var("CONFIG_TEST_SWEEP_INTERVAL_SECONDS", "0.5");
+        std::env::set_var("CONFIG_TEST_EVICTION", "least_hits");
+        let config = BrainConfig::from_env("CONFIG_TEST").unwrap();
+        assert_eq!(config.retention, 90.seconds());
+        assert_eq!(config.sweep_interval, Some(500.milliseconds()));
+        assert_eq!(config.max_entries, None);
+        assert_eq!(config.eviction, Eviction::LeastHits);
+        assert_eq!(Brain::<i32>::from_config(&config).retention, 90.seconds());
+
+        std::env::set_var("CONFIG_TEST_RETENTION_SECONDS", "-1");
+        assert!(BrainConfig::from_env("CONFIG_TEST").is_err());
+        assert_eq!(
+            BrainConfig::from_env("UNSET_CONFIG_TEST").unwrap(),
+            BrainConfig::default()
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use crate::wall_clock;
+use crate::{Brain, Duration, Engram, Memory, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::Ordering;
+use time::OffsetDateTime;
+
+/// What a consolidation did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Consolidation {
+    /// Entries forgotten for their retention or decay.
+    pub forgotten: usize,
+    /// Entries moved into a longer-lived store.
+    pub promoted: usize,
+    /// Entries moved back into a shorter-lived store.
+    pub demoted: usize,
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Forgets the expired entries, or those whose strength decayed, see
+    /// [`BrainBuilder::decay`], and compacts the storage.
+    ///
+    /// For a maintenance worker doing it, see
+    /// [`MaintenanceConfig::consolidate_every`].
+    ///
+    /// [`BrainBuilder::decay`]: crate::BrainBuilder::decay
+    /// [`MaintenanceConfig::consolidate_every`]: crate::MaintenanceConfig::consolidate_every
+    pub fn consolidate(&self) -> Consolidation {
+        let forgotten = self.forget_expired();
+        self.memory.write().compact();
+        Consolidation {
+            forgotten,
+            ..Default::default()
+        }
+    }
+}
+
+/// A short-term brain keeping new values briefly, and a long-term one
+/// keeping those which proved useful, each with its own capacity and
+/// retention, see [`Brain::builder`].
+///
+/// Values are memoized into the short-term brain and consolidated into the
+/// long-term one once recalled often enough within a window, three times
+/// within a minute by default. The others are forgotten with the short-term
+/// retention. Every key lives in one brain at a time.
+pub struct ConsolidatingMemory<T, S = HashMap<String, Engram<T>>, L = HashMap<String, Engram<T>>> {
+    short_term: Brain<T, S>,
+    long_term: Brain<T, L>,
+    recalls: usize,
+    window: Duration,
+    recalled: Mutex<HashMap<String, VecDeque<OffsetDateTime>>>,
+}
+impl<T, S, L> ConsolidatingMemory<T, S, L> {
+    pub fn new(short_term: Brain<T, S>, long_term: Brain<T, L>) -> Self {
+        Self {
+            short_term,
+            long_term,
+            recalls: 3,
+            window: Duration::minutes(1),
+            recalled: Default::default(),
+        }
+    }
+    /// Consolidates values recalled this often within the window.
+    pub fn consolidate_after(mut self, recalls: usize, window: Duration) -> Self {
+        self.recalls = recalls.max(1);
+        self.window = window;
+        self
+    }
+    pub fn short_term(&self) -> &Brain<T, S> {
+        &self.short_term
+    }
+    pub fn long_term(&self) -> &Brain<T, L> {
+        &self.long_term
+    }
+    /// Records a recall of the key, telling whether it's due to be
+    /// consolidated.
+    fn recall(&self, key: &str) -> bool {
+        let now = wall_clock::now();
+        let mut recalled = self.recalled.lock();
+        let recalls = recalled.entry(key.to_string()).or_default();
+        recalls.push_back(now);
+        while recalls.front().is_some_and(|at| now - *at > self.window) {
+            recalls.pop_front();
+        }
+        let due = recalls.len() >= self.recalls;
+        if due {
+            recalled.remove(key);
+        }
+        due
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>, L: StorageBackend<String, T>>
+    ConsolidatingMemory<T, S, L>
+{
+    /// Promotes short-term entries recalled often enough, also when
+    /// retrieved from the short-term brain itself, demotes long-term ones
+    /// whose strength decayed if the long-term brain decays, and then
+    /// consolidates both brains.
+    pub fn consolidate(&self) -> Consolidation {
+        let now = wall_clock::now();
+        let recalled =
+            |engram: &Engram<T>| engram.hits.load(Ordering::Relaxed) >= self.recalls as u64;
+        let promoted = self.move_where(&self.short_term, &self.long_term, recalled);
+        let demoted = match self.long_term.decay {
+            Some(decay) => self.move_where(&self.long_term, &self.short_term, |engram| {
+                decay.is_forgotten(engram, now) && !engram.is_expired(self.long_term.retention, now)
+            }),
+            None => 0,
+        };
+        let (short_term, long_term) = (self.short_term.consolidate(), self.long_term.consolidate());
+        self.recalled
+            .lock()
+            .retain(|key, _| self.short_term.is_cached(key));
+        Consolidation {
+            forgotten: short_term.forgotten + long_term.forgotten,
+            promoted,
+            demoted,
+        }
+    }
+    fn move_where<A: StorageBackend<String, T>, B: StorageBackend<String, T>>(
+        &self,
+        from: &Brain<T, A>,
+        to: &Brain<T, B>,
+        predicate: impl Fn(&Engram<T>) -> bool,
+    ) -> usize {
+        let keys = from
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| predicate(engram))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let mut moved = 0;
+        for key in keys {
+            if let Some(value) = from.remove_if(&key, &predicate) {
+                to.memoize(&key, value);
+                moved += 1;
+            }
+        }
+        moved
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>, L: StorageBackend<String, T>> Memory<T>
+    for ConsolidatingMemory<T, S, L>
+{
+    fn memoize(&self, key: &str, value: T) {
+        if self.long_term.is_cached(key) {
+            self.long_term.memoize(key, value);
+        } else {
+            self.short_term.memoize(key, value);
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        if let Some(value) = self.long_term.retrieve(key) {
+            return Some(value);
+        }
+        let value = self.short_term.retrieve(key)?;
+        if self.recall(key) {
+            if let Some(consolidated) = self.short_term.remove(key) {
+                self.long_term.memoize(key, consolidated);
+            }
+        }
+        Some(value)
+    }
+    /// Also drops the recalls of keys no longer in the short-term brain.
+    fn forget(&self) {
+        self.short_term.forget();
+        self.long_term.forget();
+        self.recalled
+            .lock()
+            .retain(|key, _| self.short_term.is_cached(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn consolidation() {
+        let memory = ConsolidatingMemory::new(Brain::new(1.minutes()), Brain::new(1.hours()))
+            .consolidate_after(2, 5.milliseconds());
+        memory.memoize("a", 1);
+        memory.memoize("b", 2);
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert!(memory.long_term().is_cached("a") && !memory.short_term().is_cached("a"));
+        memory.memoize("a", 3);
+        assert_eq!(memory.long_term().retrieve("a"), Some(3));
+
+        // recalls outside the window don't add up
+        memory.retrieve("b");
+        std::thread::sleep(std::time::Duration::from_millis(6));
+        memory.retrieve("b");
+        assert!(memory.short_term().is_cached("b"));
+        memory.retrieve("b");
+        assert!(memory.long_term().is_cached("b"));
+    }
+
+    #[test]
+    fn consolidate() {
+        let long_term = Brain::builder()
+            .retention(1.hours())
+            .decay(2.milliseconds(), 0.5)
+            .build();
+        let memory = ConsolidatingMemory::new(Brain::new(1.milliseconds()), long_term)
+            .consolidate_after(2, 1.minutes());
+        memory.memoize("a", 1);
+        memory.memoize("b", 2);
+        memory.short_term().retrieve("a");
+        memory.short_term().retrieve("a");
+        memory.long_term().memoize("c", 3);
+        std::thread::sleep(std::time::Duration::from_millis(3));
+
+        let consolidation = memory.consolidate();
+        assert_eq!(
+            consolidation,
+            Consolidation {
+                forgotten: 1,
+                promoted: 1,
+                demoted: 1,
+            }
+        );
+        assert!(!memory.short_term().is_cached("b"));
+        // a was just promoted, c decayed and is back in the short term
+        assert!(memory.long_term().is_cached("a"));
+        assert_eq!(memory.short_term().retrieve("c"), Some(3));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{Brain, Engram, EvictionCause, StorageBackend};
+use std::sync::Arc;
+
+impl<T, S> Brain<T, S> {
+    /// Lets retrieval see the entries memoized in the context.
+    ///
+    /// Inactive contexts hide their entries from every read, e.g.
+    /// [`Brain::is_cached`], [`Brain::time_to_live`], searches, the history
+    /// and watches, but not from writes.
+    pub fn activate_context(&self, context: &str) {
+        self.active_contexts.write().insert(context.into());
+    }
+    /// Hides the entries memoized in the context from retrieval, until
+    /// activated again.
+    pub fn deactivate_context(&self, context: &str) {
+        self.active_contexts.write().remove(context);
+    }
+    /// The active contexts, sorted.
+    pub fn active_contexts(&self) -> Vec<String> {
+        let mut contexts = self
+            .active_contexts
+            .read()
+            .iter()
+            .map(|context| context.to_string())
+            .collect::<Vec<_>>();
+        contexts.sort();
+        contexts
+    }
+    /// Tells whether retrieval sees the engram, memoized in no context or
+    /// an active one.
+    pub(crate) fn is_context_active(&self, engram: &Engram<T>) -> bool {
+        self.is_active(engram.context.as_ref())
+    }
+    /// Tells whether retrieval sees values memoized in the context, or in
+    /// no context.
+    pub(crate) fn is_active(&self, context: Option<&Arc<str>>) -> bool {
+        context.is_none_or(|context| self.active_contexts.read().contains(context))
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Memoizes a value in a context, e.g. a conversation, which retrieval
+    /// only sees while the context is active.
+    ///
+    /// Values memoized in no context are always seen. Memoizing the key
+    /// again, in or out of a context, replaces its context.
+    pub fn memoize_in_context(&self, context: &str, key: &str, value: T) {
+        let engram = Engram {
+            context: Some(Arc::from(context)),
+            ..Engram::new(value, None)
+        };
+        self.store_if(key, engram, |_| true);
+    }
+    /// Forgets all values memoized in the context at once, whether it's
+    /// active or not, telling how many.
+    pub fn forget_context(&self, context: &str) -> usize {
+        self.evict_where(EvictionCause::Removed, &mut |_, engram| {
+            engram.context.as_deref() == Some(context)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn contexts() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize("user", "ada");
+        brain.memoize_in_context("chat-1", "topic", "rust");
+        brain.memoize_in_context("chat-2", "mood", "curious");
+        assert_eq!(brain.retrieve("topic"), None);
+
+        brain.activate_context("chat-1");
+        assert_eq!(brain.retrieve("topic"), Some("rust"));
+        assert_eq!(brain.retrieve("mood"), None);
+        assert_eq!(brain.retrieve("user"), Some("ada"));
+
+        brain.deactivate_context("chat-1");
+        brain.activate_context("chat-2");
+        assert_eq!(brain.active_contexts(), ["chat-2"]);
+        assert_eq!(brain.retrieve("topic"), None);
+        assert_eq!(brain.retrieve("mood"), Some("curious"));
+
+        assert_eq!(brain.forget_context("chat-1"), 1);
+        assert!(!brain.is_cached("topic"));
+        assert!(brain.is_cached("mood") && brain.is_cached("user"));
+    }
+
+    #[test]
+    fn inactive_contexts_hide_from_reads() {
+        let brain = Brain::builder().retention(1.minutes()).history(2).build();
+        let watch = brain.watch("topic");
+        brain.memoize_in_context("chat", "topic", "rust");
+        brain.memoize("topic-id", "7");
+        brain.retrieve("topic-id");
+
+        assert!(!brain.is_cached("topic"));
+        assert_eq!(brain.time_to_live("topic"), None);
+        assert_eq!(
+            brain
+                .retrieve_fuzzy("topic", 3)
+                .iter()
+                .map(|(key, ..)| key.as_str())
+                .collect::<Vec<_>>(),
+            ["topic-id"]
+        );
+        assert_eq!(brain.top_keys(2).len(), 1);
+        assert!(brain.history("topic").is_empty());
+        assert_eq!(brain.retrieve_at("topic", crate::wall_clock::now()), None);
+        assert!(brain.watch("topic").try_recv().is_err());
+        assert!(watch.try_recv().is_err());
+
+        brain.activate_context("chat");
+        assert!(brain.is_cached("topic"));
+        assert!(brain.time_to_live("topic").is_some());
+        assert_eq!(brain.retrieve_fuzzy("topic", 3).len(), 2);
+        assert_eq!(brain.history("topic").len(), 1);
+        assert_eq!(brain.watch("topic").try_recv(), Ok("rust"));
+        brain.memoize_in_context("chat", "topic", "go");
+        assert_eq!(watch.try_recv(), Ok("go"));
+
+        let brain = Brain::new(1.minutes());
+        brain.memoize_in_context("chat", "user:topic", "rust");
+        brain.memoize("user:name", "ada");
+        assert_eq!(brain.namespace("user").keys(), ["name"]);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::wall_clock;
+use crate::{Brain, Memory, StorageBackend};
+use parking_lot::{MappedRwLockReadGuard, RwLockReadGuard};
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+/// A value retrieved by [`Brain::retrieve_cow`], borrowed from the brain or
+/// a copy of it.
+pub enum ValueCow<'a, T> {
+    /// The value as memoized, the brain being read locked until dropped.
+    Borrowed(MappedRwLockReadGuard<'a, T>),
+    Owned(T),
+}
+impl<T: Clone> ValueCow<'_, T> {
+    /// The value, copied unless owned already.
+    pub fn into_owned(self) -> T {
+        match self {
+            Self::Borrowed(value) => value.clone(),
+            Self::Owned(value) => value,
+        }
+    }
+    /// The value to change, copying it and unlocking the brain if borrowed.
+    pub fn to_mut(&mut self) -> &mut T {
+        if let Self::Borrowed(value) = self {
+            *self = Self::Owned(value.clone());
+        }
+        match self {
+            Self::Owned(value) => value,
+            Self::Borrowed(_) => unreachable!("copied above"),
+        }
+    }
+}
+impl<T> Deref for ValueCow<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Self::Borrowed(value) => value,
+            Self::Owned(value) => value,
+        }
+    }
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the value borrowed rather than copied, e.g. for reading
+    /// large values, holding the brain's read lock as long as it's kept.
+    ///
+    /// Brains with hooks or rehearsal retrieve a copy instead, as they act
+    /// on retrievals after unlocking.
+    pub fn retrieve_cow(&self, key: &str) -> Option<ValueCow<'_, T>> {
+        if self.rehearsal.is_some() || self.hooks().is_some() {
+            return self.retrieve(key).map(ValueCow::Owned);
+        }
+        if self.surely_missing(key) {
+            self.stats.retrieved(None);
+            self.record_retrieval(key);
+            return None;
+        }
+        let mut expired = None;
+        let value = RwLockReadGuard::try_map(self.memory.read(), |memory| {
+            let engram = memory
+                .get(key)
+                .filter(|engram| self.is_context_active(engram))?;
+            expired = Some(engram.is_expired(self.retention, wall_clock::now()));
+            engram.hits.fetch_add(1, Ordering::Relaxed);
+            Some(&engram.value)
+        });
+        self.stats.retrieved(expired);
+        self.record_retrieval(key);
+        value.ok().map(ValueCow::Borrowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn retrieve_cow() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize("a", vec![1, 2, 3]);
+        let value = brain.retrieve_cow("a").unwrap();
+        assert!(matches!(value, ValueCow::Borrowed(_)));
+        assert_eq!(value.len(), 3);
+        drop(value);
+        assert!(brain.retrieve_cow("b").is_none());
+        assert_eq!(brain.stats().hits, 1);
+        assert_eq!(brain.stats().misses, 1);
+
+        let mut value = brain.retrieve_cow("a").unwrap();
+        value.to_mut().push(4);
+        // unlocked, so the brain can be written meanwhile
+        brain.memoize("a", vec![]);
+        assert_eq!(value.into_owned(), [1, 2, 3, 4]);
+        assert_eq!(brain.retrieve("a"), Some(vec![]));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::wall_clock;
+use crate::{Brain, Engram, EvictionCause, StorageBackend};
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Takes the engrams of another brain which were written after ours,
+    /// returning how many were taken.
+    ///
+    /// Every key is a last-write-wins register: the engram memoized last
+    /// wins, and of engrams memoized at the same time the one of the greater
+    /// actor, see [`BrainBuilder::actor`]. Merging brains into each other in
+    /// any order leaves them holding the same engrams. Removals and forgets
+    /// leave no trace, so a key removed from one brain comes back from the
+    /// other while that still holds it. Taken engrams expire when they would
+    /// have in the other brain, expired ones aren't taken.
+    ///
+    /// [`BrainBuilder::actor`]: crate::BrainBuilder::actor
+    pub fn merge_crdt<B: StorageBackend<String, T>>(&self, other: &Brain<T, B>) -> usize {
+        let now = wall_clock::now();
+        let theirs = other
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| !engram.is_expired(other.retention, now))
+            .map(|(key, engram)| {
+                let engram = Engram {
+                    memoized: engram.memoized,
+                    retention: Some(engram.retention.unwrap_or(other.retention)),
+                    actor: engram.actor,
+                    salience: engram.salience,
+                    context: engram.context.clone(),
+                    attributes: engram.attributes.clone(),
+                    ..Engram::new(engram.value.clone(), None)
+                };
+                (key.clone(), engram)
+            })
+            .collect::<Vec<_>>();
+        self.merge_engrams(theirs)
+    }
+    /// Puts the engrams which win over ours, see [`Brain::merge_crdt`].
+    pub(crate) fn merge_engrams(&self, theirs: Vec<(String, Engram<T>)>) -> usize {
+        let order = |engram: &Engram<T>| (engram.memoized, engram.actor);
+        let mut taken = Vec::new();
+        let mut evicted = Vec::new();
+        {
+            let mut memory = self.memory.write();
+            for (key, engram) in theirs {
+                if memory
+                    .get(&key)
+                    .is_some_and(|ours| order(ours) >= order(&engram))
+                {
+                    continue;
+                }
+                let value = engram.value.clone();
+                self.value_watchers.send(&key, &value);
+                memory.put(key.clone(), engram);
+                self.filter_key(&key);
+                evicted.extend(self.make_room(&mut memory, &key));
+                taken.push((key, value));
+            }
+        }
+        let hooks = self.hooks();
+        for (key, value) in &taken {
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+            if let Some(hooks) = &hooks {
+                hooks.on_insert(key, value);
+            }
+        }
+        for (key, engram) in &evicted {
+            self.stats.evicted_for_capacity();
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+            self.discard(key, EvictionCause::Capacity);
+            if let Some(hooks) = &hooks {
+                hooks.on_evict(key, &engram.value, EvictionCause::Capacity);
+            }
+        }
+        taken.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn merge_crdt() {
+        let east = Brain::builder().retention(1.minutes()).actor(1).build();
+        let west = Brain::builder().retention(1.minutes()).actor(2).build();
+        east.memoize("a", "east");
+        east.memoize("east", "east");
+        west.memoize("a", "west");
+        west.memoize("west", "west");
+        // written at the same time, the greater actor wins
+        let at = wall_clock::now();
+        for brain in [&east, &west] {
+            let engram = Engram {
+                memoized: at,
+                actor: brain.actor,
+                ..Engram::new(["east", "west"][brain.actor as usize - 1], None)
+            };
+            brain.memory.write().put("tie".into(), engram);
+        }
+
+        assert_eq!(east.merge_crdt(&west), 3);
+        assert_eq!(west.merge_crdt(&east), 1);
+        assert_eq!(west.merge_crdt(&east), 0);
+        for key in ["a", "east", "west", "tie"] {
+            assert_eq!(east.retrieve(key), west.retrieve(key), "{key}");
+        }
+        assert_eq!(east.retrieve("a"), Some("west"));
+        assert_eq!(east.retrieve("tie"), Some("west"));
+        assert_eq!(east.memory.read().get("a").unwrap().actor(), 2);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Brain, Duration, Memory};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Counters of a [`DeadlineMemory`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeadlineStats {
+    /// Retrievals the memory didn't answer in time.
+    pub timeouts: u64,
+    /// Timed out retrievals answered with a stale value.
+    pub stale: u64,
+}
+
+/// Bounds the time retrievals from a slow memory take, e.g. a remote one
+/// behind a local brain, giving up once the deadline passed.
+///
+/// Retrievals run on a few worker threads of the wrapper, which go on
+/// waiting for the memory after the caller gave up. Memoizing and
+/// forgetting go to the memory directly. With [`DeadlineMemory::serve_stale`],
+/// timed out retrievals give the value last retrieved instead of `None`.
+pub struct DeadlineMemory<T, M> {
+    memory: Arc<M>,
+    deadline: Duration,
+    jobs: Sender<Job>,
+    stale: Option<Brain<T>>,
+    timeouts: AtomicU64,
+    stale_served: AtomicU64,
+}
+impl<T, M: Send + Sync + 'static> DeadlineMemory<T, M> {
+    /// Retrieves within the deadline, over as many threads.
+    pub fn new(memory: M, deadline: Duration, workers: usize) -> Self {
+        let (jobs, queued) = mpsc::channel::<Job>();
+        let queued = Arc::new(Mutex::new(queued));
+        for _ in 0..workers.max(1) {
+            let queued = queued.clone();
+            thread::spawn(move || work(&queued));
+        }
+        Self {
+            memory: Arc::new(memory),
+            deadline,
+            jobs,
+            stale: None,
+            timeouts: AtomicU64::new(0),
+            stale_served: AtomicU64::new(0),
+        }
+    }
+    /// Keeps the values retrieved for the retention, to answer retrievals
+    /// timing out with.
+    pub fn serve_stale(mut self, retention: Duration) -> Self {
+        self.stale = Some(Brain::new(retention));
+        self
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    pub fn stats(&self) -> DeadlineStats {
+        DeadlineStats {
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            stale: self.stale_served.load(Ordering::Relaxed),
+        }
+    }
+}
+fn work(queued: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = queued.lock().recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+impl<T: Clone + Send + 'static, M: Memory<T> + Send + Sync + 'static> DeadlineMemory<T, M> {
+    /// Retrieves the value unless the memory takes longer than the
+    /// deadline, giving a stale value then if served.
+    pub fn retrieve_within(&self, key: &str, deadline: Duration) -> Option<T> {
+        let (sender, answer) = mpsc::sync_channel(1);
+        let memory = self.memory.clone();
+        let owned = key.to_string();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(memory.retrieve(&owned));
+        });
+        let answered = match self.jobs.send(job) {
+            Ok(()) => answer.recv_timeout(deadline.unsigned_abs()).ok(),
+            Err(_) => None,
+        };
+        match answered {
+            Some(value) => {
+                if let (Some(stale), Some(value)) = (&self.stale, &value) {
+                    stale.memoize(key, value.clone());
+                }
+                value
+            }
+            None => {
+                self.timeouts.fetch_add(1, Ordering::Relaxed);
+                let value = self.stale.as_ref()?.retrieve(key)?;
+                self.stale_served.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+        }
+    }
+}
+impl<T: Clone + Send + 'static, M: Memory<T> + Send + Sync + 'static> Memory<T>
+    for DeadlineMemory<T, M>
+{
+    fn memoize(&self, key: &str, value: T) {
+        self.memory.memoize(key, value);
+    }
+    /// Retrieves within the deadline the wrapper was created with.
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.retrieve_within(key, self.deadline)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+        if let Some(stale) = &self.stale {
+            stale.forget();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    /// Answers after the delay.
+    struct Slow(Brain<i32>, Mutex<std::time::Duration>);
+    impl Memory<i32> for Slow {
+        fn memoize(&self, key: &str, value: i32) {
+            self.0.memoize(key, value);
+        }
+        fn retrieve(&self, key: &str) -> Option<i32> {
+            thread::sleep(*self.1.lock());
+            self.0.retrieve(key)
+        }
+        fn forget(&self) {
+            self.0.forget();
+        }
+    }
+
+    #[test]
+    fn retrieve_within() {
+        let slow = Slow(Brain::new(1.minutes()), Mutex::new(Default::default()));
+        let memory = DeadlineMemory::new(slow, 20.milliseconds(), 2).serve_stale(1.minutes());
+        memory.memoize("a", 1);
+        memory.memoize("b", 2);
+        assert_eq!(memory.retrieve("a"), Some(1));
+
+        *memory.memory().1.lock() = std::time::Duration::from_millis(100);
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert_eq!(memory.retrieve_within("b", 1.milliseconds()), None);
+        assert_eq!(
+            memory.stats(),
+            DeadlineStats {
+                timeouts: 2,
+                stale: 1
+            }
+        );
+    }
+}
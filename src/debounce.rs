@@ -0,0 +1,99 @@
+use crate::wall_clock::Instant;
+use crate::{Duration, Memory};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// Holds the values memoized for a key until none came for a window, then
+/// memoizes only the last one, e.g. for bursts of intermediate values
+/// nobody will read.
+///
+/// Values due are memoized by the next call on the wrapper, or right away
+/// by [`DebouncedMemory::flush`]. Retrieval gives the held value of a key
+/// meanwhile. Dropping the wrapper flushes it.
+pub struct DebouncedMemory<T: Clone, M: Memory<T>> {
+    memory: M,
+    window: std::time::Duration,
+    held: Mutex<HashMap<String, (T, Instant)>>,
+}
+impl<T: Clone, M: Memory<T>> DebouncedMemory<T, M> {
+    pub fn new(memory: M, window: Duration) -> Self {
+        Self {
+            memory,
+            window: window.unsigned_abs(),
+            held: Default::default(),
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// Memoizes every value held, due or not.
+    pub fn flush(&self) {
+        let held = std::mem::take(&mut *self.held.lock());
+        for (key, (value, _)) in held {
+            self.memory.memoize(&key, value);
+        }
+    }
+    /// Memoizes the values no newer one came for within the window.
+    pub fn commit_due(&self) {
+        let due = {
+            let mut held = self.held.lock();
+            let now = Instant::now();
+            let keys = held
+                .iter()
+                .filter(|(_, (_, since))| now.duration_since(*since) >= self.window)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            keys.into_iter()
+                .filter_map(|key| held.remove_entry(&key))
+                .collect::<Vec<_>>()
+        };
+        for (key, (value, _)) in due {
+            self.memory.memoize(&key, value);
+        }
+    }
+}
+impl<T: Clone, M: Memory<T>> Memory<T> for DebouncedMemory<T, M> {
+    fn memoize(&self, key: &str, value: T) {
+        self.commit_due();
+        self.held
+            .lock()
+            .insert(key.to_string(), (value, Instant::now()));
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.commit_due();
+        let held = self.held.lock().get(key).map(|(value, _)| value.clone());
+        held.or_else(|| self.memory.retrieve(key))
+    }
+    fn forget(&self) {
+        self.commit_due();
+        self.memory.forget();
+    }
+}
+impl<T: Clone, M: Memory<T>> Drop for DebouncedMemory<T, M> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::RecordingMemory;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn debounce() {
+        let memory = DebouncedMemory::new(RecordingMemory::new(), 5.milliseconds());
+        for value in 0..3 {
+            memory.memoize("ui", value);
+        }
+        assert_eq!(memory.retrieve("ui"), Some(2));
+        memory.memory().assert_not_memoized("ui");
+        std::thread::sleep(std::time::Duration::from_millis(6));
+        memory.memoize("other", 0);
+        assert_eq!(memory.memory().memoized("ui"), vec![2]);
+
+        memory.flush();
+        memory.memory().assert_memoized_with("other", &0);
+    }
+}
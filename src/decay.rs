@@ -0,0 +1,104 @@
+use crate::wall_clock::{self, Timestamp};
+use crate::{Brain, Duration, Engram, StorageBackend};
+use std::sync::atomic::Ordering;
+
+/// Retention by a forgetting curve, see [`BrainBuilder::decay`].
+///
+/// [`BrainBuilder::decay`]: crate::BrainBuilder::decay
+#[derive(Clone, Copy)]
+pub(crate) struct Decay {
+    pub(crate) half_life: Duration,
+    pub(crate) threshold: f64,
+}
+impl Decay {
+    /// Halves with every half-life since the engram was memoized, the
+    /// half-life growing by itself with every retrieval, and scaled from a
+    /// half to twice by the salience.
+    pub(crate) fn strength<T>(&self, engram: &Engram<T>, now: Timestamp) -> f64 {
+        let hits = engram.hits.load(Ordering::Relaxed) as f64;
+        let salience = 2f64.powf(2.0 * engram.salience - 1.0);
+        let half_life = wall_clock::seconds(self.half_life) * (1.0 + hits) * salience;
+        let age = wall_clock::seconds(now - engram.memoized).max(0.0);
+        0.5f64.powf(age / half_life)
+    }
+    pub(crate) fn is_forgotten<T>(&self, engram: &Engram<T>, now: Timestamp) -> bool {
+        self.strength(engram, now) < self.threshold
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// How strong the memory of the key is, from 1 when just memoized down
+    /// to 0, `None` unless the brain decays or there's no value.
+    pub fn strength(&self, key: &str) -> Option<f64> {
+        let decay = self.decay?;
+        let memory = self.memory.read();
+        Some(decay.strength(memory.get(key)?, wall_clock::now()))
+    }
+    /// Raises the salience of the value by the amount, up to 1, so it's
+    /// forgotten later and evicted last, e.g. for feedback that it was
+    /// good, giving the new salience.
+    ///
+    /// See [`Brain::memoize_with_salience`].
+    pub fn strengthen(&self, key: &str, amount: f64) -> Option<f64> {
+        self.adjust_salience(key, amount)
+    }
+    /// Lowers the salience of the value by the amount, down to 0, so it's
+    /// forgotten sooner and evicted first, giving the new salience.
+    pub fn weaken(&self, key: &str, amount: f64) -> Option<f64> {
+        self.adjust_salience(key, -amount)
+    }
+    fn adjust_salience(&self, key: &str, amount: f64) -> Option<f64> {
+        let mut salience = None;
+        self.memory.write().update(key, &mut |engram| {
+            engram.salience = (engram.salience + amount).clamp(0.0, 1.0);
+            salience = Some(engram.salience);
+        });
+        salience
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration};
+
+    #[test]
+    fn decay() {
+        let brain = Brain::builder()
+            .retention(1.hours())
+            .decay(4.milliseconds(), 0.5)
+            .build();
+        brain.memoize("once", 1);
+        brain.memoize("often", 2);
+        for _ in 0..3 {
+            brain.retrieve("often");
+        }
+        assert!(brain.strength("once").unwrap() > 0.5);
+        assert_eq!(brain.strength("missing"), None);
+
+        std::thread::sleep(std::time::Duration::from_millis(6));
+        assert!(brain.strength("once").unwrap() < 0.5);
+        brain.forget();
+        assert!(!brain.is_cached("once"));
+        assert!(brain.is_cached("often"));
+        assert_eq!(Brain::<u8>::new(1.hours()).strength("a"), None);
+    }
+
+    #[test]
+    fn feedback() {
+        let brain = Brain::builder()
+            .retention(1.hours())
+            .decay(4.milliseconds(), 0.5)
+            .build();
+        brain.memoize("good", 1);
+        brain.memoize("bad", 2);
+        assert_eq!(brain.strengthen("good", 0.5), Some(1.0));
+        assert_eq!(brain.weaken("bad", 0.25), Some(0.25));
+        assert_eq!(brain.weaken("bad", 1.0), Some(0.0));
+        assert_eq!(brain.strengthen("missing", 0.5), None);
+
+        std::thread::sleep(std::time::Duration::from_millis(3));
+        brain.forget();
+        assert!(brain.is_cached("good"));
+        assert!(!brain.is_cached("bad"));
+    }
+}
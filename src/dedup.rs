@@ -0,0 +1,79 @@
+use crate::{Brain, StorageBackend};
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// What a [`Brain::dedup`] pass did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dedup {
+    /// Entries now sharing a value equal to theirs.
+    pub merged: usize,
+    /// Bytes of the values freed by sharing, as weighed.
+    pub reclaimed_bytes: usize,
+}
+
+impl<V: Eq + Hash, S: StorageBackend<String, Arc<V>>> Brain<Arc<V>, S> {
+    /// Makes entries with equal values share one, e.g. the same large
+    /// payloads memoized under many keys, weighing the values to tell how
+    /// many bytes were freed.
+    ///
+    /// Values still shared beyond the brain aren't freed and so don't
+    /// count. Holds the brain's write lock for the pass.
+    pub fn dedup(&self, weigh: impl Fn(&V) -> usize) -> Dedup {
+        let mut memory = self.memory.write();
+        let keys = memory
+            .scan()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let mut canonical = HashSet::<Arc<V>>::new();
+        let mut dedup = Dedup::default();
+        for key in keys {
+            memory.update(&key, &mut |engram| {
+                let Some(shared) = canonical.get(&*engram.value) else {
+                    canonical.insert(engram.value.clone());
+                    return;
+                };
+                if Arc::ptr_eq(shared, &engram.value) {
+                    return;
+                }
+                if Arc::strong_count(&engram.value) == 1 {
+                    dedup.reclaimed_bytes += weigh(&engram.value);
+                }
+                engram.value = shared.clone();
+                dedup.merged += 1;
+            });
+        }
+        dedup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn dedup() {
+        let brain = Brain::new(1.minutes());
+        for key in ["a", "b", "c"] {
+            brain.memoize(key, Arc::new(vec![7u8; 1000]));
+        }
+        brain.memoize("d", Arc::new(vec![1u8; 10]));
+
+        let dedup = brain.dedup(|payload| payload.len());
+        assert_eq!(dedup.merged, 2);
+        assert_eq!(dedup.reclaimed_bytes, 2000);
+        let (a, b) = (brain.retrieve("a").unwrap(), brain.retrieve("b").unwrap());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(brain.dedup(|payload| payload.len()), Dedup::default());
+
+        // values still held outside aren't freed
+        brain.memoize("e", Arc::new(vec![2u8; 100]));
+        brain.memoize("f", Arc::new(vec![2u8; 100]));
+        let held = (brain.retrieve("e").unwrap(), brain.retrieve("f").unwrap());
+        let dedup = brain.dedup(|payload| payload.len());
+        assert_eq!(dedup.merged, 1);
+        assert_eq!(dedup.reclaimed_bytes, 0);
+        assert_eq!(held.0, held.1);
+    }
+}
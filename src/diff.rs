@@ -0,0 +1,187 @@
+use crate::snapshot::{self, encode_binary, read_snapshot, record, write_atomically, Engrams};
+use crate::wall_clock;
+use crate::{Brain, Engram, LoadOptions, StorageBackend};
+use std::io;
+use std::path::Path;
+
+/// What changed between two snapshots of brains, see [`diff`].
+///
+/// The engrams keep the time they were memoized. Diffs are serializable, or
+/// saved as a delta by [`BrainDiff::save_to`], to ship them instead of
+/// whole snapshots.
+pub struct BrainDiff<T> {
+    /// Engrams only the newer snapshot holds, by key.
+    pub added: Vec<(String, Engram<T>)>,
+    /// Engrams memoized anew since the older snapshot, as they are now.
+    pub changed: Vec<(String, Engram<T>)>,
+    /// Keys only the older snapshot holds.
+    pub removed: Vec<String>,
+}
+impl<T> BrainDiff<T> {
+    /// Tells whether the snapshots hold the same engrams.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+impl<T: serde::Serialize> BrainDiff<T> {
+    /// Saves the diff as a delta, which [`Brain::apply_delta_from`] applies
+    /// to a brain restored from the older snapshot.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut payload = Vec::new();
+        for (key, engram) in self.added.iter().chain(&self.changed) {
+            record(&mut payload, &(key, Some(engram)))?;
+        }
+        for key in &self.removed {
+            record(&mut payload, &(key, None::<&Engram<T>>))?;
+        }
+        write_atomically(
+            path.as_ref(),
+            &encode_binary(snapshot::DELTA_MAGIC, None, payload)?,
+            Default::default(),
+        )
+    }
+}
+
+/// Diffs the engrams of two brains, from `older` to `newer`.
+///
+/// Engrams count as changed if they were memoized at other times, with
+/// other retention or values. Expired engrams not forgotten yet count too.
+pub fn diff<T, A, B>(older: &Brain<T, A>, newer: &Brain<T, B>) -> BrainDiff<T>
+where
+    T: Clone + PartialEq,
+    A: StorageBackend<String, T>,
+    B: StorageBackend<String, T>,
+{
+    let (older, newer) = (older.memory.read(), newer.memory.read());
+    let mut diff = BrainDiff {
+        added: Vec::new(),
+        changed: Vec::new(),
+        removed: Vec::new(),
+    };
+    for (key, engram) in newer.scan() {
+        match older.get(key) {
+            None => diff.added.push((key.clone(), copied(engram))),
+            Some(old) if !same(old, engram) => diff.changed.push((key.clone(), copied(engram))),
+            Some(_) => {}
+        }
+    }
+    for (key, _) in older.scan() {
+        if newer.get(key).is_none() {
+            diff.removed.push(key.clone());
+        }
+    }
+    diff.added.sort_by(|a, b| a.0.cmp(&b.0));
+    diff.changed.sort_by(|a, b| a.0.cmp(&b.0));
+    diff.removed.sort();
+    diff
+}
+
+/// Diffs two snapshot files, JSON or binary, see [`diff`].
+pub fn diff_files<T: Clone + PartialEq + serde::de::DeserializeOwned>(
+    older: impl AsRef<Path>,
+    newer: impl AsRef<Path>,
+    options: LoadOptions<'_>,
+) -> io::Result<BrainDiff<T>> {
+    let brain = |engrams: Engrams<T>| {
+        let brain = Brain::<T>::default();
+        *brain.memory.write() = engrams.into_iter().collect();
+        brain
+    };
+    let older = brain(read_snapshot(older.as_ref(), options)?);
+    let newer = brain(read_snapshot(newer.as_ref(), options)?);
+    Ok(diff(&older, &newer))
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Applies a diff, e.g. to a brain restored from the older snapshot.
+    ///
+    /// Engrams which expired meanwhile are skipped, and forget what the
+    /// brain holds for their keys.
+    pub fn apply_diff(&self, diff: BrainDiff<T>) {
+        let now = wall_clock::now();
+        let mut memory = self.memory.write();
+        for (key, engram) in diff.added.into_iter().chain(diff.changed) {
+            if engram.is_expired(self.retention, now) {
+                memory.remove(&key);
+            } else {
+                self.filter_key(&key);
+                memory.put(key, engram);
+            }
+        }
+        for key in diff.removed {
+            memory.remove(&key);
+        }
+    }
+}
+
+fn same<T: PartialEq>(a: &Engram<T>, b: &Engram<T>) -> bool {
+    a.memoized == b.memoized && a.retention == b.retention && a.value == b.value
+}
+
+fn copied<T: Clone>(engram: &Engram<T>) -> Engram<T> {
+    Engram {
+        memoized: engram.memoized,
+        retention: engram.retention,
+        actor: engram.actor,
+        ..Engram::new(engram.value.clone(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn diff_and_apply() {
+        let older = Brain::new(1.minutes());
+        older.memoize("kept", 1);
+        older.memoize("changed", 2);
+        older.memoize("removed", 3);
+        let replica = Brain::new(1.minutes());
+        replica.merge_crdt(&older);
+
+        let newer = Brain::new(1.minutes());
+        newer.merge_crdt(&older);
+        newer.memoize("changed", 20);
+        newer.memoize("added", 4);
+        newer.memory.write().remove("removed");
+
+        let diff = diff(&older, &newer);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].0, "added");
+        assert_eq!(diff.changed[0].1.value(), &20);
+        assert_eq!(diff.removed, ["removed"]);
+
+        replica.apply_diff(diff);
+        for key in ["kept", "changed", "removed", "added"] {
+            assert_eq!(replica.retrieve(key), newer.retrieve(key), "{key}");
+        }
+        assert!(super::diff(&replica, &newer).is_empty());
+    }
+
+    #[test]
+    fn save_to() {
+        let dir = std::env::temp_dir();
+        let [older_path, newer_path, delta] = ["older", "newer", "delta"]
+            .map(|name| dir.join(format!("brain-{}-diff-{name}", std::process::id())));
+        let brain = Brain::new(1.minutes());
+        brain.memoize("a", 1);
+        brain.memoize("b", 2);
+        brain.save_binary_to(&older_path).unwrap();
+        let restored = Brain::<i32>::load_from(&older_path, 1.minutes()).unwrap();
+        brain.memoize("b", 3);
+        brain.save_to(&newer_path).unwrap();
+
+        let diff = diff_files::<i32>(&older_path, &newer_path, LoadOptions::new()).unwrap();
+        diff.save_to(&delta).unwrap();
+        restored
+            .apply_delta_from(&delta, LoadOptions::new())
+            .unwrap();
+        for path in [older_path, newer_path, delta] {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(restored.retrieve("b"), Some(3));
+        assert_eq!(restored.retrieve("a"), Some(1));
+    }
+}
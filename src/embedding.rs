@@ -0,0 +1,100 @@
+use crate::wall_clock;
+use crate::{Brain, Engram, StorageBackend};
+
+/// Cosine of the angle between two vectors, 0 if either is zero or their
+/// lengths differ.
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    let norms =
+        a.iter().map(|a| a * a).sum::<f32>().sqrt() * b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Memoizes a value with an embedding of it, e.g. of the text an agent
+    /// remembers, to be found by [`Brain::retrieve_similar`].
+    ///
+    /// Memoizing the key again without an embedding drops it.
+    pub fn memoize_with_embedding(&self, key: &str, value: T, embedding: Vec<f32>) {
+        self.store_if(key, Engram::new(value, None), |_| true);
+        self.embeddings.write().insert(key.to_string(), embedding);
+    }
+    /// Retrieves the `k` values whose embeddings are the most similar to
+    /// the query by cosine similarity, most similar first, leaving out
+    /// expired values.
+    ///
+    /// Compares the query with every embedding, which is exact and fine for
+    /// the thousands of entries of a short-term memory.
+    pub fn retrieve_similar(&self, query: &[f32], k: usize) -> Vec<(String, T, f32)> {
+        let now = wall_clock::now();
+        let memory = self.memory.read();
+        let mut similar = self
+            .embeddings
+            .read()
+            .iter()
+            .filter_map(|(key, embedding)| {
+                let engram = memory
+                    .get(key)
+                    .filter(|engram| !engram.is_expired(self.retention, now))
+                    .filter(|engram| self.is_context_active(engram))?;
+                let similarity = cosine_similarity(query, embedding);
+                Some((key.clone(), engram.value.clone(), similarity))
+            })
+            .collect::<Vec<_>>();
+        similar.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        similar.truncate(k);
+        similar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn similar() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize_with_embedding("cat", "a cat", vec![1.0, 0.1, 0.0]);
+        brain.memoize_with_embedding("dog", "a dog", vec![0.8, 0.5, 0.0]);
+        brain.memoize_with_embedding("car", "a car", vec![0.0, 0.0, 1.0]);
+        brain.memoize_for("old", "gone", 1.milliseconds());
+        brain
+            .embeddings
+            .write()
+            .insert("old".into(), vec![1.0, 0.0, 0.0]);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        let similar = brain.retrieve_similar(&[1.0, 0.0, 0.0], 2);
+        let keys = similar
+            .iter()
+            .map(|(key, ..)| key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["cat", "dog"]);
+        assert_eq!(similar[0].1, "a cat");
+        assert!(similar[0].2 > similar[1].2);
+
+        brain.memoize("cat", "no embedding");
+        brain.remove("dog");
+        let keys = brain.retrieve_similar(&[1.0, 0.0, 0.0], 3);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].0, "car");
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+
+        brain.memoize_in_context("chat", "car", "a red car");
+        brain
+            .embeddings
+            .write()
+            .insert("car".into(), vec![0.0, 0.0, 1.0]);
+        assert!(brain.retrieve_similar(&[0.0, 0.0, 1.0], 3).is_empty());
+        brain.activate_context("chat");
+        assert_eq!(brain.retrieve_similar(&[0.0, 0.0, 1.0], 3).len(), 1);
+    }
+}
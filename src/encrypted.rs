@@ -0,0 +1,160 @@
+use crate::Memory;
+
+/// An authenticated cipher with its key, e.g. AES-GCM or ChaCha20-Poly1305
+/// from a crypto crate, sealing the values of an [`EncryptedMemory`].
+pub trait Aead: Send + Sync {
+    /// Length of the nonces in bytes, at least 12, 12 by default.
+    fn nonce_len(&self) -> usize {
+        12
+    }
+    /// Fills a nonce with bytes of a cryptographically secure random number
+    /// generator, e.g. `OsRng` or `getrandom`.
+    ///
+    /// Nonces are entirely random, so they don't repeat across wrappers,
+    /// restarts and processes sharing a key. Neither a clock nor a counter
+    /// may stand in for the generator.
+    fn fill_nonce(&self, nonce: &mut [u8]);
+    /// Encrypts the plaintext and appends the tag authenticating it with
+    /// the associated data.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8>;
+    /// Decrypts the ciphertext, `None` unless the tag authenticates it with
+    /// the associated data.
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Encrypts values before memoizing them into a memory of bytes, and
+/// decrypts them when retrieved, so they neither sit in plaintext in the
+/// memory nor in what it persists.
+///
+/// Every value is sealed with a fresh random nonce of the cipher, stored in
+/// front of it. The key of a value is its associated data, so values moved
+/// to other keys don't decrypt. Values which don't decrypt are retrieved as
+/// misses.
+pub struct EncryptedMemory<M, C> {
+    memory: M,
+    cipher: C,
+}
+impl<M, C: Aead> EncryptedMemory<M, C> {
+    pub fn new(memory: M, cipher: C) -> Self {
+        assert!(cipher.nonce_len() >= 12, "nonces need at least 12 bytes");
+        Self { memory, cipher }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    fn nonce(&self) -> Vec<u8> {
+        let mut nonce = vec![0; self.cipher.nonce_len()];
+        self.cipher.fill_nonce(&mut nonce);
+        nonce
+    }
+}
+impl<M: Memory<Vec<u8>>, C: Aead> Memory<Vec<u8>> for EncryptedMemory<M, C> {
+    fn memoize(&self, key: &str, value: Vec<u8>) {
+        let mut sealed = self.nonce();
+        let ciphertext = self.cipher.seal(&sealed, key.as_bytes(), &value);
+        sealed.extend_from_slice(&ciphertext);
+        self.memory.memoize(key, sealed);
+    }
+    fn retrieve(&self, key: &str) -> Option<Vec<u8>> {
+        let sealed = self.memory.retrieve(key)?;
+        let nonce_len = self.cipher.nonce_len();
+        if sealed.len() < nonce_len {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(nonce_len);
+        self.cipher.open(nonce, key.as_bytes(), ciphertext)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+    use parking_lot::Mutex;
+
+    /// Not a cipher at all, only shaped like one.
+    struct Scramble(u8, Mutex<u64>);
+    impl Scramble {
+        fn tag(&self, nonce: &[u8], aad: &[u8], text: &[u8]) -> u8 {
+            nonce
+                .iter()
+                .chain(aad)
+                .chain(text)
+                .fold(self.0, |tag, byte| tag.rotate_left(3) ^ byte)
+        }
+    }
+    impl Aead for Scramble {
+        fn fill_nonce(&self, nonce: &mut [u8]) {
+            // xorshift, as unpredictable as the cipher is secret
+            let mut state = self.1.lock();
+            for byte in nonce {
+                *state ^= *state << 13;
+                *state ^= *state >> 7;
+                *state ^= *state << 17;
+                *byte = *state as u8;
+            }
+        }
+        fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+            let mut sealed = plaintext
+                .iter()
+                .map(|byte| byte ^ self.0)
+                .collect::<Vec<_>>();
+            sealed.push(self.tag(nonce, aad, plaintext));
+            sealed
+        }
+        fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+            let (tag, ciphertext) = ciphertext.split_last()?;
+            let plaintext = ciphertext
+                .iter()
+                .map(|byte| byte ^ self.0)
+                .collect::<Vec<_>>();
+            (self.tag(nonce, aad, &plaintext) == *tag).then_some(plaintext)
+        }
+    }
+
+    #[test]
+    fn encrypted() {
+        let brain = Brain::<Vec<u8>>::new(1.minutes());
+        let memory = EncryptedMemory::new(brain.clone(), Scramble(0x5a, Mutex::new(7)));
+        memory.memoize("token", b"secret".to_vec());
+        memory.memoize("other", b"secret".to_vec());
+        let sealed = brain.retrieve("token").unwrap();
+        assert_eq!(sealed.len(), 12 + 6 + 1);
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        assert_ne!(sealed[..12], brain.retrieve("other").unwrap()[..12]);
+        assert_eq!(memory.retrieve("token"), Some(b"secret".to_vec()));
+
+        // moved to another key, the value doesn't authenticate
+        brain.memoize("moved", sealed);
+        assert_eq!(memory.retrieve("moved"), None);
+        brain.memoize("short", vec![1]);
+        assert_eq!(memory.retrieve("short"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "nonces need at least 12 bytes")]
+    fn short_nonces() {
+        struct Short(Scramble);
+        impl Aead for Short {
+            fn nonce_len(&self) -> usize {
+                8
+            }
+            fn fill_nonce(&self, nonce: &mut [u8]) {
+                self.0.fill_nonce(nonce)
+            }
+            fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+                self.0.seal(nonce, aad, plaintext)
+            }
+            fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+                self.0.open(nonce, aad, ciphertext)
+            }
+        }
+        EncryptedMemory::new(
+            Brain::<Vec<u8>>::new(1.minutes()),
+            Short(Scramble(0, Mutex::new(1))),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{Brain, EvictionCause, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+type Watcher = Box<dyn FnOnce(EvictionCause) + Send>;
+
+/// Callbacks waiting for the entries of keys to leave the brain.
+#[derive(Default)]
+pub(crate) struct Watchers {
+    /// Number of keys watched, for brains nobody watches to skip the lock.
+    watched: AtomicUsize,
+    watchers: Mutex<HashMap<String, Vec<Watcher>>>,
+}
+impl Watchers {
+    /// Calls the watchers of the key, the brain unlocked.
+    pub(crate) fn notify(&self, key: &str, cause: EvictionCause) {
+        if self.watched.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let Some(watchers) = self.watchers.lock().remove(key) else {
+            return;
+        };
+        self.watched.fetch_sub(1, Ordering::Relaxed);
+        for watcher in watchers {
+            watcher(cause);
+        }
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Calls back once the entry for the key leaves the brain, with why,
+    /// telling whether there is an entry to watch.
+    ///
+    /// Entries leave when forgotten after expiring, evicted or removed, not
+    /// when memoized over. Expired entries leave at the next sweep, see
+    /// [`Brain::start_maintenance`]. The callback runs on the thread
+    /// removing the entry, after the brain released its lock.
+    pub fn on_expire(
+        &self,
+        key: &str,
+        callback: impl FnOnce(EvictionCause) + Send + 'static,
+    ) -> bool {
+        // removals notify after unlocking, so none is missed while locked
+        let memory = self.memory.read();
+        if memory.get(key).is_none() {
+            return false;
+        }
+        let mut watchers = self.watchers.watchers.lock();
+        let watching = watchers.entry(key.to_string()).or_default();
+        if watching.is_empty() {
+            self.watchers.watched.fetch_add(1, Ordering::Relaxed);
+        }
+        watching.push(Box::new(callback));
+        true
+    }
+    /// Completes once the entry for the key leaves the brain, with why, or
+    /// right away with `None` if there's none, see [`Brain::on_expire`].
+    pub fn expire_notified(&self, key: &str) -> ExpireNotified {
+        let state = Arc::new(Mutex::new(Notification::default()));
+        let notified = state.clone();
+        let watching = self.on_expire(key, move |cause| {
+            let mut notified = notified.lock();
+            notified.cause = Some(cause);
+            if let Some(waker) = notified.waker.take() {
+                waker.wake();
+            }
+        });
+        if !watching {
+            state.lock().done = true;
+        }
+        ExpireNotified { state }
+    }
+}
+
+#[derive(Default)]
+struct Notification {
+    cause: Option<EvictionCause>,
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// Completes once an entry left the brain, see [`Brain::expire_notified`].
+pub struct ExpireNotified {
+    state: Arc<Mutex<Notification>>,
+}
+impl Future for ExpireNotified {
+    type Output = Option<EvictionCause>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock();
+        if let Some(cause) = state.cause {
+            return Poll::Ready(Some(cause));
+        }
+        if state.done {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+    use std::sync::mpsc;
+
+    #[test]
+    fn on_expire() {
+        let brain = Brain::new(1.milliseconds());
+        let (sender, causes) = mpsc::channel();
+        assert!(!brain.on_expire("a", |_| {}));
+        brain.memoize("a", 1);
+        brain.memoize("b", 2);
+        let expired = sender.clone();
+        assert!(brain.on_expire("a", move |cause| expired.send(("a", cause)).unwrap()));
+        assert!(brain.on_expire("b", move |cause| sender.send(("b", cause)).unwrap()));
+
+        brain.memoize("a", 3);
+        brain.remove("b");
+        assert_eq!(causes.try_recv(), Ok(("b", EvictionCause::Removed)));
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.forget();
+        assert_eq!(causes.try_recv(), Ok(("a", EvictionCause::Expired)));
+        assert!(causes.try_recv().is_err());
+    }
+
+    #[test]
+    fn expire_notified() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(1)
+            .build();
+        let mut missing = brain.expire_notified("a");
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        assert_eq!(Pin::new(&mut missing).poll(&mut cx), Poll::Ready(None));
+
+        brain.memoize("a", 1);
+        let mut notified = brain.expire_notified("a");
+        assert_eq!(Pin::new(&mut notified).poll(&mut cx), Poll::Pending);
+        brain.memoize("b", 2);
+        assert_eq!(
+            Pin::new(&mut notified).poll(&mut cx),
+            Poll::Ready(Some(EvictionCause::Capacity))
+        );
+    }
+}
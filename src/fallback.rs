@@ -0,0 +1,104 @@
+use crate::Memory;
+
+/// Which memories of a [`FallbackMemory`] values are memoized into.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Only into the first memory.
+    #[default]
+    First,
+    /// Only into the second memory, the first one getting values as they
+    /// are promoted.
+    Second,
+    /// Into both memories, the second one first.
+    Both,
+}
+
+/// Retrieves from a first memory and falls back to a second one, e.g. a
+/// small hot brain in front of a big persistent one.
+///
+/// Hits of the second memory are promoted into the first one, unless turned
+/// off with [`FallbackMemory::promote`].
+pub struct FallbackMemory<A, B> {
+    first: A,
+    second: B,
+    promote: bool,
+    write: WritePolicy,
+}
+impl<A, B> FallbackMemory<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            promote: true,
+            write: WritePolicy::default(),
+        }
+    }
+    /// Whether hits of the second memory are memoized into the first one.
+    pub fn promote(mut self, promote: bool) -> Self {
+        self.promote = promote;
+        self
+    }
+    pub fn write_policy(mut self, write: WritePolicy) -> Self {
+        self.write = write;
+        self
+    }
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+}
+impl<T: Clone, A: Memory<T>, B: Memory<T>> Memory<T> for FallbackMemory<A, B> {
+    fn memoize(&self, key: &str, value: T) {
+        match self.write {
+            WritePolicy::First => self.first.memoize(key, value),
+            WritePolicy::Second => self.second.memoize(key, value),
+            WritePolicy::Both => {
+                self.second.memoize(key, value.clone());
+                self.first.memoize(key, value);
+            }
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.first.retrieve(key).or_else(|| {
+            let value = self.second.retrieve(key)?;
+            if self.promote {
+                self.first.memoize(key, value.clone());
+            }
+            Some(value)
+        })
+    }
+    fn forget(&self) {
+        self.first.forget();
+        self.second.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn fallback() {
+        let memory = FallbackMemory::new(Brain::new(1.minutes()), Brain::new(1.minutes()));
+        memory.memoize("a", 1);
+        assert!(!memory.second().is_cached("a"));
+        memory.second().memoize("b", 2);
+        assert_eq!(memory.retrieve("b"), Some(2));
+        assert_eq!(memory.first().retrieve("b"), Some(2));
+        assert_eq!(memory.retrieve("c"), None);
+
+        let memory = FallbackMemory::new(Brain::new(1.minutes()), Brain::new(1.minutes()))
+            .promote(false)
+            .write_policy(WritePolicy::Second);
+        memory.memoize("a", 1);
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert!(!memory.first().is_cached("a"));
+
+        let memory = memory.write_policy(WritePolicy::Both);
+        memory.memoize("b", 2);
+        assert!(memory.first().is_cached("b") && memory.second().is_cached("b"));
+    }
+}
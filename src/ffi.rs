@@ -0,0 +1,370 @@
+//! A C ABI over a brain of bytes, declared by `include/memory.h`.
+//!
+//! Keys and values are passed as pointers and lengths. Keys must be UTF-8.
+//! Brains are owned by the caller from `brain_new` until `brain_free`, and
+//! buffers from `brain_retrieve_bytes` and `brain_canonical_key` until
+//! `brain_bytes_free`.
+//!
+//! Every brain resolves keys through its aliases and rules first, as an
+//! [`AliasedBrain`] does.
+
+use crate::{AliasRule, AliasedBrain, Brain, BrainStats, Duration, Memory};
+use std::collections::HashMap;
+use std::ptr;
+
+/// Returned by functions which succeeded.
+pub const BRAIN_OK: i32 = 0;
+/// Returned for a null brain or out parameter, or a key which isn't UTF-8.
+pub const BRAIN_INVALID: i32 = -1;
+/// Returned by [`brain_retrieve_bytes`] for a miss.
+pub const BRAIN_MISSING: i32 = 1;
+
+/// Creates a brain keeping values for the retention in milliseconds, to be
+/// freed with [`brain_free`].
+#[no_mangle]
+pub extern "C" fn brain_new(retention_millis: u64) -> *mut AliasedBrain<Vec<u8>> {
+    let brain = Brain::new(millis(retention_millis));
+    Box::into_raw(Box::new(AliasedBrain::new(brain, HashMap::new())))
+}
+
+/// Frees a brain, doing nothing for null.
+///
+/// # Safety
+///
+/// The brain must come from [`brain_new`], not be freed already, and not be
+/// used by other threads meanwhile or afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn brain_free(brain: *mut AliasedBrain<Vec<u8>>) {
+    if !brain.is_null() {
+        drop(Box::from_raw(brain));
+    }
+}
+
+/// Memoizes a copy of the value, giving [`BRAIN_OK`] or [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// The brain must be alive, and the key and value valid for reads of their
+/// lengths, or those be 0.
+#[no_mangle]
+pub unsafe extern "C" fn brain_memoize_bytes(
+    brain: *const AliasedBrain<Vec<u8>>,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+) -> i32 {
+    let (Some(brain), Some(key)) = (brain.as_ref(), text(key, key_len)) else {
+        return BRAIN_INVALID;
+    };
+    brain.memoize(key, bytes(value, value_len).to_vec());
+    BRAIN_OK
+}
+
+/// Memoizes a copy of the value with its own retention in milliseconds,
+/// giving [`BRAIN_OK`] or [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// As for [`brain_memoize_bytes`].
+#[no_mangle]
+pub unsafe extern "C" fn brain_memoize_bytes_for(
+    brain: *const AliasedBrain<Vec<u8>>,
+    key: *const u8,
+    key_len: usize,
+    value: *const u8,
+    value_len: usize,
+    retention_millis: u64,
+) -> i32 {
+    let (Some(brain), Some(key)) = (brain.as_ref(), text(key, key_len)) else {
+        return BRAIN_INVALID;
+    };
+    brain.brain().memoize_for(
+        &brain.canonical_key(key),
+        bytes(value, value_len).to_vec(),
+        millis(retention_millis),
+    );
+    BRAIN_OK
+}
+
+/// Retrieves a copy of the value and its length, giving [`BRAIN_OK`],
+/// [`BRAIN_MISSING`] with null and 0 for a miss, or [`BRAIN_INVALID`] for a
+/// null brain, value or length, or a key which isn't UTF-8.
+///
+/// The copy is to be freed with [`brain_bytes_free`], even if empty.
+///
+/// # Safety
+///
+/// The brain must be alive, the key valid for reads of its length, or that
+/// be 0, and the value and length valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brain_retrieve_bytes(
+    brain: *const AliasedBrain<Vec<u8>>,
+    key: *const u8,
+    key_len: usize,
+    value: *mut *mut u8,
+    value_len: *mut usize,
+) -> i32 {
+    let (Some(value), Some(value_len)) = (value.as_mut(), value_len.as_mut()) else {
+        return BRAIN_INVALID;
+    };
+    (*value, *value_len) = (ptr::null_mut(), 0);
+    let (Some(brain), Some(key)) = (brain.as_ref(), text(key, key_len)) else {
+        return BRAIN_INVALID;
+    };
+    let Some(retrieved) = brain.retrieve(key) else {
+        return BRAIN_MISSING;
+    };
+    let retrieved = retrieved.into_boxed_slice();
+    *value_len = retrieved.len();
+    *value = Box::into_raw(retrieved).cast();
+    BRAIN_OK
+}
+
+/// Frees a copy from [`brain_retrieve_bytes`] or [`brain_canonical_key`],
+/// doing nothing for null.
+///
+/// # Safety
+///
+/// The copy must come from one of them with the length it wrote, and not be
+/// freed already.
+#[no_mangle]
+pub unsafe extern "C" fn brain_bytes_free(value: *mut u8, value_len: usize) {
+    if !value.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            value, value_len,
+        )));
+    }
+}
+
+/// Forgets the expired values, doing nothing for null.
+///
+/// # Safety
+///
+/// The brain must be alive.
+#[no_mangle]
+pub unsafe extern "C" fn brain_forget(brain: *const AliasedBrain<Vec<u8>>) {
+    if let Some(brain) = brain.as_ref() {
+        brain.forget();
+    }
+}
+
+/// Writes the brain's counters, giving [`BRAIN_OK`] or [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// The brain must be alive and the counters valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn brain_stats(
+    brain: *const AliasedBrain<Vec<u8>>,
+    stats: *mut BrainStats,
+) -> i32 {
+    let (Some(brain), Some(stats)) = (brain.as_ref(), stats.as_mut()) else {
+        return BRAIN_INVALID;
+    };
+    *stats = brain.brain().stats();
+    BRAIN_OK
+}
+
+/// Makes the alias stand for the key, giving [`BRAIN_OK`] or
+/// [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// The brain must be alive, and the alias and key valid for reads of their
+/// lengths, or those be 0.
+#[no_mangle]
+pub unsafe extern "C" fn brain_add_alias(
+    brain: *const AliasedBrain<Vec<u8>>,
+    alias: *const u8,
+    alias_len: usize,
+    key: *const u8,
+    key_len: usize,
+) -> i32 {
+    let (Some(brain), Some(alias), Some(key)) =
+        (brain.as_ref(), text(alias, alias_len), text(key, key_len))
+    else {
+        return BRAIN_INVALID;
+    };
+    brain.add_alias(alias, key);
+    BRAIN_OK
+}
+
+/// Makes the alias stand for itself again, giving [`BRAIN_OK`],
+/// [`BRAIN_MISSING`] if it wasn't an alias, or [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// The brain must be alive, and the alias valid for reads of its length,
+/// or that be 0.
+#[no_mangle]
+pub unsafe extern "C" fn brain_remove_alias(
+    brain: *const AliasedBrain<Vec<u8>>,
+    alias: *const u8,
+    alias_len: usize,
+) -> i32 {
+    let (Some(brain), Some(alias)) = (brain.as_ref(), text(alias, alias_len)) else {
+        return BRAIN_INVALID;
+    };
+    match brain.remove_alias(alias) {
+        Some(_) => BRAIN_OK,
+        None => BRAIN_MISSING,
+    }
+}
+
+/// Rewrites keys starting with `from` to start with `to` instead, after the
+/// rules added before, see [`AliasRule::Prefix`]. Gives [`BRAIN_OK`] or
+/// [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// As for [`brain_add_alias`].
+#[no_mangle]
+pub unsafe extern "C" fn brain_add_prefix_rule(
+    brain: *const AliasedBrain<Vec<u8>>,
+    from: *const u8,
+    from_len: usize,
+    to: *const u8,
+    to_len: usize,
+) -> i32 {
+    let (Some(brain), Some(from), Some(to)) =
+        (brain.as_ref(), text(from, from_len), text(to, to_len))
+    else {
+        return BRAIN_INVALID;
+    };
+    brain.add_rule(AliasRule::prefix(from, to));
+    BRAIN_OK
+}
+
+/// Rewrites keys matching the pattern into the replacement, after the rules
+/// added before, see [`AliasRule::Pattern`]. Gives [`BRAIN_OK`] or
+/// [`BRAIN_INVALID`].
+///
+/// # Safety
+///
+/// As for [`brain_add_alias`].
+#[no_mangle]
+pub unsafe extern "C" fn brain_add_pattern_rule(
+    brain: *const AliasedBrain<Vec<u8>>,
+    pattern: *const u8,
+    pattern_len: usize,
+    replacement: *const u8,
+    replacement_len: usize,
+) -> i32 {
+    let (Some(brain), Some(pattern), Some(replacement)) = (
+        brain.as_ref(),
+        text(pattern, pattern_len),
+        text(replacement, replacement_len),
+    ) else {
+        return BRAIN_INVALID;
+    };
+    brain.add_rule(AliasRule::pattern(pattern, replacement));
+    BRAIN_OK
+}
+
+/// Writes a copy of the key the alias stands for, by its alias or rule, and
+/// its length, giving [`BRAIN_OK`] or [`BRAIN_INVALID`] as
+/// [`brain_retrieve_bytes`] does.
+///
+/// The copy is to be freed with [`brain_bytes_free`].
+///
+/// # Safety
+///
+/// As for [`brain_retrieve_bytes`].
+#[no_mangle]
+pub unsafe extern "C" fn brain_canonical_key(
+    brain: *const AliasedBrain<Vec<u8>>,
+    alias: *const u8,
+    alias_len: usize,
+    key: *mut *mut u8,
+    key_len: *mut usize,
+) -> i32 {
+    let (Some(key), Some(key_len)) = (key.as_mut(), key_len.as_mut()) else {
+        return BRAIN_INVALID;
+    };
+    (*key, *key_len) = (ptr::null_mut(), 0);
+    let (Some(brain), Some(alias)) = (brain.as_ref(), text(alias, alias_len)) else {
+        return BRAIN_INVALID;
+    };
+    let canonical = brain.canonical_key(alias).into_bytes().into_boxed_slice();
+    *key_len = canonical.len();
+    *key = Box::into_raw(canonical).cast();
+    BRAIN_OK
+}
+
+fn millis(millis: u64) -> Duration {
+    Duration::milliseconds(millis.try_into().unwrap_or(i64::MAX))
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() || len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    }
+}
+
+unsafe fn text<'a>(data: *const u8, len: usize) -> Option<&'a str> {
+    std::str::from_utf8(bytes(data, len)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffi() {
+        unsafe {
+            let brain = brain_new(60_000);
+            let key = "a";
+            let status = brain_memoize_bytes(brain, key.as_ptr(), key.len(), [1, 2].as_ptr(), 2);
+            assert_eq!(status, BRAIN_OK);
+            assert_eq!(
+                brain_memoize_bytes(brain, [0xff].as_ptr(), 1, ptr::null(), 0),
+                BRAIN_INVALID
+            );
+
+            let (mut value, mut len) = (ptr::null_mut(), 0);
+            let status = brain_retrieve_bytes(brain, key.as_ptr(), key.len(), &mut value, &mut len);
+            assert_eq!(status, BRAIN_OK);
+            assert_eq!(std::slice::from_raw_parts(value, len), [1, 2]);
+            brain_bytes_free(value, len);
+            let status = brain_retrieve_bytes(brain, "b".as_ptr(), 1, &mut value, &mut len);
+            assert_eq!((status, value, len), (BRAIN_MISSING, ptr::null_mut(), 0));
+            let status = brain_retrieve_bytes(brain, key.as_ptr(), 1, &mut value, ptr::null_mut());
+            assert_eq!(status, BRAIN_INVALID);
+
+            let status = brain_memoize_bytes_for(brain, "c".as_ptr(), 1, ptr::null(), 0, 0);
+            assert_eq!(status, BRAIN_OK);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            brain_forget(brain);
+            let mut stats = BrainStats::default();
+            assert_eq!(brain_stats(brain, &mut stats), BRAIN_OK);
+            assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 1));
+
+            let alias = |alias: &str, key: &str| {
+                brain_add_alias(brain, alias.as_ptr(), alias.len(), key.as_ptr(), key.len())
+            };
+            assert_eq!(alias("first", key), BRAIN_OK);
+            let status = brain_add_prefix_rule(brain, "v1:".as_ptr(), 3, "".as_ptr(), 0);
+            assert_eq!(status, BRAIN_OK);
+            let status = brain_retrieve_bytes(brain, "v1:a".as_ptr(), 4, &mut value, &mut len);
+            assert_eq!(status, BRAIN_OK);
+            brain_bytes_free(value, len);
+            let status = brain_canonical_key(brain, "first".as_ptr(), 5, &mut value, &mut len);
+            assert_eq!(
+                (status, std::slice::from_raw_parts(value, len)),
+                (BRAIN_OK, &b"a"[..])
+            );
+            brain_bytes_free(value, len);
+            assert_eq!(brain_remove_alias(brain, "first".as_ptr(), 5), BRAIN_OK);
+            assert_eq!(
+                brain_remove_alias(brain, "first".as_ptr(), 5),
+                BRAIN_MISSING
+            );
+
+            brain_free(brain);
+            brain_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::snapshot::write_atomically;
+use crate::wall_clock;
+use crate::{json, Durability, Duration, Engram, Memory, TryMemoize};
+use parking_lot::{Mutex, RwLock};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::ops::Add;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Where an engram lives in the file, and when it expires.
+struct Slot {
+    offset: u64,
+    len: usize,
+    expires: OffsetDateTime,
+}
+
+/// A brain keeping its values in a file and only an index in memory.
+///
+/// Values are read from the file on retrieval, so the operating system's
+/// page cache keeps the hot ones in memory, and the cache can grow larger
+/// than the memory. Memoizing appends to the file, [`FileBrain::compact`]
+/// reclaims the space of replaced and expired engrams.
+pub struct FileBrain<T> {
+    path: PathBuf,
+    file: Mutex<File>,
+    index: RwLock<HashMap<String, Slot>>,
+    retention: Duration,
+    values: PhantomData<fn() -> T>,
+}
+impl<T: Serialize + DeserializeOwned> FileBrain<T> {
+    /// Opens the file at the path, indexing the engrams it holds.
+    pub fn open(path: impl Into<PathBuf>, retention: Duration) -> io::Result<Self> {
+        let path = path.into();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        let mut index = HashMap::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            // a crash while appending leaves a torn last record
+            let Ok((key, engram)) = json::from_str::<(String, Engram<T>)>(line.trim_end()) else {
+                break;
+            };
+            let expires = engram.memoized.add(engram.retention.unwrap_or(retention));
+            index.insert(
+                key,
+                Slot {
+                    offset,
+                    len: line.len(),
+                    expires,
+                },
+            );
+            offset += line.len() as u64;
+        }
+        file.set_len(offset)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            index: RwLock::new(index),
+            retention,
+            values: PhantomData,
+        })
+    }
+    /// Memoizes a value, failing if it couldn't be written.
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.append(key, Engram::new(value, None))
+    }
+    /// Memoizes a value with its own retention, failing if it couldn't be written.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        self.append(key, Engram::new(value, Some(retention)))
+    }
+    /// Retrieves a value, failing if it couldn't be read.
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        let (offset, len) = match self.index.read().get(key) {
+            Some(slot) if slot.expires >= wall_clock::now() => (slot.offset, slot.len),
+            _ => return Ok(None),
+        };
+        let mut line = vec![0; len];
+        {
+            let mut file = self.file.lock();
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut line)?;
+        }
+        let line = String::from_utf8(line).map_err(|error| invalid_data(error.to_string()))?;
+        let (_, engram) = json::from_str::<(String, Engram<T>)>(line.trim_end())
+            .map_err(|error| invalid_data(error.to_string()))?;
+        Ok(Some(engram.value))
+    }
+    pub fn is_cached(&self, key: &str) -> bool {
+        self.index.read().contains_key(key)
+    }
+    /// Rewrites the file to hold only the engrams still alive.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut file = self.file.lock();
+        let mut index = self.index.write();
+        let now = wall_clock::now();
+        index.retain(|_, slot| slot.expires >= now);
+        let mut slots = index.values_mut().collect::<Vec<_>>();
+        slots.sort_by_key(|slot| slot.offset);
+        let mut contents = Vec::new();
+        for slot in slots {
+            let mut line = vec![0; slot.len];
+            file.seek(SeekFrom::Start(slot.offset))?;
+            file.read_exact(&mut line)?;
+            slot.offset = contents.len() as u64;
+            contents.extend(line);
+        }
+        write_atomically(&self.path, &contents, Durability::default())?;
+        *file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        Ok(())
+    }
+    fn append(&self, key: &str, engram: Engram<T>) -> io::Result<()> {
+        let expires = engram
+            .memoized
+            .add(engram.retention.unwrap_or(self.retention));
+        let mut line = json::to_string(&(key, &engram))
+            .map_err(|error| invalid_data(error.to_string()))?
+            .into_bytes();
+        line.push(b'\n');
+        let mut file = self.file.lock();
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(&line)?;
+        self.index.write().insert(
+            key.to_string(),
+            Slot {
+                offset,
+                len: line.len(),
+                expires,
+            },
+        );
+        Ok(())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> TryMemoize<T> for FileBrain<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+
+/// I/O errors are swallowed, memoizing fails silently and retrieving misses,
+/// use the `try_` methods to learn about them.
+impl<T: Serialize + DeserializeOwned> Memory<T> for FileBrain<T> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    /// Forgets expired engrams, their space is reclaimed on compaction.
+    fn forget(&self) {
+        let now = wall_clock::now();
+        self.index.write().retain(|_, slot| slot.expires >= now);
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn file_brain() {
+        let path = std::env::temp_dir().join(format!("brain-{}.engrams", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let memory = FileBrain::open(&path, 1.minutes()).unwrap();
+            memory.memoize("a", "x".to_string());
+            memory.memoize("a", "y".to_string());
+            memory
+                .try_memoize_for("b", "z".to_string(), 1.milliseconds())
+                .unwrap();
+            assert_eq!(memory.retrieve("a"), Some("y".to_string()));
+
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            assert_eq!(memory.retrieve("b"), None);
+            memory.forget();
+            assert!(!memory.is_cached("b"));
+            memory.compact().unwrap();
+            memory.memoize("c", "w".to_string());
+        }
+        let memory = FileBrain::<String>::open(&path, 1.minutes()).unwrap();
+        assert_eq!(memory.retrieve("a"), Some("y".to_string()));
+        assert_eq!(memory.retrieve("c"), Some("w".to_string()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::wall_clock;
+use crate::{Brain, StorageBackend};
+
+/// Edits turning one text into the other, counting characters inserted,
+/// removed or replaced, `None` if more than `max`.
+fn edit_distance(a: &str, b: &str, max: u32) -> Option<u32> {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    if a.len().abs_diff(b.len()) > max as usize {
+        return None;
+    }
+    let mut previous = (0..=b.len() as u32).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.iter().enumerate() {
+        current[0] = i as u32 + 1;
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + u32::from(a != b);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|distance| *distance > max) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the values of the keys within the edit distance of the key,
+    /// e.g. to still find mistyped identifiers, nearest first and then by
+    /// key.
+    ///
+    /// Scans all keys, leaving out expired values.
+    pub fn retrieve_fuzzy(&self, key: &str, max_distance: u32) -> Vec<(String, T, u32)> {
+        let now = wall_clock::now();
+        let mut found = self
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| {
+                !engram.is_expired(self.retention, now) && self.is_context_active(engram)
+            })
+            .filter_map(|(candidate, engram)| {
+                let distance = edit_distance(key, candidate, max_distance)?;
+                Some((candidate.clone(), engram.value.clone(), distance))
+            })
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn fuzzy() {
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("", "abc", 3), Some(3));
+        assert_eq!(edit_distance("äbc", "abc", 1), Some(1));
+
+        let brain = Brain::new(1.minutes());
+        brain.memoize("user_id", 1);
+        brain.memoize("user_ids", 2);
+        brain.memoize("usr_id", 3);
+        brain.memoize("order_id", 4);
+        assert_eq!(
+            brain.retrieve_fuzzy("user_id", 1),
+            [
+                ("user_id".to_string(), 1, 0),
+                ("user_ids".to_string(), 2, 1),
+                ("usr_id".to_string(), 3, 1),
+            ]
+        );
+        assert!(brain.retrieve_fuzzy("nothing", 1).is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+//! Named brains shared by a whole program, e.g. by functions or generated
+//! code which can't be handed a brain.
+//!
+//! ```
+//! # use memory::{global, Memory};
+//! global::brain::<f64>("prices").memoize("apple", 1.5);
+//! assert_eq!(global::brain::<f64>("prices").retrieve("apple"), Some(1.5));
+//! ```
+
+use crate::{Brain, Duration};
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The retention of brains [`brain`] creates.
+pub const DEFAULT_RETENTION: Duration = Duration::minutes(5);
+
+type Registry = RwLock<HashMap<(String, TypeId), Box<dyn Any + Send + Sync>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// The brain of the name and value type, created with the
+/// [`DEFAULT_RETENTION`] if it doesn't exist yet.
+///
+/// Names are per value type, so the same name may name a brain of each.
+pub fn brain<T: Send + Sync + 'static>(name: &str) -> Brain<T> {
+    brain_with(name, || Brain::new(DEFAULT_RETENTION))
+}
+
+/// The brain of the name and value type, created by `init` if it doesn't
+/// exist yet, e.g. to configure it with a [`BrainBuilder`].
+///
+/// [`BrainBuilder`]: crate::BrainBuilder
+pub fn brain_with<T: Send + Sync + 'static>(
+    name: &str,
+    init: impl FnOnce() -> Brain<T>,
+) -> Brain<T> {
+    let key = (name.to_string(), TypeId::of::<T>());
+    if let Some(brain) = registry().read().get(&key) {
+        return downcast(&**brain);
+    }
+    let mut brains = registry().write();
+    downcast(&**brains.entry(key).or_insert_with(|| Box::new(init())))
+}
+
+fn downcast<T: Send + Sync + 'static>(brain: &(dyn Any + Send + Sync)) -> Brain<T> {
+    brain
+        .downcast_ref::<Brain<T>>()
+        .expect("brains are registered by their value type")
+        .clone()
+}
+
+/// Drops all named brains, e.g. between tests, so the names give new empty
+/// brains. Clones of the brains taken before keep their values.
+pub fn reset() {
+    registry().write().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn global_brains() {
+        let prices = brain_with::<u32>("global-test", || Brain::new(1.hours()));
+        prices.memoize("a", 1);
+        assert_eq!(brain::<u32>("global-test").retrieve("a"), Some(1));
+        assert_eq!(brain::<String>("global-test").retrieve("a"), None);
+
+        reset();
+        assert_eq!(brain::<u32>("global-test").retrieve("a"), None);
+        assert_eq!(prices.retrieve("a"), Some(1));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::client::Client;
+use crate::protocol::{read_frame, write_frame, FAILED, FOUND};
+use crate::server::{handle, response, Connection, Server, ServerConfig};
+use crate::sharding::hash;
+use crate::transport::Stream;
+use crate::wall_clock;
+use crate::{binary, Brain, ClientConfig, Duration, Engram, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
+
+/// Operation starting a gossip exchange, next to those of the [`Server`].
+const SYNC: u8 = 6;
+/// Buckets of keys digested as a whole, so only differing ones are listed.
+const BUCKETS: usize = 64;
+
+/// How a [`GossipNode`] talks to its peers.
+#[derive(Clone, Debug)]
+pub struct GossipConfig {
+    interval: Duration,
+    fanout: usize,
+    server: ServerConfig,
+    client: ClientConfig,
+}
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::seconds(1),
+            fanout: 1,
+            server: ServerConfig::default(),
+            client: ClientConfig::default().pool_size(1).retries(0),
+        }
+    }
+}
+impl GossipConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Time between gossip rounds, a second by default.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Peers gossiped with per round, one by default.
+    pub fn fanout(mut self, fanout: usize) -> Self {
+        self.fanout = fanout.max(1);
+        self
+    }
+    pub fn server(mut self, server: ServerConfig) -> Self {
+        self.server = server;
+        self
+    }
+    /// Connections to peers, not retried by default since the next round
+    /// comes soon enough.
+    pub fn client(mut self, client: ClientConfig) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+/// What an exchange starts with: who is asking, the peers it knows and the
+/// digests of its buckets.
+type Hello = (String, Vec<String>, Vec<u64>);
+/// Key, time memoized and actor of an engram, in differing buckets.
+type Digest = (String, i128, u64);
+/// What the peer answers: the peers it knows, the buckets which differ and
+/// its digests in them.
+type Answer = (Vec<String>, Vec<usize>, Vec<Digest>);
+
+struct State {
+    brain: Brain<Vec<u8>>,
+    address: OnceLock<String>,
+    peers: Mutex<BTreeSet<String>>,
+    seeds: Vec<String>,
+    round: Mutex<usize>,
+    stopping: AtomicBool,
+}
+impl State {
+    /// Merges what peers know about into ours, without ourselves.
+    fn meet(&self, peers: impl IntoIterator<Item = String>) {
+        let mut known = self.peers.lock();
+        known.extend(
+            peers
+                .into_iter()
+                .filter(|peer| Some(peer) != self.address.get()),
+        );
+    }
+    fn hello(&self) -> Hello {
+        let peers = self.peers.lock().iter().cloned().collect();
+        (
+            self.address.get().cloned().unwrap_or_default(),
+            peers,
+            self.buckets(),
+        )
+    }
+    fn live(&self) -> Vec<(Digest, usize)> {
+        let now = wall_clock::now();
+        self.brain
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| !engram.is_expired(self.brain.retention, now))
+            .map(|(key, engram)| {
+                let digest = (
+                    key.clone(),
+                    engram.memoized.unix_timestamp_nanos(),
+                    engram.actor,
+                );
+                (digest, bucket(key))
+            })
+            .collect()
+    }
+    fn buckets(&self) -> Vec<u64> {
+        let mut buckets = vec![0; BUCKETS];
+        for (digest, bucket) in self.live() {
+            buckets[bucket] ^= digest_hash(&digest);
+        }
+        buckets
+    }
+    fn differing(&self, theirs: &[u64]) -> Vec<usize> {
+        let ours = self.buckets();
+        (0..BUCKETS)
+            .filter(|bucket| theirs.get(*bucket) != Some(&ours[*bucket]))
+            .collect()
+    }
+    fn digests(&self, buckets: &[usize]) -> HashMap<String, (i128, u64)> {
+        self.live()
+            .into_iter()
+            .filter(|(_, bucket)| buckets.contains(bucket))
+            .map(|((key, memoized, actor), _)| (key, (memoized, actor)))
+            .collect()
+    }
+    /// The live engrams of the keys, expiring as they do here.
+    fn engrams(&self, keys: &[String]) -> Vec<(String, Engram<Vec<u8>>)> {
+        let now = wall_clock::now();
+        let memory = self.brain.memory.read();
+        keys.iter()
+            .filter_map(|key| {
+                let engram = memory.get(key)?;
+                if engram.is_expired(self.brain.retention, now) {
+                    return None;
+                }
+                let engram = Engram {
+                    memoized: engram.memoized,
+                    retention: Some(engram.retention.unwrap_or(self.brain.retention)),
+                    actor: engram.actor,
+                    salience: engram.salience,
+                    context: engram.context.clone(),
+                    attributes: engram.attributes.clone(),
+                    ..Engram::new(engram.value.clone(), None)
+                };
+                Some((key.clone(), engram))
+            })
+            .collect()
+    }
+    /// Gossips with the next peers in turn, dropping those not answering
+    /// until someone mentions them again, unless they are seeds.
+    fn round(&self, config: &GossipConfig) {
+        let peers = self.peers.lock().iter().cloned().collect::<Vec<_>>();
+        if peers.is_empty() {
+            return;
+        }
+        let first = {
+            let mut round = self.round.lock();
+            *round += 1;
+            *round
+        };
+        for index in (first..first + config.fanout.min(peers.len())).map(|i| i % peers.len()) {
+            let peer = &peers[index];
+            if self.exchange(peer, config.client.clone()).is_err() && !self.seeds.contains(peer) {
+                self.peers.lock().remove(peer);
+            }
+        }
+    }
+    /// Pushes our newer engrams to the peer and pulls its newer ones.
+    fn exchange(&self, peer: &str, config: ClientConfig) -> io::Result<()> {
+        let mut hello = vec![SYNC];
+        hello.extend(binary::to_vec(&self.hello()).map_err(invalid_data)?);
+        Client::new(peer, config)?.call(|stream| {
+            let (peers, differing, theirs) = decode::<Answer>(&roundtrip(stream, &hello)?)?;
+            let theirs = theirs
+                .into_iter()
+                .map(|(key, memoized, actor)| (key, (memoized, actor)))
+                .collect::<HashMap<_, _>>();
+            let ours = self.digests(&differing);
+            let request = (self.engrams(&newer(&ours, &theirs)), newer(&theirs, &ours));
+            let request = binary::to_vec(&request).map_err(invalid_data)?;
+            let pulled = decode(&roundtrip(stream, &request)?)?;
+            self.brain.merge_engrams(pulled);
+            self.meet(peers);
+            Ok(Ok(()))
+        })
+    }
+    /// Answers an exchange started by a peer.
+    fn answer(&self, connection: &mut Connection, hello: &[u8]) -> io::Result<()> {
+        let (address, peers, buckets) = decode::<Hello>(hello)?;
+        self.meet(peers.into_iter().chain([address]));
+        let known = self.peers.lock().iter().cloned().collect::<Vec<_>>();
+        let differing = self.differing(&buckets);
+        let digests = self.digests(&differing).into_iter();
+        let digests = digests.map(|(key, (memoized, actor))| (key, memoized, actor));
+        reply(connection, &(known, differing, digests.collect::<Vec<_>>()))?;
+        if !connection.wait()? {
+            return Ok(());
+        }
+        let Some(request) = read_frame(&mut connection.reader)? else {
+            return Ok(());
+        };
+        let (pushed, pulled) = decode::<(Vec<(String, Engram<Vec<u8>>)>, Vec<String>)>(&request)?;
+        self.brain.merge_engrams(pushed);
+        reply(connection, &self.engrams(&pulled))
+    }
+}
+
+/// A brain of bytes served like by a [`Server`], which finds its peers and
+/// repairs divergence from them in the background.
+///
+/// Every round the node gossips with the next of its peers in turn: they
+/// exchange the peers they know, so nodes started with a single seed learn
+/// about all others, and digests of their keys in 64 buckets, so only the
+/// keys of differing buckets are compared. Each then takes the engrams which
+/// win over its own by the rules of [`Brain::merge_crdt`], so give the brains
+/// distinct actors. Like there, removals and forgets aren't gossiped and a
+/// removed key comes back from peers still holding it.
+///
+/// Nodes are known by the address they are bound to, so bind an address
+/// peers can connect to rather than an unspecified one.
+pub struct GossipNode {
+    server: Server,
+    state: Arc<State>,
+    gossiper: JoinHandle<()>,
+    config: GossipConfig,
+}
+impl GossipNode {
+    /// Starts serving the brain and gossiping with the seeds.
+    pub fn start(
+        address: impl ToSocketAddrs,
+        brain: Brain<Vec<u8>>,
+        seeds: &[&str],
+        config: GossipConfig,
+    ) -> io::Result<Self> {
+        let state = Arc::new(State {
+            brain: brain.clone(),
+            address: OnceLock::new(),
+            peers: Mutex::new(seeds.iter().map(|seed| seed.to_string()).collect()),
+            seeds: seeds.iter().map(|seed| seed.to_string()).collect(),
+            round: Mutex::new(0),
+            stopping: AtomicBool::new(false),
+        });
+        let serving = state.clone();
+        let serve = move |connection: &mut Connection, brain: &Brain<Vec<u8>>| {
+            while connection.wait()? {
+                let Some(request) = read_frame(&mut connection.reader)? else {
+                    break;
+                };
+                if let Some(answer) = connection.admit(&request) {
+                    write_frame(&mut connection.writer, &answer)?;
+                    connection.writer.flush()?;
+                    continue;
+                }
+                match request.split_first() {
+                    Some((&SYNC, hello)) => serving.answer(connection, hello)?,
+                    _ => {
+                        write_frame(&mut connection.writer, &handle(brain, &request))?;
+                        connection.writer.flush()?;
+                    }
+                }
+            }
+            Ok(())
+        };
+        let mut refusal = Vec::new();
+        write_frame(&mut refusal, &response(FAILED, b"too many connections"))?;
+        let server = Server::listen(
+            address,
+            brain,
+            config.server.clone(),
+            Arc::new(serve),
+            refusal,
+        )?;
+        let address = server.local_addr().to_string();
+        state.peers.lock().remove(&address);
+        let _ = state.address.set(address);
+        let gossiper = {
+            let (state, config) = (state.clone(), config.clone());
+            let interval = config.interval.unsigned_abs();
+            thread::spawn(move || loop {
+                thread::park_timeout(interval);
+                if state.stopping.load(Ordering::Relaxed) {
+                    break;
+                }
+                state.round(&config);
+            })
+        };
+        Ok(Self {
+            server,
+            state,
+            gossiper,
+            config,
+        })
+    }
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+    pub fn brain(&self) -> &Brain<Vec<u8>> {
+        &self.state.brain
+    }
+    /// Addresses of the peers the node knows, in order.
+    pub fn peers(&self) -> Vec<String> {
+        self.state.peers.lock().iter().cloned().collect()
+    }
+    /// Gossips a round right away, besides those in the background.
+    pub fn gossip(&self) {
+        self.state.round(&self.config);
+    }
+    /// Stops gossiping and serving, open connections are served until they close.
+    pub fn shutdown(self) -> thread::Result<()> {
+        self.state.stopping.store(true, Ordering::Relaxed);
+        self.gossiper.thread().unpark();
+        self.gossiper.join()?;
+        self.server.shutdown()
+    }
+}
+
+fn bucket(key: &str) -> usize {
+    (hash(key.as_bytes()) % BUCKETS as u64) as usize
+}
+
+fn digest_hash((key, memoized, actor): &Digest) -> u64 {
+    let mut bytes = key.as_bytes().to_vec();
+    bytes.extend(memoized.to_le_bytes());
+    bytes.extend(actor.to_le_bytes());
+    hash(&bytes)
+}
+
+fn reply(connection: &mut Connection, body: &impl serde::Serialize) -> io::Result<()> {
+    let body = binary::to_vec(body).map_err(invalid_data)?;
+    write_frame(&mut connection.writer, &response(FOUND, &body))?;
+    connection.writer.flush()
+}
+
+/// Keys whose engrams in the first digests win over those in the second.
+fn newer(a: &HashMap<String, (i128, u64)>, b: &HashMap<String, (i128, u64)>) -> Vec<String> {
+    a.iter()
+        .filter(|(key, order)| b.get(*key).is_none_or(|other| *order > other))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// Sends a frame of the exchange and gives the body of the answer.
+fn roundtrip(stream: &mut Stream, request: &[u8]) -> io::Result<Vec<u8>> {
+    write_frame(stream, request)?;
+    let answer = read_frame(stream)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))?;
+    match answer.split_first() {
+        Some((&FOUND, body)) => Ok(body.to_vec()),
+        _ => Err(invalid_data("gossip refused")),
+    }
+}
+
+fn decode<R: serde::de::DeserializeOwned>(bytes: &[u8]) -> io::Result<R> {
+    binary::from_slice(bytes).map_err(invalid_data)
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn gossip() {
+        let config = GossipConfig::new().interval(1.hours()).fanout(2);
+        let brain = |actor| Brain::builder().retention(1.minutes()).actor(actor).build();
+        let a = GossipNode::start("127.0.0.1:0", brain(1), &[], config.clone()).unwrap();
+        let seed = a.local_addr().to_string();
+        let b = GossipNode::start("127.0.0.1:0", brain(2), &[&seed], config.clone()).unwrap();
+        let seed = b.local_addr().to_string();
+        let c = GossipNode::start("127.0.0.1:0", brain(3), &[&seed], config).unwrap();
+
+        a.brain().memoize("x", b"a".to_vec());
+        c.brain().memoize("y", b"c".to_vec());
+        b.brain().memoize("x", b"b".to_vec());
+        for _ in 0..2 {
+            for node in [&a, &b, &c] {
+                node.gossip();
+            }
+        }
+
+        for node in [&a, &b, &c] {
+            assert_eq!(node.peers().len(), 2);
+            assert_eq!(node.brain().retrieve("x"), Some(b"b".to_vec()));
+            assert_eq!(node.brain().retrieve("y"), Some(b"c".to_vec()));
+        }
+        for node in [a, b, c] {
+            node.shutdown().unwrap();
+        }
+    }
+}
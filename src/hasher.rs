@@ -0,0 +1,74 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// Builds [`FxHasher`]s, for [`BrainBuilder::hasher`].
+///
+/// [`BrainBuilder::hasher`]: crate::BrainBuilder::hasher
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// The fast hash of rustc, which isn't keyed.
+///
+/// Takes a fraction of the time of the SipHash brains use by default, but
+/// whoever picks the keys can pick many with the same hash and so turn
+/// every lookup into a scan. Use it for keys your own code makes, never
+/// for keys from requests.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rest = [0; 8];
+        let remainder = words.remainder();
+        rest[..remainder.len()].copy_from_slice(remainder);
+        if !remainder.is_empty() {
+            self.add(u64::from_le_bytes(rest));
+        }
+    }
+    fn write_u8(&mut self, n: u8) {
+        self.add(n.into());
+    }
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, Memory, NumericalDuration};
+    use std::hash::BuildHasher;
+
+    #[test]
+    fn fx_hasher() {
+        let hasher = FxBuildHasher::default();
+        assert_eq!(hasher.hash_one("key"), hasher.hash_one("key"));
+        assert_ne!(hasher.hash_one("key"), hasher.hash_one("kez"));
+        assert_ne!(hasher.hash_one("abcdefgh1"), hasher.hash_one("abcdefgh2"));
+
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .hasher(FxBuildHasher::default())
+            .build();
+        brain.memoize("a", 1);
+        assert_eq!(brain.retrieve("a"), Some(1));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::wall_clock::Timestamp;
+use crate::{Brain, Engram, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// The last values memoized per key, see [`BrainBuilder::history`].
+///
+/// [`BrainBuilder::history`]: crate::BrainBuilder::history
+pub(crate) struct History<T> {
+    depth: usize,
+    values: Mutex<HashMap<String, VecDeque<Recorded<T>>>>,
+}
+struct Recorded<T> {
+    value: T,
+    memoized: Timestamp,
+    expires: Timestamp,
+    context: Option<Arc<str>>,
+}
+impl<T> History<T> {
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            values: Default::default(),
+        }
+    }
+    pub(crate) fn record(
+        &self,
+        key: &str,
+        value: T,
+        memoized: Timestamp,
+        expires: Timestamp,
+        context: Option<Arc<str>>,
+    ) {
+        let mut values = self.values.lock();
+        let values = values.entry(key.to_string()).or_default();
+        if values.len() == self.depth {
+            values.pop_front();
+        }
+        values.push_back(Recorded {
+            value,
+            memoized,
+            expires,
+            context,
+        });
+    }
+    /// Drops the last value of the key and takes out the one before, to be
+    /// memoized and so recorded again.
+    fn undo(&self, key: &str) -> Option<(T, Option<Arc<str>>)> {
+        let mut values = self.values.lock();
+        let values = values.get_mut(key).filter(|values| values.len() > 1)?;
+        values.pop_back();
+        values
+            .pop_back()
+            .map(|recorded| (recorded.value, recorded.context))
+    }
+    pub(crate) fn forget(&self, key: &str) {
+        self.values.lock().remove(key);
+    }
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// The last values memoized for the key with when, oldest first and the
+    /// current one last, empty unless the brain keeps a history.
+    ///
+    /// Values memoized in inactive contexts are left out.
+    pub fn history(&self, key: &str) -> Vec<(T, Timestamp)> {
+        let Some(history) = &self.history else {
+            return Vec::new();
+        };
+        let values = history.values.lock();
+        values
+            .get(key)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter(|recorded| self.is_active(recorded.context.as_ref()))
+                    .map(|recorded| (recorded.value.clone(), recorded.memoized))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// The value memoized for the key at the time, unless it had expired
+    /// by then, from the brain's history, see [`BrainBuilder::history`].
+    ///
+    /// Times before the oldest value kept, or before the key was last
+    /// removed, give `None`.
+    ///
+    /// [`BrainBuilder::history`]: crate::BrainBuilder::history
+    pub fn retrieve_at(&self, key: &str, at: Timestamp) -> Option<T> {
+        let values = self.history.as_ref()?.values.lock();
+        let recorded = values
+            .get(key)?
+            .iter()
+            .rev()
+            .find(|recorded| recorded.memoized <= at)?;
+        (at <= recorded.expires && self.is_active(recorded.context.as_ref()))
+            .then(|| recorded.value.clone())
+    }
+    /// Memoizes the value the key held before the last one again, e.g. to
+    /// roll back a bad write, giving it.
+    ///
+    /// Takes a history at least two deep, see [`BrainBuilder::history`],
+    /// undoing as many writes in a row as it keeps values but one. The
+    /// value restored gets a retention anew.
+    ///
+    /// [`BrainBuilder::history`]: crate::BrainBuilder::history
+    pub fn undo(&self, key: &str) -> Option<T> {
+        let (previous, context) = self.history.as_ref()?.undo(key)?;
+        let engram = Engram {
+            context,
+            ..Engram::new(previous.clone(), None)
+        };
+        self.store_if(key, engram, |_| true);
+        Some(previous)
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration};
+
+    #[test]
+    fn history() {
+        let brain = Brain::builder().retention(1.minutes()).history(2).build();
+        for value in 1..=3 {
+            brain.memoize("a", value);
+        }
+        let history = brain.history("a");
+        assert_eq!(
+            history.iter().map(|(value, _)| *value).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert!(history[0].1 <= history[1].1);
+
+        let (before, after) = (history[0].1, history[1].1);
+        assert_eq!(brain.retrieve_at("a", before), Some(2));
+        assert_eq!(brain.retrieve_at("a", after), Some(3));
+        assert_eq!(brain.retrieve_at("a", before - 1.nanoseconds()), None);
+        assert_eq!(brain.retrieve_at("a", after + 2.minutes()), None);
+        assert_eq!(brain.undo("a"), Some(2));
+        assert_eq!(brain.retrieve("a"), Some(2));
+        assert_eq!(brain.history("a").len(), 1);
+        assert_eq!(brain.undo("a"), None);
+        assert_eq!(brain.retrieve("a"), Some(2));
+
+        brain.remove("a");
+        assert!(brain.history("a").is_empty());
+        let brain = Brain::new(1.minutes());
+        brain.memoize("a", 1);
+        assert!(brain.history("a").is_empty());
+    }
+}
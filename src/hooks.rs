@@ -0,0 +1,100 @@
+use crate::Brain;
+use std::sync::Arc;
+
+/// Why an entry left a brain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvictionCause {
+    /// The entry outlived its retention and was forgotten.
+    Expired,
+    /// The brain was full and the entry was chosen to make room.
+    Capacity,
+    /// The entry was removed with [`Brain::remove`].
+    Removed,
+}
+
+/// Observes the operations of a brain.
+///
+/// Hooks are called after the brain released its lock, so they may use the
+/// brain themselves. They run on the calling thread and delay the operation
+/// that triggered them.
+pub trait BrainHooks<T>: Send + Sync {
+    fn on_insert(&self, _key: &str, _value: &T) {}
+    fn on_hit(&self, _key: &str, _value: &T) {}
+    fn on_miss(&self, _key: &str) {}
+    fn on_evict(&self, _key: &str, _value: &T, _cause: EvictionCause) {}
+}
+
+impl<T, S> Brain<T, S> {
+    /// Installs hooks for this brain and all its clones, replacing previous ones.
+    pub fn set_hooks(&self, hooks: impl BrainHooks<T> + 'static) {
+        *self.hooks.write() = Some(Arc::new(hooks));
+    }
+    pub fn clear_hooks(&self) {
+        *self.hooks.write() = None;
+    }
+    pub(crate) fn hooks(&self) -> Option<Arc<dyn BrainHooks<T>>> {
+        self.hooks.read().clone()
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+    use parking_lot::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Journal(Arc<Mutex<Vec<String>>>);
+    impl BrainHooks<i32> for Journal {
+        fn on_insert(&self, key: &str, value: &i32) {
+            self.0.lock().push(format!("insert {key} {value}"));
+        }
+        fn on_hit(&self, key: &str, value: &i32) {
+            self.0.lock().push(format!("hit {key} {value}"));
+        }
+        fn on_miss(&self, key: &str) {
+            self.0.lock().push(format!("miss {key}"));
+        }
+        fn on_evict(&self, key: &str, value: &i32, cause: EvictionCause) {
+            self.0.lock().push(format!("evict {key} {value} {cause:?}"));
+        }
+    }
+
+    #[test]
+    fn hooks() {
+        let memory = Brain::new(1.milliseconds());
+        let journal = Journal::default();
+        memory.set_hooks(journal.clone());
+
+        memory.memoize("a", 3);
+        memory.retrieve("a");
+        memory.retrieve("b");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.forget();
+
+        memory.clear_hooks();
+        memory.memoize("c", 4);
+
+        assert_eq!(
+            *journal.0.lock(),
+            vec!["insert a 3", "hit a 3", "miss b", "evict a 3 Expired"]
+        );
+    }
+
+    #[test]
+    fn reentrant() {
+        struct Mirror(Brain<i32>);
+        impl BrainHooks<i32> for Mirror {
+            fn on_miss(&self, key: &str) {
+                self.0.memoize(key, 0);
+            }
+        }
+
+        let memory = Brain::new(1.minutes());
+        memory.set_hooks(Mirror(memory.clone()));
+
+        assert_eq!(memory.retrieve("a"), None);
+        assert_eq!(memory.retrieve("a"), Some(0));
+    }
+}
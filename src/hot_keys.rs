@@ -0,0 +1,201 @@
+use crate::wall_clock::Instant;
+use crate::{Brain, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Rows of the sketch, each counting every key once.
+const DEPTH: usize = 4;
+
+/// A key's estimated number of retrievals, see [`Brain::hottest_keys`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyFrequency {
+    pub key: String,
+    /// Retrievals since tracking started or was reset, at least the actual
+    /// number and rarely much more.
+    pub retrievals: u64,
+    /// Retrievals per second over the same time.
+    pub per_second: f64,
+}
+
+/// Counts retrievals per key in a count-min sketch, whatever keys the
+/// brain holds, and keeps the keys with the highest counts.
+pub(crate) struct HotKeys {
+    hasher: RandomState,
+    width: usize,
+    counters: Box<[AtomicU32]>,
+    top: usize,
+    hottest: Mutex<HashMap<String, u64>>,
+    /// Lowest count of the hottest keys once there are `top` of them, for
+    /// other keys to beat without locking them.
+    floor: AtomicU64,
+    started: Mutex<Instant>,
+}
+impl HotKeys {
+    pub(crate) fn new(top: usize) -> Self {
+        let width = (top.max(1) * 64).next_power_of_two().max(1024);
+        Self {
+            hasher: RandomState::new(),
+            width,
+            counters: (0..DEPTH * width).map(|_| AtomicU32::new(0)).collect(),
+            top,
+            hottest: Mutex::default(),
+            floor: AtomicU64::new(0),
+            started: Mutex::new(Instant::now()),
+        }
+    }
+    /// Positions of the key's counters, a row each.
+    fn cells(&self, key: &str) -> impl Iterator<Item = usize> + '_ {
+        let hash = self.hasher.hash_one(key);
+        let (first, second) = (hash as usize, (hash >> 32) as usize | 1);
+        (0..DEPTH).map(move |row| {
+            row * self.width + first.wrapping_add(row.wrapping_mul(second)) % self.width
+        })
+    }
+    fn estimate(&self, key: &str) -> u64 {
+        self.cells(key)
+            .map(|cell| self.counters[cell].load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+            .into()
+    }
+    pub(crate) fn record(&self, key: &str) {
+        let retrievals = self
+            .cells(key)
+            .map(|cell| {
+                self.counters[cell]
+                    .fetch_add(1, Ordering::Relaxed)
+                    .saturating_add(1)
+            })
+            .min()
+            .unwrap_or(0)
+            .into();
+        if retrievals <= self.floor.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut hottest = self.hottest.lock();
+        if let Some(count) = hottest.get_mut(key) {
+            *count = retrievals;
+        } else {
+            if hottest.len() >= self.top {
+                let coldest = hottest
+                    .iter()
+                    .min_by_key(|(_, count)| **count)
+                    .map(|(key, _)| key.clone());
+                coldest.map(|coldest| hottest.remove(&coldest));
+            }
+            hottest.insert(key.to_string(), retrievals);
+        }
+        if hottest.len() >= self.top {
+            let floor = hottest.values().min().copied().unwrap_or(0);
+            self.floor.store(floor, Ordering::Relaxed);
+        }
+    }
+    fn frequency(&self, key: String, retrievals: u64, started: Instant) -> KeyFrequency {
+        let seconds = started.elapsed().as_secs_f64();
+        KeyFrequency {
+            key,
+            retrievals,
+            per_second: if seconds > 0.0 {
+                retrievals as f64 / seconds
+            } else {
+                0.0
+            },
+        }
+    }
+    fn reset(&self) {
+        let mut hottest = self.hottest.lock();
+        for counter in self.counters.iter() {
+            counter.store(0, Ordering::Relaxed);
+        }
+        hottest.clear();
+        self.floor.store(0, Ordering::Relaxed);
+        *self.started.lock() = Instant::now();
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    pub(crate) fn record_retrieval(&self, key: &str) {
+        if let Some(hot_keys) = &self.hot_keys {
+            hot_keys.record(key);
+        }
+    }
+    /// The `k` keys retrieved the most, most first, whether the brain still
+    /// holds them or not, e.g. to spot hot keys evicted over and over from a
+    /// full brain. Unlike [`Brain::top_keys`], counts go on across values.
+    ///
+    /// Empty unless tracked with [`BrainBuilder::track_hot_keys`], and at
+    /// most as many keys as tracked.
+    ///
+    /// [`BrainBuilder::track_hot_keys`]: crate::BrainBuilder::track_hot_keys
+    pub fn hottest_keys(&self, k: usize) -> Vec<KeyFrequency> {
+        let Some(hot_keys) = &self.hot_keys else {
+            return Vec::new();
+        };
+        let started = *hot_keys.started.lock();
+        let mut hottest = hot_keys
+            .hottest
+            .lock()
+            .keys()
+            .map(|key| (key.clone(), hot_keys.estimate(key)))
+            .collect::<Vec<_>>();
+        hottest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hottest
+            .into_iter()
+            .take(k)
+            .map(|(key, retrievals)| hot_keys.frequency(key, retrievals, started))
+            .collect()
+    }
+    /// The estimated retrievals of any key, `None` unless tracked with
+    /// [`BrainBuilder::track_hot_keys`].
+    ///
+    /// [`BrainBuilder::track_hot_keys`]: crate::BrainBuilder::track_hot_keys
+    pub fn key_retrievals(&self, key: &str) -> Option<KeyFrequency> {
+        let hot_keys = self.hot_keys.as_ref()?;
+        let started = *hot_keys.started.lock();
+        Some(hot_keys.frequency(key.to_string(), hot_keys.estimate(key), started))
+    }
+    /// Starts counting retrievals anew.
+    pub fn reset_hot_keys(&self) {
+        if let Some(hot_keys) = &self.hot_keys {
+            hot_keys.reset();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn hottest_keys() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(2)
+            .track_hot_keys(2)
+            .build();
+        for round in 0..30 {
+            brain.memoize("hot", 0);
+            brain.memoize(&format!("cold-{round}"), 0);
+            brain.memoize(&format!("colder-{round}"), 0);
+            brain.retrieve("hot");
+            if round % 3 == 0 {
+                brain.retrieve("warm");
+            }
+            brain.retrieve(&format!("cold-{round}"));
+        }
+        let hottest = brain.hottest_keys(5);
+        assert_eq!(hottest.len(), 2);
+        assert_eq!(hottest[0].key, "hot");
+        assert!(hottest[0].retrievals >= 30);
+        assert_eq!(hottest[1].key, "warm");
+        assert!(brain.key_retrievals("warm").unwrap().retrievals >= 10);
+
+        brain.reset_hot_keys();
+        assert!(brain.hottest_keys(5).is_empty());
+        assert_eq!(Brain::<i32>::new(1.minutes()).key_retrievals("hot"), None);
+    }
+}
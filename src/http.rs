@@ -0,0 +1,345 @@
+use crate::server::{Connection, Server, ServerConfig};
+use crate::{json, Brain, Memory};
+use serde::de::IgnoredAny;
+use std::io::{self, BufRead, Read, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+/// Request lines and headers above this length are rejected.
+const MAX_HEAD: u64 = 16 << 10;
+/// Bodies above this length are rejected before reading them.
+const MAX_BODY: usize = 64 << 20;
+
+struct Request {
+    method: String,
+    path: String,
+    ttl: Option<String>,
+    token: Option<String>,
+    /// The length of the body, read once the request is authorized.
+    length: usize,
+    body: Vec<u8>,
+    close: bool,
+}
+
+struct Response {
+    status: &'static str,
+    ttl: Option<f64>,
+    json: bool,
+    body: Vec<u8>,
+}
+impl Response {
+    fn new(status: &'static str) -> Self {
+        Self {
+            status,
+            ttl: None,
+            json: false,
+            body: Vec::new(),
+        }
+    }
+    fn text(status: &'static str, text: &str) -> Self {
+        Self {
+            body: text.as_bytes().to_vec(),
+            ..Self::new(status)
+        }
+    }
+    fn json(body: Vec<u8>) -> Self {
+        Self {
+            json: true,
+            body,
+            ..Self::new("200 OK")
+        }
+    }
+    fn write(&self, writer: &mut impl Write, close: bool) -> io::Result<()> {
+        write!(writer, "HTTP/1.1 {}\r\n", self.status)?;
+        write!(writer, "Content-Length: {}\r\n", self.body.len())?;
+        if self.json {
+            writer.write_all(b"Content-Type: application/json\r\n")?;
+        }
+        if let Some(ttl) = self.ttl {
+            write!(writer, "X-Ttl: {ttl}\r\n")?;
+            write!(writer, "Cache-Control: max-age={}\r\n", ttl.floor())?;
+        }
+        if close {
+            writer.write_all(b"Connection: close\r\n")?;
+        }
+        writer.write_all(b"\r\n")?;
+        writer.write_all(&self.body)
+    }
+}
+
+impl Server {
+    /// Serves the brain over HTTP/1.1 for debugging and clients in other
+    /// languages, with the limits and timeouts of the config.
+    ///
+    /// - `GET /keys/{key}` responds with the value and the seconds it has
+    ///   left in an `X-Ttl` header, and `Cache-Control`,
+    /// - `PUT /keys/{key}` memoizes the body, which has to be JSON, for the
+    ///   seconds in an optional `X-Ttl` header or the brain's retention,
+    /// - `DELETE /keys/{key}` removes the value,
+    /// - `GET /stats` responds with the brain's statistics.
+    ///
+    /// If the config [authorizes](ServerConfig::authorize), every request
+    /// needs an `Authorization: Bearer {token}` header.
+    ///
+    /// Keys are percent-decoded, e.g. `curl -X PUT -d '[1,2]' localhost:8080/keys/a%2Fb`.
+    pub fn bind_http(
+        address: impl ToSocketAddrs,
+        brain: Brain<Vec<u8>>,
+        config: ServerConfig,
+    ) -> io::Result<Self> {
+        let mut refusal = Vec::new();
+        Response::text("503 Service Unavailable", "too many connections")
+            .write(&mut refusal, true)?;
+        Self::listen(address, brain, config, Arc::new(serve), refusal)
+    }
+}
+
+fn serve(connection: &mut Connection, brain: &Brain<Vec<u8>>) -> io::Result<()> {
+    while connection.wait()? {
+        let (response, close) = match read_request(&mut connection.reader)? {
+            // the body is left unread, so the connection can't go on
+            Ok(request) if !connection.authorize(request.token.as_deref().unwrap_or_default()) => {
+                (Response::text("401 Unauthorized", "invalid token"), true)
+            }
+            Ok(mut request) => {
+                read_body(&mut connection.reader, &mut request)?;
+                (route(brain, &request), request.close)
+            }
+            Err(response) => (response, true),
+        };
+        response.write(&mut connection.writer, close)?;
+        connection.writer.flush()?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn route(brain: &Brain<Vec<u8>>, request: &Request) -> Response {
+    if request.path == "/stats" {
+        return match request.method.as_str() {
+            "GET" => match json::to_string(&brain.stats()) {
+                Ok(stats) => Response::json(stats.into_bytes()),
+                Err(error) => Response::text("500 Internal Server Error", &error.to_string()),
+            },
+            _ => Response::text("405 Method Not Allowed", "use GET"),
+        };
+    }
+    let Some(key) = request.path.strip_prefix("/keys/").and_then(decode) else {
+        return Response::text("404 Not Found", "no such resource");
+    };
+    match request.method.as_str() {
+        "GET" => match (brain.retrieve(&key), brain.time_to_live(&key)) {
+            (Some(value), ttl) => Response {
+                ttl: ttl.map(|ttl| ttl.as_seconds_f64()),
+                ..Response::json(value)
+            },
+            (None, _) => Response::text("404 Not Found", "no such key"),
+        },
+        "PUT" => {
+            let valid = std::str::from_utf8(&request.body)
+                .is_ok_and(|body| json::from_str::<IgnoredAny>(body).is_ok());
+            if !valid {
+                return Response::text("400 Bad Request", "the body isn't JSON");
+            }
+            let ttl = request.ttl.as_deref().map(|ttl| {
+                ttl.trim()
+                    .parse()
+                    .ok()
+                    .and_then(crate::config::seconds)
+                    .filter(|ttl| !ttl.is_zero())
+            });
+            match ttl {
+                None => brain.memoize(&key, request.body.clone()),
+                Some(Some(ttl)) => brain.memoize_for(&key, request.body.clone(), ttl),
+                Some(None) => {
+                    return Response::text("400 Bad Request", "X-Ttl isn't a number of seconds")
+                }
+            }
+            Response::new("204 No Content")
+        }
+        "DELETE" => match brain.remove(&key) {
+            Some(_) => Response::new("204 No Content"),
+            None => Response::text("404 Not Found", "no such key"),
+        },
+        _ => Response::text("405 Method Not Allowed", "use GET, PUT or DELETE"),
+    }
+}
+
+/// Reads the head of a request, or gives the response to a malformed one.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let bad = |text| Ok(Err(Response::text("400 Bad Request", text)));
+    let mut head = reader.take(MAX_HEAD);
+    let mut line = String::new();
+    if head.read_line(&mut line)? == 0 {
+        return bad("empty request");
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return bad("malformed request line");
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.split('?').next().unwrap_or_default().to_string(),
+        ttl: None,
+        token: None,
+        length: 0,
+        body: Vec::new(),
+        close: version == "HTTP/1.0",
+    };
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            return bad("truncated headers");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return bad("malformed header");
+        };
+        let value = value.trim();
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" => match value.parse() {
+                Ok(value) if value <= MAX_BODY => request.length = value,
+                _ => {
+                    return Ok(Err(Response::text(
+                        "413 Content Too Large",
+                        "body too large",
+                    )))
+                }
+            },
+            "x-ttl" => request.ttl = Some(value.to_string()),
+            "authorization" => {
+                request.token = value
+                    .split_once(' ')
+                    .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                    .map(|(_, token)| token.trim().to_string());
+            }
+            "connection" => request.close = value.eq_ignore_ascii_case("close"),
+            _ => {}
+        }
+    }
+    Ok(Ok(request))
+}
+
+/// Reads the body of the request, growing it as its bytes arrive.
+fn read_body(reader: &mut impl Read, request: &mut Request) -> io::Result<()> {
+    reader
+        .take(request.length as u64)
+        .read_to_end(&mut request.body)?;
+    if request.body.len() < request.length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated body",
+        ));
+    }
+    Ok(())
+}
+
+/// Percent-decodes a path segment, `None` unless it's valid UTF-8.
+fn decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::io::BufReader;
+    use std::net::TcpStream;
+
+    /// Sends a request, giving the status line, the headers and the body.
+    fn send(stream: &mut BufReader<TcpStream>, request: &str) -> (String, Vec<String>, String) {
+        stream.get_mut().write_all(request.as_bytes()).unwrap();
+        let mut status = String::new();
+        stream.read_line(&mut status).unwrap();
+        let mut headers = Vec::new();
+        loop {
+            let mut header = String::new();
+            stream.read_line(&mut header).unwrap();
+            if header.trim_end().is_empty() {
+                break;
+            }
+            headers.push(header.trim_end().to_string());
+        }
+        let length = headers
+            .iter()
+            .find_map(|header| header.strip_prefix("Content-Length: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).unwrap();
+        (
+            status.trim_end().to_string(),
+            headers,
+            String::from_utf8(body).unwrap(),
+        )
+    }
+
+    #[test]
+    fn http() {
+        let brain = Brain::new(1.minutes());
+        let server = Server::bind_http("127.0.0.1:0", brain.clone(), ServerConfig::new()).unwrap();
+        let mut stream = BufReader::new(TcpStream::connect(server.local_addr()).unwrap());
+
+        let put = "PUT /keys/a%2Fb HTTP/1.1\r\nX-Ttl: 30\r\nContent-Length: 5\r\n\r\n[1,2]";
+        assert_eq!(send(&mut stream, put).0, "HTTP/1.1 204 No Content");
+        assert_eq!(brain.retrieve("a/b"), Some(b"[1,2]".to_vec()));
+
+        let (status, headers, body) = send(&mut stream, "GET /keys/a%2Fb HTTP/1.1\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, "[1,2]");
+        assert!(headers.contains(&"Content-Type: application/json".to_string()));
+        assert!(headers
+            .iter()
+            .any(|header| header.starts_with("Cache-Control: max-age=29")));
+
+        let invalid = "PUT /keys/c HTTP/1.1\r\nContent-Length: 3\r\n\r\n{x}";
+        assert_eq!(send(&mut stream, invalid).0, "HTTP/1.1 400 Bad Request");
+        let (status, _, stats) = send(&mut stream, "GET /stats HTTP/1.1\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(stats.contains(r#""insertions":1"#));
+
+        let delete = "DELETE /keys/a%2Fb HTTP/1.1\r\n\r\n";
+        assert_eq!(send(&mut stream, delete).0, "HTTP/1.1 204 No Content");
+        let (status, headers, _) = send(
+            &mut stream,
+            "GET /keys/a%2Fb HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        assert!(headers.contains(&"Connection: close".to_string()));
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn deeply_nested_body() {
+        let brain = Brain::new(1.minutes());
+        let server = Server::bind_http("127.0.0.1:0", brain.clone(), ServerConfig::new()).unwrap();
+        let mut stream = BufReader::new(TcpStream::connect(server.local_addr()).unwrap());
+        let body = "[".repeat(200_000);
+        let put = format!(
+            "PUT /keys/a HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        assert_eq!(send(&mut stream, &put).0, "HTTP/1.1 400 Bad Request");
+        assert!(!brain.is_cached("a"));
+        server.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use crate::Memory;
+use parking_lot::Mutex;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, Weak};
+
+/// Number of values interned before the first pass dropping those no
+/// longer memoized.
+const FIRST_PRUNE: usize = 64;
+
+struct Pool<V> {
+    values: HashMap<u64, Vec<Weak<V>>>,
+    len: usize,
+    pruned_to: usize,
+}
+impl<V> Pool<V> {
+    fn prune(&mut self) {
+        self.values.retain(|_, values| {
+            values.retain(|value| value.strong_count() > 0);
+            !values.is_empty()
+        });
+        self.len = self.values.values().map(Vec::len).sum();
+        self.pruned_to = self.len;
+    }
+}
+
+/// A memory sharing one copy of equal values memoized under several keys,
+/// e.g. a few canonical responses cached under many requests.
+///
+/// Values are hashed when memoized, and replaced by an equal one still
+/// memoized, if any. The pool only holds values weakly, so a value is
+/// dropped once no key holds it anymore, and the pool drops its entry
+/// whenever it doubled since it last did.
+///
+/// See [`Brain::dedup`] for sharing the values already memoized.
+///
+/// [`Brain::dedup`]: crate::Brain::dedup
+pub struct InternedMemory<V, M> {
+    memory: M,
+    hasher: RandomState,
+    pool: Mutex<Pool<V>>,
+}
+impl<V: Eq + Hash, M> InternedMemory<V, M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            hasher: RandomState::new(),
+            pool: Mutex::new(Pool {
+                values: HashMap::new(),
+                len: 0,
+                pruned_to: 0,
+            }),
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// The value memoized equal to this one, or else this one, now shared.
+    pub fn intern(&self, value: Arc<V>) -> Arc<V> {
+        let hash = self.hasher.hash_one(&*value);
+        let mut pool = self.pool.lock();
+        let values = pool.values.entry(hash).or_default();
+        let shared = values
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|shared| **shared == *value);
+        if let Some(shared) = shared {
+            return shared;
+        }
+        values.retain(|value| value.strong_count() > 0);
+        values.push(Arc::downgrade(&value));
+        pool.len += 1;
+        if pool.len > (2 * pool.pruned_to).max(FIRST_PRUNE) {
+            pool.prune();
+        }
+        value
+    }
+    /// Number of distinct values shared, after dropping those no longer
+    /// memoized.
+    pub fn interned(&self) -> usize {
+        let mut pool = self.pool.lock();
+        pool.prune();
+        pool.len
+    }
+}
+impl<V: Eq + Hash, M: Memory<Arc<V>>> Memory<Arc<V>> for InternedMemory<V, M> {
+    fn memoize(&self, key: &str, value: Arc<V>) {
+        self.memory.memoize(key, self.intern(value));
+    }
+    fn retrieve(&self, key: &str) -> Option<Arc<V>> {
+        self.memory.retrieve(key)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn interned() {
+        let brain = Brain::new(1.minutes());
+        let memory = InternedMemory::new(brain.clone());
+        for key in ["a", "b", "c"] {
+            memory.memoize(key, Arc::new("ok".repeat(100)));
+        }
+        memory.memoize("d", Arc::new("error".to_string()));
+        let (a, c) = (memory.retrieve("a").unwrap(), memory.retrieve("c").unwrap());
+        assert!(Arc::ptr_eq(&a, &c));
+        assert_eq!(memory.interned(), 2);
+
+        drop((a, c));
+        for key in ["a", "b", "c"] {
+            brain.remove(key);
+        }
+        assert_eq!(memory.interned(), 1);
+    }
+}
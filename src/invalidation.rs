@@ -0,0 +1,349 @@
+use crate::protocol::{field, read_frame_within, request, write_frame, AUTH, MAX_FRAME};
+use crate::server::Connection;
+use crate::transport::{Duplex, Stream, Wrap};
+use crate::{Brain, Duration, Memory, ServerConfig};
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+/// Time to connect to a peer and send it an invalidation.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How an [`Invalidating`] node talks to its peers.
+#[derive(Clone, Default)]
+pub struct InvalidationConfig {
+    listen: ServerConfig,
+    token: Option<Arc<str>>,
+    wrap: Option<Wrap>,
+}
+impl fmt::Debug for InvalidationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InvalidationConfig")
+            .field("listen", &self.listen)
+            .field("token", &self.token.as_ref().map(|_| ".."))
+            .field("wrap", &self.wrap.is_some())
+            .finish()
+    }
+}
+impl InvalidationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Receives invalidations only from peers the config
+    /// [authorizes](ServerConfig::authorize), over connections it may
+    /// [wrap](ServerConfig::wrap), within its timeouts. Its limit of
+    /// connections doesn't apply.
+    pub fn listen(mut self, config: ServerConfig) -> Self {
+        self.listen = config;
+        self
+    }
+    /// Presents the token to every peer.
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+    /// Sends invalidations through the stream the hook makes of every
+    /// connection to a peer, such as a TLS session for peers which wrap
+    /// theirs.
+    pub fn wrap(
+        mut self,
+        wrap: impl Fn(TcpStream) -> io::Result<Box<dyn Duplex>> + Send + Sync + 'static,
+    ) -> Self {
+        self.wrap = Some(Arc::new(wrap));
+        self
+    }
+}
+
+/// A brain telling its peers to drop their copies of the keys it memoizes
+/// or removes, and dropping its own when they tell it.
+///
+/// Every node listens for invalidations, frames holding a key, and sends
+/// them to its peers in the background, so writes don't wait for peers.
+/// Connections open with an `AUTH` frame holding the sender's token, empty
+/// if it has none, which the receiver's config has to authorize.
+/// Invalidations are sent at most once: a peer which can't be reached,
+/// even after reconnecting once, misses them and may serve its stale copy
+/// until it expires. Received invalidations aren't passed on, so every node
+/// needs all others as peers. Expired values are forgotten by every node
+/// on its own and not sent.
+pub struct Invalidating<T> {
+    brain: Brain<T>,
+    address: SocketAddr,
+    peers: Arc<Mutex<BTreeSet<String>>>,
+    outbox: Option<mpsc::Sender<String>>,
+    stopping: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+impl<T: Send + Sync + 'static> Invalidating<T> {
+    /// Listens for invalidations of the brain and starts sending its own.
+    pub fn bind(address: impl ToSocketAddrs, brain: Brain<T>, peers: &[&str]) -> io::Result<Self> {
+        Self::bind_with(address, brain, peers, InvalidationConfig::default())
+    }
+    /// Like [`Invalidating::bind`], talking to the peers the way the config
+    /// tells.
+    pub fn bind_with(
+        address: impl ToSocketAddrs,
+        brain: Brain<T>,
+        peers: &[&str],
+        config: InvalidationConfig,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let peers = Arc::new(Mutex::new(
+            peers.iter().map(|peer| peer.to_string()).collect(),
+        ));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let (outbox, inbox) = mpsc::channel();
+        let listening = {
+            let (brain, stopping) = (brain.clone(), stopping.clone());
+            let config = config.listen.clone();
+            thread::spawn(move || listen(listener, brain, config, &stopping))
+        };
+        let sending = {
+            let peers = peers.clone();
+            thread::spawn(move || send(inbox, &peers, &config))
+        };
+        Ok(Self {
+            brain,
+            address,
+            peers,
+            outbox: Some(outbox),
+            stopping,
+            threads: vec![listening, sending],
+        })
+    }
+}
+impl<T> Invalidating<T> {
+    pub fn brain(&self) -> &Brain<T> {
+        &self.brain
+    }
+    /// Address the node listens for invalidations on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+    pub fn add_peer(&self, address: &str) {
+        self.peers.lock().insert(address.to_string());
+    }
+    /// Stops sending invalidations to the peer, telling whether it was known.
+    pub fn remove_peer(&self, address: &str) -> bool {
+        self.peers.lock().remove(address)
+    }
+    /// Tells the peers to drop their copies of the key.
+    pub fn invalidate(&self, key: &str) {
+        if let Some(outbox) = &self.outbox {
+            let _ = outbox.send(key.to_string());
+        }
+    }
+    /// Removes the value for the key, here and on the peers.
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.invalidate(key);
+        self.brain.remove(key)
+    }
+    /// Sends the invalidations still queued and stops listening.
+    pub fn shutdown(mut self) -> thread::Result<()> {
+        self.stop()
+    }
+    fn stop(&mut self) -> thread::Result<()> {
+        self.stopping.store(true, Ordering::Relaxed);
+        self.outbox = None;
+        // wakes the listener up
+        let _ = TcpStream::connect_timeout(&self.address, TIMEOUT);
+        self.threads.drain(..).try_for_each(JoinHandle::join)
+    }
+}
+impl<T: Clone> Invalidating<T> {
+    /// Memoizes a value with its own retention, invalidating the peers' copies.
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        self.brain.memoize_for(key, value, retention);
+        self.invalidate(key);
+    }
+}
+impl<T> Drop for Invalidating<T> {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+impl<T: Clone> Memory<T> for Invalidating<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.brain.memoize(key, value);
+        self.invalidate(key);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(key)
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+
+fn listen<T: Send + Sync + 'static>(
+    listener: TcpListener,
+    brain: Brain<T>,
+    config: ServerConfig,
+    stopping: &AtomicBool,
+) {
+    for stream in listener.incoming() {
+        if stopping.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let (brain, config) = (brain.clone(), config.clone());
+        thread::spawn(move || {
+            let Ok(mut connection) = Connection::new(Stream::Tcp(stream), config) else {
+                return;
+            };
+            if !matches!(admit(&mut connection), Ok(true)) {
+                return;
+            }
+            while let Ok(true) = connection.wait() {
+                let max = connection.max_request(MAX_FRAME);
+                let Ok(Some(key)) = read_frame_within(&mut connection.reader, max) else {
+                    break;
+                };
+                if let Ok(key) = std::str::from_utf8(&key) {
+                    brain.remove(key);
+                }
+            }
+        });
+    }
+}
+
+/// Reads the `AUTH` frame opening a connection, telling whether its token
+/// is authorized.
+fn admit(connection: &mut Connection) -> io::Result<bool> {
+    let max = connection.max_request(MAX_FRAME);
+    let Some(frame) = read_frame_within(&mut connection.reader, max)? else {
+        return Ok(false);
+    };
+    Ok(match frame.split_first() {
+        Some((&AUTH, mut fields)) => match field(&mut fields) {
+            Some(token) => connection.authorize(&String::from_utf8_lossy(token)),
+            None => false,
+        },
+        _ => false,
+    })
+}
+
+/// Sends every key to every peer, reconnecting once to a peer which failed.
+fn send(
+    inbox: mpsc::Receiver<String>,
+    peers: &Mutex<BTreeSet<String>>,
+    config: &InvalidationConfig,
+) {
+    let mut connections = HashMap::<String, BufWriter<Stream>>::new();
+    while let Ok(key) = inbox.recv() {
+        let peers = peers.lock().clone();
+        connections.retain(|peer, _| peers.contains(peer));
+        for peer in peers {
+            let sent = connections
+                .remove(&peer)
+                .map_or_else(|| Err(io::ErrorKind::NotConnected.into()), Ok)
+                .and_then(|connection| deliver(connection, &key))
+                .or_else(|_| deliver(connect(&peer, config)?, &key));
+            if let Ok(connection) = sent {
+                connections.insert(peer, connection);
+            }
+        }
+    }
+}
+
+/// Connects to the peer, presenting the token.
+fn connect(peer: &str, config: &InvalidationConfig) -> io::Result<BufWriter<Stream>> {
+    let mut failure = io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to");
+    for address in peer.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => {
+                let stream = Stream::Tcp(stream);
+                stream.set_nodelay()?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                let stream = match &config.wrap {
+                    Some(wrap) => stream.wrap(wrap)?,
+                    None => stream,
+                };
+                let mut connection = BufWriter::new(stream);
+                let token = config.token.as_deref().unwrap_or_default();
+                write_frame(&mut connection, &request(AUTH, token))?;
+                return Ok(connection);
+            }
+            Err(error) => failure = error,
+        }
+    }
+    Err(failure)
+}
+
+fn deliver(mut connection: BufWriter<Stream>, key: &str) -> io::Result<BufWriter<Stream>> {
+    write_frame(&mut connection, key.as_bytes())?;
+    connection.flush()?;
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn invalidation() {
+        let a = Invalidating::bind("127.0.0.1:0", Brain::new(1.minutes()), &[]).unwrap();
+        let peer = a.local_addr().to_string();
+        let b = Invalidating::bind("127.0.0.1:0", Brain::new(1.minutes()), &[&peer]).unwrap();
+        a.add_peer(&b.local_addr().to_string());
+
+        a.brain().memoize("x", 1);
+        a.brain().memoize("y", 2);
+        b.memoize("x", 3);
+        b.remove("y");
+        let dropped = || !a.brain().is_cached("x") && !a.brain().is_cached("y");
+        for _ in 0..100 {
+            if dropped() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(dropped());
+        assert_eq!(b.retrieve("x"), Some(3));
+
+        assert!(a.remove_peer(&b.local_addr().to_string()));
+        b.shutdown().unwrap();
+        a.shutdown().unwrap();
+    }
+
+    #[test]
+    fn authorized_peers() {
+        let listen = ServerConfig::new().authorize(|token| token == "secret");
+        let config = InvalidationConfig::new().listen(listen);
+        let a = Invalidating::bind_with("127.0.0.1:0", Brain::new(1.minutes()), &[], config);
+        let a = a.unwrap();
+        let peer = a.local_addr().to_string();
+        let stranger = Invalidating::bind("127.0.0.1:0", Brain::new(1.minutes()), &[&peer]);
+        let stranger = stranger.unwrap();
+        let config = InvalidationConfig::new().token("secret");
+        let b = Invalidating::bind_with("127.0.0.1:0", Brain::new(1.minutes()), &[&peer], config);
+        let b = b.unwrap();
+
+        a.brain().memoize("x", 1);
+        a.brain().memoize("y", 2);
+        stranger.memoize("x", 3);
+        b.memoize("y", 4);
+        for _ in 0..100 {
+            if !a.brain().is_cached("y") {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(!a.brain().is_cached("y"));
+        assert_eq!(a.retrieve("x"), Some(1));
+
+        stranger.shutdown().unwrap();
+        b.shutdown().unwrap();
+        a.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,1044 @@
+//! A small JSON format for serde, used by the persistence and network parts.
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use std::fmt::{self, Display, Write};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for Error {}
+impl ser::Error for Error {
+    fn custom<M: Display>(message: M) -> Self {
+        Error(message.to_string())
+    }
+}
+impl de::Error for Error {
+    fn custom<M: Display>(message: M) -> Self {
+        Error(message.to_string())
+    }
+}
+
+pub fn to_string<V: Serialize + ?Sized>(value: &V) -> Result<String, Error> {
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+pub fn from_str<V: DeserializeOwned>(text: &str) -> Result<V, Error> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.position != parser.text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    V::deserialize(value)
+}
+
+#[derive(Default)]
+struct Serializer {
+    output: String,
+}
+impl Serializer {
+    fn string(&mut self, value: &str) {
+        self.output.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                c if c < ' ' => {
+                    let _ = write!(self.output, "\\u{:04x}", c as u32);
+                }
+                c => self.output.push(c),
+            }
+        }
+        self.output.push('"');
+    }
+    fn number(&mut self, value: impl Display) {
+        let _ = write!(self.output, "{value}");
+    }
+}
+
+/// Separates the elements of arrays and objects.
+struct Compound<'a> {
+    serializer: &'a mut Serializer,
+    first: bool,
+    /// Closing characters, two for variants wrapped in an object.
+    closing: &'static str,
+}
+impl Compound<'_> {
+    fn separate(&mut self) {
+        if !self.first {
+            self.serializer.output.push(',');
+        }
+        self.first = false;
+    }
+    fn key(&mut self, key: &str) {
+        self.separate();
+        self.serializer.string(key);
+        self.serializer.output.push(':');
+    }
+    fn close(self) -> Result<(), Error> {
+        self.serializer.output.push_str(self.closing);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.output.push_str(if v { "true" } else { "false" });
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.number(v);
+        Ok(())
+    }
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.number(v);
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.number(v);
+        Ok(())
+    }
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.number(v);
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if v.is_finite() {
+            self.number(v);
+        } else {
+            self.output.push_str("null");
+        }
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.string(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.string(v);
+        Ok(())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        use ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for byte in v {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.output.push_str("null");
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        self.output.push('{');
+        self.string(variant);
+        self.output.push(':');
+        value.serialize(&mut *self)?;
+        self.output.push('}');
+        Ok(())
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.output.push('[');
+        Ok(Compound {
+            serializer: self,
+            first: true,
+            closing: "]",
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        self.string(variant);
+        self.output.push_str(":[");
+        Ok(Compound {
+            serializer: self,
+            first: true,
+            closing: "]}",
+        })
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        Ok(Compound {
+            serializer: self,
+            first: true,
+            closing: "}",
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        self.string(variant);
+        self.output.push_str(":{");
+        Ok(Compound {
+            serializer: self,
+            first: true,
+            closing: "}}",
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Error> {
+        self.separate();
+        value.serialize(&mut *self.serializer)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<V: Serialize + ?Sized>(&mut self, key: &V) -> Result<(), Error> {
+        self.separate();
+        key.serialize(KeySerializer(&mut *self.serializer))?;
+        self.serializer.output.push(':');
+        Ok(())
+    }
+    fn serialize_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), Error> {
+        value.serialize(&mut *self.serializer)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        self.key(key);
+        value.serialize(&mut *self.serializer)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+/// Writes map keys, which JSON only allows as strings.
+struct KeySerializer<'a>(&'a mut Serializer);
+impl KeySerializer<'_> {
+    fn display(self, key: impl Display) -> Result<(), Error> {
+        self.0.string(&key.to_string());
+        Ok(())
+    }
+}
+impl ser::Serializer for KeySerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<(), Error>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = ser::Impossible<(), Error>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.display(v)
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(Error("bytes as map key".into()))
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Error("none as map key".into()))
+    }
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(Error("unit as map key".into()))
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.display(variant)
+    }
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &V,
+    ) -> Result<(), Error> {
+        Err(Error("variant as map key".into()))
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error("sequence as map key".into()))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error("tuple as map key".into()))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error("tuple as map key".into()))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error("variant as map key".into()))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error("map as map key".into()))
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error("struct as map key".into()))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error("variant as map key".into()))
+    }
+}
+
+/// A parsed JSON document.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/// Arrays and objects nested deeper are rejected rather than parsed
+/// recursively, so hostile documents can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+    /// Arrays and objects open at the position.
+    depth: usize,
+}
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error(format!("{message} at {}", self.position))
+    }
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.position) {
+            self.position += 1;
+        }
+    }
+    fn peek(&mut self) -> Option<u8> {
+        self.whitespace();
+        self.text.get(self.position).copied()
+    }
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", byte as char)))
+        }
+    }
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, Error> {
+        if self.text[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unknown literal"))
+        }
+    }
+    fn value(&mut self) -> Result<Value, Error> {
+        if let Some(b'[' | b'{') = self.peek() {
+            if self.depth == MAX_DEPTH {
+                return Err(self.error("nested too deeply"));
+            }
+            self.depth += 1;
+            let value = self.compound();
+            self.depth -= 1;
+            return value;
+        }
+        match self.peek() {
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("expected value")),
+        }
+    }
+    /// Parses the array or object at the position.
+    fn compound(&mut self) -> Result<Value, Error> {
+        match self.peek() {
+            Some(b'[') => {
+                self.position += 1;
+                let mut elements = Vec::new();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(Value::Array(elements));
+                }
+                loop {
+                    elements.push(self.value()?);
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']')?;
+                Ok(Value::Array(elements))
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected key"));
+                    }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}')?;
+                Ok(Value::Object(members))
+            }
+            _ => Err(self.error("expected value")),
+        }
+    }
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.text.get(self.position)
+        {
+            self.position += 1;
+        }
+        let number = std::str::from_utf8(&self.text[start..self.position])
+            .ok()
+            .filter(|number| is_number(number))
+            .ok_or_else(|| self.error("invalid number"))?;
+        if let Ok(unsigned) = number.parse() {
+            Ok(Value::Unsigned(unsigned))
+        } else if let Ok(signed) = number.parse() {
+            Ok(Value::Signed(signed))
+        } else {
+            number
+                .parse::<f64>()
+                .ok()
+                .filter(|float| float.is_finite())
+                .map(Value::Float)
+                .ok_or_else(|| self.error("number out of range"))
+        }
+    }
+    fn hex(&mut self) -> Result<u32, Error> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(&byte) = self.text.get(self.position) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escaped) = self.text.get(self.position) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.position += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let high = self.hex()?;
+                            let code = if (0xd800..0xdc00).contains(&high) {
+                                // a high surrogate has to be followed by a low one
+                                if !self.text[self.position..].starts_with(b"\\u") {
+                                    return Err(self.error("lone surrogate"));
+                                }
+                                self.position += 2;
+                                let low = self.hex()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(self.error("lone surrogate"));
+                                }
+                                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                            } else {
+                                high
+                            };
+                            char::from_u32(code).ok_or_else(|| self.error("invalid unicode"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid utf-8"))
+    }
+}
+
+/// Tells whether the text is a number by JSON's grammar, e.g. without a
+/// leading `+` or zeros.
+fn is_number(text: &str) -> bool {
+    fn digits(text: &str) -> (usize, &str) {
+        let end = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        (end, &text[end..])
+    }
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    let rest = match digits(unsigned) {
+        (0, _) => return false,
+        (1, rest) => rest,
+        (_, rest) if !unsigned.starts_with('0') => rest,
+        _ => return false,
+    };
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => match digits(fraction) {
+            (0, _) => return false,
+            (_, rest) => rest,
+        },
+        None => rest,
+    };
+    match rest.strip_prefix(['e', 'E']) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            matches!(digits(exponent), (1.., ""))
+        }
+        None => rest.is_empty(),
+    }
+}
+
+impl Value {
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            Value::Null => de::Unexpected::Unit,
+            Value::Bool(v) => de::Unexpected::Bool(*v),
+            Value::Signed(v) => de::Unexpected::Signed(*v),
+            Value::Unsigned(v) => de::Unexpected::Unsigned(*v),
+            Value::Float(v) => de::Unexpected::Float(*v),
+            Value::String(v) => de::Unexpected::Str(v),
+            Value::Array(_) => de::Unexpected::Seq,
+            Value::Object(_) => de::Unexpected::Map,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Signed(v) => visitor.visit_i64(v),
+            Value::Unsigned(v) => visitor.visit_u64(v),
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Array(elements) => {
+                visitor.visit_seq(de::value::SeqDeserializer::new(elements.into_iter()))
+            }
+            Value::Object(members) => visitor.visit_map(de::value::MapDeserializer::new(
+                members.into_iter().map(|(key, value)| (Key(key), value)),
+            )),
+        }
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(mut members) if members.len() == 1 => {
+                let (variant, content) = members.remove(0);
+                visitor.visit_enum(Variant { variant, content })
+            }
+            value => Err(de::Error::invalid_type(value.unexpected(), &"enum")),
+        }
+    }
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            // non-finite floats are written as null
+            Value::Null => visitor.visit_f64(f64::NAN),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Reads object keys, parsing them for types other than strings.
+struct Key(String);
+macro_rules! parse_key {
+    ($($deserialize:ident => $visit:ident,)*) => {$(
+        fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.0.parse() {
+                Ok(key) => visitor.$visit(key),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&self.0), &visitor)),
+            }
+        }
+    )*};
+}
+impl<'de> de::Deserializer<'de> for Key {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+    parse_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+impl<'de> IntoDeserializer<'de, Error> for Key {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct Variant {
+    variant: String,
+    content: Value,
+}
+impl<'de> de::EnumAccess<'de> for Variant {
+    type Error = Error;
+    type Variant = Value;
+    fn variant_seed<S: de::DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Value), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.content))
+    }
+}
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = Error;
+    fn unit_variant(self) -> Result<(), Error> {
+        match self {
+            Value::Null => Ok(()),
+            value => Err(de::Error::invalid_type(value.unexpected(), &"unit variant")),
+        }
+    }
+    fn newtype_variant_seed<S: de::DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self)
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::IgnoredAny;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn roundtrip() {
+        let value = (
+            "quote \" and \\ and \n and é".to_string(),
+            vec![Some(-3i64), None, Some(u32::MAX.into())],
+            BTreeMap::from([(1u8, 2.5f64), (2, -0.125)]),
+            (true, 'x', ()),
+        );
+        let text = to_string(&value).unwrap();
+        assert_eq!(
+            text,
+            r#"["quote \" and \\ and \n and é",[-3,null,4294967295],{"1":2.5,"2":-0.125},[true,"x",null]]"#
+        );
+        assert_eq!(
+            from_str::<(
+                String,
+                Vec<Option<i64>>,
+                BTreeMap<u8, f64>,
+                (bool, char, ())
+            )>(&text)
+            .unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            from_str::<Vec<String>>(r#" [ "aé😀" , "\/" ] "#).unwrap(),
+            vec!["aé😀".to_string(), "/".to_string()]
+        );
+        assert_eq!(from_str::<f64>("-1.5e3").unwrap(), -1500.0);
+        assert!(from_str::<u8>("1 2").is_err());
+        assert!(from_str::<Vec<u8>>("[1,").is_err());
+        assert!(from_str::<String>(r#""open"#).is_err());
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            from_str::<String>(r#""\"\\\/\b\f\n\r\t\u00e9\u20AC""#).unwrap(),
+            "\"\\/\u{8}\u{c}\n\r\t\u{e9}\u{20ac}"
+        );
+        assert_eq!(from_str::<String>(r#""\ud83d\ude00""#).unwrap(), "😀");
+        for lone in [
+            r#""\ud83d""#,
+            r#""\ud83dx""#,
+            r#""\ud83d\u0041""#,
+            r#""\ude00""#,
+        ] {
+            assert!(from_str::<String>(lone).is_err(), "{lone}");
+        }
+        assert!(from_str::<String>(r#""\x""#).is_err());
+        assert!(from_str::<String>(r#""\u12""#).is_err());
+        let mut parser = Parser {
+            text: b"\"\xff\"",
+            position: 0,
+            depth: 0,
+        };
+        assert!(parser.string().is_err());
+        assert_eq!(to_string("\u{1}\u{1f}").unwrap(), r#""\u0001\u001f""#);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(from_str::<u64>("18446744073709551615").unwrap(), u64::MAX);
+        assert_eq!(from_str::<i64>("-9223372036854775808").unwrap(), i64::MIN);
+        assert!(from_str::<u64>("18446744073709551616").is_err());
+        assert!(from_str::<i64>("-9223372036854775809").is_err());
+        // integers beyond 64 bits are parsed as floats
+        assert_eq!(
+            from_str::<f64>("18446744073709551616").unwrap(),
+            2f64.powi(64)
+        );
+        assert_eq!(from_str::<i64>("-0").unwrap(), 0);
+        assert_eq!(
+            from_str::<f64>("-0.0").unwrap().to_bits(),
+            (-0f64).to_bits()
+        );
+        assert_eq!(from_str::<f64>("1E2").unwrap(), 100.0);
+        assert_eq!(from_str::<f64>("2.5e-1").unwrap(), 0.25);
+        assert_eq!(from_str::<f64>("1e+2").unwrap(), 100.0);
+        assert!(from_str::<u8>("256").is_err());
+        for invalid in [
+            "+1", "01", "1.", ".5", "1e", "1e+", "-", "--1", "1.2.3", "1e400",
+        ] {
+            assert!(from_str::<f64>(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+    }
+
+    #[test]
+    fn trailing() {
+        assert!(from_str::<Vec<u8>>("[1] x").is_err());
+        assert!(from_str::<Vec<u8>>("[1]]").is_err());
+        assert!(from_str::<Vec<u8>>("[1,]").is_err());
+        assert!(from_str::<BTreeMap<String, u8>>(r#"{"a":1,}"#).is_err());
+        assert!(from_str::<bool>("truex").is_err());
+        assert_eq!(from_str::<Vec<u8>>(" [1] \n").unwrap(), [1]);
+    }
+
+    #[test]
+    fn depth() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(from_str::<IgnoredAny>(&nested(MAX_DEPTH)).is_ok());
+        let error = from_str::<IgnoredAny>(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert!(error.to_string().starts_with("nested too deeply"));
+        // rejected long before the end of the document
+        assert!(from_str::<IgnoredAny>(&"[{\"a\":".repeat(200_000)).is_err());
+    }
+}
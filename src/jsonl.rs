@@ -0,0 +1,173 @@
+use crate::wall_clock;
+use crate::{json, Attributes, Brain, Duration, Engram, StorageBackend};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// An engram as a line of JSON.
+///
+/// `inserted_at` is a Unix timestamp in nanoseconds, `retention_seconds` the
+/// engram's own retention if it has one, `context` and `attributes` those it
+/// was memoized with, if any.
+struct Line<K, T> {
+    key: K,
+    value: T,
+    inserted_at: i128,
+    retention_seconds: Option<f64>,
+    context: Option<Arc<str>>,
+    attributes: Option<Arc<Attributes>>,
+}
+
+impl<T: Serialize, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Writes the engrams as JSON Lines, one `key`, `value`, `inserted_at` object per line.
+    pub fn export_jsonl(&self, mut writer: impl Write) -> io::Result<()> {
+        for (key, engram) in self.memory.read().scan() {
+            let line = Line {
+                key,
+                value: &engram.value,
+                inserted_at: engram.memoized.unix_timestamp_nanos(),
+                retention_seconds: engram.retention.map(Duration::as_seconds_f64),
+                context: engram.context.clone(),
+                attributes: engram.attributes.clone(),
+            };
+            let mut line = json::to_string(&line).map_err(invalid_data)?;
+            line.push('\n');
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.flush()
+    }
+}
+impl<T: DeserializeOwned, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Memoizes the engrams of JSON Lines written by [`Brain::export_jsonl`].
+    ///
+    /// Engrams which already expired are skipped, blank lines too. Returns
+    /// the number of engrams memoized.
+    pub fn import_jsonl(&self, reader: impl BufRead) -> io::Result<usize> {
+        let now = wall_clock::now();
+        let mut imported = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line = json::from_str::<Line<String, T>>(&line).map_err(invalid_data)?;
+            let memoized = OffsetDateTime::from_unix_timestamp_nanos(line.inserted_at)
+                .map_err(invalid_data)?;
+            let engram = Engram {
+                memoized,
+                retention: line.retention_seconds.map(Duration::seconds_f64),
+                context: line.context,
+                attributes: line.attributes,
+                ..Engram::new(line.value, None)
+            };
+            if !engram.is_expired(self.retention, now) {
+                self.dirty.touch(&line.key);
+                let mut memory = self.memory.write();
+                self.filter_key(&line.key);
+                memory.put(line.key, engram);
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<K: Serialize, T: Serialize> Serialize for Line<K, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut line = serializer.serialize_struct("Line", 6)?;
+        line.serialize_field("key", &self.key)?;
+        line.serialize_field("value", &self.value)?;
+        line.serialize_field("inserted_at", &self.inserted_at)?;
+        line.serialize_field("retention_seconds", &self.retention_seconds)?;
+        line.serialize_field("context", &self.context.as_deref())?;
+        line.serialize_field("attributes", &self.attributes.as_deref())?;
+        line.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Line<String, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LineVisitor<T>(PhantomData<T>);
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for LineVisitor<T> {
+            type Value = Line<String, T>;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an engram line")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let (mut key, mut value, mut inserted_at, mut retention_seconds) =
+                    (None, None, None, None);
+                let (mut context, mut attributes) = (None::<String>, None::<Attributes>);
+                while let Some(field) = map.next_key::<String>()? {
+                    match field.as_str() {
+                        "key" => key = Some(map.next_value()?),
+                        "value" => value = Some(map.next_value()?),
+                        "inserted_at" => inserted_at = Some(map.next_value()?),
+                        "retention_seconds" => retention_seconds = map.next_value()?,
+                        "context" => context = map.next_value()?,
+                        "attributes" => attributes = map.next_value()?,
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(Line {
+                    key: key.ok_or_else(|| de::Error::missing_field("key"))?,
+                    value: value.ok_or_else(|| de::Error::missing_field("value"))?,
+                    inserted_at: inserted_at
+                        .ok_or_else(|| de::Error::missing_field("inserted_at"))?,
+                    retention_seconds,
+                    context: context.map(Arc::from),
+                    attributes: attributes.map(Arc::new),
+                })
+            }
+        }
+        deserializer.deserialize_struct(
+            "Line",
+            &[
+                "key",
+                "value",
+                "inserted_at",
+                "retention_seconds",
+                "context",
+                "attributes",
+            ],
+            LineVisitor(PhantomData),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn jsonl() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", vec![1, 2]);
+        memory.memoize_for("b", vec![3], 1.hours());
+        let mut exported = Vec::new();
+        memory.export_jsonl(&mut exported).unwrap();
+        let text = String::from_utf8(exported).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains(r#"{"key":"a","value":[1,2],"inserted_at":"#));
+
+        let expired = r#"{"key":"c","value":[4],"inserted_at":0,"retention_seconds":null}"#;
+        let restored = Brain::<Vec<i32>>::new(1.minutes());
+        let imported = restored
+            .import_jsonl(format!("{text}\n{expired}\n").as_bytes())
+            .unwrap();
+        assert_eq!(imported, 2);
+        assert_eq!(restored.retrieve("a"), Some(vec![1, 2]));
+        assert_eq!(restored.memory.read()["b"].retention, Some(1.hours()));
+        assert!(!restored.is_cached("c"));
+    }
+}
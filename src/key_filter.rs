@@ -0,0 +1,112 @@
+use crate::{Brain, StorageBackend};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bits per expected key, for about 1% of false positives.
+const BITS_PER_KEY: usize = 10;
+const HASHES: u64 = 7;
+
+/// A Bloom filter of the keys memoized, telling keys surely missing from
+/// the brain without locking it.
+///
+/// Keys are added while the brain is write-locked, so the filter holds
+/// every key the storage does. It's rebuilt from the storage, still
+/// locked, when sweeps remove entries: word by word, every word holding
+/// the bits of the stored keys both before and after.
+pub(crate) struct KeyFilter {
+    hasher: RandomState,
+    words: Box<[AtomicU64]>,
+}
+impl KeyFilter {
+    pub(crate) fn new(expected_keys: usize) -> Self {
+        let words = (expected_keys.max(1) * BITS_PER_KEY).div_ceil(64);
+        Self {
+            hasher: RandomState::new(),
+            words: (0..words).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+    fn bits(&self, key: &str) -> impl Iterator<Item = (usize, u64)> {
+        let hash = self.hasher.hash_one(key);
+        let (first, second) = (hash, (hash >> 32) | 1);
+        let len = self.words.len() as u64 * 64;
+        (0..HASHES).map(move |i| {
+            let bit = first.wrapping_add(i.wrapping_mul(second)) % len;
+            ((bit / 64) as usize, 1 << (bit % 64))
+        })
+    }
+    pub(crate) fn insert(&self, key: &str) {
+        for (word, bit) in self.bits(key) {
+            self.words[word].fetch_or(bit, Ordering::Relaxed);
+        }
+    }
+    pub(crate) fn may_contain(&self, key: &str) -> bool {
+        self.bits(key)
+            .all(|(word, bit)| self.words[word].load(Ordering::Relaxed) & bit != 0)
+    }
+    pub(crate) fn rebuild<'k>(&self, keys: impl Iterator<Item = &'k String>) {
+        let mut words = vec![0u64; self.words.len()];
+        for key in keys {
+            for (word, bit) in self.bits(key) {
+                words[word] |= bit;
+            }
+        }
+        for (word, bits) in self.words.iter().zip(words) {
+            word.store(bits, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Adds the key to the filter, with the brain write-locked.
+    pub(crate) fn filter_key(&self, key: &str) {
+        if let Some(filter) = &self.key_filter {
+            filter.insert(key);
+        }
+    }
+    /// Tells whether the filter rules the key out.
+    pub(crate) fn surely_missing(&self, key: &str) -> bool {
+        self.key_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.may_contain(key))
+    }
+    /// Rebuilds the filter from the storage, with the brain write-locked.
+    pub(crate) fn refilter(&self, memory: &S) {
+        if let Some(filter) = &self.key_filter {
+            filter.rebuild(memory.scan().map(|(key, _)| key));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn key_filter() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .key_filter(100)
+            .build();
+        for key in 0..100 {
+            brain.memoize_for(&key.to_string(), key, 1.milliseconds());
+        }
+        brain.memoize("kept", -1);
+        assert!((0..100).all(|key| !brain.surely_missing(&key.to_string())));
+        let missing = (100..1100)
+            .filter(|key| brain.surely_missing(&key.to_string()))
+            .count();
+        assert!(missing > 950, "{missing}");
+        assert_eq!(brain.retrieve("never"), None);
+        assert_eq!(brain.stats().misses, 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.forget();
+        assert_eq!(brain.retrieve("kept"), Some(-1));
+        let missing = (0..100)
+            .filter(|key| brain.surely_missing(&key.to_string()))
+            .count();
+        assert!(missing > 95, "{missing}");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::wall_clock;
+use crate::wall_clock::Instant;
+use crate::{Brain, Duration, Engram, StorageBackend};
+use std::fmt;
+use std::io;
+
+/// Given instead of a lease someone else holds.
+#[derive(Debug)]
+pub enum Held {
+    /// Another holder has the lease, named by its id.
+    By(String),
+    /// The memory keeping the leases couldn't be asked.
+    Failed(io::Error),
+}
+impl fmt::Display for Held {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::By(holder) => write!(f, "lease held by {holder}"),
+            Self::Failed(error) => write!(f, "lease unavailable: {error}"),
+        }
+    }
+}
+impl std::error::Error for Held {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::By(_) => None,
+            Self::Failed(error) => Some(error),
+        }
+    }
+}
+impl From<io::Error> for Held {
+    fn from(error: io::Error) -> Self {
+        Self::Failed(error)
+    }
+}
+
+/// Memories granting leases on keys, e.g. so only one worker of a cluster
+/// refreshes a value.
+///
+/// A lease is the id of its holder memoized for its ttl, taken only if no
+/// other holder's is memoized, all in one step.
+pub trait Leasing {
+    /// Holds the lease on the key for the ttl, renewing it if the holder
+    /// already does.
+    fn try_hold(&self, key: &str, holder: &str, ttl: Duration) -> Result<(), Held>;
+    /// Lets go of the lease, telling whether the holder held it.
+    fn try_release(&self, key: &str, holder: &str) -> io::Result<bool>;
+
+    /// Takes the lease on the key for the ttl, or tells who holds it.
+    fn acquire_lease(
+        &self,
+        key: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<Lease<'_, Self>, Held> {
+        let started = Instant::now();
+        self.try_hold(key, holder, ttl)?;
+        Ok(Lease {
+            leasing: self,
+            key: key.to_string(),
+            holder: holder.to_string(),
+            expires: started + ttl.unsigned_abs(),
+            released: false,
+        })
+    }
+}
+
+/// A lease taken by [`Leasing::acquire_lease`], released when dropped.
+///
+/// The lease may expire before, as seen by the memory keeping it, so work
+/// outlasting the ttl should [`Lease::renew`] it.
+pub struct Lease<'a, L: Leasing + ?Sized> {
+    leasing: &'a L,
+    key: String,
+    holder: String,
+    expires: Instant,
+    released: bool,
+}
+impl<L: Leasing + ?Sized> Lease<'_, L> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+    /// Time left until the lease expires, at the latest, zero once it has.
+    pub fn time_left(&self) -> Duration {
+        self.expires
+            .saturating_duration_since(Instant::now())
+            .try_into()
+            .unwrap_or(Duration::ZERO)
+    }
+    /// Holds the lease for the ttl from now on, unless it was lost meanwhile.
+    pub fn renew(&mut self, ttl: Duration) -> Result<(), Held> {
+        let started = Instant::now();
+        self.leasing.try_hold(&self.key, &self.holder, ttl)?;
+        self.expires = started + ttl.unsigned_abs();
+        Ok(())
+    }
+    /// Lets go of the lease, telling whether it was still held.
+    pub fn release(mut self) -> io::Result<bool> {
+        self.released = true;
+        self.leasing.try_release(&self.key, &self.holder)
+    }
+}
+impl<L: Leasing + ?Sized> Drop for Lease<'_, L> {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self.leasing.try_release(&self.key, &self.holder);
+        }
+    }
+}
+
+/// Leases are kept as the bytes of their holder's id.
+impl<T, S> Leasing for Brain<T, S>
+where
+    T: Clone + AsRef<[u8]> + for<'a> From<&'a str>,
+    S: StorageBackend<String, T>,
+{
+    fn try_hold(&self, key: &str, holder: &str, ttl: Duration) -> Result<(), Held> {
+        let now = wall_clock::now();
+        let mut held = None;
+        let lease = Engram::new(T::from(holder), Some(ttl));
+        self.store_if(key, lease, |engram| match engram {
+            Some(engram)
+                if !engram.is_expired(self.retention, now)
+                    && engram.value.as_ref() != holder.as_bytes() =>
+            {
+                held = Some(String::from_utf8_lossy(engram.value.as_ref()).into_owned());
+                false
+            }
+            _ => true,
+        });
+        held.map_or(Ok(()), |holder| Err(Held::By(holder)))
+    }
+    fn try_release(&self, key: &str, holder: &str) -> io::Result<bool> {
+        let now = wall_clock::now();
+        let released = self.remove_if(key, |engram| {
+            !engram.is_expired(self.retention, now) && engram.value.as_ref() == holder.as_bytes()
+        });
+        Ok(released.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn lease() {
+        let brain = Brain::<String>::new(1.minutes());
+        let mut lease = brain.acquire_lease("refresh", "a", 1.minutes()).unwrap();
+        assert!(lease.time_left() > 59.seconds());
+        match brain.acquire_lease("refresh", "b", 1.minutes()) {
+            Err(Held::By(holder)) => assert_eq!(holder, "a"),
+            _ => panic!("lease granted twice"),
+        }
+        lease.renew(1.milliseconds()).unwrap();
+        assert!(!brain.try_release("refresh", "b").unwrap());
+        assert!(lease.release().unwrap());
+
+        let lease = brain
+            .acquire_lease("refresh", "b", 1.milliseconds())
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        // expired, so another holder takes it and the old one can't release it
+        let taken = brain.acquire_lease("refresh", "c", 1.minutes()).unwrap();
+        drop(lease);
+        assert_eq!(taken.holder(), "c");
+        assert!(matches!(
+            brain.try_hold("refresh", "b", 1.minutes()),
+            Err(Held::By(_))
+        ));
+        drop(taken);
+        assert!(!brain.is_cached("refresh"));
+    }
+}
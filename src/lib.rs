@@ -1,10 +1,383 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod active_expiry;
+#[cfg(feature = "std")]
+mod alias;
+#[cfg(feature = "time")]
+mod any_brain;
+#[cfg(feature = "std")]
+mod association;
+#[cfg(feature = "time")]
+mod async_brain;
+#[cfg(feature = "std")]
+mod attributes;
+#[cfg(feature = "std")]
+mod audit;
+#[cfg(feature = "serde")]
+mod binary;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "time")]
+mod clocks;
+#[cfg(feature = "time")]
+mod codec;
+#[cfg(feature = "time")]
+mod compressed;
+#[cfg(feature = "time")]
+mod config;
+#[cfg(feature = "time")]
+mod consolidation;
+#[cfg(feature = "std")]
+mod context;
+#[cfg(feature = "time")]
+mod cow;
+#[cfg(feature = "serde")]
+mod crc;
+#[cfg(feature = "time")]
+mod crdt;
+#[cfg(feature = "time")]
+mod deadline;
+#[cfg(feature = "time")]
+mod debounce;
+#[cfg(feature = "std")]
+mod decay;
+#[cfg(feature = "time")]
+mod dedup;
+#[cfg(feature = "serde")]
+mod diff;
+#[cfg(feature = "embeddings")]
+mod embedding;
+#[cfg(feature = "time")]
+mod encrypted;
+#[cfg(feature = "std")]
+mod expiry;
+#[cfg(feature = "time")]
+mod fallback;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "serde")]
+mod file_brain;
+#[cfg(feature = "time")]
+mod fuzzy;
+#[cfg(feature = "global")]
+pub mod global;
+#[cfg(feature = "gossip")]
+mod gossip;
+#[cfg(feature = "time")]
+mod hasher;
+#[cfg(feature = "std")]
+mod history;
+#[cfg(feature = "std")]
+mod hooks;
+#[cfg(feature = "std")]
+mod hot_keys;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "time")]
+mod interned;
+#[cfg(feature = "server")]
+mod invalidation;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+mod jsonl;
+#[cfg(feature = "std")]
+mod key_filter;
+#[cfg(feature = "time")]
+mod lease;
+#[cfg(feature = "std")]
+mod limits;
+#[cfg(feature = "time")]
+mod load_many;
+#[cfg(feature = "time")]
+mod local_brain;
+#[cfg(feature = "time")]
+mod maintenance;
+#[cfg(feature = "memcached")]
+mod memcached;
+#[cfg(feature = "time")]
+mod memoizer;
+#[cfg(feature = "time")]
+mod memory_key;
+#[cfg(feature = "time")]
+pub mod metrics;
+#[cfg(feature = "serde")]
+mod migration;
+#[cfg(feature = "time")]
+mod mirror;
+#[cfg(feature = "time")]
+mod namespace;
+#[cfg(feature = "time")]
+mod near_cache;
+#[cfg(feature = "time")]
+mod normalized;
+#[cfg(feature = "time")]
+mod null;
+#[cfg(feature = "time")]
+mod pool;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(any(feature = "server", feature = "client"))]
+mod protocol;
+#[cfg(feature = "time")]
+mod read_only;
+#[cfg(feature = "time")]
+mod read_through;
+#[cfg(feature = "time")]
+mod recall;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "client")]
+mod replication;
+#[cfg(feature = "time")]
+mod report;
+#[cfg(feature = "resp")]
+mod resp;
+#[cfg(feature = "std")]
+mod review;
+#[cfg(feature = "time")]
+mod scoped;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "time")]
+mod session;
+#[cfg(feature = "client")]
+mod sharding;
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "serde")]
+mod spillover;
+mod static_brain;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
+mod storage;
+#[cfg(feature = "time")]
+pub mod test_util;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "time")]
+mod throttle;
+mod tick_brain;
+#[cfg(feature = "time")]
+mod tiered;
+#[cfg(feature = "time")]
+mod transform;
+#[cfg(any(feature = "server", feature = "client"))]
+mod transport;
+#[cfg(feature = "time")]
+mod typed_key;
+#[cfg(feature = "time")]
+mod unsized_value;
+#[cfg(feature = "time")]
+mod validated;
+#[cfg(feature = "std")]
+mod version;
+#[cfg(feature = "serde")]
+mod wal;
+#[cfg(feature = "std")]
+mod wall_clock;
+#[cfg(feature = "std")]
+mod watch;
+#[cfg(feature = "time")]
+mod weak;
+#[cfg(feature = "serde")]
+mod web_storage;
+#[cfg(feature = "serde")]
+mod wire;
+#[cfg(feature = "time")]
+mod write_behind;
+#[cfg(feature = "time")]
+mod write_through;
+
+#[cfg(feature = "std")]
+pub use alias::AliasRule;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+#[cfg(feature = "time")]
+pub use any_brain::AnyBrain;
+#[cfg(feature = "time")]
+pub use async_brain::{AsyncBrain, AsyncMemory, BoxFuture, SharedAsyncMemory};
+#[cfg(feature = "std")]
+pub use attributes::{Attribute, Attributes, RetentionRule};
+#[cfg(feature = "std")]
+pub use audit::{AuditEntry, AuditOperation};
+#[cfg(feature = "std")]
+pub use builder::{BrainBuilder, Eviction};
+#[cfg(feature = "client")]
+pub use client::{ClientConfig, RemoteBrain};
+#[cfg(feature = "time")]
+pub use clocks::{ClockedBrain, Siblings, VectorClock};
+#[cfg(feature = "time")]
+pub use codec::{CodecMemory, ValueCodec};
+#[cfg(feature = "time")]
+pub use compressed::{CompressedMemory, Compression, CompressionStats, Lz4};
+#[cfg(feature = "time")]
+pub use config::BrainConfig;
+#[cfg(feature = "time")]
+pub use consolidation::{ConsolidatingMemory, Consolidation};
+#[cfg(feature = "time")]
+pub use cow::ValueCow;
+#[cfg(feature = "time")]
+pub use deadline::{DeadlineMemory, DeadlineStats};
+#[cfg(feature = "time")]
+pub use debounce::DebouncedMemory;
+#[cfg(feature = "time")]
+pub use dedup::Dedup;
+#[cfg(feature = "serde")]
+pub use diff::{diff, diff_files, BrainDiff};
+#[cfg(feature = "time")]
+pub use encrypted::{Aead, EncryptedMemory};
+#[cfg(feature = "std")]
+pub use expiry::ExpireNotified;
+#[cfg(feature = "time")]
+pub use fallback::{FallbackMemory, WritePolicy};
+#[cfg(feature = "serde")]
+pub use file_brain::FileBrain;
+#[cfg(feature = "gossip")]
+pub use gossip::{GossipConfig, GossipNode};
+#[cfg(feature = "time")]
+pub use hasher::{FxBuildHasher, FxHasher};
+#[cfg(feature = "std")]
+pub use hooks::{BrainHooks, EvictionCause};
+#[cfg(feature = "std")]
+pub use hot_keys::KeyFrequency;
+#[cfg(feature = "time")]
+pub use interned::InternedMemory;
+#[cfg(feature = "server")]
+pub use invalidation::{Invalidating, InvalidationConfig};
+#[cfg(feature = "time")]
+pub use lease::{Held, Lease, Leasing};
+#[cfg(feature = "time")]
+pub use local_brain::LocalBrain;
+#[cfg(feature = "time")]
+pub use maintenance::{Maintenance, MaintenanceConfig, MaintenanceShutdown};
+#[cfg(feature = "memcached")]
+pub use memcached::MemcachedMemory;
+#[cfg(feature = "time")]
+pub use memoizer::{Caching, CircuitOpen, Memoizer};
+#[cfg(feature = "time")]
+pub use memory_key::{KeyEncoder, KeyPart, MemoryKey};
+#[cfg(feature = "time")]
+pub use metrics::{MeteredMemory, MetricsSink};
+#[cfg(feature = "serde")]
+pub use migration::{migrate, migrate_with};
+#[cfg(feature = "time")]
+pub use mirror::{MirrorError, MirroredMemory, OnMirrorFailure, TryMemoize};
+#[cfg(feature = "time")]
+pub use namespace::{Namespace, Quota, QuotaExceeded};
+#[cfg(feature = "time")]
+pub use near_cache::NearCache;
+#[cfg(feature = "time")]
+pub use normalized::NormalizedMemory;
+#[cfg(feature = "time")]
+pub use null::NullMemory;
+#[cfg(feature = "std")]
 use parking_lot::RwLock;
-use std::collections::HashMap;
+#[cfg(feature = "time")]
+pub use pool::BrainPool;
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusExporter;
+#[cfg(feature = "time")]
+pub use read_only::ReadOnlyBrain;
+#[cfg(feature = "time")]
+pub use read_through::ReadThrough;
+#[cfg(feature = "time")]
+pub use recall::SplitMix64;
+#[cfg(feature = "redis")]
+pub use redis::RedisMemory;
+#[cfg(feature = "client")]
+pub use replication::{Primary, ReplicationLag};
+#[cfg(feature = "time")]
+pub use report::{AgeBucket, BrainReport};
+#[cfg(feature = "std")]
+pub use review::{NotMemoized, Review};
+#[cfg(feature = "time")]
+pub use scoped::ScopedMemory;
+#[cfg(all(unix, feature = "server"))]
+pub use server::UnixServer;
+#[cfg(feature = "server")]
+pub use server::{Server, ServerConfig};
+#[cfg(feature = "time")]
+pub use session::{Session, SessionMemory};
+#[cfg(feature = "client")]
+pub use sharding::ShardedClient;
+#[cfg(feature = "serde")]
+pub use snapshot::{Durability, LoadOptions, SnapshotCodec};
+#[cfg(feature = "serde")]
+pub use spillover::Spillover;
+pub use static_brain::{KeyTooLong, StaticBrain};
+#[cfg(feature = "std")]
+use stats::Stats;
+#[cfg(feature = "std")]
+pub use stats::{BrainStats, HotKey, StatsWindow};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::collections::{BTreeSet, HashMap, HashSet};
+#[cfg(feature = "std")]
 use std::ops::Add;
-use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::atomic::AtomicU64;
+/// Retention of values, `time::Duration` with the `time` feature.
+#[cfg(all(feature = "std", not(feature = "time")))]
+pub use std::time::Duration;
+#[cfg(feature = "std")]
+pub use storage::{InsertionOrdered, StorageBackend};
+#[cfg(feature = "time")]
+pub use throttle::ThrottledMemory;
+#[cfg(feature = "std")]
+pub use tick_brain::monotonic_millis;
+pub use tick_brain::{Lock, SpinLock, TickBrain, TickEntries, Ticks};
+#[cfg(feature = "time")]
+pub use tiered::TieredMemory;
+#[cfg(feature = "time")]
 pub use time::ext::NumericalDuration;
+#[cfg(feature = "time")]
 pub use time::Duration;
-use time::OffsetDateTime;
+#[cfg(feature = "time")]
+pub use transform::TransformMemory;
+#[cfg(any(feature = "server", feature = "client"))]
+pub use transport::Duplex;
+#[cfg(feature = "time")]
+pub use typed_key::{TypedKey, TypedMemory};
+#[cfg(feature = "time")]
+pub use validated::ValidatedMemory;
+#[cfg(feature = "std")]
+pub use version::{VersionConflict, VersionError};
+#[cfg(feature = "serde")]
+pub use wal::WalBrain;
+#[cfg(feature = "time")]
+pub use wall_clock::set_clock;
+#[cfg(feature = "std")]
+use wall_clock::Timestamp;
+#[cfg(feature = "std")]
+pub use watch::WatchHandle;
+#[cfg(feature = "time")]
+pub use weak::WeakBrain;
+#[cfg(feature = "serde")]
+pub use web_storage::{WebStorage, WebStorageBrain};
+#[cfg(feature = "serde")]
+pub use wire::{BinaryCodec, JsonCodec};
+#[cfg(feature = "time")]
+pub use write_behind::{WriteBehind, WriteBehindConfig};
+#[cfg(feature = "time")]
+pub use write_through::WriteThrough;
+/// Encodes values on their way to and from a server, the [`ValueCodec`] of
+/// clients.
+///
+/// Servers keep the bytes as they get them, so clients sharing keys need
+/// the same codec. Clients use [`BinaryCodec`] unless told otherwise.
+#[cfg(feature = "client")]
+pub use ValueCodec as WireCodec;
 
 pub trait Memory<T> {
     fn memoize(&self, key: &str, value: T);
@@ -15,63 +388,469 @@ pub trait MemoryDefaultRetrieval<T>: Memory<T> {
     fn retrieve_or_default(&self, key: &str) -> T;
 }
 
-#[derive(Clone)]
-struct Engram<T>(T, OffsetDateTime);
+/// A memory chosen at runtime, e.g. from a config, shared between threads.
+pub type SharedMemory<T> = Arc<dyn Memory<T> + Send + Sync>;
+
+macro_rules! forward_memory {
+    ($($pointer:ty),*) => {$(
+        impl<T, M: Memory<T> + ?Sized> Memory<T> for $pointer {
+            fn memoize(&self, key: &str, value: T) {
+                (**self).memoize(key, value);
+            }
+            fn retrieve(&self, key: &str) -> Option<T> {
+                (**self).retrieve(key)
+            }
+            fn forget(&self) {
+                (**self).forget();
+            }
+        }
+        impl<T, M: MemoryDefaultRetrieval<T> + ?Sized> MemoryDefaultRetrieval<T> for $pointer {
+            fn retrieve_or_default(&self, key: &str) -> T {
+                (**self).retrieve_or_default(key)
+            }
+        }
+    )*};
+}
+forward_memory!(&M, Box<M>, Arc<M>);
+
+#[cfg(feature = "std")]
+/// Salience of values memoized without one.
+const DEFAULT_SALIENCE: f64 = 0.5;
+
+#[cfg(feature = "std")]
+/// A memoized value, as kept by a [`StorageBackend`].
+pub struct Engram<T> {
+    value: T,
+    memoized: Timestamp,
+    retention: Option<Duration>,
+    actor: u64,
+    hits: AtomicU64,
+    salience: f64,
+    version: u64,
+    context: Option<Arc<str>>,
+    attributes: Option<Arc<Attributes>>,
+}
+#[cfg(feature = "std")]
+impl<T> Engram<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+    pub fn memoized(&self) -> Timestamp {
+        self.memoized
+    }
+    /// The engram's own retention, `None` for the brain's.
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+    /// Id of the brain which memoized the value, see [`BrainBuilder::actor`].
+    pub fn actor(&self) -> u64 {
+        self.actor
+    }
+    /// The context the value was memoized in, see
+    /// [`Brain::memoize_in_context`].
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+    /// The attribute of the value, see [`Brain::memoize_with_attributes`].
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.as_ref()?.get(name)
+    }
+    /// Version of the value, see [`Brain::retrieve_versioned`].
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+    /// How important the value is, from 0 to 1, see
+    /// [`Brain::memoize_with_salience`].
+    pub fn salience(&self) -> f64 {
+        self.salience
+    }
+    fn new(value: T, retention: Option<Duration>) -> Self {
+        Self {
+            value,
+            memoized: wall_clock::now(),
+            retention,
+            actor: 0,
+            hits: AtomicU64::new(0),
+            salience: DEFAULT_SALIENCE,
+            version: 0,
+            context: None,
+            attributes: None,
+        }
+    }
+    /// Tells whether the engram outlived its retention, or else the default.
+    fn is_expired(&self, retention: Duration, now: Timestamp) -> bool {
+        self.memoized.add(self.retention.unwrap_or(retention)) < now
+    }
+}
 
-#[derive(Clone, Default)]
-pub struct Brain<T> {
-    memory: Arc<RwLock<HashMap<String, Engram<T>>>>,
+#[cfg(feature = "std")]
+pub struct Brain<T, S = HashMap<String, Engram<T>>> {
+    memory: Arc<RwLock<S>>,
     retention: Duration,
+    stats: Arc<Stats>,
+    hooks: Arc<RwLock<Option<Arc<dyn BrainHooks<T>>>>>,
+    associations: Arc<association::Associations>,
+    reviews: Arc<RwLock<HashMap<String, review::Review>>>,
+    history: Option<Arc<history::History<T>>>,
+    hot_keys: Option<Arc<hot_keys::HotKeys>>,
+    key_filter: Option<Arc<key_filter::KeyFilter>>,
+    watchers: Arc<expiry::Watchers>,
+    value_watchers: Arc<watch::ValueWatchers<T>>,
+    expiry_queue: Option<Arc<active_expiry::ExpiryQueue>>,
+    limits: Option<Arc<limits::Limits>>,
+    versions: Arc<AtomicU64>,
+    audit: Option<Arc<audit::AuditLog>>,
+    audit_actor: Option<Arc<str>>,
+    active_contexts: Arc<RwLock<HashSet<Arc<str>>>>,
+    retention_rules: Arc<[RetentionRule]>,
+    #[cfg(feature = "embeddings")]
+    embeddings: Arc<RwLock<HashMap<String, Vec<f32>>>>,
+    capacity: Option<builder::Capacity>,
+    rehearsal: Option<builder::Rehearsal>,
+    decay: Option<decay::Decay>,
+    sweeping: Option<Arc<builder::Sweeping>>,
+    actor: u64,
+    #[cfg(feature = "serde")]
+    dirty: Arc<snapshot::Dirty>,
+    #[cfg(feature = "serde")]
+    on_drop: Option<Arc<snapshot::SaveOnDrop>>,
+    #[cfg(feature = "serde")]
+    durability: Durability,
+}
+#[cfg(feature = "std")]
+impl<T, S: Default> Default for Brain<T, S> {
+    fn default() -> Self {
+        Self::with_storage(Default::default(), Default::default())
+    }
 }
+#[cfg(feature = "std")]
+impl<T, S> Clone for Brain<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            memory: self.memory.clone(),
+            retention: self.retention,
+            stats: self.stats.clone(),
+            hooks: self.hooks.clone(),
+            associations: self.associations.clone(),
+            reviews: self.reviews.clone(),
+            history: self.history.clone(),
+            hot_keys: self.hot_keys.clone(),
+            key_filter: self.key_filter.clone(),
+            watchers: self.watchers.clone(),
+            value_watchers: self.value_watchers.clone(),
+            expiry_queue: self.expiry_queue.clone(),
+            limits: self.limits.clone(),
+            versions: self.versions.clone(),
+            audit: self.audit.clone(),
+            audit_actor: self.audit_actor.clone(),
+            active_contexts: self.active_contexts.clone(),
+            retention_rules: self.retention_rules.clone(),
+            #[cfg(feature = "embeddings")]
+            embeddings: self.embeddings.clone(),
+            capacity: self.capacity,
+            rehearsal: self.rehearsal,
+            decay: self.decay,
+            sweeping: self.sweeping.clone(),
+            actor: self.actor,
+            #[cfg(feature = "serde")]
+            dirty: self.dirty.clone(),
+            #[cfg(feature = "serde")]
+            on_drop: self.on_drop.clone(),
+            #[cfg(feature = "serde")]
+            durability: self.durability,
+        }
+    }
+}
+#[cfg(feature = "std")]
 impl<T> Brain<T> {
     pub fn new(retention: Duration) -> Self {
+        Self::with_storage(retention, HashMap::new())
+    }
+}
+#[cfg(feature = "std")]
+impl<T, S> Brain<T, S> {
+    /// Creates a brain keeping its engrams in the storage.
+    pub fn with_storage(retention: Duration, storage: S) -> Self {
+        Self::sharing(retention, Arc::new(RwLock::new(storage)))
+    }
+    /// Creates a brain on the engrams of another, sharing nothing else.
+    pub(crate) fn sharing(retention: Duration, memory: Arc<RwLock<S>>) -> Self {
         Self {
-            memory: Default::default(),
+            memory,
             retention,
+            stats: Default::default(),
+            hooks: Default::default(),
+            associations: Default::default(),
+            reviews: Default::default(),
+            history: None,
+            hot_keys: None,
+            key_filter: None,
+            watchers: Default::default(),
+            value_watchers: Default::default(),
+            expiry_queue: None,
+            limits: None,
+            versions: Default::default(),
+            audit: None,
+            audit_actor: None,
+            active_contexts: Default::default(),
+            retention_rules: Arc::new([]),
+            #[cfg(feature = "embeddings")]
+            embeddings: Default::default(),
+            capacity: None,
+            rehearsal: None,
+            decay: None,
+            sweeping: None,
+            actor: 0,
+            #[cfg(feature = "serde")]
+            dirty: Default::default(),
+            #[cfg(feature = "serde")]
+            on_drop: None,
+            #[cfg(feature = "serde")]
+            durability: Default::default(),
         }
     }
 }
-impl<T: Clone> Memory<T> for Brain<T> {
-    fn memoize(&self, key: &str, value: T) {
+#[cfg(feature = "std")]
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Tells whether a value is memoized for the key, whatever the value is,
+    /// unless an inactive context hides it.
+    pub fn is_cached(&self, key: &str) -> bool {
         self.memory
-            .write()
-            .insert(key.to_string(), Engram(value, OffsetDateTime::now_utc()));
+            .read()
+            .get(key)
+            .is_some_and(|engram| self.is_context_active(engram))
     }
-    fn forget(&self) {
-        let now = OffsetDateTime::now_utc();
-        let mut binding = self.memory.write();
-        let vec = binding
-            .iter()
-            .map(|(key, value)| (key.to_owned(), value.clone()))
-            .collect::<Vec<_>>();
-        vec.iter().for_each(|(key, value)| {
-            if value.1.add(self.retention) < now {
-                let _ = binding.remove(key.as_str());
+    /// Forgets the value for the key right away, giving it back.
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.remove_if(key, |_| true)
+    }
+    /// Removes the engram for the key if `remove` accepts it, without letting
+    /// other writes in between.
+    pub(crate) fn remove_if(
+        &self,
+        key: &str,
+        remove: impl FnOnce(&Engram<T>) -> bool,
+    ) -> Option<T> {
+        self.remove_engram_if(key, remove)
+            .map(|engram| engram.value)
+    }
+    /// Like [`Brain::remove_if`], giving back the whole engram.
+    pub(crate) fn remove_engram_if(
+        &self,
+        key: &str,
+        remove: impl FnOnce(&Engram<T>) -> bool,
+    ) -> Option<Engram<T>> {
+        let (key, engram) = {
+            let mut memory = self.memory.write();
+            memory.get(key).filter(|engram| remove(engram))?;
+            memory.remove(key)?
+        };
+        #[cfg(feature = "serde")]
+        self.dirty.touch(&key);
+        self.discard(&key, EvictionCause::Removed);
+        if let Some(hooks) = self.hooks() {
+            hooks.on_evict(&key, &engram.value, EvictionCause::Removed);
+        }
+        Some(engram)
+    }
+    /// Removes the engrams the predicate holds for, evicted for the cause,
+    /// telling how many.
+    pub(crate) fn evict_where(
+        &self,
+        cause: EvictionCause,
+        predicate: &mut dyn FnMut(&String, &Engram<T>) -> bool,
+    ) -> usize {
+        let removed = {
+            let mut memory = self.memory.write();
+            let removed = memory.sweep(predicate);
+            if !removed.is_empty() {
+                self.refilter(&memory);
+            }
+            removed
+        };
+        for (key, _) in &removed {
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+            self.discard(key, cause);
+        }
+        if let Some(hooks) = self.hooks() {
+            for (key, engram) in &removed {
+                hooks.on_evict(key, &engram.value, cause);
             }
+        }
+        removed.len()
+    }
+    /// Drops what the brain keeps about a key besides its engram, once the
+    /// engram is gone.
+    fn discard(&self, key: &str, cause: EvictionCause) {
+        self.audit_record(key, audit::AuditOperation::Evicted(cause));
+        self.associations.unlink(key);
+        #[cfg(feature = "embeddings")]
+        self.embeddings.write().remove(key);
+        if let Some(history) = &self.history {
+            history.forget(key);
+        }
+        self.value_watchers.end(key);
+        self.watchers.notify(key, cause);
+    }
+    /// Forgets the expired engrams, and those the brain's decay forgot,
+    /// telling how many.
+    pub(crate) fn forget_expired(&self) -> usize {
+        let started = wall_clock::Instant::now();
+        let now = wall_clock::now();
+        let removed = self.evict_where(EvictionCause::Expired, &mut |_, engram| {
+            engram.is_expired(self.retention, now)
+                || self
+                    .decay
+                    .is_some_and(|decay| decay.is_forgotten(engram, now))
         });
+        self.stats.swept(removed, started.elapsed());
+        removed
+    }
+    /// Time left until the value for the key expires, zero once it has.
+    pub fn time_to_live(&self, key: &str) -> Option<Duration> {
+        let memory = self.memory.read();
+        let engram = memory
+            .get(key)
+            .filter(|engram| self.is_context_active(engram))?;
+        let expires = engram.memoized + engram.retention.unwrap_or(self.retention);
+        Some((expires - wall_clock::now()).max(Duration::ZERO))
+    }
+}
+#[cfg(feature = "std")]
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Memoizes a value with its own retention instead of the brain's.
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        self.store(key, Engram::new(value, Some(retention)));
+    }
+    /// Memoizes a value with its salience, from 0 to 1 and 0.5 by default.
+    ///
+    /// A full brain evicts among the entries of the least salience, and a
+    /// decaying brain forgets less salient values sooner: its half-life is
+    /// halved for a salience of 0 and doubled for 1. Snapshots don't keep
+    /// the salience.
+    pub fn memoize_with_salience(&self, key: &str, value: T, salience: f64) {
+        let engram = Engram {
+            salience: salience.clamp(0.0, 1.0),
+            ..Engram::new(value, None)
+        };
+        self.store(key, engram);
+    }
+    fn store(&self, key: &str, engram: Engram<T>) {
+        self.store_if(key, engram, |_| true);
+    }
+    /// Stores the engram unless `allow` refuses the one memoized for the key,
+    /// telling whether it did, without letting other writes in between.
+    pub(crate) fn store_if(
+        &self,
+        key: &str,
+        engram: Engram<T>,
+        allow: impl FnOnce(Option<&Engram<T>>) -> bool,
+    ) -> bool {
+        self.store_evicting(key, engram, allow).is_some()
+    }
+    /// Like [`Brain::store_if`], giving back the entries evicted to make room
+    /// if it stored the engram.
+    pub(crate) fn store_evicting(
+        &self,
+        key: &str,
+        mut engram: Engram<T>,
+        allow: impl FnOnce(Option<&Engram<T>>) -> bool,
+    ) -> Option<Vec<(String, Engram<T>)>> {
+        engram.actor = self.actor;
+        if engram.version == 0 {
+            engram.version = self.next_version();
+        }
+        let hooks = self.hooks();
+        let value = hooks.as_ref().map(|_| engram.value.clone());
+        let expires = engram.memoized + engram.retention.unwrap_or(self.retention);
+        let version = engram.version;
+        let recorded = self.history.as_ref().map(|_| {
+            let context = engram.context.clone();
+            (engram.value.clone(), engram.memoized, expires, context)
+        });
+        let evicted = {
+            let mut memory = self.memory.write();
+            if !allow(memory.get(key)) || self.sheds(&memory, key) {
+                return None;
+            }
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+            if self.is_context_active(&engram) {
+                self.value_watchers.send(key, &engram.value);
+            }
+            memory.put(key.to_string(), engram);
+            self.filter_key(key);
+            if let (Some(history), Some((value, memoized, expires, context))) =
+                (&self.history, recorded)
+            {
+                history.record(key, value, memoized, expires, context);
+            }
+            self.audit_record(key, audit::AuditOperation::Memoized);
+            #[cfg(feature = "embeddings")]
+            self.embeddings.write().remove(key);
+            self.make_room(&mut memory, key)
+        };
+        self.stats.inserted();
+        self.queue_expiry(key, expires, version);
+        if let (Some(hooks), Some(value)) = (&hooks, value) {
+            hooks.on_insert(key, &value);
+        }
+        for (key, engram) in &evicted {
+            self.stats.evicted_for_capacity();
+            #[cfg(feature = "serde")]
+            self.dirty.touch(key);
+            self.discard(key, EvictionCause::Capacity);
+            if let Some(hooks) = &hooks {
+                hooks.on_evict(key, &engram.value, EvictionCause::Capacity);
+            }
+        }
+        self.sweep_if_due();
+        self.sweep_if_soft_limit();
+        self.expire_due();
+        Some(evicted)
+    }
+}
+#[cfg(feature = "std")]
+impl<T: Clone, S: StorageBackend<String, Option<T>>> Brain<Option<T>, S> {
+    /// Retrieves the inner value, a memoized `None` and a miss both give `None`.
+    ///
+    /// Use [`Brain::is_cached`] or [`Memory::retrieve`] to tell them apart.
+    pub fn retrieve_flat(&self, key: &str) -> Option<T> {
+        self.retrieve(key).flatten()
+    }
+}
+#[cfg(feature = "std")]
+impl<T: Clone, S: StorageBackend<String, T>> Memory<T> for Brain<T, S> {
+    fn memoize(&self, key: &str, value: T) {
+        self.store(key, Engram::new(value, None));
+    }
+    fn forget(&self) {
+        self.forget_expired();
     }
     fn retrieve(&self, key: &str) -> Option<T> {
-        self.memory.read().get(key).map(|engram| &engram.0).cloned()
+        self.retrieve_versioned(key).map(|(value, _)| value)
     }
 }
-impl<T: Default + Clone> MemoryDefaultRetrieval<T> for Brain<T> {
+#[cfg(feature = "std")]
+impl<T: Default + Clone, S: StorageBackend<String, T>> MemoryDefaultRetrieval<T> for Brain<T, S> {
     fn retrieve_or_default(&self, key: &str) -> T {
         self.retrieve(key).unwrap_or(T::default())
     }
 }
 
+#[cfg(feature = "std")]
 pub struct MemorySubstitute<'map, 'memory, T> {
     map: &'map HashMap<String, String>,
     memory: &'memory dyn Memory<T>,
 }
+#[cfg(feature = "std")]
 impl<'map, 'memory, T> MemorySubstitute<'map, 'memory, T> {
-    pub fn new(
-        memory: &'memory dyn Memory<T>,
-        map: &'map HashMap<String, String>,
-    ) -> Self {
+    pub fn new(memory: &'memory dyn Memory<T>, map: &'map HashMap<String, String>) -> Self {
         Self { map, memory }
     }
 }
+#[cfg(feature = "std")]
 impl<T: Clone> Memory<T> for MemorySubstitute<'_, '_, T> {
     fn memoize(&self, key: &str, value: T) {
         self.memory
@@ -85,13 +864,168 @@ impl<T: Clone> Memory<T> for MemorySubstitute<'_, '_, T> {
         self.memory.forget();
     }
 }
+#[cfg(feature = "std")]
 impl<T: Default + Clone> MemoryDefaultRetrieval<T> for MemorySubstitute<'_, '_, T> {
     fn retrieve_or_default(&self, key: &str) -> T {
         self.retrieve(key).unwrap_or(T::default())
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "std")]
+/// A [`MemorySubstitute`] owning its brain and map, so it can be kept in
+/// structs and moved across threads.
+///
+/// Keys without an alias are rewritten by the first [`AliasRule`] applying
+/// to them, if any. The aliases and rules may change while it is used,
+/// e.g. when reloaded from a config, and clones share them.
+pub struct AliasedBrain<T, S = HashMap<String, Engram<T>>> {
+    map: Arc<RwLock<Aliases>>,
+    brain: Brain<T, S>,
+}
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Aliases {
+    exact: HashMap<String, String>,
+    /// The aliases standing for each key.
+    reverse: HashMap<String, BTreeSet<String>>,
+    rules: Vec<AliasRule>,
+}
+#[cfg(feature = "std")]
+impl Aliases {
+    fn new(exact: HashMap<String, String>) -> Self {
+        let mut reverse = HashMap::<_, BTreeSet<_>>::new();
+        for (alias, key) in &exact {
+            reverse
+                .entry(key.clone())
+                .or_default()
+                .insert(alias.clone());
+        }
+        Self {
+            exact,
+            reverse,
+            rules: Vec::new(),
+        }
+    }
+    fn insert(&mut self, alias: &str, key: &str) -> Option<String> {
+        let previous = self.remove(alias);
+        self.exact.insert(alias.to_string(), key.to_string());
+        self.reverse
+            .entry(key.to_string())
+            .or_default()
+            .insert(alias.to_string());
+        previous
+    }
+    fn remove(&mut self, alias: &str) -> Option<String> {
+        let key = self.exact.remove(alias)?;
+        if let Some(aliases) = self.reverse.get_mut(&key) {
+            aliases.remove(alias);
+            if aliases.is_empty() {
+                self.reverse.remove(&key);
+            }
+        }
+        Some(key)
+    }
+}
+#[cfg(feature = "std")]
+impl<T, S> Clone for AliasedBrain<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            brain: self.brain.clone(),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl<T, S> AliasedBrain<T, S> {
+    pub fn new(brain: Brain<T, S>, map: HashMap<String, String>) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(Aliases::new(map))),
+            brain,
+        }
+    }
+    pub fn brain(&self) -> &Brain<T, S> {
+        &self.brain
+    }
+    /// Makes the alias stand for the key, giving back the key it stood for.
+    pub fn add_alias(&self, alias: &str, key: &str) -> Option<String> {
+        self.map.write().insert(alias, key)
+    }
+    /// Makes the alias stand for itself again, giving back the key it stood for.
+    pub fn remove_alias(&self, alias: &str) -> Option<String> {
+        self.map.write().remove(alias)
+    }
+    /// Replaces all aliases at once, giving back the previous ones.
+    pub fn replace_aliases(&self, map: HashMap<String, String>) -> HashMap<String, String> {
+        let mut aliases = self.map.write();
+        let rules = std::mem::take(&mut aliases.rules);
+        let previous = std::mem::replace(&mut *aliases, Aliases::new(map));
+        aliases.rules = rules;
+        previous.exact
+    }
+    /// A copy of the aliases.
+    pub fn aliases(&self) -> HashMap<String, String> {
+        self.map.read().exact.clone()
+    }
+    /// The key of the brain the alias stands for, by its alias or rule.
+    pub fn canonical_key(&self, alias: &str) -> String {
+        self.resolve(alias).into_owned()
+    }
+    /// The aliases standing for the key, in order, e.g. to tell which ones an
+    /// eviction of the key affects.
+    ///
+    /// Only aliases are found, keys rewritten into it by rules are not.
+    pub fn aliases_of(&self, key: &str) -> Vec<String> {
+        self.map
+            .read()
+            .reverse
+            .get(key)
+            .map(|aliases| aliases.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// Rewrites keys with the rule, after the rules added before.
+    pub fn add_rule(&self, rule: AliasRule) {
+        self.map.write().rules.push(rule);
+    }
+    /// Replaces all rules at once, giving back the previous ones.
+    pub fn replace_rules(&self, rules: Vec<AliasRule>) -> Vec<AliasRule> {
+        std::mem::replace(&mut self.map.write().rules, rules)
+    }
+    pub fn rules(&self) -> Vec<AliasRule> {
+        self.map.read().rules.clone()
+    }
+    fn resolve<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let map = self.map.read();
+        if let Some(key) = map.exact.get(key) {
+            return Cow::Owned(key.clone());
+        }
+        map.rules
+            .iter()
+            .find_map(|rule| rule.rewrite(key))
+            .map_or(Cow::Borrowed(key), Cow::Owned)
+    }
+}
+#[cfg(feature = "std")]
+impl<T: Clone, S: StorageBackend<String, T>> Memory<T> for AliasedBrain<T, S> {
+    fn memoize(&self, key: &str, value: T) {
+        self.brain.memoize(&self.resolve(key), value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(&self.resolve(key))
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+#[cfg(feature = "std")]
+impl<T: Default + Clone, S: StorageBackend<String, T>> MemoryDefaultRetrieval<T>
+    for AliasedBrain<T, S>
+{
+    fn retrieve_or_default(&self, key: &str) -> T {
+        self.retrieve(key).unwrap_or(T::default())
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
 mod tests {
     use super::*;
     use common_macros::hash_map;
@@ -118,6 +1052,46 @@ mod tests {
         assert_eq!(memory.retrieve("b"), Some(6));
     }
 
+    #[test]
+    fn remove() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        assert!(memory.time_to_live("a").unwrap() > 59.seconds());
+        assert_eq!(memory.remove("a"), Some(3));
+        assert_eq!(memory.remove("a"), None);
+        assert_eq!(memory.time_to_live("a"), None);
+    }
+
+    #[test]
+    fn individual_retention() {
+        let memory = Brain::new(3.milliseconds());
+
+        memory.memoize("a", 3);
+        memory.memoize_for("b", 6, 1.minutes());
+
+        std::thread::sleep(std::time::Duration::from_millis(4));
+        memory.forget();
+
+        assert_eq!(memory.retrieve("a"), None);
+        assert_eq!(memory.retrieve("b"), Some(6));
+    }
+
+    #[test]
+    fn negative_lookup() {
+        let memory = Brain::<Option<i32>>::new(3.milliseconds());
+
+        memory.memoize("a", Some(3));
+        memory.memoize("b", None);
+
+        assert_eq!(memory.retrieve_flat("a"), Some(3));
+        assert_eq!(memory.retrieve_flat("b"), None);
+        assert_eq!(memory.retrieve_flat("c"), None);
+
+        assert!(memory.is_cached("a"));
+        assert!(memory.is_cached("b"));
+        assert!(!memory.is_cached("c"));
+    }
+
     #[test]
     fn alias() {
         let memory = Brain::new(3.milliseconds());
@@ -142,4 +1116,96 @@ mod tests {
         assert_eq!(alias.retrieve("ccc"), Some(9));
         assert_eq!(memory.retrieve("ccc"), Some(9));
     }
+
+    #[test]
+    fn shared_memory() {
+        fn memoize_twice(memory: impl Memory<i32>) {
+            memory.memoize("a", 1);
+            memory.memoize("b", 2);
+        }
+        let brain = Brain::new(1.minutes());
+        let memories: Vec<SharedMemory<i32>> =
+            vec![Arc::new(brain.clone()), Arc::new(NullMemory::new())];
+        for memory in &memories {
+            memoize_twice(memory);
+        }
+        memoize_twice(memories[0].clone());
+        let boxed: Box<dyn MemoryDefaultRetrieval<i32>> = Box::new(brain);
+        assert_eq!(boxed.retrieve("b"), Some(2));
+        assert_eq!(memories[1].retrieve("b"), None);
+        assert_eq!(boxed.retrieve_or_default("c"), 0);
+    }
+
+    #[test]
+    fn aliased_brain() {
+        let memory = Brain::new(1.minutes());
+        let alias = AliasedBrain::new(
+            memory.clone(),
+            hash_map!("aaa".to_string() => "a".to_string()),
+        );
+
+        let moved = alias.clone();
+        std::thread::spawn(move || moved.memoize("aaa", 5))
+            .join()
+            .unwrap();
+        assert_eq!(memory.retrieve("a"), Some(5));
+        assert_eq!(alias.retrieve("aaa"), Some(5));
+        assert_eq!(alias.retrieve_or_default("bbb"), 0);
+        alias.memoize("ccc", 9);
+        assert_eq!(memory.retrieve("ccc"), Some(9));
+
+        assert_eq!(alias.add_alias("bbb", "ccc"), None);
+        assert_eq!(alias.retrieve("bbb"), Some(9));
+        assert_eq!(alias.remove_alias("aaa"), Some("a".to_string()));
+        assert_eq!(alias.retrieve("aaa"), None);
+        let previous = alias.replace_aliases(hash_map!("aaa".to_string() => "ccc".to_string()));
+        assert_eq!(previous.len(), 1);
+        assert_eq!(alias.retrieve("aaa"), Some(9));
+        assert_eq!(alias.aliases().len(), 1);
+
+        alias.add_rule(AliasRule::prefix("legacy:", "v2:"));
+        alias.add_rule(AliasRule::prefix("legacy:", "v3:"));
+        alias.memoize("legacy:x", 1);
+        assert_eq!(memory.retrieve("v2:x"), Some(1));
+        // aliases go before rules
+        alias.add_alias("legacy:y", "y");
+        alias.memoize("legacy:y", 2);
+        assert_eq!(memory.retrieve("y"), Some(2));
+        assert_eq!(alias.replace_rules(Vec::new()).len(), 2);
+        assert_eq!(alias.retrieve("legacy:x"), None);
+
+        alias.add_alias("zzz", "ccc");
+        assert_eq!(alias.aliases_of("ccc"), vec!["aaa", "zzz"]);
+        alias.add_alias("aaa", "y");
+        assert_eq!(alias.aliases_of("ccc"), vec!["zzz"]);
+        assert_eq!(alias.aliases_of("y"), vec!["aaa", "legacy:y"]);
+        assert_eq!(alias.canonical_key("zzz"), "ccc");
+        assert_eq!(alias.canonical_key("other"), "other");
+        alias.remove_alias("zzz");
+        assert!(alias.aliases_of("ccc").is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "std", not(feature = "time")))]
+mod std_tests {
+    use super::*;
+
+    #[test]
+    fn std_retention() {
+        let memory = Brain::builder()
+            .retention(Duration::from_millis(3))
+            .decay(Duration::from_secs(60), 0.1)
+            .build();
+        memory.memoize("a", 3);
+        memory.memoize_for("b", 6, Duration::from_secs(60));
+        assert!(memory.time_to_live("b").unwrap() > Duration::from_secs(59));
+        assert!(memory.strength("b").unwrap() > 0.9);
+
+        std::thread::sleep(Duration::from_millis(4));
+        assert_eq!(memory.time_to_live("a"), Some(Duration::ZERO));
+        memory.forget();
+
+        assert_eq!(memory.retrieve("a"), None);
+        assert_eq!(memory.retrieve("b"), Some(6));
+    }
 }
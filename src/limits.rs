@@ -0,0 +1,102 @@
+use crate::{Brain, StorageBackend};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Soft and hard limits of the number of entries, see
+/// [`BrainBuilder::soft_limit`] and [`BrainBuilder::hard_limit`].
+///
+/// [`BrainBuilder::soft_limit`]: crate::BrainBuilder::soft_limit
+/// [`BrainBuilder::hard_limit`]: crate::BrainBuilder::hard_limit
+pub(crate) struct Limits {
+    pub(crate) soft: Option<usize>,
+    pub(crate) hard: Option<usize>,
+    /// Entries left by the last sweep the soft limit triggered.
+    swept_to: AtomicUsize,
+    shed: AtomicU64,
+}
+impl Limits {
+    pub(crate) fn new(soft: Option<usize>, hard: Option<usize>) -> Self {
+        Self {
+            soft,
+            hard,
+            swept_to: AtomicUsize::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Tells whether memoizing the key would grow the brain beyond its hard
+    /// limit, counting the write as shed if so.
+    pub(crate) fn sheds(&self, memory: &S, key: &str) -> bool {
+        let Some(limits) = &self.limits else {
+            return false;
+        };
+        let full = limits
+            .hard
+            .is_some_and(|hard| memory.len() >= hard && memory.get(key).is_none());
+        if full {
+            limits.shed.fetch_add(1, Ordering::Relaxed);
+        }
+        full
+    }
+    /// Writes refused at the hard limit.
+    pub fn shed_writes(&self) -> u64 {
+        self.limits
+            .as_ref()
+            .map_or(0, |limits| limits.shed.load(Ordering::Relaxed))
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Forgets the expired engrams once the brain is beyond its soft limit,
+    /// and again whenever it grew by a sixteenth of the limit since.
+    pub(crate) fn sweep_if_soft_limit(&self) {
+        let Some(limits) = &self.limits else {
+            return;
+        };
+        let Some(soft) = limits.soft else {
+            return;
+        };
+        let len = self.memory.read().len();
+        if len <= soft {
+            limits.swept_to.store(0, Ordering::Relaxed);
+            return;
+        }
+        let swept_to = limits.swept_to.load(Ordering::Relaxed);
+        if swept_to != 0 && len < swept_to + (soft / 16).max(1) {
+            return;
+        }
+        self.forget_expired();
+        limits
+            .swept_to
+            .store(self.memory.read().len(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration, TryMemoize};
+
+    #[test]
+    fn limits() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .soft_limit(2)
+            .hard_limit(3)
+            .build();
+        brain.memoize_for("expiring", 0, 1.milliseconds());
+        brain.memoize("a", 1);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        // beyond the soft limit, sweeping the expired entry
+        brain.memoize("b", 2);
+        assert!(!brain.is_cached("expiring"));
+
+        brain.memoize("c", 3);
+        let error = brain.try_memoize("d", 4).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::OutOfMemory);
+        brain.memoize("d", 4);
+        assert_eq!(brain.retrieve("d"), None);
+        brain.try_memoize("a", 5).unwrap();
+        assert_eq!(brain.retrieve("a"), Some(5));
+        assert_eq!(brain.shed_writes(), 2);
+    }
+}
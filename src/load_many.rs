@@ -0,0 +1,80 @@
+use crate::{Brain, Memory, StorageBackend};
+use std::collections::HashMap;
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the values of all the keys, loading the missing ones with
+    /// one call of `load`, e.g. a batch query, and memoizing what it found.
+    ///
+    /// Keys `load` finds nothing for are left out of the map, and loaded
+    /// again next time. Values it returns for keys not asked for are
+    /// memoized too, but not returned.
+    pub fn get_or_load_many<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k str>,
+        load: impl FnOnce(&[&str]) -> HashMap<String, T>,
+    ) -> HashMap<String, T> {
+        let (mut values, missing) = self.found_and_missing(keys);
+        if missing.is_empty() {
+            return values;
+        }
+        let loaded = load(&missing);
+        self.merge_loaded(&missing, loaded, &mut values);
+        values
+    }
+    pub(crate) fn found_and_missing<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k str>,
+    ) -> (HashMap<String, T>, Vec<&'k str>) {
+        let mut values = HashMap::new();
+        let mut missing = Vec::new();
+        for key in keys {
+            if values.contains_key(key) || missing.contains(&key) {
+                continue;
+            }
+            match self.retrieve(key) {
+                Some(value) => {
+                    values.insert(key.to_string(), value);
+                }
+                None => missing.push(key),
+            }
+        }
+        (values, missing)
+    }
+    pub(crate) fn merge_loaded(
+        &self,
+        missing: &[&str],
+        loaded: HashMap<String, T>,
+        values: &mut HashMap<String, T>,
+    ) {
+        for (key, value) in loaded {
+            self.memoize(&key, value.clone());
+            if missing.contains(&key.as_str()) {
+                values.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration};
+    use std::collections::HashMap;
+
+    #[test]
+    fn get_or_load_many() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize("a", 1);
+        let mut loads = Vec::new();
+        let values = brain.get_or_load_many(["a", "b", "c", "b"], |missing| {
+            loads.push(missing.join(","));
+            HashMap::from([("b".to_string(), 2), ("z".to_string(), 26)])
+        });
+        assert_eq!(loads, ["b,c"]);
+        assert_eq!(values, HashMap::from([("a".into(), 1), ("b".into(), 2)]));
+        assert_eq!(brain.retrieve("b"), Some(2));
+        assert_eq!(brain.retrieve("z"), Some(26));
+
+        let values = brain.get_or_load_many(["a", "b"], |_| unreachable!());
+        assert_eq!(values.len(), 2);
+    }
+}
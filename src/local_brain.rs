@@ -0,0 +1,87 @@
+use crate::wall_clock::Instant;
+use crate::{Duration, Memory};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A brain for a single thread, e.g. on wasm or as a per-thread cache,
+/// taking neither locks nor atomics.
+///
+/// Clones share the values, within the thread: the brain is neither `Send`
+/// nor `Sync`. Like a [`Brain`], it retrieves expired values until
+/// forgetting them.
+///
+/// [`Brain`]: crate::Brain
+#[derive(Clone)]
+pub struct LocalBrain<T> {
+    retention: Duration,
+    entries: Rc<RefCell<HashMap<String, (T, Instant)>>>,
+}
+impl<T> LocalBrain<T> {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            entries: Rc::default(),
+        }
+    }
+    pub fn retention(&self) -> Duration {
+        self.retention
+    }
+    /// Memoizes the value for the retention instead of the brain's.
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        let expires = Instant::now() + std::time::Duration::try_from(retention).unwrap_or_default();
+        self.entries
+            .borrow_mut()
+            .insert(key.to_string(), (value, expires));
+    }
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.entries
+            .borrow_mut()
+            .remove(key)
+            .map(|(value, _)| value)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+impl<T: Clone> Memory<T> for LocalBrain<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.memoize_for(key, value, self.retention);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.entries
+            .borrow()
+            .get(key)
+            .map(|(value, _)| value.clone())
+    }
+    fn forget(&self) {
+        let now = Instant::now();
+        self.entries
+            .borrow_mut()
+            .retain(|_, (_, expires)| *expires > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn local_brain() {
+        let brain = LocalBrain::new(1.minutes());
+        let shared = brain.clone();
+        brain.memoize("a", 1);
+        brain.memoize_for("b", 2, 1.milliseconds());
+        assert_eq!(shared.retrieve("a"), Some(1));
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        shared.forget();
+        assert_eq!(brain.retrieve("b"), None);
+        assert_eq!(brain.len(), 1);
+        assert_eq!(brain.remove("a"), Some(1));
+        assert!(shared.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{Brain, Duration, Memory};
+use parking_lot::Mutex;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// Shortest interval jobs run at, so a zero one doesn't spin.
+pub(crate) const MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1);
+
+type Task<T> = Box<dyn FnMut(&Brain<T>) + Send>;
+type Flush<T> = Box<dyn FnOnce(&Brain<T>) + Send>;
+
+struct Job<T> {
+    interval: std::time::Duration,
+    due: Instant,
+    task: Task<T>,
+}
+
+/// Describes the periodic work of a maintenance worker.
+pub struct MaintenanceConfig<T> {
+    jobs: Vec<Job<T>>,
+    flushes: Vec<Flush<T>>,
+}
+impl<T> Default for MaintenanceConfig<T> {
+    fn default() -> Self {
+        Self {
+            jobs: Vec::new(),
+            flushes: Vec::new(),
+        }
+    }
+}
+impl<T: Clone + 'static> MaintenanceConfig<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Forgets expired engrams every interval.
+    pub fn sweep_every(self, interval: Duration) -> Self {
+        self.every(interval, |brain| brain.forget())
+    }
+    /// Consolidates the brain every interval, see [`Brain::consolidate`].
+    pub fn consolidate_every(self, interval: Duration) -> Self {
+        self.every(interval, |brain| {
+            brain.consolidate();
+        })
+    }
+    /// Runs a task every interval, e.g. refreshing entries before they expire.
+    ///
+    /// Intervals shorter than a millisecond, zero or negative ones too, run
+    /// the task every millisecond.
+    pub fn every(
+        mut self,
+        interval: Duration,
+        task: impl FnMut(&Brain<T>) + Send + 'static,
+    ) -> Self {
+        let interval = if interval.is_positive() {
+            interval.unsigned_abs().max(MIN_INTERVAL)
+        } else {
+            MIN_INTERVAL
+        };
+        self.jobs.push(Job {
+            interval,
+            due: Instant::now() + interval,
+            task: Box::new(task),
+        });
+        self
+    }
+    /// Runs a task once when the worker shuts down, e.g. a final snapshot.
+    pub fn on_shutdown(mut self, flush: impl FnOnce(&Brain<T>) + Send + 'static) -> Self {
+        self.flushes.push(Box::new(flush));
+        self
+    }
+    fn run(mut self, brain: Brain<T>, stop: Receiver<()>) {
+        loop {
+            let signal = match self.jobs.iter().map(|job| job.due).min() {
+                Some(due) => stop.recv_timeout(due.saturating_duration_since(Instant::now())),
+                None => stop.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            if signal != Err(RecvTimeoutError::Timeout) {
+                break;
+            }
+            let now = Instant::now();
+            for job in self.jobs.iter_mut().filter(|job| job.due <= now) {
+                (job.task)(&brain);
+                job.due = Instant::now() + job.interval;
+            }
+        }
+        for flush in self.flushes {
+            flush(&brain);
+        }
+    }
+}
+impl<V: Eq + Hash + 'static> MaintenanceConfig<Arc<V>> {
+    /// Shares equal values across entries every interval, see
+    /// [`Brain::dedup`].
+    pub fn dedup_every(
+        self,
+        interval: Duration,
+        weigh: impl Fn(&V) -> usize + Send + 'static,
+    ) -> Self {
+        self.every(interval, move |brain| {
+            brain.dedup(&weigh);
+        })
+    }
+}
+
+/// Handle of a running maintenance worker.
+///
+/// Dropping the handle stops the worker too, but without waiting for it.
+pub struct Maintenance {
+    stop: Sender<()>,
+    worker: JoinHandle<()>,
+    finished: Arc<Mutex<Finished>>,
+}
+#[derive(Default)]
+struct Finished {
+    done: bool,
+    waker: Option<Waker>,
+}
+/// Tells the handle the worker finished, even if it panicked.
+struct Finishing(Arc<Mutex<Finished>>);
+impl Drop for Finishing {
+    fn drop(&mut self) {
+        let mut finished = self.0.lock();
+        finished.done = true;
+        if let Some(waker) = finished.waker.take() {
+            waker.wake();
+        }
+    }
+}
+impl Maintenance {
+    /// Runs the work on a background thread, handing it the receiver the
+    /// handle stops it through.
+    pub(crate) fn spawn(work: impl FnOnce(Receiver<()>) + Send + 'static) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let finished = Arc::new(Mutex::new(Finished::default()));
+        let finishing = Finishing(finished.clone());
+        let worker = thread::spawn(move || {
+            let _finishing = finishing;
+            work(stopped);
+        });
+        Self {
+            stop,
+            worker,
+            finished,
+        }
+    }
+    /// Stops the worker and waits until its shutdown tasks have run.
+    pub fn shutdown(self) -> thread::Result<()> {
+        let _ = self.stop.send(());
+        self.worker.join()
+    }
+    /// Stops the worker, completing once its shutdown tasks have run, so
+    /// async code awaits it rather than blocking on it.
+    pub fn shutdown_async(self) -> MaintenanceShutdown {
+        let _ = self.stop.send(());
+        MaintenanceShutdown {
+            worker: Some(self.worker),
+            finished: self.finished,
+        }
+    }
+}
+
+/// Completes once a maintenance worker shut down, see
+/// [`Maintenance::shutdown_async`].
+pub struct MaintenanceShutdown {
+    worker: Option<JoinHandle<()>>,
+    finished: Arc<Mutex<Finished>>,
+}
+impl Future for MaintenanceShutdown {
+    type Output = thread::Result<()>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut finished = self.finished.lock();
+            if !finished.done {
+                finished.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(self.worker.take().map_or(Ok(()), JoinHandle::join))
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Brain<T> {
+    /// Starts a background thread doing the configured maintenance.
+    pub fn start_maintenance(&self, config: MaintenanceConfig<T>) -> Maintenance {
+        let brain = self.clone();
+        Maintenance::spawn(move |stopped| config.run(brain, stopped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_brain::tests::block_on;
+    use crate::NumericalDuration;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn maintenance() {
+        let memory = Brain::new(1.milliseconds());
+        memory.memoize("a", 3);
+
+        let maintenance = memory.start_maintenance(
+            MaintenanceConfig::new()
+                .sweep_every(1.milliseconds())
+                .on_shutdown(|brain| brain.memoize("flushed", 1)),
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(memory.retrieve("a"), None);
+        assert_eq!(memory.retrieve("flushed"), None);
+
+        maintenance.shutdown().unwrap();
+        assert_eq!(memory.retrieve("flushed"), Some(1));
+    }
+
+    #[test]
+    fn zero_interval() {
+        let memory = Brain::<i32>::new(1.minutes());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let started = Instant::now();
+        let maintenance =
+            memory.start_maintenance(MaintenanceConfig::new().every(Duration::ZERO, move |_| {
+                counted.fetch_add(1, Ordering::Relaxed);
+            }));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        block_on(maintenance.shutdown_async()).unwrap();
+        let elapsed = started.elapsed().as_millis() as usize;
+        let runs = runs.load(Ordering::Relaxed);
+        assert!(
+            (1..=elapsed + 1).contains(&runs),
+            "{runs} runs in {elapsed} ms"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use crate::client::Client;
+use crate::transport::Stream;
+use crate::wall_clock;
+use crate::{
+    AsyncMemory, BinaryCodec, BoxFuture, ClientConfig, Duration, Memory, TryMemoize, WireCodec,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+/// Longest key memcached accepts.
+const MAX_KEY: usize = 250;
+/// Expiration times above this many seconds are taken as unix timestamps.
+const MAX_RELATIVE: i64 = 30 * 24 * 60 * 60;
+
+/// A memory kept by a memcached server, over its text protocol.
+///
+/// Values are stored in the crate's binary format, or encoded by the codec it
+/// was given, and expire in memcached, so [`Memory::forget`] does nothing.
+/// Keys longer than 250 bytes or holding whitespace or control characters,
+/// and encoded values larger than the server's item size, are rejected with [`io::ErrorKind::InvalidInput`] before they
+/// are sent. Connections are pooled and retried like those of a
+/// [`RemoteBrain`], and [`Memory`] drops errors, the `try_` methods report
+/// them.
+///
+/// [`RemoteBrain`]: crate::RemoteBrain
+pub struct MemcachedMemory<T> {
+    client: Arc<Client>,
+    retention: Duration,
+    max_value_size: usize,
+    codec: Arc<dyn WireCodec<T>>,
+}
+impl<T> Clone for MemcachedMemory<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            retention: self.retention,
+            max_value_size: self.max_value_size,
+            codec: self.codec.clone(),
+        }
+    }
+}
+impl<T: Serialize + DeserializeOwned + 'static> MemcachedMemory<T> {
+    /// Connects to a server, memoizing values for the retention.
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        retention: Duration,
+        config: ClientConfig,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            // the text protocol has no authentication
+            client: Arc::new(Client::speaking(address, config, |_, _| Ok(()))?),
+            retention,
+            max_value_size: 1 << 20,
+            codec: Arc::new(BinaryCodec),
+        })
+    }
+}
+impl<T> MemcachedMemory<T> {
+    /// Encodes values with the codec from now on.
+    pub fn codec(mut self, codec: impl WireCodec<T> + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+    /// Item size of the server, memcached's default of 1 MiB unless set.
+    pub fn max_value_size(mut self, max_value_size: usize) -> Self {
+        self.max_value_size = max_value_size;
+        self
+    }
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize_for(key, value, self.retention)
+    }
+    /// Memoizes a value with its own retention, rounded up to seconds.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        check(key)?;
+        let value = self.codec.encode(&value)?;
+        if value.len() > self.max_value_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("value of {} bytes exceeds the item size", value.len()),
+            ));
+        }
+        let seconds = retention.as_seconds_f64().ceil().max(1.0) as i64;
+        let expiration = match seconds {
+            relative if relative <= MAX_RELATIVE => relative,
+            _ => (wall_clock::now() + retention).unix_timestamp(),
+        };
+        let mut command = format!("set {key} 0 {expiration} {}\r\n", value.len()).into_bytes();
+        command.extend(value);
+        command.extend(b"\r\n");
+        self.client.call(|stream: &mut Stream| {
+            stream.write_all(&command)?;
+            Ok(match read_line(&mut BufReader::new(stream))?.as_str() {
+                "STORED" => Ok(()),
+                reply => Err(error(reply)),
+            })
+        })
+    }
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        check(key)?;
+        let value = self.client.call(|stream: &mut Stream| {
+            stream.write_all(format!("get {key}\r\n").as_bytes())?;
+            let mut reader = BufReader::new(stream);
+            let reply = read_line(&mut reader)?;
+            if reply == "END" {
+                return Ok(Ok(None));
+            }
+            let Some(len) = reply
+                .strip_prefix("VALUE ")
+                .and_then(|header| header.split(' ').nth(2))
+                .and_then(|len| len.parse::<usize>().ok())
+            else {
+                return Ok(Err(error(&reply)));
+            };
+            let mut value = vec![0; len + 2];
+            reader.read_exact(&mut value)?;
+            value.truncate(len);
+            if read_line(&mut reader)? != "END" {
+                return Err(invalid("unterminated get reply"));
+            }
+            Ok(Ok(Some(value)))
+        })?;
+        value.map(|value| self.codec.decode(&value)).transpose()
+    }
+    /// Removes the value for the key, telling whether there was one.
+    pub fn try_remove(&self, key: &str) -> io::Result<bool> {
+        check(key)?;
+        self.client.call(|stream: &mut Stream| {
+            stream.write_all(format!("delete {key}\r\n").as_bytes())?;
+            Ok(match read_line(&mut BufReader::new(stream))?.as_str() {
+                "DELETED" => Ok(true),
+                "NOT_FOUND" => Ok(false),
+                reply => Err(error(reply)),
+            })
+        })
+    }
+}
+impl<T> TryMemoize<T> for MemcachedMemory<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+impl<T> Memory<T> for MemcachedMemory<T> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    fn forget(&self) {}
+}
+/// Commands run on worker threads of the client, as many as the pooled
+/// connections, so they don't block the executor.
+impl<T: Send + 'static> AsyncMemory<T> for MemcachedMemory<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        let (memory, key) = (self.clone(), key.to_string());
+        Box::pin(
+            self.client
+                .offload(move || Memory::memoize(&memory, &key, value)),
+        )
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        let (memory, key) = (self.clone(), key.to_string());
+        Box::pin(self.client.offload(move || Memory::retrieve(&memory, &key)))
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Rejects keys memcached doesn't accept.
+fn check(key: &str) -> io::Result<()> {
+    let reason = if key.is_empty() {
+        "key is empty"
+    } else if key.len() > MAX_KEY {
+        "key is longer than 250 bytes"
+    } else if key.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        "key holds whitespace or control characters"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidInput, reason))
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    match line.strip_suffix("\r\n") {
+        Some(line) => Ok(line.to_string()),
+        None => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated reply",
+        )),
+    }
+}
+
+/// Turns an error reply into an error.
+fn error(reply: &str) -> io::Error {
+    io::Error::other(format!("memcached replied {reply}"))
+}
+
+fn invalid(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+
+    /// Serves `set`, `get` and `delete` for one connection, like memcached.
+    fn fake_memcached() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut items = HashMap::<String, Vec<u8>>::new();
+            while let Ok(line) = read_line(&mut reader) {
+                let parts = line.split(' ').collect::<Vec<_>>();
+                let reply = match parts[..] {
+                    ["set", key, _, _, len] => {
+                        let mut value = vec![0; len.parse::<usize>().unwrap() + 2];
+                        reader.read_exact(&mut value).unwrap();
+                        value.truncate(value.len() - 2);
+                        items.insert(key.to_string(), value);
+                        b"STORED\r\n".to_vec()
+                    }
+                    ["get", key] => match items.get(key) {
+                        Some(value) => {
+                            let mut reply =
+                                format!("VALUE {key} 0 {}\r\n", value.len()).into_bytes();
+                            reply.extend(value);
+                            reply.extend(b"\r\nEND\r\n");
+                            reply
+                        }
+                        None => b"END\r\n".to_vec(),
+                    },
+                    ["delete", key] => match items.remove(key) {
+                        Some(_) => b"DELETED\r\n".to_vec(),
+                        None => b"NOT_FOUND\r\n".to_vec(),
+                    },
+                    _ => b"ERROR\r\n".to_vec(),
+                };
+                writer.write_all(&reply).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn memcached() {
+        let config = ClientConfig::new().pool_size(1);
+        let memory = MemcachedMemory::connect(fake_memcached(), 1.hours(), config).unwrap();
+
+        Memory::memoize(&memory, "a", vec![1, 2, 3]);
+        assert_eq!(memory.try_retrieve("a").unwrap(), Some(vec![1, 2, 3]));
+        assert!(memory.try_remove("a").unwrap());
+        assert!(!memory.try_remove("a").unwrap());
+        assert_eq!(memory.try_retrieve("a").unwrap(), None);
+
+        let invalid = |error: io::Error| error.kind() == io::ErrorKind::InvalidInput;
+        assert!(memory.try_memoize("a b", vec![]).is_err_and(invalid));
+        assert!(memory.try_retrieve(&"k".repeat(251)).is_err_and(invalid));
+        let memory = memory.max_value_size(8);
+        assert!(memory.try_memoize("big", vec![0; 16]).is_err_and(invalid));
+    }
+}
@@ -0,0 +1,401 @@
+use crate::wall_clock::Instant;
+use crate::{Brain, Duration, Memory};
+use parking_lot::{Condvar, Mutex};
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Decides whether and for how long a computed value is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Caching {
+    /// Keep the value for the brain's retention.
+    Retain,
+    /// Keep the value for the given retention.
+    RetainFor(Duration),
+    /// Don't keep the value at all.
+    Skip,
+}
+
+/// Given instead of calling a function whose circuit breaker is open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitOpen;
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("circuit open")
+    }
+}
+impl std::error::Error for CircuitOpen {}
+
+/// Caches the results of a function in a [`Brain`].
+///
+/// Concurrent calls for the same key are coalesced: one caller computes the
+/// value while the others wait for it instead of computing it again.
+pub struct Memoizer<A, T, K, F> {
+    brain: Brain<T>,
+    key_fn: K,
+    compute_fn: F,
+    caching: Box<dyn Fn(&T) -> Caching + Send + Sync>,
+    breaker: Option<Breaker<T>>,
+    in_flight: Mutex<HashMap<String, Arc<Flight<T>>>>,
+    args: PhantomData<fn(&A)>,
+}
+impl<A, T, K, F> Memoizer<A, T, K, F>
+where
+    T: Clone,
+    K: Fn(&A) -> String,
+    F: Fn(&A) -> T,
+{
+    pub fn new(brain: Brain<T>, key_fn: K, compute_fn: F) -> Self {
+        Self {
+            brain,
+            key_fn,
+            compute_fn,
+            caching: Box::new(|_| Caching::Retain),
+            breaker: None,
+            in_flight: Default::default(),
+            args: PhantomData,
+        }
+    }
+    /// Sets the policy deciding how computed values are cached.
+    pub fn caching(mut self, policy: impl Fn(&T) -> Caching + Send + Sync + 'static) -> Self {
+        self.caching = Box::new(policy);
+        self
+    }
+    pub fn call(&self, args: A) -> T {
+        let key = (self.key_fn)(&args);
+        if let Some(value) = self.brain.retrieve(&key) {
+            return value;
+        }
+        let flight = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(&key) {
+                Some(flight) => Err(flight.clone()),
+                None => {
+                    // a previous leader may have landed since the first lookup
+                    if let Some(value) = self.brain.retrieve(&key) {
+                        return value;
+                    }
+                    let flight = Arc::new(Flight::default());
+                    in_flight.insert(key.clone(), flight.clone());
+                    Ok(flight)
+                }
+            }
+        };
+        match flight {
+            Ok(flight) => {
+                let _landing = Landing {
+                    in_flight: &self.in_flight,
+                    key: &key,
+                    flight: &flight,
+                };
+                let value = match &self.breaker {
+                    Some(breaker) if !breaker.allows(&key) => {
+                        let value = (breaker.open)();
+                        flight.land(value.clone());
+                        return value;
+                    }
+                    Some(breaker) => {
+                        let value = (self.compute_fn)(&args);
+                        breaker.record(&key, &value);
+                        value
+                    }
+                    None => (self.compute_fn)(&args),
+                };
+                match (self.caching)(&value) {
+                    Caching::Retain => self.brain.memoize(&key, value.clone()),
+                    Caching::RetainFor(retention) => {
+                        self.brain.memoize_for(&key, value.clone(), retention)
+                    }
+                    Caching::Skip => {}
+                }
+                flight.land(value.clone());
+                value
+            }
+            Err(flight) => match flight.wait() {
+                Some(value) => value,
+                // the leader panicked, try again
+                None => self.call(args),
+            },
+        }
+    }
+    pub fn brain(&self) -> &Brain<T> {
+        &self.brain
+    }
+}
+
+impl<A, V, E, K, F> Memoizer<A, Result<V, E>, K, F>
+where
+    V: Clone,
+    E: Clone,
+    K: Fn(&A) -> String,
+    F: Fn(&A) -> Result<V, E>,
+{
+    /// Sets how errors are cached, successes keep the brain's retention.
+    pub fn cache_errors(self, caching: Caching) -> Self {
+        self.caching(move |result| match result {
+            Ok(_) => Caching::Retain,
+            Err(_) => caching,
+        })
+    }
+}
+impl<A, V, E, K, F> Memoizer<A, Result<V, E>, K, F>
+where
+    V: Clone,
+    E: Clone + From<CircuitOpen>,
+    K: Fn(&A) -> String,
+    F: Fn(&A) -> Result<V, E>,
+{
+    /// Stops calling the function for a key after `threshold` consecutive
+    /// errors, answering [`CircuitOpen`] instead until `cooldown` has passed.
+    ///
+    /// Memoized values are still served while the circuit is open.
+    pub fn circuit_breaker(self, threshold: usize, cooldown: Duration) -> Self {
+        self.breaker(threshold, cooldown, false)
+    }
+    /// Like [`Memoizer::circuit_breaker`], but errors of all keys add up.
+    pub fn global_circuit_breaker(self, threshold: usize, cooldown: Duration) -> Self {
+        self.breaker(threshold, cooldown, true)
+    }
+    fn breaker(mut self, threshold: usize, cooldown: Duration, global: bool) -> Self {
+        self.breaker = Some(Breaker {
+            threshold,
+            cooldown: cooldown.unsigned_abs(),
+            global,
+            is_failure: Result::is_err,
+            open: || Err(CircuitOpen.into()),
+            circuits: Default::default(),
+        });
+        self
+    }
+}
+
+#[derive(Default)]
+struct Circuit {
+    failures: usize,
+    opened: Option<Instant>,
+}
+
+struct Breaker<T> {
+    threshold: usize,
+    cooldown: std::time::Duration,
+    global: bool,
+    is_failure: fn(&T) -> bool,
+    open: fn() -> T,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+impl<T> Breaker<T> {
+    fn circuit<'key>(&self, key: &'key str) -> &'key str {
+        if self.global {
+            ""
+        } else {
+            key
+        }
+    }
+    fn allows(&self, key: &str) -> bool {
+        match self.circuits.lock().get(self.circuit(key)) {
+            Some(Circuit {
+                opened: Some(opened),
+                ..
+            }) => opened.elapsed() >= self.cooldown,
+            _ => true,
+        }
+    }
+    fn record(&self, key: &str, value: &T) {
+        let key = self.circuit(key);
+        let mut circuits = self.circuits.lock();
+        if (self.is_failure)(value) {
+            let circuit = circuits.entry(key.to_string()).or_default();
+            circuit.failures += 1;
+            if circuit.failures >= self.threshold {
+                // half open after the cooldown, one more error opens it again
+                circuit.opened = Some(Instant::now());
+            }
+        } else {
+            circuits.remove(key);
+        }
+    }
+}
+
+pub(crate) enum FlightState<T> {
+    Running,
+    Landed(T),
+    Abandoned,
+}
+
+pub(crate) struct Flight<T> {
+    pub(crate) state: Mutex<FlightState<T>>,
+    pub(crate) done: Condvar,
+}
+impl<T> Default for Flight<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(FlightState::Running),
+            done: Condvar::new(),
+        }
+    }
+}
+impl<T: Clone> Flight<T> {
+    pub(crate) fn land(&self, value: T) {
+        *self.state.lock() = FlightState::Landed(value);
+        self.done.notify_all();
+    }
+    pub(crate) fn wait(&self) -> Option<T> {
+        let mut state = self.state.lock();
+        loop {
+            match &*state {
+                FlightState::Running => self.done.wait(&mut state),
+                FlightState::Landed(value) => return Some(value.clone()),
+                FlightState::Abandoned => return None,
+            }
+        }
+    }
+}
+
+/// Unregisters a flight once its leader is done, even if it panicked.
+pub(crate) struct Landing<'a, T> {
+    pub(crate) in_flight: &'a Mutex<HashMap<String, Arc<Flight<T>>>>,
+    pub(crate) key: &'a str,
+    pub(crate) flight: &'a Flight<T>,
+}
+impl<T> Drop for Landing<'_, T> {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(self.key);
+        let mut state = self.flight.state.lock();
+        if let FlightState::Running = *state {
+            *state = FlightState::Abandoned;
+        }
+        self.flight.done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn memoize_function() {
+        let calls = AtomicUsize::new(0);
+        let memoizer = Memoizer::new(
+            Brain::new(1.minutes()),
+            |n: &u64| n.to_string(),
+            |n: &u64| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                n * n
+            },
+        );
+
+        assert_eq!(memoizer.call(3), 9);
+        assert_eq!(memoizer.call(3), 9);
+        assert_eq!(memoizer.call(4), 16);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(memoizer.brain().retrieve("3"), Some(9));
+    }
+
+    #[test]
+    fn stampede() {
+        let calls = AtomicUsize::new(0);
+        let memoizer = Memoizer::new(
+            Brain::new(1.minutes()),
+            |_: &()| "key".to_string(),
+            |_: &()| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                42
+            },
+        );
+
+        std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| scope.spawn(|| memoizer.call(())))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), 42);
+            }
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn skip_errors() {
+        let calls = AtomicUsize::new(0);
+        let memoizer = Memoizer::new(
+            Brain::new(1.minutes()),
+            |n: &i64| n.to_string(),
+            |n: &i64| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                if *n < 0 {
+                    Err("negative")
+                } else {
+                    Ok(*n)
+                }
+            },
+        )
+        .cache_errors(Caching::Skip);
+
+        assert_eq!(memoizer.call(-1), Err("negative"));
+        assert_eq!(memoizer.call(-1), Err("negative"));
+        assert_eq!(memoizer.call(1), Ok(1));
+        assert_eq!(memoizer.call(1), Ok(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(memoizer.brain().retrieve("-1"), None);
+    }
+
+    #[test]
+    fn short_lived_errors() {
+        let memoizer = Memoizer::new(
+            Brain::new(1.minutes()),
+            |n: &i64| n.to_string(),
+            |n: &i64| if *n < 0 { Err(()) } else { Ok(*n) },
+        )
+        .cache_errors(Caching::RetainFor(1.milliseconds()));
+
+        assert_eq!(memoizer.call(-1), Err(()));
+        assert_eq!(memoizer.call(1), Ok(1));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memoizer.brain().forget();
+
+        assert_eq!(memoizer.brain().retrieve("-1"), None);
+        assert_eq!(memoizer.brain().retrieve("1"), Some(Ok(1)));
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Error {
+        Failed,
+        Open,
+    }
+    impl From<CircuitOpen> for Error {
+        fn from(_: CircuitOpen) -> Self {
+            Error::Open
+        }
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let calls = AtomicUsize::new(0);
+        let memoizer = Memoizer::new(
+            Brain::new(1.minutes()),
+            |n: &i64| n.to_string(),
+            |_: &i64| -> Result<i64, Error> {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::Failed)
+            },
+        )
+        .cache_errors(Caching::Skip)
+        .circuit_breaker(2, 5.milliseconds());
+
+        assert_eq!(memoizer.call(1), Err(Error::Failed));
+        assert_eq!(memoizer.call(1), Err(Error::Failed));
+        assert_eq!(memoizer.call(1), Err(Error::Open));
+        assert_eq!(memoizer.call(2), Err(Error::Failed));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        std::thread::sleep(std::time::Duration::from_millis(6));
+        assert_eq!(memoizer.call(1), Err(Error::Failed));
+        assert_eq!(memoizer.call(1), Err(Error::Open));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,162 @@
+/// Types encoding canonically into a key, e.g. a struct scoping cached
+/// values, so call sites don't each format it their own way.
+///
+/// Implemented by [`memory_key!`], encoding the type's name and then its
+/// fields in order, each after a `:` and with `:` and `\` escaped, so
+/// distinct values give distinct keys.
+///
+/// [`memory_key!`]: crate::memory_key
+pub trait MemoryKey {
+    fn encode_key(&self, key: &mut KeyEncoder);
+
+    fn to_key(&self) -> String {
+        let mut key = KeyEncoder::default();
+        self.encode_key(&mut key);
+        key.finish()
+    }
+}
+
+/// Builds a key from a name and parts, see [`MemoryKey`].
+#[derive(Debug, Default)]
+pub struct KeyEncoder {
+    key: String,
+}
+impl KeyEncoder {
+    /// Writes the name of the type, or of the enum and its variant, as is.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.key.push_str(name);
+        self
+    }
+    pub fn part(&mut self, part: &(impl KeyPart + ?Sized)) -> &mut Self {
+        self.key.push(':');
+        part.encode_part(self);
+        self
+    }
+    /// Writes text into the current part, escaping separators.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            if matches!(c, ':' | '\\') {
+                self.key.push('\\');
+            }
+            self.key.push(c);
+        }
+        self
+    }
+    pub fn finish(self) -> String {
+        self.key
+    }
+}
+
+/// Values making up a [`MemoryKey`].
+pub trait KeyPart {
+    fn encode_part(&self, key: &mut KeyEncoder);
+}
+impl KeyPart for str {
+    fn encode_part(&self, key: &mut KeyEncoder) {
+        key.text(self);
+    }
+}
+impl KeyPart for String {
+    fn encode_part(&self, key: &mut KeyEncoder) {
+        key.text(self);
+    }
+}
+impl<P: KeyPart + ?Sized> KeyPart for &P {
+    fn encode_part(&self, key: &mut KeyEncoder) {
+        (**self).encode_part(key);
+    }
+}
+/// `None` is empty, `Some` is marked by a `+`.
+impl<P: KeyPart> KeyPart for Option<P> {
+    fn encode_part(&self, key: &mut KeyEncoder) {
+        if let Some(part) = self {
+            key.key.push('+');
+            part.encode_part(key);
+        }
+    }
+}
+macro_rules! display_parts {
+    ($($part:ty),*) => {$(
+        impl KeyPart for $part {
+            fn encode_part(&self, key: &mut KeyEncoder) {
+                key.text(&self.to_string());
+            }
+        }
+    )*};
+}
+display_parts!(bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Implements [`MemoryKey`] for a struct by its named fields, or for an
+/// enum by its unit and tuple variants, naming their fields in order.
+///
+/// ```
+/// # use memory::{memory_key, MemoryKey};
+/// struct UserScope {
+///     tenant: String,
+///     user: u64,
+///     locale: Option<String>,
+/// }
+/// memory_key!(UserScope { tenant, user, locale });
+///
+/// enum Scope {
+///     Global,
+///     Tenant(String),
+/// }
+/// memory_key!(enum Scope { Global, Tenant(tenant) });
+///
+/// let scope = UserScope { tenant: "a:b".into(), user: 42, locale: None };
+/// assert_eq!(scope.to_key(), r"UserScope:a\:b:42:");
+/// assert_eq!(Scope::Tenant("acme".into()).to_key(), "Scope.Tenant:acme");
+/// ```
+#[macro_export]
+macro_rules! memory_key {
+    (enum $name:ident { $($variant:ident $(($($field:ident),* $(,)?))?),* $(,)? }) => {
+        impl $crate::MemoryKey for $name {
+            fn encode_key(&self, key: &mut $crate::KeyEncoder) {
+                match self {
+                    $($name::$variant $(($($field),*))? => {
+                        key.name(concat!(stringify!($name), ".", stringify!($variant)));
+                        $($(key.part($field);)*)?
+                    })*
+                }
+            }
+        }
+    };
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::MemoryKey for $name {
+            fn encode_key(&self, key: &mut $crate::KeyEncoder) {
+                key.name(stringify!($name));
+                $(key.part(&self.$field);)*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Scope {
+        tenant: String,
+        user: u64,
+        locale: Option<String>,
+    }
+    memory_key!(Scope {
+        tenant,
+        user,
+        locale
+    });
+
+    #[test]
+    fn keys() {
+        let scope = |tenant: &str, locale: Option<&str>| Scope {
+            tenant: tenant.into(),
+            user: 7,
+            locale: locale.map(Into::into),
+        };
+        assert_eq!(scope("acme", Some("en")).to_key(), "Scope:acme:7:+en");
+        assert_eq!(scope(r"a\:b", None).to_key(), r"Scope:a\\\:b:7:");
+        assert_ne!(scope("a", Some("")).to_key(), scope("a", None).to_key());
+        assert_ne!(scope("a:7", None).to_key(), scope("a", None).to_key());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::stats::SWEEP_BUCKETS;
+use crate::wall_clock::Instant;
+use crate::{AsyncMemory, BoxFuture, Brain, Memory, StorageBackend};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Name and description of a metric.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+pub const HITS: Metric = Metric {
+    name: "brain_hits_total",
+    help: "Retrievals finding a value.",
+};
+pub const MISSES: Metric = Metric {
+    name: "brain_misses_total",
+    help: "Retrievals finding no value.",
+};
+pub const INSERTIONS: Metric = Metric {
+    name: "brain_insertions_total",
+    help: "Memoized values.",
+};
+pub const ENTRIES: Metric = Metric {
+    name: "brain_entries",
+    help: "Memoized entries.",
+};
+pub const ESTIMATED_BYTES: Metric = Metric {
+    name: "brain_estimated_bytes",
+    help: "Estimated memory held by entries.",
+};
+pub const EVICTIONS: Metric = Metric {
+    name: "brain_evictions_total",
+    help: "Evicted entries by cause.",
+};
+pub const REPLICATION_PENDING: Metric = Metric {
+    name: "brain_replication_pending",
+    help: "Operations a replica has yet to apply.",
+};
+pub const REPLICATION_LAG: Metric = Metric {
+    name: "brain_replication_lag_seconds",
+    help: "Age of the oldest operation a replica has yet to apply.",
+};
+pub const SWEEP_DURATION: Metric = Metric {
+    name: "brain_sweep_duration_seconds",
+    help: "Duration of forget sweeps.",
+};
+pub const MEMORY_HITS: Metric = Metric {
+    name: "memory_hits_total",
+    help: "Retrievals of a metered memory finding a value.",
+};
+pub const MEMORY_MISSES: Metric = Metric {
+    name: "memory_misses_total",
+    help: "Retrievals of a metered memory finding no value.",
+};
+pub const MEMORY_WRITES: Metric = Metric {
+    name: "memory_writes_total",
+    help: "Values memoized into a metered memory.",
+};
+pub const MEMORY_DURATION: Metric = Metric {
+    name: "memory_operation_duration_seconds",
+    help: "Duration of the operations of a metered memory by operation.",
+};
+
+/// Upper bounds of the buckets of [`MEMORY_DURATION`], in seconds.
+pub const LATENCY_BUCKETS: [f64; 7] = [0.000_01, 0.000_1, 0.001, 0.01, 0.1, 1.0, 10.0];
+
+/// Distribution of observations over buckets.
+#[derive(Clone, Copy, Debug)]
+pub struct Histogram<'a> {
+    /// Upper bounds of the buckets.
+    pub bounds: &'a [f64],
+    /// Observations per bucket, not cumulative.
+    pub counts: &'a [u64],
+    pub count: u64,
+    pub sum: f64,
+}
+
+/// Receives the metrics of brains, e.g. to hand them to a metrics backend.
+///
+/// Every metric is labeled with the `cache` name it was reported under.
+pub trait MetricsSink {
+    fn counter(&mut self, metric: Metric, labels: &[(&str, &str)], value: u64);
+    fn gauge(&mut self, metric: Metric, labels: &[(&str, &str)], value: f64);
+    fn histogram(&mut self, metric: Metric, labels: &[(&str, &str)], histogram: Histogram<'_>);
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Reports the current metrics of the brain to a sink.
+    pub fn report_metrics(&self, cache: &str, sink: &mut dyn MetricsSink) {
+        let sample = self.sample();
+        let labels = [("cache", cache)];
+        sink.counter(HITS, &labels, sample.stats.hits);
+        sink.counter(MISSES, &labels, sample.stats.misses);
+        sink.counter(INSERTIONS, &labels, sample.stats.insertions);
+        sink.gauge(ENTRIES, &labels, sample.entries as f64);
+        sink.gauge(ESTIMATED_BYTES, &labels, sample.bytes as f64);
+        sink.counter(
+            EVICTIONS,
+            &[("cache", cache), ("cause", "expired")],
+            sample.stats.evictions,
+        );
+        sink.counter(
+            EVICTIONS,
+            &[("cache", cache), ("cause", "capacity")],
+            sample.stats.capacity_evictions,
+        );
+        sink.histogram(
+            SWEEP_DURATION,
+            &labels,
+            Histogram {
+                bounds: &SWEEP_BUCKETS,
+                counts: &sample.sweeps,
+                count: sample.sweep_count,
+                sum: sample.sweep_seconds,
+            },
+        );
+    }
+}
+
+/// Counters of a [`MeteredMemory`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeteredStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub writes: u64,
+    pub forgets: u64,
+}
+
+/// Records hits, misses and the duration of the operations of any memory
+/// it wraps, e.g. a remote or tiered one, to report them to a sink.
+pub struct MeteredMemory<M> {
+    memory: M,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    writes: AtomicU64,
+    forgets: AtomicU64,
+    retrievals: Latencies,
+    memoizations: Latencies,
+    sweeps: Latencies,
+}
+#[derive(Default)]
+struct Latencies {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    nanos: AtomicU64,
+}
+impl Latencies {
+    fn observe(&self, started: Instant) {
+        let elapsed = started.elapsed();
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+    fn report(&self, sink: &mut dyn MetricsSink, labels: &[(&str, &str)]) {
+        let counts = self
+            .buckets
+            .each_ref()
+            .map(|bucket| bucket.load(Ordering::Relaxed));
+        sink.histogram(
+            MEMORY_DURATION,
+            labels,
+            Histogram {
+                bounds: &LATENCY_BUCKETS,
+                counts: &counts,
+                count: self.count.load(Ordering::Relaxed),
+                sum: self.nanos.load(Ordering::Relaxed) as f64 / 1e9,
+            },
+        );
+    }
+}
+impl<M> MeteredMemory<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            forgets: AtomicU64::new(0),
+            retrievals: Latencies::default(),
+            memoizations: Latencies::default(),
+            sweeps: Latencies::default(),
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    pub fn stats(&self) -> MeteredStats {
+        MeteredStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            forgets: self.forgets.load(Ordering::Relaxed),
+        }
+    }
+    /// Reports the metrics of the memory to a sink, the durations labeled
+    /// with their `operation`.
+    pub fn report_metrics(&self, cache: &str, sink: &mut dyn MetricsSink) {
+        let stats = self.stats();
+        let labels = [("cache", cache)];
+        sink.counter(MEMORY_HITS, &labels, stats.hits);
+        sink.counter(MEMORY_MISSES, &labels, stats.misses);
+        sink.counter(MEMORY_WRITES, &labels, stats.writes);
+        for (operation, latencies) in [
+            ("retrieve", &self.retrievals),
+            ("memoize", &self.memoizations),
+            ("forget", &self.sweeps),
+        ] {
+            latencies.report(sink, &[("cache", cache), ("operation", operation)]);
+        }
+    }
+    fn retrieved<T>(&self, value: &Option<T>, started: Instant) {
+        self.retrievals.observe(started);
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+    fn memoized(&self, started: Instant) {
+        self.memoizations.observe(started);
+        self.writes.fetch_add(1, Ordering::Relaxed);
+    }
+    fn forgot(&self, started: Instant) {
+        self.sweeps.observe(started);
+        self.forgets.fetch_add(1, Ordering::Relaxed);
+    }
+}
+impl<T, M: Memory<T>> Memory<T> for MeteredMemory<M> {
+    fn memoize(&self, key: &str, value: T) {
+        let started = Instant::now();
+        self.memory.memoize(key, value);
+        self.memoized(started);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        let started = Instant::now();
+        let value = self.memory.retrieve(key);
+        self.retrieved(&value, started);
+        value
+    }
+    fn forget(&self) {
+        let started = Instant::now();
+        self.memory.forget();
+        self.forgot(started);
+    }
+}
+/// Durations include the time the operations waited to be polled.
+impl<T: Send + 'static, M: AsyncMemory<T> + Sync> AsyncMemory<T> for MeteredMemory<M> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let started = Instant::now();
+            self.memory.memoize(key, value).await;
+            self.memoized(started);
+        })
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let value = self.memory.retrieve(key).await;
+            self.retrieved(&value, started);
+            value
+        })
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let started = Instant::now();
+            self.memory.forget().await;
+            self.forgot(started);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[derive(Default)]
+    struct Recorder(Vec<(String, String, f64)>);
+    impl MetricsSink for Recorder {
+        fn counter(&mut self, metric: Metric, labels: &[(&str, &str)], value: u64) {
+            self.gauge(metric, labels, value as f64);
+        }
+        fn gauge(&mut self, metric: Metric, labels: &[(&str, &str)], value: f64) {
+            let labels = labels
+                .iter()
+                .map(|(label, value)| format!("{label}={value}"))
+                .collect::<Vec<_>>();
+            self.0
+                .push((metric.name.to_string(), labels.join(","), value));
+        }
+        fn histogram(&mut self, metric: Metric, labels: &[(&str, &str)], histogram: Histogram<'_>) {
+            self.counter(metric, labels, histogram.count);
+        }
+    }
+
+    #[test]
+    fn report() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        memory.retrieve("a");
+        memory.forget();
+
+        let mut recorder = Recorder::default();
+        memory.report_metrics("numbers", &mut recorder);
+
+        let metric = |name: &str| {
+            recorder
+                .0
+                .iter()
+                .find(|(metric, ..)| metric == name)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            metric(HITS.name),
+            (HITS.name.to_string(), "cache=numbers".to_string(), 1.0)
+        );
+        assert_eq!(metric(MISSES.name).2, 0.0);
+        assert_eq!(metric(INSERTIONS.name).2, 1.0);
+        assert_eq!(metric(ENTRIES.name).2, 1.0);
+        assert_eq!(metric(EVICTIONS.name).1, "cache=numbers,cause=expired");
+        assert_eq!(metric(SWEEP_DURATION.name).2, 1.0);
+    }
+
+    #[test]
+    fn metered() {
+        let memory = MeteredMemory::new(Brain::new(1.minutes()));
+        memory.memoize("a", 1);
+        memory.retrieve("a");
+        memory.retrieve("b");
+        assert_eq!(
+            memory.stats(),
+            MeteredStats {
+                hits: 1,
+                misses: 1,
+                writes: 1,
+                forgets: 0
+            }
+        );
+
+        let mut recorder = Recorder::default();
+        memory.report_metrics("any", &mut recorder);
+        let durations = recorder
+            .0
+            .iter()
+            .filter(|(metric, ..)| metric == MEMORY_DURATION.name)
+            .map(|(_, labels, count)| (labels.as_str(), *count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            durations,
+            vec![
+                ("cache=any,operation=retrieve", 2.0),
+                ("cache=any,operation=memoize", 1.0),
+                ("cache=any,operation=forget", 0.0),
+            ]
+        );
+    }
+}
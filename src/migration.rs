@@ -0,0 +1,166 @@
+use crate::snapshot::{self, invalid_data, DELTA_MAGIC, MAGIC};
+use crate::wal::{self, Record};
+use crate::{json, Brain, Engram, InsertionOrdered, LoadOptions, StorageBackend};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+
+/// Upgrades a snapshot, snapshot delta or write-ahead log to the current
+/// format, see [`migrate_with`].
+pub fn migrate<T: Serialize + DeserializeOwned>(
+    reader: impl Read,
+    writer: impl Write,
+) -> io::Result<()> {
+    migrate_with(reader, writer, LoadOptions::new(), |_, value: T| {
+        Some(value)
+    })
+}
+
+/// Upgrades a file like [`migrate`], converting the values from the type
+/// they were saved with, an upgrade giving `None` drops the engram.
+///
+/// Files keep their kind: binary snapshots and deltas of any version are
+/// rewritten in the current version, without a codec, JSON snapshots stay
+/// JSON and log records are checksummed, as [`WalBrain::recover`] expects
+/// since it checks them. Engrams keep the time they were memoized, expired
+/// ones are left to the load.
+///
+/// [`WalBrain::recover`]: crate::WalBrain::recover
+pub fn migrate_with<Old: DeserializeOwned, New: Serialize>(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    options: LoadOptions<'_>,
+    mut upgrade: impl FnMut(&str, Old) -> Option<New>,
+) -> io::Result<()> {
+    let mut upgrade = |key: &str, engram: Engram<Old>| {
+        Some(Engram {
+            value: upgrade(key, engram.value)?,
+            memoized: engram.memoized,
+            retention: engram.retention,
+            actor: engram.actor,
+            hits: engram.hits,
+            salience: engram.salience,
+            version: engram.version,
+            context: engram.context,
+            attributes: engram.attributes,
+        })
+    };
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents)?;
+    let migrated = if contents.starts_with(MAGIC) {
+        let mut payload = Vec::new();
+        for (key, engram) in snapshot::read_engrams::<Old>(&contents, options)? {
+            if let Some(engram) = upgrade(&key, engram) {
+                snapshot::record(&mut payload, &(key, engram))?;
+            }
+        }
+        snapshot::encode_binary(MAGIC, None, payload)?
+    } else if contents.starts_with(DELTA_MAGIC) {
+        let mut payload = Vec::new();
+        for (key, engram) in snapshot::read_delta::<Old>(&contents, options)? {
+            let engram = engram.and_then(|engram| upgrade(&key, engram));
+            snapshot::record(&mut payload, &(key, engram))?;
+        }
+        snapshot::encode_binary(DELTA_MAGIC, None, payload)?
+    } else {
+        let text = std::str::from_utf8(&contents).map_err(invalid_data)?;
+        if text.trim_start().starts_with('{') {
+            let brain =
+                json::from_str::<Brain<Old, InsertionOrdered<Old>>>(text).map_err(invalid_data)?;
+            let migrated = Brain::with_storage(brain.retention, InsertionOrdered::default());
+            {
+                let mut memory = migrated.memory.write();
+                for (key, engram) in snapshot::into_engrams(brain) {
+                    if let Some(engram) = upgrade(&key, engram) {
+                        memory.put(key, engram);
+                    }
+                }
+            }
+            json::to_string(&migrated)
+                .map_err(invalid_data)?
+                .into_bytes()
+        } else {
+            migrate_log(text, upgrade)?.into_bytes()
+        }
+    };
+    writer.write_all(&migrated)
+}
+
+/// Rewrites the records of a log with checksums, dropping a torn last one.
+fn migrate_log<Old: DeserializeOwned, New: Serialize>(
+    text: &str,
+    mut upgrade: impl FnMut(&str, Engram<Old>) -> Option<Engram<New>>,
+) -> io::Result<String> {
+    let lines = text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    let mut migrated = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        // logs written before records were checksummed hold bare records
+        let record = if line.starts_with('[') {
+            Some(*line)
+        } else {
+            wal::verified(line)
+        };
+        let record = record.and_then(|record| json::from_str::<Record<Old>>(record).ok());
+        let record = match record {
+            Some(Record::Memoize(key, engram)) => match upgrade(&key, engram) {
+                Some(engram) => json::to_string(&("memoize", key, engram)),
+                None => continue,
+            },
+            Some(Record::Forget(now)) => json::to_string(&("forget", now)),
+            None if index + 1 == lines.len() => break,
+            None => return Err(invalid_data("corrupt log record")),
+        };
+        migrated += &wal::line(&record.map_err(invalid_data)?);
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wall_clock;
+    use crate::{binary, Duration, Memory, NumericalDuration, WalBrain};
+    use std::collections::HashMap;
+
+    #[test]
+    fn snapshot() {
+        // the first binary format held the brain as a whole, with engrams
+        // of a value, the time it was memoized and its retention
+        let now = wall_clock::now();
+        let engrams = HashMap::from([
+            ("a".to_string(), (3, now, None::<Duration>)),
+            ("b".to_string(), (-1, now, None)),
+        ]);
+        let mut contents = MAGIC.to_vec();
+        contents.extend(1u16.to_le_bytes());
+        contents.extend(binary::to_vec(&(1.minutes(), engrams)).unwrap());
+
+        let mut migrated = Vec::new();
+        let upgrade = |_: &str, value: i32| (value >= 0).then(|| value.to_string());
+        migrate_with(&contents[..], &mut migrated, LoadOptions::new(), upgrade).unwrap();
+
+        let path = std::env::temp_dir().join(format!("brain-{}-migrated", std::process::id()));
+        std::fs::write(&path, &migrated).unwrap();
+        let restored = Brain::<String>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some("3".to_string()));
+        assert!(!restored.is_cached("b"));
+    }
+
+    #[test]
+    fn log() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        let engram = json::to_string(&memory.memory.read()["a"]).unwrap();
+        let legacy = format!("[\"memoize\",\"a\",{engram}]\n[\"forget\",");
+
+        let path = std::env::temp_dir().join(format!("brain-{}-migrated.wal", std::process::id()));
+        migrate::<i32>(legacy.as_bytes(), std::fs::File::create(&path).unwrap()).unwrap();
+        let restored = WalBrain::<i32>::recover(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(3));
+    }
+}
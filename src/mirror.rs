@@ -0,0 +1,185 @@
+use crate::{Brain, Engram, Memory, StorageBackend};
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A memory telling whether memoizing succeeded, e.g. a remote one, to
+/// mirror writes into with [`MirroredMemory::mirror_fallible`].
+pub trait TryMemoize<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()>;
+}
+impl<T: Clone, S: StorageBackend<String, T>> TryMemoize<T> for Brain<T, S> {
+    /// Fails with [`io::ErrorKind::OutOfMemory`] for new keys once the
+    /// brain is at its hard limit, see [`BrainBuilder::hard_limit`].
+    ///
+    /// [`BrainBuilder::hard_limit`]: crate::BrainBuilder::hard_limit
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        if self.store_if(key, Engram::new(value, None), |_| true) {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            "brain at its hard limit",
+        ))
+    }
+}
+
+/// What a [`MirroredMemory`] does when writing to a mirror fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnMirrorFailure {
+    /// Counts the failure, the write succeeds.
+    #[default]
+    Ignore,
+    /// Writes to the mirror up to this many times again, then ignores the
+    /// failure.
+    Retry(u32),
+    /// Fails [`MirroredMemory::try_memoize`], after writing to every other
+    /// mirror.
+    Fail,
+}
+
+/// Given by [`MirroredMemory::try_memoize`] for the mirrors which failed,
+/// by their index in the order they were added.
+#[derive(Debug)]
+pub struct MirrorError {
+    pub failures: Vec<(usize, io::Error)>,
+}
+impl fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mirrors failed", self.failures.len())?;
+        for (mirror, error) in &self.failures {
+            write!(f, ", mirror {mirror}: {error}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for MirrorError {}
+
+type Mirror<T> = Box<dyn Fn(&str, T) -> io::Result<()> + Send + Sync>;
+
+/// Memoizes into a memory and every mirror added to it, e.g. to warm a
+/// standby node, retrieving from the first memory only.
+///
+/// Mirrors are written one after another, after the memory. Forgetting
+/// only forgets the memory.
+pub struct MirroredMemory<T, M> {
+    memory: M,
+    mirrors: Vec<Mirror<T>>,
+    on_failure: OnMirrorFailure,
+    failures: AtomicU64,
+}
+impl<T: Clone, M: Memory<T>> MirroredMemory<T, M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            memory,
+            mirrors: Vec::new(),
+            on_failure: OnMirrorFailure::default(),
+            failures: AtomicU64::new(0),
+        }
+    }
+    /// Mirrors writes into a memory which can't fail.
+    pub fn mirror(mut self, mirror: impl Memory<T> + Send + Sync + 'static) -> Self {
+        self.mirrors.push(Box::new(move |key, value| {
+            mirror.memoize(key, value);
+            Ok(())
+        }));
+        self
+    }
+    /// Mirrors writes into a memory which may fail, handled as chosen by
+    /// [`MirroredMemory::on_failure`].
+    pub fn mirror_fallible(mut self, mirror: impl TryMemoize<T> + Send + Sync + 'static) -> Self {
+        self.mirrors
+            .push(Box::new(move |key, value| mirror.try_memoize(key, value)));
+        self
+    }
+    pub fn on_failure(mut self, on_failure: OnMirrorFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// Writes to mirrors which failed in the end.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+    /// Memoizes into the memory and every mirror, failing for the mirrors
+    /// which did if failures aren't ignored.
+    pub fn try_memoize(&self, key: &str, value: T) -> Result<(), MirrorError> {
+        let retries = match self.on_failure {
+            OnMirrorFailure::Retry(retries) => retries,
+            _ => 0,
+        };
+        let mut failures = Vec::new();
+        for (index, mirror) in self.mirrors.iter().enumerate() {
+            let mut result = mirror(key, value.clone());
+            for _ in 0..retries {
+                if result.is_ok() {
+                    break;
+                }
+                result = mirror(key, value.clone());
+            }
+            if let Err(error) = result {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                failures.push((index, error));
+            }
+        }
+        self.memory.memoize(key, value);
+        match self.on_failure {
+            OnMirrorFailure::Fail if !failures.is_empty() => Err(MirrorError { failures }),
+            _ => Ok(()),
+        }
+    }
+}
+impl<T: Clone, M: Memory<T>> Memory<T> for MirroredMemory<T, M> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.memory.retrieve(key)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::sync::Arc;
+
+    /// Fails the first writes.
+    struct Flaky(Arc<AtomicU64>);
+    impl TryMemoize<i32> for Flaky {
+        fn try_memoize(&self, _key: &str, _value: i32) -> io::Result<()> {
+            match self.0.fetch_sub(1, Ordering::Relaxed) {
+                0 => {
+                    self.0.store(0, Ordering::Relaxed);
+                    Ok(())
+                }
+                _ => Err(io::ErrorKind::ConnectionRefused.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored() {
+        let (local, standby) = (Brain::new(1.minutes()), Brain::new(1.minutes()));
+        let flaky = Arc::new(AtomicU64::new(2));
+        let memory = MirroredMemory::new(local.clone())
+            .mirror(standby.clone())
+            .mirror_fallible(Flaky(flaky.clone()))
+            .on_failure(OnMirrorFailure::Fail);
+        let error = memory.try_memoize("a", 1).unwrap_err();
+        assert_eq!(error.failures[0].0, 1);
+        assert_eq!(local.retrieve("a"), Some(1));
+        assert_eq!(standby.retrieve("a"), Some(1));
+        assert_eq!(memory.retrieve("a"), Some(1));
+
+        let memory = memory.on_failure(OnMirrorFailure::Retry(1));
+        flaky.store(1, Ordering::Relaxed);
+        assert!(memory.try_memoize("b", 2).is_ok());
+        assert_eq!(memory.failures(), 1);
+    }
+}
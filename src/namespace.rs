@@ -0,0 +1,323 @@
+use crate::wall_clock;
+use crate::{
+    Brain, Duration, Engram, EvictionCause, Memory, MemoryDefaultRetrieval, StorageBackend,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+type Weigh<T> = Arc<dyn Fn(&T) -> usize + Send + Sync>;
+
+/// Limits of the entries of a namespace, see [`Namespace::with_quota`].
+///
+/// Entries of nested namespaces count toward the quota too.
+pub struct Quota<T> {
+    max_entries: Option<usize>,
+    max_bytes: Option<(usize, Weigh<T>)>,
+    reject: bool,
+}
+impl<T> Clone for Quota<T> {
+    fn clone(&self) -> Self {
+        Self {
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes.clone(),
+            reject: self.reject,
+        }
+    }
+}
+impl<T> Default for Quota<T> {
+    fn default() -> Self {
+        Self {
+            max_entries: None,
+            max_bytes: None,
+            reject: false,
+        }
+    }
+}
+impl<T> Quota<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+    /// Limits the bytes of the keys, with the namespace, and of the values,
+    /// as weighed by `weigh`.
+    pub fn max_bytes(
+        mut self,
+        max_bytes: usize,
+        weigh: impl Fn(&T) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.max_bytes = Some((max_bytes, Arc::new(weigh)));
+        self
+    }
+    /// Refuses writes into a full namespace, instead of evicting its oldest
+    /// entries to make room.
+    pub fn reject(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+    fn weigh(&self, key: &str, value: &T) -> usize {
+        self.max_bytes
+            .as_ref()
+            .map_or(0, |(_, weigh)| key.len() + weigh(value))
+    }
+    fn fits(&self, entries: usize, bytes: usize) -> bool {
+        self.max_entries.is_none_or(|max| entries <= max)
+            && self.max_bytes.as_ref().is_none_or(|(max, _)| bytes <= *max)
+    }
+}
+
+/// Given instead of memoizing a value the quota of a namespace has no room
+/// for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded;
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("namespace quota exceeded")
+    }
+}
+impl std::error::Error for QuotaExceeded {}
+
+/// A handle on the keys of a brain within a namespace, see
+/// [`Brain::namespace`].
+///
+/// Keys are memoized in the brain as `{namespace}:{key}`, and nothing done
+/// through the handle reaches keys outside of it. A namespace contains the
+/// namespaces nested in it, so `tenant:42` contains `tenant:42:users`, but
+/// not `tenant:421`.
+pub struct Namespace<T, S = HashMap<String, Engram<T>>> {
+    brain: Brain<T, S>,
+    prefix: String,
+    quota: Option<Quota<T>>,
+}
+impl<T, S> Clone for Namespace<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            brain: self.brain.clone(),
+            prefix: self.prefix.clone(),
+            quota: self.quota.clone(),
+        }
+    }
+}
+impl<T, S> Brain<T, S> {
+    /// A handle memoizing into this brain within the namespace.
+    pub fn namespace(&self, namespace: &str) -> Namespace<T, S> {
+        Namespace {
+            brain: self.clone(),
+            prefix: format!("{namespace}:"),
+            quota: None,
+        }
+    }
+}
+impl<T, S> Namespace<T, S> {
+    pub fn name(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+    /// Keeps the entries of the namespace within the quota when memoizing
+    /// through this handle and its clones, so the namespace can't crowd out
+    /// other ones.
+    ///
+    /// Checking the quota scans the brain. Handles writing concurrently may
+    /// exceed it by an entry each.
+    pub fn with_quota(mut self, quota: Quota<T>) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+    /// A handle on the namespace nested in this one, without a quota of its
+    /// own.
+    pub fn namespace(&self, namespace: &str) -> Self {
+        self.brain.namespace(&format!("{}{namespace}", self.prefix))
+    }
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+impl<T, S: StorageBackend<String, T>> Namespace<T, S> {
+    pub fn is_cached(&self, key: &str) -> bool {
+        self.brain.is_cached(&self.key(key))
+    }
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.brain.remove(&self.key(key))
+    }
+    pub fn time_to_live(&self, key: &str) -> Option<Duration> {
+        self.brain.time_to_live(&self.key(key))
+    }
+    /// The keys memoized within the namespace, without it, in no particular
+    /// order.
+    pub fn keys(&self) -> Vec<String> {
+        self.brain
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| self.brain.is_context_active(engram))
+            .filter_map(|(key, _)| key.strip_prefix(&self.prefix))
+            .map(str::to_string)
+            .collect()
+    }
+    /// Removes all values of the namespace, expired or not, telling how many.
+    pub fn clear(&self) -> usize {
+        self.brain
+            .evict_where(EvictionCause::Removed, &mut |key, _| {
+                key.starts_with(&self.prefix)
+            })
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Namespace<T, S> {
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        let _ = self.try_memoize_for(key, value, retention);
+    }
+    /// Memoizes the value unless the quota rejects it.
+    pub fn try_memoize(&self, key: &str, value: T) -> Result<(), QuotaExceeded> {
+        let key = self.key(key);
+        self.make_room(&key, &value)?;
+        self.brain.memoize(&key, value);
+        Ok(())
+    }
+    /// Memoizes the value with its own retention unless the quota rejects it.
+    pub fn try_memoize_for(
+        &self,
+        key: &str,
+        value: T,
+        retention: Duration,
+    ) -> Result<(), QuotaExceeded> {
+        let key = self.key(key);
+        self.make_room(&key, &value)?;
+        self.brain.memoize_for(&key, value, retention);
+        Ok(())
+    }
+    /// Evicts the oldest entries of the namespace until the value fits its
+    /// quota, unless the quota rejects it.
+    fn make_room(&self, key: &str, value: &T) -> Result<(), QuotaExceeded> {
+        let Some(quota) = &self.quota else {
+            return Ok(());
+        };
+        let mut entries = self
+            .brain
+            .memory
+            .read()
+            .scan()
+            .filter(|(other, _)| other.starts_with(&self.prefix) && other.as_str() != key)
+            .map(|(other, engram)| {
+                (
+                    engram.memoized,
+                    other.clone(),
+                    quota.weigh(other, &engram.value),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut bytes =
+            quota.weigh(key, value) + entries.iter().map(|(.., bytes)| bytes).sum::<usize>();
+        if quota.fits(entries.len() + 1, bytes) {
+            return Ok(());
+        }
+        if quota.reject || !quota.fits(1, quota.weigh(key, value)) {
+            return Err(QuotaExceeded);
+        }
+        entries.sort();
+        let mut victims = HashSet::new();
+        for (_, other, weight) in entries.iter() {
+            if quota.fits(entries.len() + 1 - victims.len(), bytes) {
+                break;
+            }
+            bytes -= weight;
+            victims.insert(other.as_str());
+        }
+        let evicted = self
+            .brain
+            .evict_where(EvictionCause::Capacity, &mut |other, _| {
+                victims.contains(other.as_str())
+            });
+        for _ in 0..evicted {
+            self.brain.stats.evicted_for_capacity();
+        }
+        Ok(())
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Memory<T> for Namespace<T, S> {
+    /// Drops the value if the quota rejects it.
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(&self.key(key))
+    }
+    /// Forgets the expired values of the namespace only.
+    fn forget(&self) {
+        let now = wall_clock::now();
+        self.brain
+            .evict_where(EvictionCause::Expired, &mut |key, engram| {
+                key.starts_with(&self.prefix) && engram.is_expired(self.brain.retention, now)
+            });
+    }
+}
+impl<T: Default + Clone, S: StorageBackend<String, T>> MemoryDefaultRetrieval<T>
+    for Namespace<T, S>
+{
+    fn retrieve_or_default(&self, key: &str) -> T {
+        self.retrieve(key).unwrap_or(T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn namespace() {
+        let brain = Brain::new(1.minutes());
+        let tenant = brain.namespace("tenant:42");
+        let other = brain.namespace("tenant:4");
+        tenant.memoize("a", 1);
+        other.memoize("2:a", 2);
+        assert_eq!(brain.retrieve("tenant:42:a"), Some(1));
+        assert_eq!(tenant.retrieve("a"), Some(1));
+        assert_eq!(other.retrieve("2:a"), Some(2));
+        assert_eq!(tenant.keys(), vec!["a"]);
+
+        let users = tenant.namespace("users");
+        assert_eq!(users.name(), "tenant:42:users");
+        users.memoize_for("b", 3, 1.milliseconds());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.memoize_for("c", 4, 1.milliseconds());
+        tenant.forget();
+        assert!(!users.is_cached("b"));
+        assert!(brain.is_cached("c"));
+
+        assert_eq!(tenant.clear(), 1);
+        assert!(!tenant.is_cached("a"));
+        assert!(other.is_cached("2:a"));
+    }
+
+    #[test]
+    fn quota() {
+        let brain = Brain::new(1.minutes());
+        let noisy = brain
+            .namespace("noisy")
+            .with_quota(Quota::new().max_entries(2));
+        let quiet = brain.namespace("quiet");
+        quiet.memoize("a", 0);
+        for (key, value) in [("a", 1), ("b", 2), ("b", 3), ("c", 4)] {
+            noisy.memoize(key, value);
+        }
+        let mut keys = noisy.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["b", "c"]);
+        assert!(quiet.is_cached("a"));
+        assert_eq!(brain.stats().capacity_evictions, 1);
+
+        let strict = brain.namespace("strict").with_quota(
+            Quota::new()
+                .max_bytes(30, |s: &i32| s.unsigned_abs() as usize)
+                .reject(),
+        );
+        assert_eq!(strict.try_memoize("a", 5), Ok(()));
+        assert_eq!(strict.try_memoize("b", 5), Ok(()));
+        assert_eq!(strict.try_memoize("c", 1), Err(QuotaExceeded));
+        // replacing a value only counts it once
+        assert_eq!(strict.try_memoize("b", 4), Ok(()));
+        assert_eq!(strict.keys().len(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{AsyncMemory, BoxFuture, Brain, Memory, StorageBackend};
+
+/// A small local memory in front of a remote or slow one.
+///
+/// Retrieval looks into the local memory first and fills it from the remote
+/// one, memoizing writes into both. Copies of values written elsewhere stay
+/// stale until they expire locally, so a short local retention bounds the
+/// staleness, and [`NearCache::invalidate`] drops them early, e.g. from an
+/// [`Invalidating`] bound to a clone of the local brain.
+///
+/// [`Invalidating`]: crate::Invalidating
+pub struct NearCache<L, R> {
+    local: L,
+    remote: R,
+}
+impl<L, R> NearCache<L, R> {
+    pub fn new(local: L, remote: R) -> Self {
+        Self { local, remote }
+    }
+    pub fn local(&self) -> &L {
+        &self.local
+    }
+    pub fn remote(&self) -> &R {
+        &self.remote
+    }
+}
+impl<T, S: StorageBackend<String, T>, R> NearCache<Brain<T, S>, R> {
+    /// Drops the local copy of the key, telling whether there was one.
+    ///
+    /// The next retrieval gets the value from the remote memory again.
+    pub fn invalidate(&self, key: &str) -> bool {
+        self.local.remove(key).is_some()
+    }
+}
+impl<T: Clone, L: Memory<T>, R: Memory<T>> Memory<T> for NearCache<L, R> {
+    fn memoize(&self, key: &str, value: T) {
+        self.remote.memoize(key, value.clone());
+        self.local.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.local.retrieve(key).or_else(|| {
+            let value = self.remote.retrieve(key)?;
+            self.local.memoize(key, value.clone());
+            Some(value)
+        })
+    }
+    fn forget(&self) {
+        self.local.forget();
+        self.remote.forget();
+    }
+}
+/// Only the remote memory is awaited, local hits are ready right away.
+impl<T, L, R> AsyncMemory<T> for NearCache<L, R>
+where
+    T: Clone + Send + 'static,
+    L: Memory<T> + Sync,
+    R: AsyncMemory<T> + Sync,
+{
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            self.remote.memoize(key, value.clone()).await;
+            self.local.memoize(key, value);
+        })
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        Box::pin(async move {
+            if let Some(value) = self.local.retrieve(key) {
+                return Some(value);
+            }
+            let value = self.remote.retrieve(key).await?;
+            self.local.memoize(key, value.clone());
+            Some(value)
+        })
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            self.local.forget();
+            self.remote.forget().await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_brain::tests::block_on;
+    use crate::{AsyncBrain, NumericalDuration};
+
+    #[test]
+    fn near_cache() {
+        let cache = NearCache::new(Brain::new(1.minutes()), Brain::new(1.minutes()));
+        cache.memoize("a", 1);
+        assert_eq!(cache.remote().retrieve("a"), Some(1));
+
+        // written elsewhere, the local copy is stale until invalidated
+        cache.remote().memoize("a", 2);
+        assert_eq!(cache.retrieve("a"), Some(1));
+        assert!(cache.invalidate("a"));
+        assert!(!cache.invalidate("a"));
+        assert_eq!(cache.retrieve("a"), Some(2));
+        assert_eq!(cache.local().retrieve("a"), Some(2));
+
+        let cache = NearCache::new(Brain::new(1.milliseconds()), AsyncBrain::new(1.minutes()));
+        block_on(async {
+            AsyncMemory::memoize(&cache, "b", 3).await;
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            cache.local().forget();
+            assert!(!cache.local().is_cached("b"));
+            assert_eq!(AsyncMemory::retrieve(&cache, "b").await, Some(3));
+            assert!(cache.local().is_cached("b"));
+        });
+    }
+}
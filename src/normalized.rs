@@ -0,0 +1,65 @@
+use crate::Memory;
+
+type Normalize = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// A memory normalizing every key before memoizing or retrieving, so keys
+/// differing in e.g. case or surrounding whitespace hit the same value.
+///
+/// Normalizing to a Unicode form takes a function from a crate such as
+/// `unicode-normalization`, e.g. `|key| key.nfc().collect()`.
+pub struct NormalizedMemory<M> {
+    memory: M,
+    normalize: Normalize,
+}
+impl<M> NormalizedMemory<M> {
+    pub fn new(memory: M, normalize: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        Self {
+            memory,
+            normalize: Box::new(normalize),
+        }
+    }
+    /// Trims and lowercases keys.
+    pub fn case_insensitive(memory: M) -> Self {
+        Self::new(memory, |key| key.trim().to_lowercase())
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// The key as memoized.
+    pub fn normalize(&self, key: &str) -> String {
+        (self.normalize)(key)
+    }
+}
+impl<T, M: Memory<T>> Memory<T> for NormalizedMemory<M> {
+    fn memoize(&self, key: &str, value: T) {
+        self.memory.memoize(&self.normalize(key), value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.memory.retrieve(&self.normalize(key))
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn normalized() {
+        let brain = Brain::new(1.minutes());
+        let memory = NormalizedMemory::case_insensitive(brain.clone());
+        memory.memoize(" User:Ärger ", 1);
+        assert_eq!(memory.retrieve("user:ärger"), Some(1));
+        assert_eq!(memory.retrieve("USER:ÄRGER\n"), Some(1));
+        assert_eq!(brain.retrieve("user:ärger"), Some(1));
+        assert_eq!(memory.normalize(" A"), "a");
+
+        let memory = NormalizedMemory::new(brain, |key| key.replace('-', "_"));
+        assert_eq!(memory.retrieve("user:ärger"), Some(1));
+        memory.memoize("a-b", 2);
+        assert_eq!(memory.retrieve("a_b"), Some(2));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{AsyncMemory, BoxFuture, Memory, MemoryDefaultRetrieval};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A memory keeping nothing, so every retrieval misses, e.g. to turn
+/// caching off or measure a baseline without it.
+pub struct NullMemory<T>(PhantomData<fn() -> T>);
+impl<T> NullMemory<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<T> Default for NullMemory<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> Clone for NullMemory<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for NullMemory<T> {}
+impl<T> fmt::Debug for NullMemory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NullMemory")
+    }
+}
+impl<T> Memory<T> for NullMemory<T> {
+    fn memoize(&self, _key: &str, _value: T) {}
+    fn retrieve(&self, _key: &str) -> Option<T> {
+        None
+    }
+    fn forget(&self) {}
+}
+impl<T: Default> MemoryDefaultRetrieval<T> for NullMemory<T> {
+    fn retrieve_or_default(&self, _key: &str) -> T {
+        T::default()
+    }
+}
+impl<T: Send + 'static> AsyncMemory<T> for NullMemory<T> {
+    fn memoize<'a>(&'a self, _key: &'a str, _value: T) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+    fn retrieve<'a>(&'a self, _key: &'a str) -> BoxFuture<'a, Option<T>> {
+        Box::pin(async { None })
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_brain::tests::block_on;
+
+    #[test]
+    fn null() {
+        let memory = NullMemory::new();
+        Memory::memoize(&memory, "a", 1);
+        assert_eq!(Memory::retrieve(&memory, "a"), None);
+        assert_eq!(memory.retrieve_or_default("a"), 0);
+        assert_eq!(block_on(AsyncMemory::retrieve(&memory, "a")), None);
+    }
+}
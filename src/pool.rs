@@ -0,0 +1,160 @@
+use crate::maintenance::MIN_INTERVAL;
+use crate::wall_clock::Instant;
+use crate::{Brain, BrainConfig, BrainStats, Duration, Maintenance, Memory};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{mpsc, Arc};
+
+struct Tenant<T> {
+    brain: Brain<T>,
+    used: Mutex<Instant>,
+}
+
+/// One brain per tenant, e.g. per customer of a service, all created with
+/// the same config when the tenant is first asked for.
+///
+/// A tenant is idle when its brain wasn't asked for by [`BrainPool::brain`]
+/// for a while, and [`BrainPool::reap_idle`] drops the brains of idle
+/// tenants. Clones share the tenants.
+pub struct BrainPool<T, K = String> {
+    config: BrainConfig,
+    tenants: Arc<RwLock<HashMap<K, Tenant<T>>>>,
+}
+impl<T, K> Clone for BrainPool<T, K> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config,
+            tenants: self.tenants.clone(),
+        }
+    }
+}
+impl<T, K: Hash + Eq + Clone> BrainPool<T, K> {
+    pub fn new(config: BrainConfig) -> Self {
+        Self {
+            config,
+            tenants: Default::default(),
+        }
+    }
+    pub fn config(&self) -> &BrainConfig {
+        &self.config
+    }
+    /// The brain of the tenant, created unless there is one.
+    pub fn brain(&self, tenant: &K) -> Brain<T> {
+        if let Some(tenant) = self.tenants.read().get(tenant) {
+            *tenant.used.lock() = Instant::now();
+            return tenant.brain.clone();
+        }
+        let mut tenants = self.tenants.write();
+        let tenant = tenants.entry(tenant.clone()).or_insert_with(|| Tenant {
+            brain: Brain::from_config(&self.config),
+            used: Mutex::new(Instant::now()),
+        });
+        *tenant.used.lock() = Instant::now();
+        tenant.brain.clone()
+    }
+    /// Drops the brain of the tenant, giving it back if there was one.
+    pub fn remove(&self, tenant: &K) -> Option<Brain<T>> {
+        self.tenants
+            .write()
+            .remove(tenant)
+            .map(|tenant| tenant.brain)
+    }
+    /// Drops the brains of the tenants idle for longer than `idle`, giving
+    /// how many.
+    ///
+    /// Clones of the brains taken before keep their values.
+    pub fn reap_idle(&self, idle: Duration) -> usize {
+        let idle = idle.unsigned_abs();
+        let mut tenants = self.tenants.write();
+        let before = tenants.len();
+        tenants.retain(|_, tenant| tenant.used.lock().elapsed() <= idle);
+        before - tenants.len()
+    }
+    pub fn tenants(&self) -> Vec<K> {
+        self.tenants.read().keys().cloned().collect()
+    }
+    pub fn len(&self) -> usize {
+        self.tenants.read().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The counters of all brains in the pool, summed.
+    ///
+    /// Counters of dropped brains are gone from the sum.
+    pub fn stats(&self) -> BrainStats {
+        self.tenants
+            .read()
+            .values()
+            .map(|tenant| tenant.brain.stats())
+            .sum()
+    }
+}
+impl<T: Clone, K: Hash + Eq + Clone> BrainPool<T, K> {
+    /// Forgets the expired values of all brains.
+    pub fn forget(&self) {
+        let brains = self
+            .tenants
+            .read()
+            .values()
+            .map(|tenant| tenant.brain.clone())
+            .collect::<Vec<_>>();
+        for brain in brains {
+            brain.forget();
+        }
+    }
+}
+impl<T: Clone + Send + Sync + 'static, K: Hash + Eq + Clone + Send + Sync + 'static>
+    BrainPool<T, K>
+{
+    /// Starts a background thread which every interval reaps the tenants
+    /// idle for longer than `idle` and forgets the expired values of the
+    /// others.
+    pub fn start_reaper(&self, idle: Duration, interval: Duration) -> Maintenance {
+        let pool = self.clone();
+        let interval = interval.unsigned_abs().max(MIN_INTERVAL);
+        Maintenance::spawn(move |stopped| {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                pool.reap_idle(idle);
+                pool.forget();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn brain_pool() {
+        let pool = BrainPool::<i32>::new(BrainConfig {
+            retention: 1.minutes(),
+            ..BrainConfig::default()
+        });
+        pool.brain(&"a".to_string()).memoize("x", 1);
+        pool.brain(&"b".to_string()).memoize("x", 2);
+        assert_eq!(pool.brain(&"a".to_string()).retrieve("x"), Some(1));
+        assert_eq!(pool.brain(&"c".to_string()).retrieve("x"), None);
+        let stats = pool.stats();
+        assert_eq!((stats.insertions, stats.hits, stats.misses), (2, 1, 1));
+        assert_eq!(pool.len(), 3);
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        pool.brain(&"b".to_string());
+        assert_eq!(pool.reap_idle(2.milliseconds()), 2);
+        assert_eq!(pool.tenants(), ["b".to_string()]);
+    }
+
+    #[test]
+    fn reaper() {
+        let pool = BrainPool::<i32, u32>::new(BrainConfig::default());
+        pool.brain(&1);
+        let reaper = pool.start_reaper(1.milliseconds(), 1.milliseconds());
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(pool.is_empty());
+        reaper.shutdown().unwrap();
+    }
+}
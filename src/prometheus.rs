@@ -0,0 +1,153 @@
+use crate::metrics::{Histogram, Metric, MetricsSink};
+use crate::{Brain, StorageBackend};
+use std::fmt::Write;
+
+struct Family {
+    metric: Metric,
+    kind: &'static str,
+    samples: String,
+}
+
+/// Renders brain metrics in the Prometheus text exposition format.
+///
+/// Every brain is added under a `cache` label, typically once per scrape:
+///
+/// ```
+/// # use memory::{Brain, NumericalDuration, PrometheusExporter};
+/// let prices = Brain::<f64>::new(1.minutes());
+/// let users = Brain::<String>::new(1.hours());
+/// let text = PrometheusExporter::new()
+///     .add("prices", &prices)
+///     .add("users", &users)
+///     .render();
+/// assert!(text.contains(r#"brain_hits_total{cache="prices"} 0"#));
+/// ```
+#[derive(Default)]
+pub struct PrometheusExporter {
+    families: Vec<Family>,
+}
+impl PrometheusExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Takes a sample of the brain's metrics.
+    pub fn add<T, S: StorageBackend<String, T>>(
+        &mut self,
+        cache: &str,
+        brain: &Brain<T, S>,
+    ) -> &mut Self {
+        brain.report_metrics(cache, self);
+        self
+    }
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for family in &self.families {
+            let _ = writeln!(text, "# HELP {} {}", family.metric.name, family.metric.help);
+            let _ = writeln!(text, "# TYPE {} {}", family.metric.name, family.kind);
+            text.push_str(&family.samples);
+        }
+        text
+    }
+    fn samples(&mut self, metric: Metric, kind: &'static str) -> &mut String {
+        let index = match self
+            .families
+            .iter()
+            .position(|family| family.metric == metric)
+        {
+            Some(index) => index,
+            None => {
+                self.families.push(Family {
+                    metric,
+                    kind,
+                    samples: String::new(),
+                });
+                self.families.len() - 1
+            }
+        };
+        &mut self.families[index].samples
+    }
+}
+impl MetricsSink for PrometheusExporter {
+    fn counter(&mut self, metric: Metric, labels: &[(&str, &str)], value: u64) {
+        let samples = self.samples(metric, "counter");
+        let _ = writeln!(samples, "{}{} {value}", metric.name, render_labels(labels));
+    }
+    fn gauge(&mut self, metric: Metric, labels: &[(&str, &str)], value: f64) {
+        let samples = self.samples(metric, "gauge");
+        let _ = writeln!(samples, "{}{} {value}", metric.name, render_labels(labels));
+    }
+    fn histogram(&mut self, metric: Metric, labels: &[(&str, &str)], histogram: Histogram<'_>) {
+        let samples = self.samples(metric, "histogram");
+        let mut cumulative = 0;
+        for (bound, count) in histogram.bounds.iter().zip(histogram.counts) {
+            cumulative += count;
+            let bound = bound.to_string();
+            let labels = [labels, &[("le", bound.as_str())]].concat();
+            let _ = writeln!(
+                samples,
+                "{}_bucket{} {cumulative}",
+                metric.name,
+                render_labels(&labels)
+            );
+        }
+        let labels_inf = [labels, &[("le", "+Inf")]].concat();
+        let _ = writeln!(
+            samples,
+            "{}_bucket{} {}",
+            metric.name,
+            render_labels(&labels_inf),
+            histogram.count
+        );
+        let labels = render_labels(labels);
+        let _ = writeln!(samples, "{}_sum{labels} {}", metric.name, histogram.sum);
+        let _ = writeln!(samples, "{}_count{labels} {}", metric.name, histogram.count);
+    }
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    let labels = labels
+        .iter()
+        .map(|(label, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            format!(r#"{label}="{value}""#)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InsertionOrdered, Memory, NumericalDuration};
+
+    #[test]
+    fn render() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        memory.retrieve("a");
+        memory.retrieve("b");
+        memory.forget();
+
+        let ordered = Brain::with_storage(1.minutes(), InsertionOrdered::default());
+        ordered.memoize("a", 3);
+
+        let text = PrometheusExporter::new()
+            .add("my \"cache\"", &memory)
+            .add("other", &memory)
+            .add("ordered", &ordered)
+            .render();
+
+        assert_eq!(text.matches("# TYPE brain_hits_total counter\n").count(), 1);
+        assert!(text.contains(r#"brain_hits_total{cache="my \"cache\""} 1"#));
+        assert!(text.contains(r#"brain_hits_total{cache="other"} 1"#));
+        assert!(text.contains(r#"brain_misses_total{cache="my \"cache\""} 1"#));
+        assert!(text.contains(r#"brain_entries{cache="my \"cache\""} 1"#));
+        assert!(text.contains(r#"brain_insertions_total{cache="ordered"} 1"#));
+        assert!(text.contains(r#"brain_evictions_total{cache="other",cause="expired"} 0"#));
+        assert!(text.contains(r#"brain_sweep_duration_seconds_bucket{cache="other",le="+Inf"} 1"#));
+        assert!(text.contains(r#"brain_sweep_duration_seconds_count{cache="other"} 1"#));
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{self, Read, Write};
+
+/// Frames above this length are rejected before reading them.
+pub(crate) const MAX_FRAME: usize = 64 << 20;
+
+pub(crate) const GET: u8 = 1;
+pub(crate) const SET: u8 = 2;
+pub(crate) const DEL: u8 = 3;
+pub(crate) const TTL: u8 = 4;
+pub(crate) const FORGET: u8 = 5;
+/// Takes or renews a lease, answering the other holder as missing. 6 starts
+/// a gossip exchange.
+pub(crate) const LEASE: u8 = 7;
+pub(crate) const RELEASE: u8 = 8;
+/// Presents a token in place of the key.
+pub(crate) const AUTH: u8 = 9;
+
+pub(crate) const FOUND: u8 = 0;
+pub(crate) const MISSING: u8 = 1;
+pub(crate) const FAILED: u8 = 2;
+
+/// Starts a request for the key.
+pub(crate) fn request(operation: u8, key: &str) -> Vec<u8> {
+    let mut frame = vec![operation];
+    put_field(&mut frame, key.as_bytes());
+    frame
+}
+
+/// Appends a field prefixed by its length.
+pub(crate) fn put_field(frame: &mut Vec<u8>, field: &[u8]) {
+    frame.extend((field.len() as u32).to_le_bytes());
+    frame.extend(field);
+}
+
+/// Takes a field prefixed by its length off the front.
+#[cfg(feature = "server")]
+pub(crate) fn field<'a>(fields: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let (len, rest) = fields.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let field = rest.get(..len)?;
+    *fields = &rest[len..];
+    Some(field)
+}
+
+pub(crate) fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len())
+        .ok()
+        .filter(|len| *len as usize <= MAX_FRAME)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(frame)
+}
+
+/// Reads a frame, `None` if the stream ended before it.
+#[cfg(any(test, feature = "client"))]
+pub(crate) fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    read_frame_within(reader, MAX_FRAME)
+}
+/// Reads a frame of at most `max` bytes, `None` if the stream ended before
+/// it.
+///
+/// The frame grows as its bytes arrive, so announcing a large frame doesn't
+/// allocate it.
+pub(crate) fn read_frame_within(reader: &mut impl Read, max: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    let mut frame = Vec::new();
+    reader.take(len as u64).read_to_end(&mut frame)?;
+    if frame.len() < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated frame",
+        ));
+    }
+    Ok(Some(frame))
+}
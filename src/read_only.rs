@@ -0,0 +1,60 @@
+use crate::{Brain, BrainStats, Duration, Engram, Memory, StorageBackend};
+use std::collections::HashMap;
+
+/// A view of a brain which can only retrieve values, e.g. to hand it to
+/// code which must not memoize or forget them, see [`Brain::read_only`].
+pub struct ReadOnlyBrain<T, S = HashMap<String, Engram<T>>> {
+    brain: Brain<T, S>,
+}
+impl<T, S> Clone for ReadOnlyBrain<T, S> {
+    fn clone(&self) -> Self {
+        Self {
+            brain: self.brain.clone(),
+        }
+    }
+}
+impl<T, S> Brain<T, S> {
+    /// A view of this brain which can only retrieve its values.
+    pub fn read_only(&self) -> ReadOnlyBrain<T, S> {
+        ReadOnlyBrain {
+            brain: self.clone(),
+        }
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> ReadOnlyBrain<T, S> {
+    pub fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(key)
+    }
+    pub fn retrieve_or_default(&self, key: &str) -> T
+    where
+        T: Default,
+    {
+        self.retrieve(key).unwrap_or_default()
+    }
+    pub fn is_cached(&self, key: &str) -> bool {
+        self.brain.is_cached(key)
+    }
+    pub fn time_to_live(&self, key: &str) -> Option<Duration> {
+        self.brain.time_to_live(key)
+    }
+    pub fn stats(&self) -> BrainStats {
+        self.brain.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Brain, Memory, NumericalDuration};
+
+    #[test]
+    fn read_only() {
+        let brain = Brain::new(1.minutes());
+        let view = brain.read_only();
+        brain.memoize("a", 1);
+        assert_eq!(view.clone().retrieve("a"), Some(1));
+        assert_eq!(view.retrieve_or_default("b"), 0);
+        assert!(view.is_cached("a"));
+        assert!(view.time_to_live("a").is_some());
+        assert_eq!(view.stats().hits, 1);
+    }
+}
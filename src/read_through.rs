@@ -0,0 +1,214 @@
+use crate::memoizer::{Flight, Landing};
+use crate::wall_clock;
+use crate::{Brain, Duration, Memory, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
+
+type Prime = Box<dyn Fn(&str) + Send + Sync>;
+
+/// A memory fetching the values it misses, e.g. from a database, and
+/// memoizing them.
+///
+/// Like a [`Memoizer`], concurrent misses of the same key are coalesced: one
+/// caller fetches the value while the others wait for it. Keys the fetch
+/// function finds nothing for stay misses, and are fetched again.
+///
+/// [`Memoizer`]: crate::Memoizer
+pub struct ReadThrough<T, M, F> {
+    memory: M,
+    fetch: Arc<F>,
+    in_flight: Mutex<HashMap<String, Arc<Flight<Option<T>>>>>,
+    prime: Option<Prime>,
+}
+impl<T, M, F> ReadThrough<T, M, F>
+where
+    T: Clone,
+    M: Memory<T>,
+    F: Fn(&str) -> Option<T>,
+{
+    pub fn new(memory: M, fetch: F) -> Self {
+        Self {
+            memory,
+            fetch: Arc::new(fetch),
+            in_flight: Default::default(),
+            prime: None,
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+}
+impl<T, S, F> ReadThrough<T, Brain<T, S>, F>
+where
+    T: Clone + Send + Sync + 'static,
+    S: StorageBackend<String, T> + Send + Sync + 'static,
+    F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+{
+    /// Whenever a key is retrieved, fetches the keys associated with it
+    /// which expired or expire within the lead on a background thread, so
+    /// correlated retrievals following it hit, see [`Brain::associate`].
+    ///
+    /// Links go with forgotten keys, so only those expired but not yet
+    /// forgotten are fetched again. A key is fetched by one prefetch at a
+    /// time, and retrievals don't wait for prefetches.
+    pub fn prefetch_associated(mut self, lead: Duration) -> Self {
+        let brain = self.memory.clone();
+        let fetch = self.fetch.clone();
+        let prefetching = Arc::new(Mutex::new(HashSet::new()));
+        self.prime = Some(Box::new(move |key| {
+            let associated = brain.associated(key);
+            let now = wall_clock::now();
+            let keys = {
+                let memory = brain.memory.read();
+                associated
+                    .into_iter()
+                    .filter(|other| {
+                        memory
+                            .get(other)
+                            .is_none_or(|engram| engram.is_expired(brain.retention, now + lead))
+                    })
+                    .filter(|other| prefetching.lock().insert(other.clone()))
+                    .collect::<Vec<_>>()
+            };
+            if keys.is_empty() {
+                return;
+            }
+            let (brain, fetch, prefetching) = (brain.clone(), fetch.clone(), prefetching.clone());
+            thread::spawn(move || {
+                for key in keys {
+                    let value = fetch(&key);
+                    prefetching.lock().remove(&key);
+                    if let Some(value) = value {
+                        brain.memoize(&key, value);
+                    }
+                }
+            });
+        }));
+        self
+    }
+}
+impl<T, M, F> Memory<T> for ReadThrough<T, M, F>
+where
+    T: Clone,
+    M: Memory<T>,
+    F: Fn(&str) -> Option<T>,
+{
+    fn memoize(&self, key: &str, value: T) {
+        self.memory.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        let value = self.fetched(key);
+        if let (Some(prime), Some(_)) = (&self.prime, &value) {
+            prime(key);
+        }
+        value
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+impl<T, M, F> ReadThrough<T, M, F>
+where
+    T: Clone,
+    M: Memory<T>,
+    F: Fn(&str) -> Option<T>,
+{
+    fn fetched(&self, key: &str) -> Option<T> {
+        if let Some(value) = self.memory.retrieve(key) {
+            return Some(value);
+        }
+        let flight = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(key) {
+                Some(flight) => Err(flight.clone()),
+                None => {
+                    // a previous fetch may have landed since the first lookup
+                    if let Some(value) = self.memory.retrieve(key) {
+                        return Some(value);
+                    }
+                    let flight = Arc::new(Flight::default());
+                    in_flight.insert(key.to_string(), flight.clone());
+                    Ok(flight)
+                }
+            }
+        };
+        match flight {
+            Ok(flight) => {
+                let _landing = Landing {
+                    in_flight: &self.in_flight,
+                    key,
+                    flight: &flight,
+                };
+                let value = (self.fetch)(key);
+                if let Some(value) = &value {
+                    self.memory.memoize(key, value.clone());
+                }
+                flight.land(value.clone());
+                value
+            }
+            Err(flight) => match flight.wait() {
+                Some(value) => value,
+                // the fetch panicked, try again
+                None => self.fetched(key),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn read_through() {
+        let fetches = AtomicUsize::new(0);
+        let brain = Brain::new(1.minutes());
+        let memory = ReadThrough::new(brain.clone(), |key: &str| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            key.parse::<u32>().ok()
+        });
+
+        std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| scope.spawn(|| memory.retrieve("7")))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), Some(7));
+            }
+        });
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(brain.retrieve("7"), Some(7));
+
+        assert_eq!(memory.retrieve("x"), None);
+        assert_eq!(memory.retrieve("x"), None);
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn prefetch_associated() {
+        let brain = Brain::new(1.minutes());
+        let memory = ReadThrough::new(brain.clone(), |key: &str| Some(key.len()))
+            .prefetch_associated(10.seconds());
+        brain.memoize("user", 1);
+        brain.memoize_for("feed", 2, 5.seconds());
+        brain.memoize("profile", 3);
+        brain.associate("user", "feed");
+        brain.associate("user", "profile");
+
+        assert_eq!(memory.retrieve("user"), Some(1));
+        // feed expires within the lead, so it's fetched anew
+        for _ in 0..100 {
+            if brain.retrieve("feed") == Some(4) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(brain.retrieve("feed"), Some(4));
+        assert_eq!(brain.retrieve("profile"), Some(3));
+    }
+}
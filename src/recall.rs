@@ -0,0 +1,106 @@
+use crate::wall_clock;
+use crate::{Brain, Memory, StorageBackend};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A small seeded random number generator, e.g. for reproducible
+/// simulations with [`Brain::recall_with`].
+#[derive(Clone, Debug)]
+pub struct SplitMix64(u64);
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+thread_local! {
+    static SEED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// A number in `[0, 1)` from a generator per thread, seeded randomly.
+fn chance() -> f64 {
+    SEED.with(|seed| {
+        let mut rng = SplitMix64::new(
+            seed.get()
+                .unwrap_or_else(|| RandomState::new().build_hasher().finish()),
+        );
+        let chance = rng.next_f64();
+        seed.set(Some(rng.0));
+        chance
+    })
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the value with a probability of its strength, so weak
+    /// memories may be missed, e.g. by agents of a game.
+    ///
+    /// For brains which don't decay, see [`BrainBuilder::decay`], values
+    /// are recalled until they expire.
+    ///
+    /// [`BrainBuilder::decay`]: crate::BrainBuilder::decay
+    pub fn recall(&self, key: &str) -> Option<T> {
+        self.recall_with(key, &mut chance)
+    }
+    /// Like [`Brain::recall`], drawing from the random numbers in `[0, 1)`,
+    /// e.g. of a [`SplitMix64`].
+    pub fn recall_with(&self, key: &str, random: &mut dyn FnMut() -> f64) -> Option<T> {
+        let strength = {
+            let memory = self.memory.read();
+            let engram = memory.get(key)?;
+            let now = wall_clock::now();
+            match self.decay {
+                _ if engram.is_expired(self.retention, now) => 0.0,
+                Some(decay) => decay.strength(engram, now),
+                None => 1.0,
+            }
+        };
+        if random() < strength {
+            self.retrieve(key)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn recall() {
+        let brain = Brain::builder()
+            .retention(1.hours())
+            .decay(1.milliseconds(), 0.0)
+            .build();
+        brain.memoize("a", 1);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let strength = brain.strength("a").unwrap();
+        assert!(strength < 0.5);
+        // strength only decays, so draws above it miss and those below hit
+        assert_eq!(brain.recall_with("a", &mut || strength), None);
+        assert_eq!(brain.recall_with("a", &mut || 0.0), Some(1));
+        assert_eq!(brain.recall_with("b", &mut || 0.0), None);
+
+        let brain = Brain::new(1.hours());
+        brain.memoize("a", 1);
+        assert_eq!(brain.recall("a"), Some(1));
+        let mut rng = SplitMix64::new(7);
+        let draws = (0..1000).map(|_| rng.next_f64()).collect::<Vec<_>>();
+        assert!(draws.iter().all(|draw| (0.0..1.0).contains(draw)));
+        assert!((draws.iter().sum::<f64>() / 1000.0 - 0.5).abs() < 0.05);
+        assert_eq!(SplitMix64::new(7).next_u64(), SplitMix64::new(7).next_u64());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::client::Client;
+use crate::transport::Stream;
+use crate::{
+    AsyncMemory, BinaryCodec, BoxFuture, ClientConfig, Duration, Held, Leasing, Memory, TryMemoize,
+    WireCodec,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+/// Bulk replies above this length are rejected before reading them.
+const MAX_BULK: usize = 512 << 20;
+/// Sets the lease unless another holder has it, replying with that holder.
+const HOLD: &str = "local holder = redis.call('GET', KEYS[1])
+if holder and holder ~= ARGV[1] then return holder end
+redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+return false";
+/// Deletes the lease if the holder has it.
+const RELEASE: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then
+return redis.call('DEL', KEYS[1]) end
+return 0";
+
+enum Reply {
+    Status,
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+}
+
+/// A memory kept by a Redis or Valkey server.
+///
+/// Values are stored in the crate's binary format, or encoded by the codec it
+/// was given, and expire in Redis, so [`Memory::forget`] does nothing.
+/// Connections are pooled and retried like those of a [`RemoteBrain`], and
+/// [`Memory`] drops errors, the `try_`
+/// methods report them.
+///
+/// [`RemoteBrain`]: crate::RemoteBrain
+pub struct RedisMemory<T> {
+    client: Arc<Client>,
+    retention: Duration,
+    codec: Arc<dyn WireCodec<T>>,
+}
+impl<T> Clone for RedisMemory<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            retention: self.retention,
+            codec: self.codec.clone(),
+        }
+    }
+}
+impl<T: Serialize + DeserializeOwned + 'static> RedisMemory<T> {
+    /// Connects to a server, memoizing values for the retention.
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        retention: Duration,
+        config: ClientConfig,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            client: Arc::new(Client::speaking(address, config, authenticate)?),
+            retention,
+            codec: Arc::new(BinaryCodec),
+        })
+    }
+}
+impl<T> RedisMemory<T> {
+    /// Encodes values with the codec from now on.
+    pub fn codec(mut self, codec: impl WireCodec<T> + 'static) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize_for(key, value, self.retention)
+    }
+    /// Memoizes a value with its own retention, rounded to milliseconds.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        let value = self.codec.encode(&value)?;
+        let milliseconds = retention.whole_milliseconds().clamp(1, i64::MAX.into());
+        let milliseconds = milliseconds.to_string();
+        let command = [
+            b"SET",
+            key.as_bytes(),
+            &value,
+            b"PX",
+            milliseconds.as_bytes(),
+        ];
+        match self.command(&command)? {
+            Reply::Status => Ok(()),
+            _ => Err(invalid("unexpected reply to SET")),
+        }
+    }
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        match self.command(&[b"GET", key.as_bytes()])? {
+            Reply::Bulk(Some(value)) => self.codec.decode(&value).map(Some),
+            Reply::Bulk(None) => Ok(None),
+            _ => Err(invalid("unexpected reply to GET")),
+        }
+    }
+    /// Removes the value for the key, telling whether there was one.
+    pub fn try_remove(&self, key: &str) -> io::Result<bool> {
+        match self.command(&[b"DEL", key.as_bytes()])? {
+            Reply::Integer(removed) => Ok(removed > 0),
+            _ => Err(invalid("unexpected reply to DEL")),
+        }
+    }
+    fn command(&self, arguments: &[&[u8]]) -> io::Result<Reply> {
+        let mut command = format!("*{}\r\n", arguments.len()).into_bytes();
+        for argument in arguments {
+            command.extend(format!("${}\r\n", argument.len()).as_bytes());
+            command.extend(*argument);
+            command.extend(b"\r\n");
+        }
+        self.client.call(|stream: &mut Stream| {
+            stream.write_all(&command)?;
+            read_reply(&mut BufReader::new(stream))
+        })
+    }
+}
+/// Leases are taken by scripts run by the server, so they hold for all its
+/// clients.
+impl<T> Leasing for RedisMemory<T> {
+    fn try_hold(&self, key: &str, holder: &str, ttl: Duration) -> Result<(), Held> {
+        let milliseconds = ttl.whole_milliseconds().clamp(1, i64::MAX.into());
+        let milliseconds = milliseconds.to_string();
+        let command = [
+            b"EVAL",
+            HOLD.as_bytes(),
+            b"1",
+            key.as_bytes(),
+            holder.as_bytes(),
+            milliseconds.as_bytes(),
+        ];
+        match self.command(&command)? {
+            Reply::Bulk(None) => Ok(()),
+            Reply::Bulk(Some(holder)) => Err(Held::By(String::from_utf8_lossy(&holder).into())),
+            _ => Err(invalid("unexpected reply to EVAL").into()),
+        }
+    }
+    fn try_release(&self, key: &str, holder: &str) -> io::Result<bool> {
+        let command = [
+            b"EVAL",
+            RELEASE.as_bytes(),
+            b"1",
+            key.as_bytes(),
+            holder.as_bytes(),
+        ];
+        match self.command(&command)? {
+            Reply::Integer(released) => Ok(released > 0),
+            _ => Err(invalid("unexpected reply to EVAL")),
+        }
+    }
+}
+impl<T> TryMemoize<T> for RedisMemory<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+impl<T> Memory<T> for RedisMemory<T> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    fn forget(&self) {}
+}
+/// Commands run on worker threads of the client, as many as the pooled
+/// connections, so they don't block the executor.
+impl<T: Send + 'static> AsyncMemory<T> for RedisMemory<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        let (memory, key) = (self.clone(), key.to_string());
+        Box::pin(
+            self.client
+                .offload(move || Memory::memoize(&memory, &key, value)),
+        )
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        let (memory, key) = (self.clone(), key.to_string());
+        Box::pin(self.client.offload(move || Memory::retrieve(&memory, &key)))
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Presents the token with `AUTH`.
+fn authenticate(stream: &mut Stream, token: &str) -> io::Result<()> {
+    let command = format!("*2\r\n$4\r\nAUTH\r\n${}\r\n{token}\r\n", token.len());
+    stream.write_all(command.as_bytes())?;
+    match read_reply(&mut BufReader::new(stream))? {
+        Ok(Reply::Status) => Ok(()),
+        Ok(_) => Err(invalid("unexpected reply to AUTH")),
+        Err(error) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            error.to_string(),
+        )),
+    }
+}
+
+/// Reads a reply, the inner result being an error reply of the server.
+fn read_reply(reader: &mut impl BufRead) -> io::Result<io::Result<Reply>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let Some(line) = line.strip_suffix("\r\n") else {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated reply",
+        ));
+    };
+    let (kind, rest) = line.split_at(line.len().min(1));
+    Ok(Ok(match kind {
+        "+" => Reply::Status,
+        "-" => return Ok(Err(io::Error::other(rest.to_string()))),
+        ":" => Reply::Integer(
+            rest.parse()
+                .map_err(|_| invalid("malformed integer reply"))?,
+        ),
+        "$" if rest == "-1" => Reply::Bulk(None),
+        "$" => {
+            let len = rest
+                .parse::<usize>()
+                .ok()
+                .filter(|len| *len <= MAX_BULK)
+                .ok_or_else(|| invalid("malformed bulk reply"))?;
+            let mut bulk = vec![0; len + 2];
+            reader.read_exact(&mut bulk)?;
+            bulk.truncate(len);
+            Reply::Bulk(Some(bulk))
+        }
+        _ => return Err(invalid("unexpected reply")),
+    }))
+}
+
+fn invalid(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(all(test, feature = "resp"))]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration, Server, ServerConfig};
+
+    #[test]
+    fn redis() {
+        let brain = Brain::new(1.minutes());
+        let server = Server::bind_resp("127.0.0.1:0", brain.clone(), ServerConfig::new()).unwrap();
+        let config = ClientConfig::new();
+        let memory = RedisMemory::connect(server.local_addr(), 1.hours(), config).unwrap();
+
+        Memory::memoize(&memory, "a", ("one".to_string(), 1));
+        assert_eq!(Memory::retrieve(&memory, "a"), Some(("one".to_string(), 1)));
+        assert!(brain.time_to_live("a").unwrap() > 59.minutes());
+        assert!(memory.try_remove("a").unwrap());
+        assert_eq!(memory.try_retrieve("a").unwrap(), None);
+        server.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+use crate::client::Client;
+use crate::metrics::{MetricsSink, REPLICATION_LAG, REPLICATION_PENDING};
+use crate::protocol::{put_field, request, DEL, FORGET, SET};
+use crate::wall_clock;
+use crate::{BinaryCodec, Brain, ClientConfig, Duration, Memory, StorageBackend, WireCodec};
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// How far a replica is behind its primary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicationLag {
+    pub address: String,
+    /// Operations not yet applied by the replica.
+    pub pending: usize,
+    /// Age of the oldest of them, zero once the replica caught up.
+    pub behind: Duration,
+}
+
+#[derive(Clone)]
+enum Operation {
+    Set {
+        key: String,
+        value: Vec<u8>,
+        expires: Instant,
+    },
+    Remove(String),
+    Forget,
+}
+
+struct Queue {
+    pending: VecDeque<(Instant, Operation)>,
+    applying: Option<Instant>,
+    closed: bool,
+}
+
+struct Replica {
+    address: String,
+    client: Client,
+    queue: Mutex<Queue>,
+    changed: Condvar,
+    backoff: std::time::Duration,
+}
+impl Replica {
+    fn push(&self, operation: Operation) {
+        self.queue
+            .lock()
+            .pending
+            .push_back((Instant::now(), operation));
+        self.changed.notify_all();
+    }
+    fn run(&self) {
+        let mut queue = self.queue.lock();
+        loop {
+            while queue.pending.is_empty() && !queue.closed {
+                self.changed.wait(&mut queue);
+            }
+            let Some((queued, operation)) = queue.pending.pop_front() else {
+                return;
+            };
+            queue.applying = Some(queued);
+            drop(queue);
+            // a replica that is down keeps its operations until it is back,
+            // or the primary is dropped
+            while self.apply(&operation).is_err() && !self.queue.lock().closed {
+                thread::sleep(self.backoff);
+            }
+            queue = self.queue.lock();
+            queue.applying = None;
+            self.changed.notify_all();
+        }
+    }
+    fn apply(&self, operation: &Operation) -> io::Result<()> {
+        let frame = match operation {
+            Operation::Set {
+                key,
+                value,
+                expires,
+            } => {
+                let Some(left) = expires.checked_duration_since(Instant::now()) else {
+                    return Ok(());
+                };
+                let mut frame = request(SET, key);
+                put_field(&mut frame, value);
+                let milliseconds = u64::try_from(left.as_millis()).unwrap_or(u64::MAX);
+                frame.extend(milliseconds.max(1).to_le_bytes());
+                frame
+            }
+            Operation::Remove(key) => request(DEL, key),
+            Operation::Forget => request(FORGET, ""),
+        };
+        self.client.request(&frame).map(drop)
+    }
+    fn lag(&self) -> ReplicationLag {
+        let queue = self.queue.lock();
+        let oldest = queue
+            .applying
+            .or_else(|| queue.pending.front().map(|(queued, _)| *queued));
+        ReplicationLag {
+            address: self.address.clone(),
+            pending: queue.pending.len() + usize::from(queue.applying.is_some()),
+            behind: oldest.map_or(Duration::ZERO, |queued| {
+                queued.elapsed().try_into().unwrap_or(Duration::MAX)
+            }),
+        }
+    }
+}
+
+/// A brain streaming its changes to replicas, which serve reads.
+///
+/// Replicas are [`Server`]s of their own, read with a [`RemoteBrain`] or a
+/// [`ShardedClient`]. A replica added to the primary first gets everything
+/// the brain holds, then every memoize, removal and forget in order, in the
+/// background, so the primary never waits for its replicas. Values are sent
+/// expiring when they expire on the primary. Entries the primary evicts for
+/// capacity or sweeps aren't sent, replicas expire them on their own.
+///
+/// A replica that can't be reached keeps its operations queued and is retried
+/// with the client's backoff, [`Primary::lag`] tells how far behind it is.
+///
+/// [`Server`]: crate::Server
+/// [`RemoteBrain`]: crate::RemoteBrain
+/// [`ShardedClient`]: crate::ShardedClient
+pub struct Primary<T> {
+    brain: Brain<T>,
+    replicas: RwLock<Vec<(Arc<Replica>, JoinHandle<()>)>>,
+    codec: Arc<dyn WireCodec<T>>,
+}
+impl<T: Clone + Serialize + DeserializeOwned + 'static> Primary<T> {
+    pub fn new(brain: Brain<T>) -> Self {
+        Self::with_codec(brain, BinaryCodec)
+    }
+}
+impl<T: Clone> Primary<T> {
+    /// Sends values to the replicas encoded by the codec, which their own
+    /// clients need to share.
+    pub fn with_codec(brain: Brain<T>, codec: impl WireCodec<T> + 'static) -> Self {
+        Self {
+            brain,
+            replicas: RwLock::new(Vec::new()),
+            codec: Arc::new(codec),
+        }
+    }
+    pub fn brain(&self) -> &Brain<T> {
+        &self.brain
+    }
+    /// Connects to a replica and starts syncing it, with what the brain holds.
+    pub fn add_replica(&self, address: &str, config: ClientConfig) -> io::Result<()> {
+        let backoff = config.backoff.unsigned_abs();
+        let replica = Arc::new(Replica {
+            address: address.to_string(),
+            client: Client::new(address, config)?,
+            queue: Mutex::new(Queue {
+                pending: VecDeque::new(),
+                applying: None,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            backoff,
+        });
+        // no operation gets between the snapshot and the stream
+        let mut replicas = self.replicas.write();
+        let now = wall_clock::now();
+        for (key, engram) in self.brain.memory.read().scan() {
+            let expires = engram.memoized + engram.retention.unwrap_or(self.brain.retention);
+            if let Some(operation) = self.set(key, &engram.value, expires - now) {
+                replica.push(operation);
+            }
+        }
+        let worker = {
+            let replica = replica.clone();
+            thread::spawn(move || replica.run())
+        };
+        replicas.push((replica, worker));
+        Ok(())
+    }
+    /// Memoizes a value with its own retention instead of the brain's.
+    pub fn memoize_for(&self, key: &str, value: T, retention: Duration) {
+        let replicas = self.replicas.read();
+        self.brain.memoize_for(key, value.clone(), retention);
+        if let Some(operation) = self.set(key, &value, retention) {
+            self.broadcast(&replicas, operation);
+        }
+    }
+    /// Forgets the value for the key right away, on the replicas too.
+    pub fn remove(&self, key: &str) -> Option<T> {
+        let replicas = self.replicas.read();
+        let value = self.brain.remove(key);
+        self.broadcast(&replicas, Operation::Remove(key.to_string()));
+        value
+    }
+    /// How far each replica is behind, in the order they were added.
+    pub fn lag(&self) -> Vec<ReplicationLag> {
+        let replicas = self.replicas.read();
+        replicas.iter().map(|(replica, _)| replica.lag()).collect()
+    }
+    /// Reports the lag of every replica to a sink, labeled with its address.
+    pub fn report_metrics(&self, cache: &str, sink: &mut dyn MetricsSink) {
+        for lag in self.lag() {
+            let labels = [("cache", cache), ("replica", lag.address.as_str())];
+            sink.gauge(REPLICATION_PENDING, &labels, lag.pending as f64);
+            sink.gauge(REPLICATION_LAG, &labels, lag.behind.as_seconds_f64());
+        }
+    }
+    /// Waits until every replica applied every operation so far.
+    pub fn flush(&self) {
+        for (replica, _) in self.replicas.read().iter() {
+            let mut queue = replica.queue.lock();
+            while !queue.pending.is_empty() || queue.applying.is_some() {
+                replica.changed.wait(&mut queue);
+            }
+        }
+    }
+    /// Sets the value on a replica, `None` if it expired or can't be encoded.
+    fn set(&self, key: &str, value: &T, left: Duration) -> Option<Operation> {
+        let left = std::time::Duration::try_from(left).ok()?;
+        Some(Operation::Set {
+            key: key.to_string(),
+            value: self.codec.encode(value).ok()?,
+            expires: Instant::now() + left,
+        })
+    }
+    fn broadcast(&self, replicas: &[(Arc<Replica>, JoinHandle<()>)], operation: Operation) {
+        if let Some(((last, _), others)) = replicas.split_last() {
+            for (replica, _) in others {
+                replica.push(operation.clone());
+            }
+            last.push(operation);
+        }
+    }
+}
+impl<T> Drop for Primary<T> {
+    /// Gives the replicas what was queued, unless they can't be reached.
+    fn drop(&mut self) {
+        for (replica, worker) in self.replicas.get_mut().drain(..) {
+            replica.queue.lock().closed = true;
+            replica.changed.notify_all();
+            let _ = worker.join();
+        }
+    }
+}
+impl<T: Clone> Memory<T> for Primary<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.memoize_for(key, value, self.brain.retention);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(key)
+    }
+    fn forget(&self) {
+        let replicas = self.replicas.read();
+        self.brain.forget();
+        self.broadcast(&replicas, Operation::Forget);
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::{NumericalDuration, RemoteBrain, Server, ServerConfig};
+
+    #[test]
+    fn replication() {
+        let config = ClientConfig::new().backoff(1.milliseconds());
+        let primary = Primary::new(Brain::new(1.minutes()));
+        primary.memoize("before", 1);
+        assert!(primary.lag().is_empty());
+
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.seconds()), ServerConfig::new());
+        let server = server.unwrap();
+        let address = server.local_addr().to_string();
+        primary.add_replica(&address, config.clone()).unwrap();
+        primary.memoize("after", 2);
+        primary.memoize_for("short", 3, 1.milliseconds());
+        primary.flush();
+
+        let replica = RemoteBrain::<i32>::connect(&*address, config).unwrap();
+        assert_eq!(replica.try_retrieve("before").unwrap(), Some(1));
+        assert_eq!(replica.try_retrieve("after").unwrap(), Some(2));
+        // the replica keeps the primary's retention, not its own
+        assert!(replica.try_time_to_live("after").unwrap().unwrap() > 1.seconds());
+
+        primary.remove("before");
+        primary.flush();
+        assert_eq!(replica.try_retrieve("before").unwrap(), None);
+        let lag = primary.lag();
+        assert_eq!((lag[0].pending, lag[0].behind), (0, Duration::ZERO));
+        drop(primary);
+        server.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use crate::wall_clock;
+use crate::{Brain, BrainStats, Duration, StorageBackend};
+
+/// Share of the retention closing each age bucket of a [`BrainReport`].
+const AGE_BUCKETS: [f64; 4] = [0.25, 0.5, 0.75, 1.0];
+
+/// Entries of a certain age.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AgeBucket {
+    /// Upper bound of the age, `None` for entries past their retention.
+    pub up_to: Option<Duration>,
+    pub entries: usize,
+}
+
+/// Overview of a brain's contents and configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrainReport {
+    pub entries: usize,
+    pub estimated_bytes: usize,
+    /// Entries by age relative to the brain's retention.
+    pub ages: Vec<AgeBucket>,
+    pub retention: Duration,
+    pub stats: BrainStats,
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    pub fn inspect(&self) -> BrainReport {
+        let sample = self.sample();
+        let now = wall_clock::now();
+        let mut ages = AGE_BUCKETS
+            .iter()
+            .map(|share| AgeBucket {
+                up_to: Some(self.retention * *share),
+                entries: 0,
+            })
+            .chain([AgeBucket {
+                up_to: None,
+                entries: 0,
+            }])
+            .collect::<Vec<_>>();
+        for (_, engram) in self.memory.read().scan() {
+            let bucket = if engram.is_expired(self.retention, now) {
+                AGE_BUCKETS.len()
+            } else {
+                let age = now - engram.memoized;
+                ages.iter()
+                    .position(|bucket| bucket.up_to.is_some_and(|up_to| age <= up_to))
+                    .unwrap_or(AGE_BUCKETS.len() - 1)
+            };
+            ages[bucket].entries += 1;
+        }
+        BrainReport {
+            entries: sample.entries,
+            estimated_bytes: sample.bytes,
+            ages,
+            retention: self.retention,
+            stats: sample.stats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn inspect() {
+        let memory = Brain::new(4.milliseconds());
+        memory.memoize("a", 1);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        memory.memoize("b", 2);
+        memory.memoize("c", 3);
+
+        let report = memory.inspect();
+        assert_eq!(report.entries, 3);
+        assert_eq!(report.retention, 4.milliseconds());
+        assert_eq!(report.stats.insertions, 3);
+        assert_eq!(report.ages.len(), 5);
+        assert_eq!(report.ages[0].up_to, Some(1.milliseconds()));
+        assert_eq!(report.ages.last().unwrap().up_to, None);
+        assert_eq!(report.ages.last().unwrap().entries, 1);
+        assert_eq!(
+            report
+                .ages
+                .iter()
+                .map(|bucket| bucket.entries)
+                .sum::<usize>(),
+            3
+        );
+        assert!(report.estimated_bytes > 0);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::server::{Connection, Server, ServerConfig};
+use crate::{Brain, Duration, Memory, StorageBackend};
+use std::io::{self, BufRead, Read, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+/// Bulk strings above this length are rejected before reading them.
+const MAX_BULK: usize = 64 << 20;
+/// Arrays above this length are rejected.
+const MAX_ARGUMENTS: usize = 1 << 20;
+
+enum Reply {
+    Status(&'static str),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+impl Reply {
+    fn error(message: impl Into<String>) -> Self {
+        Self::Error(message.into())
+    }
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            Self::Status(status) => write!(writer, "+{status}\r\n"),
+            Self::Error(message) => write!(writer, "-ERR {message}\r\n"),
+            Self::Integer(integer) => write!(writer, ":{integer}\r\n"),
+            Self::Bulk(None) => writer.write_all(b"$-1\r\n"),
+            Self::Bulk(Some(bulk)) => {
+                write!(writer, "${}\r\n", bulk.len())?;
+                writer.write_all(bulk)?;
+                writer.write_all(b"\r\n")
+            }
+            Self::Array(replies) => {
+                write!(writer, "*{}\r\n", replies.len())?;
+                replies.iter().try_for_each(|reply| reply.write(writer))
+            }
+        }
+    }
+}
+
+impl Server {
+    /// Serves the brain with a subset of the Redis protocol, so Redis
+    /// clients can use it, e.g. in tests.
+    ///
+    /// The commands are `GET`, `SET` with `EX` or `PX`, `DEL`, `EXISTS`,
+    /// `TTL`, `PTTL`, `SCAN` with `MATCH` and `COUNT`, `PING`, `QUIT` and
+    /// `AUTH`, which has to come first if the config
+    /// [authorizes](ServerConfig::authorize). Keys have to be UTF-8. `SCAN` orders the keys for its cursor, which
+    /// is the number of keys returned so far.
+    pub fn bind_resp(
+        address: impl ToSocketAddrs,
+        brain: Brain<Vec<u8>>,
+        config: ServerConfig,
+    ) -> io::Result<Self> {
+        let mut refusal = Vec::new();
+        Reply::error("max number of clients reached").write(&mut refusal)?;
+        Self::listen(address, brain, config, Arc::new(serve), refusal)
+    }
+}
+
+fn serve(connection: &mut Connection, brain: &Brain<Vec<u8>>) -> io::Result<()> {
+    while connection.wait()? {
+        let max = connection.max_request(MAX_BULK);
+        let (reply, close) = match read_command(&mut connection.reader, max)? {
+            Ok(command) => {
+                let name = command.first().map(|name| name.to_ascii_uppercase());
+                let quit = name.as_deref() == Some(b"QUIT");
+                let reply = match (name.as_deref(), &command[..]) {
+                    // the last argument is the token, after an optional user name
+                    (Some(b"AUTH"), [_, .., token]) if command.len() <= 3 => {
+                        match connection.authorize(&String::from_utf8_lossy(token)) {
+                            true => Reply::Status("OK"),
+                            false => Reply::error("invalid token"),
+                        }
+                    }
+                    (Some(b"AUTH"), _) => {
+                        Reply::error("wrong number of arguments for 'AUTH' command")
+                    }
+                    _ if !quit && !connection.is_authorized() => {
+                        Reply::error("authentication required")
+                    }
+                    _ => execute(brain, &command),
+                };
+                (reply, quit)
+            }
+            Err(message) => (Reply::error(format!("Protocol error: {message}")), true),
+        };
+        reply.write(&mut connection.writer)?;
+        connection.writer.flush()?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn execute(brain: &Brain<Vec<u8>>, command: &[Vec<u8>]) -> Reply {
+    let Some((name, arguments)) = command.split_first() else {
+        return Reply::error("empty command");
+    };
+    let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+    let keys = arguments
+        .iter()
+        .map(|key| std::str::from_utf8(key))
+        .collect::<Result<Vec<_>, _>>();
+    let Ok(keys) = keys else {
+        return Reply::error("keys have to be UTF-8");
+    };
+    let arity = |valid: bool| {
+        (!valid).then(|| Reply::error(format!("wrong number of arguments for '{name}' command")))
+    };
+    match name.as_str() {
+        "PING" => match arguments {
+            [] => Reply::Status("PONG"),
+            [message] => Reply::Bulk(Some(message.clone())),
+            _ => arity(false).unwrap(),
+        },
+        "QUIT" => Reply::Status("OK"),
+        "GET" => arity(keys.len() == 1).unwrap_or_else(|| Reply::Bulk(brain.retrieve(keys[0]))),
+        "SET" => {
+            if let Some(reply) = arity(keys.len() == 2 || keys.len() == 4) {
+                return reply;
+            }
+            let value = arguments[1].clone();
+            match keys.get(2..4) {
+                None => brain.memoize(keys[0], value),
+                Some([unit, amount]) => {
+                    let amount = amount.parse::<i64>().ok().filter(|amount| *amount > 0);
+                    let retention = match (unit.to_ascii_uppercase().as_str(), amount) {
+                        ("EX", Some(seconds)) => Duration::seconds(seconds),
+                        ("PX", Some(milliseconds)) => Duration::milliseconds(milliseconds),
+                        (_, None) => return Reply::error("invalid expire time in 'set' command"),
+                        _ => return Reply::error("syntax error"),
+                    };
+                    brain.memoize_for(keys[0], value, retention);
+                }
+                Some(_) => unreachable!("two options"),
+            }
+            Reply::Status("OK")
+        }
+        "DEL" => arity(!keys.is_empty()).unwrap_or_else(|| {
+            let removed = keys
+                .iter()
+                .filter(|key| brain.remove(key).is_some())
+                .count();
+            Reply::Integer(removed as i64)
+        }),
+        "EXISTS" => arity(!keys.is_empty()).unwrap_or_else(|| {
+            Reply::Integer(keys.iter().filter(|key| brain.is_cached(key)).count() as i64)
+        }),
+        "TTL" | "PTTL" => arity(keys.len() == 1).unwrap_or_else(|| {
+            Reply::Integer(match brain.time_to_live(keys[0]) {
+                Some(left) if name == "TTL" => left.whole_seconds(),
+                Some(left) => left.whole_milliseconds() as i64,
+                None => -2,
+            })
+        }),
+        "SCAN" => arity(keys.len() % 2 == 1).unwrap_or_else(|| scan(brain, &keys)),
+        _ => Reply::error(format!("unknown command '{name}'")),
+    }
+}
+
+fn scan(brain: &Brain<Vec<u8>>, arguments: &[&str]) -> Reply {
+    let Ok(cursor) = arguments[0].parse::<usize>() else {
+        return Reply::error("invalid cursor");
+    };
+    let (mut pattern, mut count) = ("*", 10);
+    for option in arguments[1..].chunks(2) {
+        match option[0].to_ascii_uppercase().as_str() {
+            "MATCH" => pattern = option[1],
+            "COUNT" => match option[1].parse() {
+                Ok(value) if value > 0 => count = value,
+                _ => return Reply::error("value is not an integer or out of range"),
+            },
+            _ => return Reply::error("syntax error"),
+        }
+    }
+    let mut keys = brain
+        .memory
+        .read()
+        .scan()
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+    keys.sort_unstable();
+    let next = cursor.saturating_add(count);
+    let batch = keys
+        .iter()
+        .skip(cursor)
+        .take(count)
+        .filter(|key| matches(pattern.as_bytes(), key.as_bytes()))
+        .map(|key| Reply::Bulk(Some(key.clone().into_bytes())))
+        .collect();
+    let next = if next >= keys.len() { 0 } else { next };
+    Reply::Array(vec![
+        Reply::Bulk(Some(next.to_string().into_bytes())),
+        Reply::Array(batch),
+    ])
+}
+
+/// Matches a glob with `*` and `?`, in time proportional to the product
+/// of their lengths at worst.
+///
+/// On a mismatch, the last `*` takes one more byte of the key and matching
+/// goes on after it, earlier stars never need to take more.
+fn matches(pattern: &[u8], key: &[u8]) -> bool {
+    let (mut p, mut k) = (0, 0);
+    let mut star = None;
+    while k < key.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, k));
+                p += 1;
+            }
+            Some(&expected) if expected == b'?' || expected == key[k] => {
+                p += 1;
+                k += 1;
+            }
+            _ => match star {
+                Some((star_p, star_k)) => {
+                    star = Some((star_p, star_k + 1));
+                    p = star_p + 1;
+                    k = star_k + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&byte| byte == b'*')
+}
+
+/// Reads an array of bulk strings or an inline command, or describes why
+/// the input isn't one, with at most `max` arguments of at most `max`
+/// bytes each.
+fn read_command(reader: &mut impl BufRead, max: usize) -> io::Result<Result<Vec<Vec<u8>>, String>> {
+    let line = read_line(reader)?;
+    let Some(count) = line.strip_prefix('*') else {
+        let inline = line.split_whitespace().map(|part| part.as_bytes().to_vec());
+        return Ok(Ok(inline.collect()));
+    };
+    let count = match count.parse::<usize>() {
+        Ok(count) if count <= MAX_ARGUMENTS.min(max) => count,
+        _ => return Ok(Err("invalid multibulk length".to_string())),
+    };
+    let mut command = Vec::with_capacity(count);
+    for _ in 0..count {
+        let line = read_line(reader)?;
+        let len = match line.strip_prefix('$').map(str::parse::<usize>) {
+            Some(Ok(len)) if len <= max => len,
+            _ => return Ok(Err("invalid bulk length".to_string())),
+        };
+        let mut bulk = Vec::new();
+        reader.take(len as u64 + 2).read_to_end(&mut bulk)?;
+        if !bulk.ends_with(b"\r\n") {
+            return Ok(Err("missing bulk terminator".to_string()));
+        }
+        bulk.truncate(len);
+        command.push(bulk);
+    }
+    Ok(Ok(command))
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_ARGUMENTS as u64).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated command",
+        ));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+    use std::io::BufReader;
+    use std::net::TcpStream;
+
+    #[test]
+    fn resp() {
+        let brain = Brain::new(1.minutes());
+        let server = Server::bind_resp("127.0.0.1:0", brain.clone(), ServerConfig::new()).unwrap();
+        let mut stream = BufReader::new(TcpStream::connect(server.local_addr()).unwrap());
+        let mut send = |request: &str, lines: usize| {
+            stream.get_mut().write_all(request.as_bytes()).unwrap();
+            (0..lines)
+                .map(|_| read_line(&mut stream).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(send("PING\r\n", 1), ["+PONG"]);
+        let set = "*5\r\n$3\r\nSET\r\n$1\r\na\r\n$2\r\nhi\r\n$2\r\nEX\r\n$2\r\n30\r\n";
+        assert_eq!(send(set, 1), ["+OK"]);
+        assert_eq!(brain.retrieve("a"), Some(b"hi".to_vec()));
+        assert_eq!(send("GET a\r\n", 2), ["$2", "hi"]);
+        assert_eq!(send("GET b\r\n", 1), ["$-1"]);
+        assert_eq!(send("TTL a\r\n", 1), [":29"]);
+        assert_eq!(send("TTL b\r\n", 1), [":-2"]);
+        send("SET ab x\r\n", 1);
+        send("SET c y\r\n", 1);
+        let first = send("SCAN 0 MATCH a* COUNT 2\r\n", 8);
+        assert_eq!(first, ["*2", "$1", "2", "*2", "$1", "a", "$2", "ab"]);
+        let last = send("SCAN 2 MATCH a* COUNT 2\r\n", 4);
+        assert_eq!(last, ["*2", "$1", "0", "*0"]);
+        assert_eq!(send("DEL a c d\r\n", 1), [":2"]);
+        assert_eq!(send("FLUSHALL\r\n", 1), ["-ERR unknown command 'FLUSHALL'"]);
+        assert_eq!(send("QUIT\r\n", 1), ["+OK"]);
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn glob() {
+        assert!(matches(b"user:*:name", b"user:42:name"));
+        assert!(matches(b"?b*", b"ab"));
+        assert!(!matches(b"a?", b"a"));
+        assert!(matches(b"*", b""));
+        assert!(matches(b"a*b*c", b"aXbYbc"));
+        assert!(!matches(b"a*b", b"ac"));
+
+        let pattern = [&[b'*'; 64][..], b"b"].concat();
+        let started = std::time::Instant::now();
+        assert!(!matches(&pattern, &[b'a'; 1 << 16]));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::wall_clock::{self, Timestamp};
+use crate::{Brain, Duration, StorageBackend};
+use std::fmt;
+
+/// Easiness of values never reviewed.
+const INITIAL_EASINESS: f64 = 2.5;
+/// Easiness never drops below.
+const MIN_EASINESS: f64 = 1.3;
+/// Intervals grow from a day.
+const DAY: Duration = Duration::new(24 * 60 * 60, 0);
+
+/// The review schedule of a value, see [`Brain::mark_reviewed`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Review {
+    /// Reviews in a row recalled well enough, with a quality of 3 or more.
+    pub repetitions: u32,
+    /// Factor the interval grows by with every good review.
+    pub easiness: f64,
+    pub interval: Duration,
+    pub due: Timestamp,
+}
+impl Review {
+    /// The schedule after a review of the quality at the time, by SM-2.
+    fn next(self, quality: u8, now: Timestamp) -> Self {
+        let quality = quality.min(5);
+        let lapse = f64::from(5 - quality);
+        let easiness = (self.easiness + 0.1 - lapse * (0.08 + lapse * 0.02)).max(MIN_EASINESS);
+        let (repetitions, interval) = match (quality, self.repetitions) {
+            (0..=2, _) => (0, DAY),
+            (_, 0) => (1, DAY),
+            (_, 1) => (2, DAY * 6),
+            (_, repetitions) => (
+                repetitions + 1,
+                wall_clock::scaled(self.interval, self.easiness),
+            ),
+        };
+        Self {
+            repetitions,
+            easiness,
+            interval,
+            due: now + interval,
+        }
+    }
+}
+
+/// Given for reviewing a key which isn't memoized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotMemoized;
+impl fmt::Display for NotMemoized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not memoized")
+    }
+}
+impl std::error::Error for NotMemoized {}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    /// The memoized keys due for review at the time, e.g. the cards of a
+    /// flashcard app, those overdue the longest first.
+    ///
+    /// Values never reviewed are due once memoized. The schedules of keys
+    /// no longer memoized are dropped.
+    pub fn due_for_review(&self, now: Timestamp) -> Vec<String> {
+        let memory = self.memory.read();
+        let mut reviews = self.reviews.write();
+        reviews.retain(|key, _| memory.get(key).is_some());
+        let mut due = memory
+            .scan()
+            .map(|(key, engram)| {
+                let due = reviews
+                    .get(key)
+                    .map_or(engram.memoized, |review| review.due);
+                (due, key)
+            })
+            .filter(|(due, _)| *due <= now)
+            .collect::<Vec<_>>();
+        due.sort();
+        due.into_iter().map(|(_, key)| key.clone()).collect()
+    }
+    /// Schedules the next review of the key by how well it was recalled,
+    /// from 0 for a blackout to 5 for a perfect response, the way SM-2
+    /// does.
+    ///
+    /// Intervals run in days, so the brain's retention should outlast
+    /// them.
+    pub fn mark_reviewed(&self, key: &str, quality: u8) -> Result<Review, NotMemoized> {
+        if !self.is_cached(key) {
+            return Err(NotMemoized);
+        }
+        let now = wall_clock::now();
+        let mut reviews = self.reviews.write();
+        let review = reviews.entry(key.to_string()).or_insert(Review {
+            repetitions: 0,
+            easiness: INITIAL_EASINESS,
+            interval: Duration::ZERO,
+            due: now,
+        });
+        *review = review.next(quality, now);
+        Ok(*review)
+    }
+    /// The review schedule of the key, `None` unless it was reviewed.
+    pub fn review(&self, key: &str) -> Option<Review> {
+        self.reviews.read().get(key).copied()
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn spaced_repetition() {
+        let brain = Brain::new(1000.days());
+        brain.memoize("hola", "hello");
+        brain.memoize("gato", "cat");
+        let now = wall_clock::now();
+        assert_eq!(brain.due_for_review(now).len(), 2);
+
+        let intervals =
+            [5, 4, 5].map(|quality| brain.mark_reviewed("hola", quality).unwrap().interval);
+        assert_eq!(intervals[..2], [1.days(), 6.days()]);
+        assert_eq!(intervals[2], 6.days() * 2.6);
+        assert_eq!(brain.due_for_review(now), ["gato"]);
+        assert_eq!(brain.due_for_review(now + 16.days()), ["gato", "hola"]);
+
+        let lapse = brain.mark_reviewed("hola", 1).unwrap();
+        assert_eq!((lapse.repetitions, lapse.interval), (0, 1.days()));
+        assert!(lapse.easiness < 2.5 && lapse.easiness >= MIN_EASINESS);
+        assert_eq!(brain.mark_reviewed("perro", 5), Err(NotMemoized));
+
+        brain.remove("hola");
+        brain.due_for_review(now);
+        assert_eq!(brain.review("hola"), None);
+    }
+}
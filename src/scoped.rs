@@ -0,0 +1,75 @@
+use crate::{Brain, Engram, Memory, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+
+/// Memoizes into a brain for a scope, e.g. a request, removing every key
+/// it memoized when dropped, see [`Brain::scoped`].
+///
+/// Its keys are removed even if they were memoized again through the brain
+/// meanwhile.
+pub struct ScopedMemory<T: Clone, S: StorageBackend<String, T> = HashMap<String, Engram<T>>> {
+    brain: Brain<T, S>,
+    keys: Mutex<HashSet<String>>,
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// A memory for a scope, memoizing into this brain until dropped.
+    pub fn scoped(&self) -> ScopedMemory<T, S> {
+        ScopedMemory {
+            brain: self.clone(),
+            keys: Default::default(),
+        }
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> ScopedMemory<T, S> {
+    /// The keys memoized in the scope so far.
+    pub fn keys(&self) -> Vec<String> {
+        self.keys.lock().iter().cloned().collect()
+    }
+    /// Removes the keys memoized in the scope so far, as dropping it does.
+    pub fn clear(&self) {
+        for key in std::mem::take(&mut *self.keys.lock()) {
+            self.brain.remove(&key);
+        }
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Memory<T> for ScopedMemory<T, S> {
+    fn memoize(&self, key: &str, value: T) {
+        self.keys.lock().insert(key.to_string());
+        self.brain.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(key)
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Drop for ScopedMemory<T, S> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn scoped() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize("kept", 0);
+        {
+            let scope = brain.scoped();
+            scope.memoize("a", 1);
+            scope.memoize("b", 2);
+            assert_eq!(scope.retrieve("kept"), Some(0));
+            assert_eq!(brain.retrieve("a"), Some(1));
+            let mut keys = scope.keys();
+            keys.sort();
+            assert_eq!(keys, vec!["a", "b"]);
+        }
+        assert!(!brain.is_cached("a") && !brain.is_cached("b"));
+        assert!(brain.is_cached("kept"));
+    }
+}
@@ -0,0 +1,547 @@
+use crate::{
+    AgeBucket, Attribute, Attributes, Brain, BrainConfig, BrainDiff, BrainReport, BrainStats,
+    Duration, Engram, Eviction, Siblings, StorageBackend, VectorClock, DEFAULT_SALIENCE,
+};
+use serde::de::{
+    self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+impl<T: Serialize> Serialize for Engram<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut engram = serializer.serialize_struct("Engram", ENGRAM_FIELDS.len())?;
+        engram.serialize_field("value", &self.value)?;
+        engram.serialize_field("memoized", &self.memoized)?;
+        engram.serialize_field("retention", &self.retention)?;
+        engram.serialize_field("actor", &self.actor)?;
+        engram.serialize_field("context", &self.context.as_deref())?;
+        engram.serialize_field("attributes", &self.attributes.as_deref())?;
+        engram.end()
+    }
+}
+
+/// Diffs are encoded as the tuple of their lists.
+impl<T: Serialize> Serialize for BrainDiff<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.added, &self.changed, &self.removed).serialize(serializer)
+    }
+}
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BrainDiff<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        type Fields<T> = (
+            Vec<(String, Engram<T>)>,
+            Vec<(String, Engram<T>)>,
+            Vec<String>,
+        );
+        let (added, changed, removed) = Fields::<T>::deserialize(deserializer)?;
+        Ok(BrainDiff {
+            added,
+            changed,
+            removed,
+        })
+    }
+}
+
+/// Fields of an engram, the actor was added with snapshot version 4, the
+/// context and attributes with version 5.
+const ENGRAM_FIELDS: [&str; 6] = [
+    "value",
+    "memoized",
+    "retention",
+    "actor",
+    "context",
+    "attributes",
+];
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Engram<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Engram", &ENGRAM_FIELDS, EngramVisitor(PhantomData))
+    }
+}
+
+/// An engram encoded with the first fields only, by binary formats before
+/// version 5: without its actor before version 4, and without its context
+/// and attributes before version 5.
+pub(crate) struct Legacy<T, const FIELDS: usize = 3>(pub(crate) Engram<T>);
+impl<'de, T: Deserialize<'de>, const FIELDS: usize> Deserialize<'de> for Legacy<T, FIELDS> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_struct(
+                "Engram",
+                &ENGRAM_FIELDS[..FIELDS],
+                EngramVisitor(PhantomData),
+            )
+            .map(Legacy)
+    }
+}
+
+struct EngramVisitor<T>(PhantomData<T>);
+impl<'de, T: Deserialize<'de>> Visitor<'de> for EngramVisitor<T> {
+    type Value = Engram<T>;
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an engram")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let missing = |index| de::Error::invalid_length(index, &"3 fields");
+        let engram = engram(
+            seq.next_element()?.ok_or_else(|| missing(0))?,
+            seq.next_element()?.ok_or_else(|| missing(1))?,
+            seq.next_element()?.ok_or_else(|| missing(2))?,
+        );
+        let actor = seq.next_element()?.unwrap_or_default();
+        let context = seq.next_element::<Option<String>>()?.flatten();
+        let attributes = seq.next_element::<Option<Attributes>>()?.flatten();
+        Ok(Engram {
+            actor,
+            context: context.map(Arc::from),
+            attributes: attributes.map(Arc::new),
+            ..engram
+        })
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut value, mut memoized, mut retention, mut actor) = (None, None, None, None);
+        let (mut context, mut attributes) = (None::<String>, None::<Attributes>);
+        while let Some(field) = map.next_key::<String>()? {
+            match field.as_str() {
+                "value" => value = Some(map.next_value()?),
+                "memoized" => memoized = Some(map.next_value()?),
+                "retention" => retention = Some(map.next_value()?),
+                "actor" => actor = Some(map.next_value()?),
+                "context" => context = map.next_value()?,
+                "attributes" => attributes = map.next_value()?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(Engram {
+            actor: actor.unwrap_or_default(),
+            context: context.map(Arc::from),
+            attributes: attributes.map(Arc::new),
+            ..engram(
+                value.ok_or_else(|| de::Error::missing_field("value"))?,
+                memoized.ok_or_else(|| de::Error::missing_field("memoized"))?,
+                retention.unwrap_or_default(),
+            )
+        })
+    }
+}
+
+fn engram<T>(value: T, memoized: OffsetDateTime, retention: Option<Duration>) -> Engram<T> {
+    Engram {
+        value,
+        memoized,
+        retention,
+        actor: 0,
+        hits: AtomicU64::new(0),
+        salience: DEFAULT_SALIENCE,
+        version: 0,
+        context: None,
+        attributes: None,
+    }
+}
+
+/// Attributes are encoded as enums, the binary format isn't self-describing.
+impl Serialize for Attribute {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Text(text) => serializer.serialize_newtype_variant("Attribute", 0, "Text", text),
+            Self::Number(number) => {
+                serializer.serialize_newtype_variant("Attribute", 1, "Number", number)
+            }
+        }
+    }
+}
+
+const ATTRIBUTE_VARIANTS: [&str; 2] = ["Text", "Number"];
+
+impl<'de> Deserialize<'de> for Attribute {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Attribute", &ATTRIBUTE_VARIANTS, AttributeVisitor)
+    }
+}
+
+struct AttributeVisitor;
+impl<'de> Visitor<'de> for AttributeVisitor {
+    type Value = Attribute;
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an attribute")
+    }
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (variant, value) = data.variant::<AttributeVariant>()?;
+        match variant {
+            AttributeVariant::Text => value.newtype_variant().map(Attribute::Text),
+            AttributeVariant::Number => value.newtype_variant().map(Attribute::Number),
+        }
+    }
+}
+
+/// The variant of an attribute, by index or by name.
+enum AttributeVariant {
+    Text,
+    Number,
+}
+impl<'de> Deserialize<'de> for AttributeVariant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VariantVisitor;
+        impl Visitor<'_> for VariantVisitor {
+            type Value = AttributeVariant;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an attribute variant")
+            }
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Self::Value, E> {
+                match index {
+                    0 => Ok(AttributeVariant::Text),
+                    1 => Ok(AttributeVariant::Number),
+                    _ => Err(E::invalid_value(de::Unexpected::Unsigned(index), &self)),
+                }
+            }
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                match name {
+                    "Text" => Ok(AttributeVariant::Text),
+                    "Number" => Ok(AttributeVariant::Number),
+                    _ => Err(E::unknown_variant(name, &ATTRIBUTE_VARIANTS)),
+                }
+            }
+        }
+        deserializer.deserialize_identifier(VariantVisitor)
+    }
+}
+
+/// Serializes the engrams of a brain while it is read locked.
+struct Engrams<'a, T, B>(&'a B, PhantomData<T>);
+impl<T: Serialize, B: StorageBackend<String, T>> Serialize for Engrams<'_, T, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.scan())
+    }
+}
+
+/// Serializes the retention and the engrams with their timestamps.
+///
+/// Statistics and hooks aren't serialized.
+impl<T: Serialize, B: StorageBackend<String, T>> Serialize for Brain<T, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let memory = self.memory.read();
+        let mut brain = serializer.serialize_struct("Brain", 2)?;
+        brain.serialize_field("retention", &self.retention)?;
+        brain.serialize_field("engrams", &Engrams(&*memory, PhantomData))?;
+        brain.end()
+    }
+}
+
+/// Deserializes the engrams into the storage in the order they were
+/// serialized, so storages keeping an order, such as [`InsertionOrdered`],
+/// keep the one they were saved in.
+///
+/// [`InsertionOrdered`]: crate::InsertionOrdered
+impl<'de, T: Deserialize<'de>, B: StorageBackend<String, T> + Default> Deserialize<'de>
+    for Brain<T, B>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BrainVisitor<T, B>(PhantomData<(T, B)>);
+        impl<'de, T: Deserialize<'de>, B: StorageBackend<String, T> + Default> Visitor<'de>
+            for BrainVisitor<T, B>
+        {
+            type Value = Brain<T, B>;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a brain")
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let missing = |index| de::Error::invalid_length(index, &"2 fields");
+                Ok(brain(
+                    seq.next_element()?.ok_or_else(|| missing(0))?,
+                    seq.next_element()?.ok_or_else(|| missing(1))?,
+                ))
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let (mut retention, mut engrams) = (None, None);
+                while let Some(field) = map.next_key::<String>()? {
+                    match field.as_str() {
+                        "retention" => retention = Some(map.next_value()?),
+                        "engrams" => engrams = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(brain(
+                    retention.ok_or_else(|| de::Error::missing_field("retention"))?,
+                    engrams.unwrap_or(Ordered(Vec::new())),
+                ))
+            }
+        }
+        deserializer.deserialize_struct(
+            "Brain",
+            &["retention", "engrams"],
+            BrainVisitor(PhantomData),
+        )
+    }
+}
+
+/// Engrams by key in the order they were serialized.
+struct Ordered<T>(Vec<(String, Engram<T>)>);
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ordered<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedVisitor<T>(PhantomData<T>);
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OrderedVisitor<T> {
+            type Value = Ordered<T>;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("engrams by key")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut engrams = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+                while let Some(entry) = map.next_entry()? {
+                    engrams.push(entry);
+                }
+                Ok(Ordered(engrams))
+            }
+        }
+        deserializer.deserialize_map(OrderedVisitor(PhantomData))
+    }
+}
+
+fn brain<T, B: StorageBackend<String, T> + Default>(
+    retention: Duration,
+    Ordered(engrams): Ordered<T>,
+) -> Brain<T, B> {
+    let mut storage = B::default();
+    for (key, engram) in engrams {
+        storage.put(key, engram);
+    }
+    Brain::with_storage(retention, storage)
+}
+
+/// Serializes the writes of every actor as pairs.
+impl Serialize for VectorClock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for VectorClock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<(u64, u64)>::deserialize(deserializer)
+            .map(|writes| VectorClock(writes.into_iter().collect()))
+    }
+}
+
+impl<T: Serialize> Serialize for Siblings<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Siblings<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Siblings)
+    }
+}
+
+impl Serialize for AgeBucket {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bucket = serializer.serialize_struct("AgeBucket", 2)?;
+        bucket.serialize_field(
+            "up_to_seconds",
+            &self.up_to.map(|up_to| up_to.as_seconds_f64()),
+        )?;
+        bucket.serialize_field("entries", &self.entries)?;
+        bucket.end()
+    }
+}
+
+impl Serialize for BrainStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut stats = serializer.serialize_struct("BrainStats", 6)?;
+        stats.serialize_field("hits", &self.hits)?;
+        stats.serialize_field("misses", &self.misses)?;
+        stats.serialize_field("insertions", &self.insertions)?;
+        stats.serialize_field("evictions", &self.evictions)?;
+        stats.serialize_field("capacity_evictions", &self.capacity_evictions)?;
+        stats.serialize_field("expired_on_read", &self.expired_on_read)?;
+        stats.end()
+    }
+}
+
+impl Serialize for BrainReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut report = serializer.serialize_struct("BrainReport", 5)?;
+        report.serialize_field("entries", &self.entries)?;
+        report.serialize_field("estimated_bytes", &self.estimated_bytes)?;
+        report.serialize_field("ages", &self.ages)?;
+        report.serialize_field("retention_seconds", &self.retention.as_seconds_f64())?;
+        report.serialize_field("stats", &self.stats)?;
+        report.end()
+    }
+}
+
+/// Durations are given in seconds, as in config files.
+impl Serialize for BrainConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut config = serializer.serialize_struct("BrainConfig", 4)?;
+        config.serialize_field("retention_seconds", &self.retention.as_seconds_f64())?;
+        config.serialize_field(
+            "sweep_interval_seconds",
+            &self.sweep_interval.map(Duration::as_seconds_f64),
+        )?;
+        config.serialize_field("max_entries", &self.max_entries)?;
+        config.serialize_field("eviction", self.eviction.name())?;
+        config.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for BrainConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ConfigVisitor;
+        impl<'de> Visitor<'de> for ConfigVisitor {
+            type Value = BrainConfig;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a brain config")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut config = BrainConfig::default();
+                while let Some(field) = map.next_key::<String>()? {
+                    match field.as_str() {
+                        "retention_seconds" => config.retention = seconds(map.next_value()?)?,
+                        "sweep_interval_seconds" => {
+                            config.sweep_interval =
+                                map.next_value::<Option<f64>>()?.map(seconds).transpose()?
+                        }
+                        "max_entries" => config.max_entries = map.next_value()?,
+                        "eviction" => {
+                            let name = map.next_value::<String>()?;
+                            config.eviction = Eviction::from_name(&name).ok_or_else(|| {
+                                de::Error::unknown_variant(
+                                    &name,
+                                    &["oldest", "least_hits", "soonest_expiring"],
+                                )
+                            })?
+                        }
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(config)
+            }
+        }
+        fn seconds<E: de::Error>(seconds: f64) -> Result<Duration, E> {
+            crate::config::seconds(seconds).ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Float(seconds), &"a number of seconds")
+            })
+        }
+        deserializer.deserialize_struct(
+            "BrainConfig",
+            &[
+                "retention_seconds",
+                "sweep_interval_seconds",
+                "max_entries",
+                "eviction",
+            ],
+            ConfigVisitor,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, ClockedBrain, Memory, NumericalDuration};
+
+    #[test]
+    fn roundtrip() {
+        let memory = Brain::new(3.minutes());
+        memory.memoize("a", "x".to_string());
+        memory.memoize_for("b", "y".to_string(), 1.seconds());
+
+        let text = json::to_string(&memory).unwrap();
+        let restored = json::from_str::<Brain<String>>(&text).unwrap();
+
+        assert_eq!(restored.retention, 3.minutes());
+        assert_eq!(restored.retrieve("a"), Some("x".to_string()));
+        assert_eq!(restored.retrieve("b"), Some("y".to_string()));
+        let (original, restored) = (memory.memory.read(), restored.memory.read());
+        assert_eq!(original["a"].memoized, restored["a"].memoized);
+        assert_eq!(restored["a"].retention, None);
+        assert_eq!(restored["b"].retention, Some(1.seconds()));
+    }
+
+    #[test]
+    fn contexts_and_attributes() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize_in_context("chat", "topic", "rust".to_string());
+        memory.memoize_with_attributes("a", "x".to_string(), [("tier", "gold")]);
+        memory.memoize_with_attributes("b", "y".to_string(), [("score", 2.5)]);
+        let check = |restored: Brain<String>| {
+            assert_eq!(restored.retrieve("topic"), None);
+            restored.activate_context("chat");
+            assert_eq!(restored.retrieve("topic"), Some("rust".to_string()));
+            let memory = restored.memory.read();
+            assert_eq!(memory["a"].attribute("tier"), Some(&"gold".into()));
+            assert_eq!(memory["b"].attribute("score"), Some(&2.5.into()));
+            assert_eq!(memory["a"].context(), None);
+        };
+
+        check(json::from_str(&json::to_string(&memory).unwrap()).unwrap());
+        let path = std::env::temp_dir().join(format!("brain-{}-contexts", std::process::id()));
+        memory.save_binary_to(&path).unwrap();
+        let restored = Brain::load_from(&path, 1.minutes());
+        std::fs::remove_file(&path).unwrap();
+        check(restored.unwrap());
+        let mut lines = Vec::new();
+        memory.export_jsonl(&mut lines).unwrap();
+        let restored = Brain::new(1.minutes());
+        restored.import_jsonl(&lines[..]).unwrap();
+        check(restored);
+
+        // engrams of version 4 end after their actor
+        let mut record = crate::binary::to_vec(&("x", OffsetDateTime::UNIX_EPOCH)).unwrap();
+        record.extend(crate::binary::to_vec(&(None::<Duration>, 7u64)).unwrap());
+        let Legacy::<String, 4>(engram) = crate::binary::from_slice(&record).unwrap();
+        assert_eq!((engram.actor, engram.context()), (7, None));
+    }
+
+    #[test]
+    fn siblings() {
+        let east = ClockedBrain::new(Brain::new(1.minutes()), 1);
+        let west = ClockedBrain::new(Brain::new(1.minutes()), 2);
+        east.memoize("a", 1);
+        west.memoize("a", 2);
+        east.merge(west.brain());
+
+        let text = json::to_string(east.brain()).unwrap();
+        let restored = ClockedBrain::new(json::from_str(&text).unwrap(), 1);
+        assert_eq!(restored.brain().retrieve("a"), east.brain().retrieve("a"));
+        assert_eq!(restored.retrieve_conflicted("a").len(), 2);
+    }
+
+    #[test]
+    fn report() {
+        let memory = Brain::<i32>::new(4.seconds());
+        let text = json::to_string(&memory.inspect()).unwrap();
+        assert_eq!(
+            text,
+            r#"{"entries":0,"estimated_bytes":0,"ages":[{"up_to_seconds":1,"entries":0},{"up_to_seconds":2,"entries":0},{"up_to_seconds":3,"entries":0},{"up_to_seconds":4,"entries":0},{"up_to_seconds":null,"entries":0}],"retention_seconds":4,"stats":{"hits":0,"misses":0,"insertions":0,"evictions":0,"capacity_evictions":0,"expired_on_read":0}}"#
+        );
+    }
+
+    #[test]
+    fn config() {
+        let config: BrainConfig = json::from_str(
+            r#"{"retention_seconds":60,"sweep_interval_seconds":null,"eviction":"least_hits"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.retention, 1.minutes());
+        assert_eq!(config.sweep_interval, None);
+        assert_eq!(config.eviction, Eviction::LeastHits);
+        assert_eq!(
+            json::from_str::<BrainConfig>(&json::to_string(&config).unwrap()).unwrap(),
+            config
+        );
+        assert!(json::from_str::<BrainConfig>(r#"{"retention_seconds":-1}"#).is_err());
+    }
+}
@@ -0,0 +1,521 @@
+use crate::protocol::{
+    field, read_frame_within, write_frame, AUTH, DEL, FAILED, FORGET, FOUND, GET, LEASE, MAX_FRAME,
+    MISSING, RELEASE, SET, TTL,
+};
+use crate::transport::{Duplex, Listener, Stream, Wrap};
+use crate::{Brain, Duration, Held, Leasing, Memory};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// How a [`Server`] treats its connections.
+#[derive(Clone)]
+pub struct ServerConfig {
+    max_connections: usize,
+    timeout: Duration,
+    idle_timeout: Option<Duration>,
+    authorize: Option<Authorize>,
+    wrap: Option<Wrap>,
+}
+/// Tells whether a token may be served.
+type Authorize = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("max_connections", &self.max_connections)
+            .field("timeout", &self.timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("authorize", &self.authorize.is_some())
+            .field("wrap", &self.wrap.is_some())
+            .finish()
+    }
+}
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 64,
+            timeout: Duration::seconds(5),
+            idle_timeout: None,
+            authorize: None,
+            wrap: None,
+        }
+    }
+}
+impl ServerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Refuses connections beyond the limit, 64 by default.
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+    /// Time to receive a request once it started and to send the response,
+    /// 5 seconds by default. The connection is closed when it runs out.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Closes connections waiting longer for a request, never by default.
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+    /// Serves only connections presenting a token the hook accepts, with
+    /// `AUTH`, a bearer `Authorization` header over HTTP or `AUTH` over RESP.
+    ///
+    /// Tokens are sent in the clear unless the connections are
+    /// [wrapped](ServerConfig::wrap) in TLS, so keep them to trusted
+    /// networks otherwise.
+    pub fn authorize(mut self, authorize: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.authorize = Some(Arc::new(authorize));
+        self
+    }
+    /// Serves accepted TCP connections through the stream the hook makes of
+    /// them, such as a TLS session which may also verify client
+    /// certificates. The hook runs on the connection's thread within the
+    /// timeout, and connections it fails for are closed. Unix domain sockets
+    /// aren't wrapped.
+    pub fn wrap(
+        mut self,
+        wrap: impl Fn(TcpStream) -> io::Result<Box<dyn Duplex>> + Send + Sync + 'static,
+    ) -> Self {
+        self.wrap = Some(Arc::new(wrap));
+        self
+    }
+}
+
+/// Serves a brain of bytes over TCP, so several processes can share it.
+///
+/// Requests and responses are frames of a little endian `u32` length and
+/// as many bytes. A request is an operation byte followed by the key as a
+/// length-prefixed string:
+///
+/// - `GET` (1) responds with the value,
+/// - `SET` (2) takes the length-prefixed value and the retention as an `u64`
+///   of milliseconds, zero for the brain's,
+/// - `DEL` (3) removes the value,
+/// - `TTL` (4) responds with the milliseconds left as an `u64`,
+/// - `FORGET` (5) forgets the expired values, whatever the key,
+/// - `LEASE` (7) takes the length-prefixed holder id and the ttl as an `u64`
+///   of milliseconds, and responds with the other holder if it isn't granted,
+/// - `RELEASE` (8) takes the length-prefixed holder id,
+/// - `AUTH` (9) takes the token in place of the key, and has to come first
+///   on every connection if the config [authorizes](ServerConfig::authorize).
+///
+/// A response is a status byte, 0 if the key was found, 1 if it wasn't and
+/// 2 for an error, followed by the value, the time left, the holder or the
+/// error message.
+pub struct Server {
+    address: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    acceptor: JoinHandle<()>,
+}
+impl Server {
+    /// Starts serving the brain on a background thread.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        brain: Brain<Vec<u8>>,
+        config: ServerConfig,
+    ) -> io::Result<Self> {
+        let mut refusal = Vec::new();
+        write_frame(&mut refusal, &response(FAILED, b"too many connections"))?;
+        Self::listen(address, brain, config, Arc::new(serve), refusal)
+    }
+    /// Accepts connections, serving each on a thread of its own and sending
+    /// the refusal to those beyond the limit.
+    pub(crate) fn listen(
+        address: impl ToSocketAddrs,
+        brain: Brain<Vec<u8>>,
+        config: ServerConfig,
+        serve: Serve,
+        refusal: Vec<u8>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (stopping, acceptor) =
+            spawn_acceptor(Listener::Tcp(listener), brain, config, serve, refusal);
+        Ok(Self {
+            address,
+            stopping,
+            acceptor,
+        })
+    }
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+    /// Stops accepting connections, open ones are served until they close.
+    pub fn shutdown(self) -> thread::Result<()> {
+        self.stopping.store(true, Ordering::Relaxed);
+        // wakes the acceptor up
+        let _ = TcpStream::connect(self.address);
+        self.acceptor.join()
+    }
+}
+
+/// Serves a brain like a [`Server`], on a Unix domain socket instead of TCP.
+///
+/// For processes on the same host, e.g. sidecars where network listeners
+/// aren't allowed, and with less latency than over loopback. Connect with
+/// [`RemoteBrain::connect_unix`].
+///
+/// [`RemoteBrain::connect_unix`]: crate::RemoteBrain::connect_unix
+#[cfg(unix)]
+pub struct UnixServer {
+    path: PathBuf,
+    stopping: Arc<AtomicBool>,
+    acceptor: JoinHandle<()>,
+}
+#[cfg(unix)]
+impl UnixServer {
+    /// Starts serving the brain on a background thread, failing if
+    /// something exists at the path already.
+    pub fn bind(
+        path: impl AsRef<Path>,
+        brain: Brain<Vec<u8>>,
+        config: ServerConfig,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)?;
+        let mut refusal = Vec::new();
+        write_frame(&mut refusal, &response(FAILED, b"too many connections"))?;
+        let (stopping, acceptor) = spawn_acceptor(
+            Listener::Unix(listener),
+            brain,
+            config,
+            Arc::new(serve),
+            refusal,
+        );
+        Ok(Self {
+            path,
+            stopping,
+            acceptor,
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Stops accepting connections and removes the socket, open connections
+    /// are served until they close.
+    pub fn shutdown(self) -> thread::Result<()> {
+        self.stopping.store(true, Ordering::Relaxed);
+        // wakes the acceptor up
+        let _ = UnixStream::connect(&self.path);
+        let joined = self.acceptor.join();
+        let _ = std::fs::remove_file(&self.path);
+        joined
+    }
+}
+
+fn spawn_acceptor(
+    listener: Listener,
+    brain: Brain<Vec<u8>>,
+    config: ServerConfig,
+    serve: Serve,
+    refusal: Vec<u8>,
+) -> (Arc<AtomicBool>, JoinHandle<()>) {
+    let stopping = Arc::new(AtomicBool::new(false));
+    let acceptor = {
+        let stopping = stopping.clone();
+        thread::spawn(move || {
+            accept(listener, brain, config, serve, &refusal, &stopping);
+        })
+    };
+    (stopping, acceptor)
+}
+
+pub(crate) type Serve =
+    Arc<dyn Fn(&mut Connection, &Brain<Vec<u8>>) -> io::Result<()> + Send + Sync>;
+
+/// Requests of connections not authorized yet are refused above this
+/// length, so they can't make the server allocate large buffers.
+const MAX_UNAUTHORIZED: usize = 4 << 10;
+
+/// A connection of a [`Server`].
+pub(crate) struct Connection {
+    pub(crate) reader: BufReader<Stream>,
+    pub(crate) writer: BufWriter<Stream>,
+    config: ServerConfig,
+    authorized: bool,
+}
+impl Connection {
+    pub(crate) fn new(stream: Stream, config: ServerConfig) -> io::Result<Self> {
+        stream.set_nodelay()?;
+        stream.set_read_timeout(timeout(config.timeout))?;
+        stream.set_write_timeout(timeout(config.timeout))?;
+        let stream = match &config.wrap {
+            Some(wrap) => stream.wrap(wrap)?,
+            None => stream,
+        };
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            authorized: config.authorize.is_none(),
+            config,
+        })
+    }
+    /// Tells whether the connection may be served, once a token was accepted.
+    #[cfg(feature = "resp")]
+    pub(crate) fn is_authorized(&self) -> bool {
+        self.authorized
+    }
+    /// The largest request to read, `max` once authorized.
+    pub(crate) fn max_request(&self, max: usize) -> usize {
+        match self.authorized {
+            true => max,
+            false => max.min(MAX_UNAUTHORIZED),
+        }
+    }
+    /// Serves the connection from now on if the token is accepted.
+    pub(crate) fn authorize(&mut self, token: &str) -> bool {
+        self.authorized = match &self.config.authorize {
+            Some(authorize) => authorize(token),
+            None => true,
+        };
+        self.authorized
+    }
+    /// Answers an `AUTH` request, or refuses any other on a connection not
+    /// authorized yet, `None` for a request to serve.
+    pub(crate) fn admit(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+        if let Some((&AUTH, mut fields)) = request.split_first() {
+            let token = field(&mut fields).map(String::from_utf8_lossy);
+            return Some(match token {
+                Some(token) if self.authorize(&token) => response(FOUND, &[]),
+                _ => response(FAILED, b"unauthorized"),
+            });
+        }
+        (!self.authorized).then(|| response(FAILED, b"unauthorized"))
+    }
+    /// Waits for the next request for at most the idle timeout, `false` if
+    /// the connection was closed instead. The request then has to arrive
+    /// within the timeout.
+    pub(crate) fn wait(&mut self) -> io::Result<bool> {
+        let stream = self.reader.get_ref();
+        stream.set_read_timeout(self.config.idle_timeout.and_then(timeout))?;
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(false);
+        }
+        self.reader
+            .get_ref()
+            .set_read_timeout(timeout(self.config.timeout))?;
+        Ok(true)
+    }
+}
+
+/// Bounds of the pause after a failed accept, doubled while it fails.
+const MIN_ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(5);
+const MAX_ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+
+fn accept(
+    listener: Listener,
+    brain: Brain<Vec<u8>>,
+    config: ServerConfig,
+    serve: Serve,
+    refusal: &[u8],
+    stopping: &AtomicBool,
+) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let mut backoff = MIN_ACCEPT_BACKOFF;
+    loop {
+        let stream = listener.accept();
+        if stopping.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(mut stream) = stream else {
+            // e.g. out of file descriptors, which accepting again won't fix
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+            continue;
+        };
+        backoff = MIN_ACCEPT_BACKOFF;
+        if connections.fetch_add(1, Ordering::Relaxed) >= config.max_connections {
+            connections.fetch_sub(1, Ordering::Relaxed);
+            let _ = stream.set_write_timeout(timeout(config.timeout));
+            let _ = stream.write_all(refusal);
+            continue;
+        }
+        let (brain, serve, connections) = (brain.clone(), serve.clone(), connections.clone());
+        let config = config.clone();
+        thread::spawn(move || {
+            if let Ok(mut connection) = Connection::new(stream, config) {
+                let _ = serve(&mut connection, &brain);
+            }
+            connections.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+fn serve(connection: &mut Connection, brain: &Brain<Vec<u8>>) -> io::Result<()> {
+    while connection.wait()? {
+        let max = connection.max_request(MAX_FRAME);
+        let Some(request) = read_frame_within(&mut connection.reader, max)? else {
+            break;
+        };
+        let response = connection
+            .admit(&request)
+            .unwrap_or_else(|| handle(brain, &request));
+        write_frame(&mut connection.writer, &response)?;
+        connection.writer.flush()?;
+    }
+    Ok(())
+}
+
+pub(crate) fn handle(brain: &Brain<Vec<u8>>, request: &[u8]) -> Vec<u8> {
+    let malformed = || response(FAILED, b"malformed request");
+    let Some((&operation, mut fields)) = request.split_first() else {
+        return malformed();
+    };
+    let Some(Ok(key)) = field(&mut fields).map(std::str::from_utf8) else {
+        return malformed();
+    };
+    match operation {
+        GET => match brain.retrieve(key) {
+            Some(value) => response(FOUND, &value),
+            None => response(MISSING, &[]),
+        },
+        SET => {
+            let Some(value) = field(&mut fields) else {
+                return malformed();
+            };
+            let Some(retention) = fields.first_chunk::<8>().map(|ms| u64::from_le_bytes(*ms))
+            else {
+                return malformed();
+            };
+            match retention {
+                0 => brain.memoize(key, value.to_vec()),
+                ms => brain.memoize_for(key, value.to_vec(), milliseconds(ms)),
+            }
+            response(FOUND, &[])
+        }
+        DEL => match brain.remove(key) {
+            Some(_) => response(FOUND, &[]),
+            None => response(MISSING, &[]),
+        },
+        TTL => match brain.time_to_live(key) {
+            Some(left) => response(FOUND, &(left.whole_milliseconds() as u64).to_le_bytes()),
+            None => response(MISSING, &[]),
+        },
+        FORGET => {
+            brain.forget();
+            response(FOUND, &[])
+        }
+        LEASE => {
+            let Some(Ok(holder)) = field(&mut fields).map(std::str::from_utf8) else {
+                return malformed();
+            };
+            let Some(ttl) = fields.first_chunk::<8>().map(|ms| u64::from_le_bytes(*ms)) else {
+                return malformed();
+            };
+            match brain.try_hold(key, holder, milliseconds(ttl)) {
+                Ok(()) => response(FOUND, &[]),
+                Err(Held::By(holder)) => response(MISSING, holder.as_bytes()),
+                Err(Held::Failed(error)) => response(FAILED, error.to_string().as_bytes()),
+            }
+        }
+        RELEASE => {
+            let Some(Ok(holder)) = field(&mut fields).map(std::str::from_utf8) else {
+                return malformed();
+            };
+            match brain.try_release(key, holder) {
+                Ok(true) => response(FOUND, &[]),
+                Ok(false) => response(MISSING, &[]),
+                Err(error) => response(FAILED, error.to_string().as_bytes()),
+            }
+        }
+        _ => response(FAILED, b"unknown operation"),
+    }
+}
+
+pub(crate) fn response(status: u8, body: &[u8]) -> Vec<u8> {
+    let mut response = vec![status];
+    response.extend(body);
+    response
+}
+
+/// Converts a timeout, zero meaning none as sockets don't take zero.
+fn timeout(timeout: Duration) -> Option<std::time::Duration> {
+    Some(timeout.unsigned_abs()).filter(|timeout| !timeout.is_zero())
+}
+
+fn milliseconds(ms: u64) -> Duration {
+    Duration::milliseconds(i64::try_from(ms).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{put_field, read_frame, request as frame};
+    use crate::NumericalDuration;
+
+    fn request(stream: &mut TcpStream, operation: u8, key: &str, rest: &[u8]) -> Vec<u8> {
+        let mut frame = frame(operation, key);
+        frame.extend(rest);
+        write_frame(stream, &frame).unwrap();
+        read_frame(stream).unwrap().unwrap()
+    }
+
+    #[test]
+    fn server() {
+        let brain = Brain::new(1.minutes());
+        let server = Server::bind("127.0.0.1:0", brain.clone(), ServerConfig::new()).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+
+        let mut set = Vec::new();
+        put_field(&mut set, b"value");
+        set.extend(1000u64.to_le_bytes());
+        assert_eq!(request(&mut stream, SET, "a", &set), [FOUND]);
+        assert_eq!(request(&mut stream, GET, "a", &[]), b"\0value");
+        assert_eq!(brain.retrieve("a"), Some(b"value".to_vec()));
+
+        let ttl = request(&mut stream, TTL, "a", &[]);
+        assert_eq!(ttl[0], FOUND);
+        assert!(u64::from_le_bytes(ttl[1..].try_into().unwrap()) <= 1000);
+        assert_eq!(request(&mut stream, DEL, "a", &[]), [FOUND]);
+        assert_eq!(request(&mut stream, GET, "a", &[]), [MISSING]);
+        assert_eq!(request(&mut stream, 42, "a", &[])[0], FAILED);
+
+        drop(stream);
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn unauthorized_frames() {
+        let config = ServerConfig::new().authorize(|token| token == "secret");
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), config).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        // announcing a large frame doesn't get it allocated, or even read
+        stream
+            .write_all(&(MAX_UNAUTHORIZED as u32 + 1).to_le_bytes())
+            .unwrap();
+        assert!(read_frame(&mut stream).unwrap().is_none());
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        assert_eq!(request(&mut stream, AUTH, "secret", &[]), [FOUND]);
+        let mut set = Vec::new();
+        put_field(&mut set, &[7; MAX_UNAUTHORIZED]);
+        set.extend(0u64.to_le_bytes());
+        assert_eq!(request(&mut stream, SET, "a", &set), [FOUND]);
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn max_connections() {
+        let config = ServerConfig::new().max_connections(1);
+        let server = Server::bind("127.0.0.1:0", Brain::new(1.minutes()), config).unwrap();
+        let mut first = TcpStream::connect(server.local_addr()).unwrap();
+        assert_eq!(request(&mut first, GET, "a", &[]), [MISSING]);
+
+        let mut second = TcpStream::connect(server.local_addr()).unwrap();
+        let refused = read_frame(&mut second).unwrap().unwrap();
+        assert_eq!(refused, response(FAILED, b"too many connections"));
+        drop(first);
+        server.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use crate::wall_clock;
+use crate::{Brain, Duration, Memory};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+struct Activity {
+    last: OffsetDateTime,
+    idle_timeout: Duration,
+}
+impl Activity {
+    fn is_idle(&self, now: OffsetDateTime) -> bool {
+        now - self.last > self.idle_timeout
+    }
+}
+
+/// Brains of many sessions, e.g. of a web app's users, each ending as a
+/// whole once idle for its timeout.
+///
+/// A session is idle when none of its values was memoized or retrieved,
+/// and it wasn't touched, for the timeout. Its values expire for the
+/// retention on their own meanwhile.
+pub struct SessionMemory<T> {
+    retention: Duration,
+    idle_timeout: Duration,
+    sessions: RwLock<HashMap<String, Session<T>>>,
+}
+impl<T> SessionMemory<T> {
+    pub fn new(retention: Duration, idle_timeout: Duration) -> Self {
+        Self {
+            retention,
+            idle_timeout,
+            sessions: Default::default(),
+        }
+    }
+    /// The session, started anew unless there is one which isn't idle.
+    pub fn session(&self, id: &str) -> Session<T> {
+        if let Some(session) = self.get(id) {
+            return session;
+        }
+        let session = Session {
+            id: id.into(),
+            brain: Brain::new(self.retention),
+            activity: Arc::new(Mutex::new(Activity {
+                last: wall_clock::now(),
+                idle_timeout: self.idle_timeout,
+            })),
+        };
+        self.sessions
+            .write()
+            .insert(id.to_string(), session.clone());
+        session
+    }
+    /// The session unless there is none or it's idle, touching it.
+    pub fn get(&self, id: &str) -> Option<Session<T>> {
+        let session = self.sessions.read().get(id).cloned()?;
+        if session.is_idle() {
+            return None;
+        }
+        session.touch();
+        Some(session)
+    }
+    /// Ends the session, all its values at once, telling whether there was
+    /// one.
+    ///
+    /// Handles of the session keep their values, which are gone for
+    /// [`SessionMemory::session`].
+    pub fn end(&self, id: &str) -> bool {
+        self.sessions.write().remove(id).is_some()
+    }
+    /// Ends the idle sessions, giving how many.
+    pub fn expire_idle(&self) -> usize {
+        let now = wall_clock::now();
+        let mut sessions = self.sessions.write();
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.activity.lock().is_idle(now));
+        before - sessions.len()
+    }
+    /// Ids of the sessions, including idle ones not expired yet.
+    pub fn ids(&self) -> Vec<String> {
+        self.sessions.read().keys().cloned().collect()
+    }
+    pub fn len(&self) -> usize {
+        self.sessions.read().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+impl<T: Clone> SessionMemory<T> {
+    /// Ends the idle sessions and forgets the expired values of the others.
+    pub fn forget(&self) {
+        self.expire_idle();
+        let sessions = self.sessions.read().values().cloned().collect::<Vec<_>>();
+        for session in sessions {
+            session.brain.forget();
+        }
+    }
+}
+
+/// A session of a [`SessionMemory`], touched by memoizing into it and
+/// retrieving from it.
+pub struct Session<T> {
+    id: Arc<str>,
+    brain: Brain<T>,
+    activity: Arc<Mutex<Activity>>,
+}
+impl<T> Clone for Session<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            brain: self.brain.clone(),
+            activity: self.activity.clone(),
+        }
+    }
+}
+impl<T> Session<T> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// The brain keeping the session's values, which doesn't touch it.
+    pub fn brain(&self) -> &Brain<T> {
+        &self.brain
+    }
+    /// Keeps the session from being idle for its timeout from now on.
+    pub fn touch(&self) {
+        self.activity.lock().last = wall_clock::now();
+    }
+    /// Sets the idle timeout of this session alone.
+    pub fn set_idle_timeout(&self, idle_timeout: Duration) {
+        self.activity.lock().idle_timeout = idle_timeout;
+    }
+    pub fn is_idle(&self) -> bool {
+        self.activity.lock().is_idle(wall_clock::now())
+    }
+}
+impl<T: Clone> Memory<T> for Session<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.touch();
+        self.brain.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.touch();
+        self.brain.retrieve(key)
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn sessions() {
+        let sessions = SessionMemory::new(1.minutes(), 5.milliseconds());
+        let alice = sessions.session("alice");
+        alice.memoize("cart", 3);
+        sessions.session("bob").memoize("cart", 1);
+        assert_eq!(sessions.session("alice").retrieve("cart"), Some(3));
+        alice.set_idle_timeout(1.minutes());
+        assert_eq!(sessions.len(), 2);
+
+        std::thread::sleep(std::time::Duration::from_millis(6));
+        assert!(sessions.get("bob").is_none());
+        assert_eq!(sessions.expire_idle(), 1);
+        assert_eq!(sessions.ids(), ["alice"]);
+        assert_eq!(sessions.session("bob").retrieve("cart"), None);
+
+        assert!(sessions.end("alice"));
+        assert!(!sessions.end("alice"));
+        assert_eq!(sessions.session("alice").retrieve("cart"), None);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    AsyncMemory, BinaryCodec, BoxFuture, ClientConfig, Duration, Memory, RemoteBrain, TryMemoize,
+    WireCodec,
+};
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+/// Remote brains sharing the keys among them by consistent hashing.
+///
+/// Every node is placed on a hash ring at a number of virtual points, and a
+/// key goes to the node owning the first point at or after its hash. Adding
+/// or removing a node only moves the keys of the ring segments it gains or
+/// loses, about one in N. Moved keys are not copied over, they are misses
+/// until memoized again on their new node.
+pub struct ShardedClient<T> {
+    ring: Arc<RwLock<Ring<T>>>,
+    config: ClientConfig,
+    codec: Arc<dyn WireCodec<T>>,
+    virtual_nodes: usize,
+}
+struct Ring<T> {
+    points: BTreeMap<u64, String>,
+    nodes: BTreeMap<String, RemoteBrain<T>>,
+}
+impl<T> Clone for ShardedClient<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ring: self.ring.clone(),
+            config: self.config.clone(),
+            codec: self.codec.clone(),
+            virtual_nodes: self.virtual_nodes,
+        }
+    }
+}
+impl<T: Serialize + DeserializeOwned + 'static> ShardedClient<T> {
+    /// A client without nodes, placing every node at 160 points.
+    pub fn new(config: ClientConfig) -> Self {
+        Self::with_codec(config, BinaryCodec)
+    }
+}
+impl<T> ShardedClient<T> {
+    /// A client without nodes, encoding values with the codec on every node.
+    pub fn with_codec(config: ClientConfig, codec: impl WireCodec<T> + 'static) -> Self {
+        Self {
+            ring: Arc::new(RwLock::new(Ring {
+                points: BTreeMap::new(),
+                nodes: BTreeMap::new(),
+            })),
+            config,
+            codec: Arc::new(codec),
+            virtual_nodes: 160,
+        }
+    }
+    /// Points per node on the ring, more spread the keys more evenly.
+    ///
+    /// Takes effect for nodes added afterwards.
+    pub fn virtual_nodes(mut self, virtual_nodes: usize) -> Self {
+        self.virtual_nodes = virtual_nodes.max(1);
+        self
+    }
+    /// Connects to the server at the address and gives it its share of keys.
+    ///
+    /// Nodes are known by their address, adding one twice reconnects it.
+    pub fn add_node(&self, address: &str) -> io::Result<()> {
+        let brain = RemoteBrain::connect_shared(address, self.config.clone(), self.codec.clone())?;
+        let mut ring = self.ring.write();
+        for point in 0..self.virtual_nodes {
+            ring.points.insert(
+                hash(format!("{address}#{point}").as_bytes()),
+                address.into(),
+            );
+        }
+        ring.nodes.insert(address.into(), brain);
+        Ok(())
+    }
+    /// Hands the keys of the node to the others, telling whether it was known.
+    pub fn remove_node(&self, address: &str) -> bool {
+        let mut ring = self.ring.write();
+        ring.points.retain(|_, node| node != address);
+        ring.nodes.remove(address).is_some()
+    }
+    /// Addresses of the nodes, in order.
+    pub fn nodes(&self) -> Vec<String> {
+        self.ring.read().nodes.keys().cloned().collect()
+    }
+    /// Address of the node the key goes to.
+    pub fn node_for(&self, key: &str) -> Option<String> {
+        self.ring
+            .read()
+            .owner(key)
+            .map(|(address, _)| address.clone())
+    }
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.node(key)?.try_memoize(key, value)
+    }
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        self.node(key)?.try_memoize_for(key, value, retention)
+    }
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        self.node(key)?.try_retrieve(key)
+    }
+    /// Removes the value for the key, telling whether there was one.
+    pub fn try_remove(&self, key: &str) -> io::Result<bool> {
+        self.node(key)?.try_remove(key)
+    }
+    pub fn try_time_to_live(&self, key: &str) -> io::Result<Option<Duration>> {
+        self.node(key)?.try_time_to_live(key)
+    }
+    /// Forgets on every node, failing with the first error.
+    pub fn try_forget(&self) -> io::Result<()> {
+        let nodes = self.ring.read().nodes.values().cloned().collect::<Vec<_>>();
+        nodes.iter().try_for_each(RemoteBrain::try_forget)
+    }
+    /// Forgets the expired values on every node at once, whatever the
+    /// result of the others, giving each node's result by address.
+    pub fn forget_all_nodes(&self) -> Vec<(String, io::Result<()>)> {
+        self.on_all_nodes(RemoteBrain::try_forget)
+    }
+    /// Removes the value for the key from every node at once, also those no
+    /// longer owning it, telling for each node whether it had one.
+    pub fn forget_key_all_nodes(&self, key: &str) -> Vec<(String, io::Result<bool>)> {
+        self.on_all_nodes(|brain| brain.try_remove(key))
+    }
+    fn on_all_nodes<R: Send>(
+        &self,
+        request: impl Fn(&RemoteBrain<T>) -> io::Result<R> + Sync,
+    ) -> Vec<(String, io::Result<R>)> {
+        let nodes = self.ring.read().nodes.clone();
+        let request = &request;
+        thread::scope(|scope| {
+            let requests = nodes
+                .iter()
+                .map(|(address, brain)| (address, scope.spawn(move || request(brain))))
+                .collect::<Vec<_>>();
+            requests
+                .into_iter()
+                .map(|(address, request)| {
+                    let result = request
+                        .join()
+                        .unwrap_or_else(|_| Err(io::Error::other("request panicked")));
+                    (address.clone(), result)
+                })
+                .collect()
+        })
+    }
+    fn node(&self, key: &str) -> io::Result<RemoteBrain<T>> {
+        self.ring
+            .read()
+            .owner(key)
+            .map(|(_, brain)| brain.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "no nodes"))
+    }
+}
+impl<T> Ring<T> {
+    fn owner(&self, key: &str) -> Option<(&String, &RemoteBrain<T>)> {
+        let hash = hash(key.as_bytes());
+        let (_, address) = self
+            .points
+            .range(hash..)
+            .next()
+            .or_else(|| self.points.iter().next())?;
+        self.nodes.get_key_value(address)
+    }
+}
+impl<T> TryMemoize<T> for ShardedClient<T> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+impl<T> Memory<T> for ShardedClient<T> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    fn forget(&self) {
+        let _ = self.try_forget();
+    }
+}
+/// Requests run on worker threads of the node owning the key, as many as
+/// its pooled connections, so they don't block the executor.
+impl<T: Send + 'static> AsyncMemory<T> for ShardedClient<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        match self.node(key) {
+            Ok(node) => Box::pin(async move { AsyncMemory::memoize(&node, key, value).await }),
+            Err(_) => Box::pin(std::future::ready(())),
+        }
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        match self.node(key) {
+            Ok(node) => Box::pin(async move { AsyncMemory::retrieve(&node, key).await }),
+            Err(_) => Box::pin(std::future::ready(None)),
+        }
+    }
+    /// Forgets on every node at once.
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        let nodes = self.ring.read().nodes.values().cloned().collect::<Vec<_>>();
+        Box::pin(async move {
+            let forgetting = nodes.iter().map(AsyncMemory::forget).collect::<Vec<_>>();
+            for forgotten in forgetting {
+                forgotten.await;
+            }
+        })
+    }
+}
+
+/// FNV-1a with a final mix, stable across processes and releases.
+pub(crate) fn hash(bytes: &[u8]) -> u64 {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3)
+    });
+    let hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration, Server, ServerConfig};
+
+    #[test]
+    fn sharding() {
+        let servers = (0..3)
+            .map(|_| Server::bind("127.0.0.1:0", Brain::new(1.minutes()), ServerConfig::new()))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let addresses = servers
+            .iter()
+            .map(|server| server.local_addr().to_string())
+            .collect::<Vec<_>>();
+        let client = ShardedClient::new(ClientConfig::new().pool_size(1));
+        assert!(client.try_retrieve("a").is_err());
+        client.add_node(&addresses[0]).unwrap();
+        client.add_node(&addresses[1]).unwrap();
+
+        let keys = (0..200).map(|key| key.to_string()).collect::<Vec<_>>();
+        for key in &keys {
+            client.try_memoize(key, key.clone()).unwrap();
+        }
+        let owners = |client: &ShardedClient<String>| {
+            keys.iter()
+                .map(|key| client.node_for(key).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let before = owners(&client);
+        assert!(addresses[..2]
+            .iter()
+            .all(|address| before.contains(address)));
+
+        client.add_node(&addresses[2]).unwrap();
+        let after = owners(&client);
+        let moved = before.iter().zip(&after).filter(|(a, b)| a != b).count();
+        assert!(moved > 0 && moved < 120, "{moved} keys moved");
+        assert!(before
+            .iter()
+            .zip(&after)
+            .all(|(before, after)| before == after || after == &addresses[2]));
+
+        // keys which stayed are still found
+        let stayed = keys.iter().zip(before.iter().zip(&after));
+        for (key, _) in stayed.filter(|(_, (a, b))| a == b) {
+            assert_eq!(client.try_retrieve(key).unwrap().as_ref(), Some(key));
+        }
+
+        assert!(client.remove_node(&addresses[2]));
+        assert!(!client.remove_node(&addresses[2]));
+        assert_eq!(owners(&client), before);
+        assert_eq!(client.nodes().len(), 2);
+        for server in servers {
+            server.shutdown().unwrap();
+        }
+    }
+
+    #[test]
+    fn forget_all_nodes() {
+        let brains = [Brain::new(1.minutes()), Brain::new(1.minutes())];
+        let servers = brains
+            .iter()
+            .map(|brain| Server::bind("127.0.0.1:0", brain.clone(), ServerConfig::new()))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let client = ShardedClient::<Vec<u8>>::new(ClientConfig::new().retries(0));
+        for (server, brain) in servers.iter().zip(&brains) {
+            client.add_node(&server.local_addr().to_string()).unwrap();
+            brain.memoize("a", vec![]);
+        }
+
+        let removed = client.forget_key_all_nodes("a");
+        assert_eq!(removed.len(), 2);
+        assert!(removed
+            .iter()
+            .all(|(_, removed)| *removed.as_ref().unwrap()));
+        assert!(brains.iter().all(|brain| !brain.is_cached("a")));
+
+        // nothing answers on the address once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        client.add_node(&address).unwrap();
+        drop(listener);
+        let forgotten = client.forget_all_nodes();
+        assert_eq!(forgotten.len(), 3);
+        for (node, result) in forgotten {
+            assert_eq!(result.is_err(), node == address, "{node}");
+        }
+        for server in servers {
+            server.shutdown().unwrap();
+        }
+    }
+}
@@ -0,0 +1,841 @@
+use crate::crc::crc32;
+use crate::serialization::Legacy;
+use crate::wall_clock;
+use crate::{
+    binary, json, Brain, BrainBuilder, Duration, Engram, InsertionOrdered, MaintenanceConfig,
+    StorageBackend,
+};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use time::OffsetDateTime;
+
+/// Starts binary snapshots, JSON snapshots never start with it.
+pub(crate) const MAGIC: &[u8; 8] = b"\x89BRAIN\r\n";
+/// Starts delta files, see [`Brain::save_delta_to`].
+pub(crate) const DELTA_MAGIC: &[u8; 8] = b"\x89BRDLT\r\n";
+/// Version of the binary formats, written after the magic.
+///
+/// Version 2 added the name of the codec to the header, version 3 stores
+/// the engrams as checksummed records followed by a checksum of the file,
+/// version 4 adds the actor to every engram, version 5 its context and
+/// attributes.
+const VERSION: u16 = 5;
+
+/// Engrams by key in the order a snapshot holds them.
+pub(crate) type Engrams<T> = Vec<(String, Engram<T>)>;
+
+/// Transforms binary snapshots on their way to and from the file, e.g.
+/// compressing them.
+pub trait SnapshotCodec {
+    /// Identifies the codec in the snapshot header, a snapshot is only
+    /// loaded with a codec of the same name.
+    fn name(&self) -> &str;
+    /// Encodes the payload written after the header.
+    ///
+    /// The header is passed e.g. for a cipher to authenticate it.
+    fn encode(&self, header: &[u8], payload: Vec<u8>) -> io::Result<Vec<u8>>;
+    fn decode(&self, header: &[u8], encoded: Vec<u8>) -> io::Result<Vec<u8>>;
+}
+
+/// How far snapshots are flushed before saving them returns.
+///
+/// Snapshots are written next to their file and then renamed over it, so
+/// the file holds either the previous or the new snapshot. Without syncing,
+/// a power loss may still leave the new file empty or partially written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Durability {
+    /// Leaves flushing to the operating system.
+    Rename,
+    /// Syncs the snapshot to the disk before renaming it.
+    #[default]
+    SyncFile,
+    /// Syncs the directory after renaming too, so the rename survives a
+    /// power loss. Only Unix syncs directories.
+    SyncDirectory,
+}
+
+/// How [`Brain::load_with`] loads a snapshot.
+#[derive(Clone, Copy, Default)]
+pub struct LoadOptions<'a> {
+    codec: Option<&'a dyn SnapshotCodec>,
+    skip_corrupt: bool,
+    max_remaining: Option<Duration>,
+}
+impl<'a> LoadOptions<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Decodes binary snapshots saved with the codec.
+    pub fn codec(mut self, codec: &'a dyn SnapshotCodec) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+    /// Skips engrams failing their checksum instead of failing the load,
+    /// e.g. to restore what's left of a partially written snapshot.
+    pub fn skip_corrupt(mut self) -> Self {
+        self.skip_corrupt = true;
+        self
+    }
+    /// Caps the time restored engrams have left until they expire.
+    ///
+    /// Engrams keep the time they were memoized, so they expire when they
+    /// would have without the restart, or after the cap, whatever is earlier.
+    pub fn max_remaining(mut self, max_remaining: Duration) -> Self {
+        self.max_remaining = Some(max_remaining);
+        self
+    }
+    /// Drops an expired engram, or caps the time it has left.
+    fn restore<T>(
+        &self,
+        mut engram: Engram<T>,
+        retention: Duration,
+        now: OffsetDateTime,
+    ) -> Option<Engram<T>> {
+        if engram.is_expired(retention, now) {
+            return None;
+        }
+        if let Some(max_remaining) = self.max_remaining {
+            let capped = now + max_remaining - engram.memoized;
+            if engram.retention.unwrap_or(retention) > capped {
+                engram.retention = Some(capped);
+            }
+        }
+        Some(engram)
+    }
+}
+
+/// Keys memoized or forgotten since the last snapshot.
+///
+/// Keys are only tracked once a snapshot was saved or loaded.
+#[derive(Default)]
+pub(crate) struct Dirty(Mutex<Option<HashSet<String>>>);
+impl Dirty {
+    pub(crate) fn touch(&self, key: &str) {
+        if let Some(keys) = &mut *self.0.lock() {
+            keys.insert(key.to_string());
+        }
+    }
+    /// Takes the keys touched so far, `None` if they weren't tracked yet.
+    fn take(&self) -> Option<HashSet<String>> {
+        self.0.lock().replace(HashSet::new())
+    }
+    /// Saves with the keys touched so far, putting them back if it fails.
+    fn saving(
+        &self,
+        save: impl FnOnce(Option<&HashSet<String>>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let taken = self.take();
+        let saved = save(taken.as_ref());
+        if saved.is_err() {
+            let mut keys = self.0.lock();
+            match (taken, &mut *keys) {
+                (Some(taken), Some(keys)) => keys.extend(taken),
+                (None, keys) => *keys = None,
+                (Some(_), None) => {}
+            }
+        }
+        saved
+    }
+}
+
+/// Runs a last save when the last handle of a brain is dropped.
+pub(crate) struct SaveOnDrop {
+    save: Option<Box<dyn FnOnce() + Send + Sync>>,
+    timeout: std::time::Duration,
+}
+impl Drop for SaveOnDrop {
+    fn drop(&mut self) {
+        if let Some(save) = self.save.take() {
+            let (done, saved) = mpsc::channel();
+            std::thread::spawn(move || {
+                save();
+                let _ = done.send(());
+            });
+            let _ = saved.recv_timeout(self.timeout);
+        }
+    }
+}
+
+impl<T, S> Brain<T, S>
+where
+    T: Serialize + Send + Sync + 'static,
+    S: StorageBackend<String, T> + Send + Sync + 'static,
+{
+    /// Saves a binary snapshot when the last handle of the brain is dropped.
+    ///
+    /// Saving is best effort, dropping waits for it at most for the
+    /// timeout. Handles cloned before don't keep the brain from saving.
+    pub fn snapshot_on_drop(mut self, path: impl Into<PathBuf>, timeout: Duration) -> Self {
+        let (path, memory, retention) = (path.into(), self.memory.clone(), self.retention);
+        let durability = self.durability;
+        self.on_drop = Some(Arc::new(SaveOnDrop {
+            save: Some(Box::new(move || {
+                let brain = Brain {
+                    durability,
+                    ..Brain::sharing(retention, memory)
+                };
+                let _ = brain.save_binary_to(path);
+            })),
+            timeout: timeout.unsigned_abs(),
+        }));
+        self
+    }
+}
+impl<T: Serialize, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Saves the engrams, with the time they were memoized, to a file.
+    ///
+    /// The snapshot is written next to the file and then renamed, so the
+    /// file holds either the previous or the new snapshot, even on a crash,
+    /// see [`BrainBuilder::durability`].
+    pub fn save_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.dirty.saving(|_| {
+            let text = json::to_string(self).map_err(invalid_data)?;
+            write_atomically(path.as_ref(), text.as_bytes(), self.durability)
+        })
+    }
+    /// Saves the engrams like [`Brain::save_to`], in a compact binary format.
+    ///
+    /// The snapshot starts with a magic number and the format version, so
+    /// later releases can still load it. Every engram and the whole file
+    /// are checksummed.
+    pub fn save_binary_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_binary(path.as_ref(), None)
+    }
+    /// Saves a binary snapshot passing through the codec.
+    pub fn save_binary_with(
+        &self,
+        path: impl AsRef<Path>,
+        codec: &dyn SnapshotCodec,
+    ) -> io::Result<()> {
+        self.save_binary(path.as_ref(), Some(codec))
+    }
+    /// Saves the engrams memoized or forgotten since the last snapshot, or
+    /// delta, see [`Brain::apply_delta_from`].
+    ///
+    /// Fails unless a snapshot was saved or loaded before.
+    pub fn save_delta_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.dirty.saving(|keys| {
+            let keys = keys.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no snapshot to take a delta of",
+                )
+            })?;
+            let mut payload = Vec::new();
+            {
+                let memory = self.memory.read();
+                for key in keys {
+                    record(&mut payload, &(key, memory.get(key)))?;
+                }
+            }
+            write_atomically(
+                path.as_ref(),
+                &encode_binary(DELTA_MAGIC, None, payload)?,
+                self.durability,
+            )
+        })
+    }
+    fn save_binary(&self, path: &Path, codec: Option<&dyn SnapshotCodec>) -> io::Result<()> {
+        self.dirty.saving(|_| {
+            let mut payload = Vec::new();
+            for engram in self.memory.read().scan() {
+                record(&mut payload, &engram)?;
+            }
+            write_atomically(
+                path,
+                &encode_binary(MAGIC, codec, payload)?,
+                self.durability,
+            )
+        })
+    }
+}
+impl<T: DeserializeOwned> Brain<T> {
+    /// Restores a brain saved with [`Brain::save_to`] or [`Brain::save_binary_to`].
+    ///
+    /// Engrams keep the time they were memoized, so they only have the rest
+    /// of their retention left, and engrams which already expired are skipped. Binary snapshots of a
+    /// newer format version are rejected.
+    pub fn load_from(path: impl AsRef<Path>, retention: Duration) -> io::Result<Self> {
+        Self::load_with(path, retention, LoadOptions::new())
+    }
+    /// Restores a brain saved with [`Brain::save_binary_with`], or loads
+    /// snapshots with other options.
+    pub fn load_with(
+        path: impl AsRef<Path>,
+        retention: Duration,
+        options: LoadOptions<'_>,
+    ) -> io::Result<Self> {
+        Self::load_into(path, retention, HashMap::new(), options)
+    }
+}
+impl<T: DeserializeOwned, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Like [`Brain::load_with`], restoring the engrams into the storage in
+    /// the order the snapshot holds them, so an [`InsertionOrdered`] one
+    /// keeps the order it was saved in.
+    ///
+    /// [`InsertionOrdered`]: crate::InsertionOrdered
+    pub fn load_into(
+        path: impl AsRef<Path>,
+        retention: Duration,
+        storage: S,
+        options: LoadOptions<'_>,
+    ) -> io::Result<Self> {
+        let engrams = read_snapshot(path.as_ref(), options)?;
+        let now = wall_clock::now();
+        let brain = Brain::with_storage(retention, storage);
+        {
+            let mut memory = brain.memory.write();
+            for (key, engram) in engrams {
+                if let Some(engram) = options.restore(engram, retention, now) {
+                    memory.put(key, engram);
+                }
+            }
+        }
+        brain.dirty.take();
+        Ok(brain)
+    }
+}
+
+impl<T: DeserializeOwned, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Applies a delta saved with [`Brain::save_delta_to`] on top of the
+    /// snapshot it was taken of.
+    ///
+    /// Deltas have to be applied in the order they were saved.
+    pub fn apply_delta_from(
+        &self,
+        path: impl AsRef<Path>,
+        options: LoadOptions<'_>,
+    ) -> io::Result<()> {
+        let changes = read_delta::<T>(&std::fs::read(path)?, options)?;
+        let now = wall_clock::now();
+        let mut memory = self.memory.write();
+        for (key, engram) in changes {
+            match engram.and_then(|engram| options.restore(engram, self.retention, now)) {
+                Some(engram) => {
+                    self.filter_key(&key);
+                    memory.put(key, engram);
+                }
+                None => {
+                    memory.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + Serialize + 'static> MaintenanceConfig<T> {
+    /// Saves a snapshot every interval, see [`Brain::save_to`].
+    ///
+    /// A failed snapshot is retried at the next interval.
+    pub fn snapshot_every(self, interval: Duration, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.every(interval, move |brain| {
+            let _ = brain.save_to(&path);
+        })
+    }
+}
+
+impl<T, S> BrainBuilder<T, S> {
+    /// Chooses how far snapshots are flushed, syncing the file by default.
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+}
+impl<T, S> BrainBuilder<T, S>
+where
+    T: Serialize + Send + Sync + 'static,
+    S: StorageBackend<String, T> + Send + Sync + 'static,
+{
+    /// Saves a binary snapshot when the last handle of the brain built is
+    /// dropped, see [`Brain::snapshot_on_drop`].
+    ///
+    /// Choose the storage before, [`storage`](Self::storage) and
+    /// [`hasher`](Self::hasher) don't keep this setting.
+    pub fn snapshot_on_drop(mut self, path: impl Into<PathBuf>, timeout: Duration) -> Self {
+        let path = path.into();
+        self.on_drop = Some(Box::new(move |brain| brain.snapshot_on_drop(path, timeout)));
+        self
+    }
+}
+
+/// Writes the contents next to the file and renames them over it.
+///
+/// Every write has a temporary file of its own, so concurrent writes of
+/// the same file, even by several processes, don't write into each other.
+pub(crate) fn write_atomically(
+    path: &Path,
+    contents: &[u8],
+    durability: Durability,
+) -> io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let mut temporary = OsString::from(path.as_os_str());
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    temporary.push(format!(".{}.{write}.tmp", std::process::id()));
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(contents)?;
+        if durability >= Durability::SyncFile {
+            file.sync_all()?;
+        }
+        Ok(())
+    });
+    if let Err(error) = written {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(error);
+    }
+    std::fs::rename(&temporary, path)?;
+    #[cfg(unix)]
+    if durability == Durability::SyncDirectory {
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(directory)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Reads the engrams of a JSON or binary snapshot in their order, expired
+/// ones included.
+pub(crate) fn read_snapshot<T: DeserializeOwned>(
+    path: &Path,
+    options: LoadOptions<'_>,
+) -> io::Result<Engrams<T>> {
+    let contents = std::fs::read(path)?;
+    if contents.starts_with(MAGIC) {
+        return read_engrams(&contents, options);
+    }
+    let text = std::str::from_utf8(&contents).map_err(invalid_data)?;
+    Ok(into_engrams(
+        json::from_str::<Brain<T, InsertionOrdered<T>>>(text).map_err(invalid_data)?,
+    ))
+}
+
+/// Decodes the engrams of a binary snapshot of any version in their order,
+/// snapshots before version 3 having none.
+pub(crate) fn read_engrams<T: DeserializeOwned>(
+    contents: &[u8],
+    options: LoadOptions<'_>,
+) -> io::Result<Engrams<T>> {
+    match read_binary(contents, options)? {
+        (1 | 2, payload) => binary::from_slice::<(Duration, HashMap<String, Legacy<T>>)>(&payload)
+            .map(|(_, engrams)| {
+                engrams
+                    .into_iter()
+                    .map(|(key, Legacy(engram))| (key, engram))
+                    .collect()
+            })
+            .map_err(invalid_data),
+        (3, payload) => Ok(records(&payload, options.skip_corrupt)?
+            .into_iter()
+            .map(|(key, Legacy::<T>(engram))| (key, engram))
+            .collect()),
+        (4, payload) => Ok(records(&payload, options.skip_corrupt)?
+            .into_iter()
+            .map(|(key, Legacy::<T, 4>(engram))| (key, engram))
+            .collect()),
+        (_, payload) => Ok(records(&payload, options.skip_corrupt)?
+            .into_iter()
+            .collect()),
+    }
+}
+
+/// Decodes the changes of a delta, `None` for forgotten keys.
+pub(crate) fn read_delta<T: DeserializeOwned>(
+    contents: &[u8],
+    options: LoadOptions<'_>,
+) -> io::Result<Vec<(String, Option<Engram<T>>)>> {
+    if !contents.starts_with(DELTA_MAGIC) {
+        return Err(invalid_data("not a snapshot delta"));
+    }
+    match read_binary(contents, options)? {
+        (3, payload) => Ok(records(&payload, options.skip_corrupt)?
+            .into_iter()
+            .map(|(key, engram): (String, Option<Legacy<T>>)| {
+                (key, engram.map(|Legacy(engram)| engram))
+            })
+            .collect()),
+        (4, payload) => Ok(records(&payload, options.skip_corrupt)?
+            .into_iter()
+            .map(|(key, engram): (String, Option<Legacy<T, 4>>)| {
+                (key, engram.map(|Legacy(engram)| engram))
+            })
+            .collect()),
+        (_, payload) => records(&payload, options.skip_corrupt),
+    }
+}
+
+/// Appends a record prefixed by its length and checksum.
+pub(crate) fn record(payload: &mut Vec<u8>, record: &impl Serialize) -> io::Result<()> {
+    let record = binary::to_vec(record).map_err(invalid_data)?;
+    let record_len = u32::try_from(record.len()).map_err(|_| invalid_data("engram too large"))?;
+    payload.extend(record_len.to_le_bytes());
+    payload.extend(crc32(&record).to_le_bytes());
+    payload.extend(record);
+    Ok(())
+}
+
+/// Prepends the header to the encoded payload and appends the checksum of the file.
+pub(crate) fn encode_binary(
+    magic: &[u8; 8],
+    codec: Option<&dyn SnapshotCodec>,
+    payload: Vec<u8>,
+) -> io::Result<Vec<u8>> {
+    let name = codec.map_or("", |codec| codec.name());
+    let name_len = u8::try_from(name.len()).map_err(|_| invalid_data("codec name too long"))?;
+    let mut contents = magic.to_vec();
+    contents.extend(VERSION.to_le_bytes());
+    contents.push(name_len);
+    contents.extend(name.as_bytes());
+    let payload = match codec {
+        Some(codec) => codec.encode(&contents, payload)?,
+        None => payload,
+    };
+    contents.extend(payload);
+    contents.extend(crc32(&contents).to_le_bytes());
+    Ok(contents)
+}
+
+/// Checks the header and the checksum, returning the version and the decoded payload.
+pub(crate) fn read_binary(contents: &[u8], options: LoadOptions<'_>) -> io::Result<(u16, Vec<u8>)> {
+    let truncated = || invalid_data("truncated snapshot header");
+    let (version, rest) = contents[MAGIC.len()..]
+        .split_first_chunk::<2>()
+        .ok_or_else(truncated)?;
+    let version = u16::from_le_bytes(*version);
+    if version == 1 {
+        return Ok((version, rest.to_vec()));
+    }
+    if version > VERSION {
+        return Err(invalid_data(format!(
+            "unsupported snapshot version {version}"
+        )));
+    }
+    let (name_len, rest) = rest.split_first().ok_or_else(truncated)?;
+    let name = rest.get(..usize::from(*name_len)).ok_or_else(truncated)?;
+    let (header, body) = contents.split_at(MAGIC.len() + 3 + name.len());
+    let encoded = if version == 2 {
+        body
+    } else {
+        let (encoded, checksum) = body.split_last_chunk::<4>().ok_or_else(truncated)?;
+        let intact = crc32(&contents[..contents.len() - 4]) == u32::from_le_bytes(*checksum);
+        if !intact && !options.skip_corrupt {
+            return Err(invalid_data("snapshot checksum mismatch"));
+        }
+        encoded
+    };
+    let payload = match options.codec {
+        _ if name.is_empty() => encoded.to_vec(),
+        Some(codec) if codec.name().as_bytes() == name => codec.decode(header, encoded.to_vec())?,
+        _ => {
+            let name = String::from_utf8_lossy(name);
+            return Err(invalid_data(format!("snapshot needs the {name} codec")));
+        }
+    };
+    Ok((version, payload))
+}
+
+/// Decodes checksummed records, each prefixed by its length and checksum.
+fn records<R: DeserializeOwned>(mut payload: &[u8], skip_corrupt: bool) -> io::Result<Vec<R>> {
+    let mut records = Vec::new();
+    while let Some((head, rest)) = payload.split_first_chunk::<8>() {
+        let (record_len, checksum) = head.split_at(4);
+        let record_len = u32::from_le_bytes(record_len.try_into().expect("4 bytes")) as usize;
+        let checksum = u32::from_le_bytes(checksum.try_into().expect("4 bytes"));
+        let Some(record) = rest.get(..record_len) else {
+            break;
+        };
+        payload = &rest[record_len..];
+        let decoded = (crc32(record) == checksum)
+            .then(|| binary::from_slice(record).ok())
+            .flatten();
+        match decoded {
+            Some(decoded) => records.push(decoded),
+            None if skip_corrupt => {}
+            None => return Err(invalid_data("corrupt snapshot record")),
+        }
+    }
+    if !payload.is_empty() && !skip_corrupt {
+        return Err(invalid_data("truncated snapshot record"));
+    }
+    Ok(records)
+}
+
+pub(crate) fn into_engrams<T>(brain: Brain<T, InsertionOrdered<T>>) -> Engrams<T> {
+    std::mem::take(&mut *brain.memory.write())
+        .into_entries()
+        .collect()
+}
+
+pub(crate) fn invalid_data(
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    fn temporary(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("brain-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temporary("save");
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        memory.memoize_for("b", 6, 1.milliseconds());
+        memory.save_to(&path).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let restored = Brain::<i32>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.retrieve("a"), Some(3));
+        assert!(!restored.is_cached("b"));
+        assert_eq!(
+            restored.memory.read()["a"].memoized,
+            memory.memory.read()["a"].memoized
+        );
+    }
+
+    #[test]
+    fn insertion_order() {
+        let keys = (0..50)
+            .map(|n| format!("k{}", (n * 7) % 50))
+            .collect::<Vec<_>>();
+        let memory = Brain::with_storage(1.minutes(), InsertionOrdered::default());
+        for key in &keys {
+            memory.memoize(key, 1);
+        }
+        let order = |brain: &Brain<i32, InsertionOrdered<i32>>| {
+            let memory = brain.memory.read();
+            memory
+                .scan()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>()
+        };
+        let (json, binary) = (temporary("ordered"), temporary("ordered-binary"));
+        memory.save_to(&json).unwrap();
+        memory.save_binary_to(&binary).unwrap();
+
+        for path in [json, binary] {
+            let storage = InsertionOrdered::default();
+            let restored = Brain::load_into(&path, 1.minutes(), storage, LoadOptions::new());
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(order(&restored.unwrap()), keys);
+        }
+    }
+
+    #[test]
+    fn snapshot_every() {
+        let path = temporary("periodic");
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+
+        let maintenance = memory
+            .start_maintenance(MaintenanceConfig::new().snapshot_every(1.milliseconds(), &path));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        maintenance.shutdown().unwrap();
+
+        let restored = Brain::<i32>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(3));
+    }
+
+    #[test]
+    fn binary() {
+        let path = temporary("binary");
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", Some("x".to_string()));
+        memory.memoize_for("b", None, 2.minutes());
+        memory.save_binary_to(&path).unwrap();
+
+        let restored = Brain::<Option<String>>::load_from(&path, 1.minutes()).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(Some("x".to_string())));
+        assert_eq!(restored.retrieve("b"), Some(None));
+        assert_eq!(restored.memory.read()["b"].retention, Some(2.minutes()));
+
+        let mut contents = std::fs::read(&path).unwrap();
+        contents[MAGIC.len()] = 9;
+        std::fs::write(&path, contents).unwrap();
+        let restored = Brain::<Option<String>>::load_from(&path, 1.minutes());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            restored.err().unwrap().to_string(),
+            "unsupported snapshot version 9"
+        );
+    }
+
+    struct Reverse;
+    impl SnapshotCodec for Reverse {
+        fn name(&self) -> &str {
+            "reverse"
+        }
+        fn encode(&self, header: &[u8], mut payload: Vec<u8>) -> io::Result<Vec<u8>> {
+            assert!(header.starts_with(MAGIC));
+            payload.reverse();
+            Ok(payload)
+        }
+        fn decode(&self, header: &[u8], encoded: Vec<u8>) -> io::Result<Vec<u8>> {
+            self.encode(header, encoded)
+        }
+    }
+
+    #[test]
+    fn codec() {
+        let path = temporary("codec");
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 3);
+        memory.save_binary_with(&path, &Reverse).unwrap();
+
+        let plain = Brain::<i32>::load_from(&path, 1.minutes());
+        assert_eq!(
+            plain.err().unwrap().to_string(),
+            "snapshot needs the reverse codec"
+        );
+        let restored =
+            Brain::<i32>::load_with(&path, 1.minutes(), LoadOptions::new().codec(&Reverse))
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(3));
+    }
+
+    #[test]
+    fn corruption() {
+        let path = temporary("corrupt");
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", "x".repeat(20));
+        memory.memoize("b", "y".repeat(20));
+        memory.save_binary_to(&path).unwrap();
+
+        let mut contents = std::fs::read(&path).unwrap();
+        let position = contents
+            .windows(20)
+            .position(|window| window == "x".repeat(20).as_bytes());
+        contents[position.unwrap()] = b'z';
+        std::fs::write(&path, contents).unwrap();
+
+        let strict = Brain::<String>::load_from(&path, 1.minutes());
+        assert_eq!(
+            strict.err().unwrap().to_string(),
+            "snapshot checksum mismatch"
+        );
+        let lenient =
+            Brain::<String>::load_with(&path, 1.minutes(), LoadOptions::new().skip_corrupt());
+        std::fs::remove_file(&path).unwrap();
+        let lenient = lenient.unwrap();
+        assert!(!lenient.is_cached("a"));
+        assert_eq!(lenient.retrieve("b"), Some("y".repeat(20)));
+    }
+
+    #[test]
+    fn delta() {
+        let (base, delta) = (temporary("base"), temporary("delta"));
+        let memory = Brain::new(1.minutes());
+        assert!(memory.save_delta_to(&delta).is_err());
+        memory.memoize("a", 1);
+        memory.memoize_for("b", 2, 1.milliseconds());
+        memory.save_binary_to(&base).unwrap();
+
+        memory.memoize("c", 3);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.forget();
+        // failed saves keep the keys for the next delta
+        let unwritable = base.join("missing");
+        assert!(memory.save_binary_to(&unwritable).is_err());
+        assert!(memory.save_delta_to(&unwritable).is_err());
+        memory.save_delta_to(&delta).unwrap();
+
+        let restored = Brain::<i32>::load_from(&base, 1.minutes()).unwrap();
+        restored
+            .apply_delta_from(&delta, LoadOptions::new())
+            .unwrap();
+        std::fs::remove_file(&base).unwrap();
+        std::fs::remove_file(&delta).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(1));
+        assert!(!restored.is_cached("b"));
+        assert_eq!(restored.retrieve("c"), Some(3));
+    }
+
+    #[test]
+    fn snapshot_on_drop() {
+        let path = temporary("drop");
+        let memory = Brain::new(1.minutes()).snapshot_on_drop(&path, 1.seconds());
+        let handle = memory.clone();
+        memory.memoize("a", 3);
+        drop(memory);
+        assert!(!path.exists());
+
+        drop(handle);
+        let restored = Brain::<i32>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(3));
+
+        let ordered = Brain::builder()
+            .retention(1.minutes())
+            .storage(InsertionOrdered::default())
+            .snapshot_on_drop(&path, 1.seconds())
+            .build();
+        ordered.memoize("b", 2);
+        ordered.memoize("a", 1);
+        drop(ordered);
+        let restored = Brain::<i32>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(1));
+        assert_eq!(restored.retrieve("b"), Some(2));
+    }
+
+    #[test]
+    fn durability() {
+        let path = temporary("durable");
+        let memory = Brain::builder()
+            .retention(1.minutes())
+            .durability(Durability::SyncDirectory)
+            .build();
+        memory.memoize("a", 1);
+        memory.save_binary_to(&path).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let leftovers = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|file| file.starts_with(name) && file != name);
+        assert_eq!(leftovers.count(), 0);
+
+        let restored = Brain::<i32>::load_from(&path, 1.minutes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.retrieve("a"), Some(1));
+    }
+
+    #[test]
+    fn max_remaining() {
+        let path = temporary("capped");
+        let memory = Brain::new(1.hours());
+        memory.memoize("a", 1);
+        memory.memoize_for("b", 2, 1.seconds());
+        memory.save_binary_to(&path).unwrap();
+
+        let options = LoadOptions::new().max_remaining(1.minutes());
+        let restored = Brain::<i32>::load_with(&path, 1.hours(), options).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let restored = restored.memory.read();
+        let remaining = |key: &str| {
+            let engram = &restored[key];
+            engram.memoized + engram.retention.unwrap() - wall_clock::now()
+        };
+        assert!(remaining("a") <= 1.minutes() && remaining("a") > 59.seconds());
+        assert_eq!(restored["b"].retention, Some(1.seconds()));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{BinaryCodec, Brain, BrainHooks, Duration, EvictionCause, Memory, ValueCodec};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone)]
+enum Slot<T> {
+    Inline(T),
+    Spilled(PathBuf),
+}
+
+/// Removes the files of spilled values once they're forgotten.
+struct RemoveSpilled;
+impl<T> BrainHooks<Slot<T>> for RemoveSpilled {
+    fn on_evict(&self, _key: &str, slot: &Slot<T>, _cause: EvictionCause) {
+        if let Slot::Spilled(path) = slot {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// A brain keeping large values in files instead of memory.
+///
+/// Values taking more than the threshold when encoded, by [`BinaryCodec`]
+/// unless created [`with_codec`](Self::with_codec), are written to the
+/// spill directory and read back on retrieval. If a value can't be
+/// written, it's kept in memory. Dropping the brain removes its files.
+pub struct Spillover<T, C = BinaryCodec> {
+    brain: Brain<Slot<T>>,
+    directory: PathBuf,
+    threshold: usize,
+    codec: C,
+    spilled: AtomicU64,
+}
+impl<T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static> Spillover<T> {
+    /// Creates the spill directory unless it exists.
+    pub fn new(
+        retention: Duration,
+        directory: impl Into<PathBuf>,
+        threshold: usize,
+    ) -> io::Result<Self> {
+        Self::with_codec(retention, directory, threshold, BinaryCodec)
+    }
+}
+impl<T: Clone + Send + Sync + 'static, C: ValueCodec<T>> Spillover<T, C> {
+    /// Encodes values with the codec to weigh and spill them.
+    pub fn with_codec(
+        retention: Duration,
+        directory: impl Into<PathBuf>,
+        threshold: usize,
+        codec: C,
+    ) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        let brain = Brain::new(retention);
+        brain.set_hooks(RemoveSpilled);
+        Ok(Self {
+            brain,
+            directory,
+            threshold,
+            codec,
+            spilled: AtomicU64::new(0),
+        })
+    }
+    /// Tells whether the value for the key lives in a file.
+    pub fn is_spilled(&self, key: &str) -> bool {
+        matches!(
+            self.brain
+                .memory
+                .read()
+                .get(key)
+                .map(|engram| &engram.value),
+            Some(Slot::Spilled(_))
+        )
+    }
+    fn spill(&self, value: &T) -> Option<PathBuf> {
+        let bytes = self.codec.encode(value).ok()?;
+        if bytes.len() <= self.threshold {
+            return None;
+        }
+        let file = format!("{}.spill", self.spilled.fetch_add(1, Ordering::Relaxed));
+        let path = self.directory.join(file);
+        std::fs::write(&path, bytes).ok()?;
+        Some(path)
+    }
+}
+impl<T: Clone + Send + Sync + 'static, C: ValueCodec<T>> Memory<T> for Spillover<T, C> {
+    fn memoize(&self, key: &str, value: T) {
+        let slot = match self.spill(&value) {
+            Some(path) => Slot::Spilled(path),
+            None => Slot::Inline(value),
+        };
+        let replaced = self
+            .brain
+            .memory
+            .write()
+            .insert(key.to_string(), crate::Engram::new(slot, None));
+        self.brain.stats.inserted();
+        if let Some(Slot::Spilled(path)) = replaced.map(|engram| engram.value) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        match self.brain.retrieve(key)? {
+            Slot::Inline(value) => Some(value),
+            Slot::Spilled(path) => self.codec.decode(&std::fs::read(path).ok()?).ok(),
+        }
+    }
+    fn forget(&self) {
+        self.brain.forget();
+    }
+}
+impl<T, C> Drop for Spillover<T, C> {
+    fn drop(&mut self) {
+        for engram in self.brain.memory.read().values() {
+            if let Slot::Spilled(path) = &engram.value {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn spillover() {
+        let directory = std::env::temp_dir().join(format!("brain-{}-spill", std::process::id()));
+        let memory = Spillover::new(1.milliseconds(), &directory, 16).unwrap();
+        memory.memoize("small", "x".to_string());
+        memory.memoize("large", "y".repeat(100));
+        assert!(!memory.is_spilled("small"));
+        assert!(memory.is_spilled("large"));
+        assert_eq!(memory.retrieve("large"), Some("y".repeat(100)));
+
+        memory.memoize("large", "z".repeat(100));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.forget();
+        assert_eq!(memory.retrieve("large"), None);
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
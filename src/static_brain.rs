@@ -0,0 +1,193 @@
+use crate::{Lock, Memory, SpinLock};
+use core::fmt;
+
+/// Given for keys longer than a [`StaticBrain`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyTooLong {
+    /// Most bytes a key may have.
+    pub max: usize,
+}
+impl fmt::Display for KeyTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key longer than {} bytes", self.max)
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for KeyTooLong {}
+
+struct Slot<T, const K: usize> {
+    key: [u8; K],
+    len: usize,
+    value: T,
+    memoized: u64,
+}
+impl<T, const K: usize> Slot<T, K> {
+    fn key(&self) -> &[u8] {
+        &self.key[..self.len]
+    }
+    /// Ticks since the slot was memoized, zero if the source went back.
+    fn age(&self, now: u64) -> u64 {
+        now.saturating_sub(self.memoized)
+    }
+}
+
+/// A brain of up to `N` entries with keys of up to `K` bytes, e.g. for
+/// embedded targets, allocating nothing after its creation.
+///
+/// Entries live in an array scanned by every operation, so each takes time
+/// proportional to `N` at worst, whatever was memoized before. A full brain
+/// replaces its oldest entry. Like a [`TickBrain`], it times retention by
+/// ticks and retrieves expired values until forgetting them.
+///
+/// [`TickBrain`]: crate::TickBrain
+pub struct StaticBrain<T, const N: usize, const K: usize = 32> {
+    retention: u64,
+    ticks: fn() -> u64,
+    slots: SpinLock<[Option<Slot<T, K>>; N]>,
+}
+impl<T, const N: usize, const K: usize> StaticBrain<T, N, K> {
+    /// A brain keeping values for the retention in ticks of the source.
+    pub fn new(retention: u64, ticks: fn() -> u64) -> Self {
+        Self {
+            retention,
+            ticks,
+            slots: SpinLock::new(core::array::from_fn(|_| None)),
+        }
+    }
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    pub fn len(&self) -> usize {
+        self.slots.with(|slots| slots.iter().flatten().count())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Memoizes a value unless the key is too long, replacing the oldest
+    /// entry of a full brain.
+    pub fn try_memoize(&self, key: &str, value: T) -> Result<(), KeyTooLong> {
+        let bytes = key.as_bytes();
+        if bytes.len() > K {
+            return Err(KeyTooLong { max: K });
+        }
+        let now = (self.ticks)();
+        let mut slot = Slot {
+            key: [0; K],
+            len: bytes.len(),
+            value,
+            memoized: now,
+        };
+        slot.key[..bytes.len()].copy_from_slice(bytes);
+        self.slots.with(|slots| {
+            // the entry of the key, else a free slot, else an expired entry,
+            // else the oldest entry
+            let rank = |slot: &Option<Slot<T, K>>| match slot {
+                Some(slot) if slot.key() == bytes => (3, 0),
+                None => (2, 0),
+                Some(slot) if slot.age(now) > self.retention => (1, slot.age(now)),
+                Some(slot) => (0, slot.age(now)),
+            };
+            if let Some(at) = (0..N).max_by_key(|at| rank(&slots[*at])) {
+                slots[at] = Some(slot);
+            }
+        });
+        Ok(())
+    }
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.slots.with(|slots| {
+            let slot = slots.iter_mut().find(|slot| {
+                slot.as_ref()
+                    .is_some_and(|slot| slot.key() == key.as_bytes())
+            })?;
+            slot.take().map(|slot| slot.value)
+        })
+    }
+}
+/// Values memoized under keys too long are dropped, see
+/// [`StaticBrain::try_memoize`].
+impl<T: Clone, const N: usize, const K: usize> Memory<T> for StaticBrain<T, N, K> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.slots.with(|slots| {
+            slots
+                .iter()
+                .flatten()
+                .find(|slot| slot.key() == key.as_bytes())
+                .map(|slot| slot.value.clone())
+        })
+    }
+    fn forget(&self) {
+        let now = (self.ticks)();
+        self.slots.with(|slots| {
+            for slot in slots.iter_mut() {
+                if slot
+                    .as_ref()
+                    .is_some_and(|slot| slot.age(now) > self.retention)
+                {
+                    *slot = None;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn now() -> u64 {
+        NOW.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn static_brain() {
+        let brain = StaticBrain::<u32, 2, 4>::new(10, now);
+        brain.memoize("a", 1);
+        NOW.store(5, Ordering::Relaxed);
+        brain.memoize("b", 2);
+        NOW.store(6, Ordering::Relaxed);
+        brain.memoize("a", 3);
+        assert_eq!(brain.try_memoize("long key", 0), Err(KeyTooLong { max: 4 }));
+        assert_eq!((brain.len(), brain.retrieve("a")), (2, Some(3)));
+
+        // full, so the oldest entry makes room
+        NOW.store(7, Ordering::Relaxed);
+        brain.memoize("c", 4);
+        assert_eq!(brain.retrieve("b"), None);
+        assert_eq!(brain.retrieve("a"), Some(3));
+
+        NOW.store(17, Ordering::Relaxed);
+        brain.forget();
+        assert_eq!(brain.retrieve("a"), None);
+        assert_eq!(brain.remove("c"), Some(4));
+        assert!(brain.is_empty());
+    }
+
+    #[test]
+    fn clock_going_back() {
+        static TICKS: AtomicU64 = AtomicU64::new(100);
+        let ticks = || TICKS.load(Ordering::Relaxed);
+        let brain = StaticBrain::<u32, 3, 4>::new(10, ticks);
+        brain.memoize("a", 1);
+        TICKS.store(50, Ordering::Relaxed);
+        brain.memoize("b", 2);
+        brain.forget();
+        assert_eq!(brain.retrieve("a"), Some(1));
+
+        // a free slot goes first, however far back the ticks went
+        TICKS.store(55, Ordering::Relaxed);
+        brain.memoize("c", 3);
+        assert_eq!(brain.len(), 3);
+        // then the oldest expired entry, not the one from the future
+        TICKS.store(70, Ordering::Relaxed);
+        brain.memoize("d", 4);
+        assert_eq!(brain.retrieve("b"), None);
+        assert_eq!(brain.retrieve("a"), Some(1));
+        assert_eq!(brain.retrieve("c"), Some(3));
+    }
+}
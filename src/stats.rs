@@ -0,0 +1,342 @@
+use crate::wall_clock;
+use crate::wall_clock::Instant;
+use crate::{Brain, Duration, StorageBackend};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bounds of the sweep duration buckets, in seconds.
+pub(crate) const SWEEP_BUCKETS: [f64; 6] = [0.000_1, 0.001, 0.01, 0.1, 1.0, 10.0];
+
+/// Counters of a brain's operations since creation or the last reset.
+///
+/// Laid out as in C, for the `ffi` feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct BrainStats {
+    /// Retrievals finding a value.
+    pub hits: u64,
+    /// Retrievals finding no value.
+    pub misses: u64,
+    /// Memoized values, including overwrites.
+    pub insertions: u64,
+    /// Entries removed by forgetting.
+    pub evictions: u64,
+    /// Entries removed to make room in a full brain.
+    pub capacity_evictions: u64,
+    /// Hits on entries past their retention that weren't forgotten yet.
+    pub expired_on_read: u64,
+}
+impl BrainStats {
+    /// Share of retrievals finding a value, zero without any retrievals.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            retrievals => self.hits as f64 / retrievals as f64,
+        }
+    }
+    fn since(&self, earlier: &BrainStats) -> BrainStats {
+        BrainStats {
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+            insertions: self.insertions.saturating_sub(earlier.insertions),
+            evictions: self.evictions.saturating_sub(earlier.evictions),
+            capacity_evictions: self
+                .capacity_evictions
+                .saturating_sub(earlier.capacity_evictions),
+            expired_on_read: self.expired_on_read.saturating_sub(earlier.expired_on_read),
+        }
+    }
+}
+impl std::iter::Sum for BrainStats {
+    fn sum<I: Iterator<Item = BrainStats>>(stats: I) -> Self {
+        stats.fold(BrainStats::default(), |sum, stats| BrainStats {
+            hits: sum.hits + stats.hits,
+            misses: sum.misses + stats.misses,
+            insertions: sum.insertions + stats.insertions,
+            evictions: sum.evictions + stats.evictions,
+            capacity_evictions: sum.capacity_evictions + stats.capacity_evictions,
+            expired_on_read: sum.expired_on_read + stats.expired_on_read,
+        })
+    }
+}
+
+/// Changes of a brain's counters over a span of time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatsWindow {
+    pub delta: BrainStats,
+    pub elapsed: std::time::Duration,
+}
+impl StatsWindow {
+    pub fn hits_per_second(&self) -> f64 {
+        self.per_second(self.delta.hits)
+    }
+    pub fn misses_per_second(&self) -> f64 {
+        self.per_second(self.delta.misses)
+    }
+    pub fn insertions_per_second(&self) -> f64 {
+        self.per_second(self.delta.insertions)
+    }
+    pub fn evictions_per_second(&self) -> f64 {
+        self.per_second(self.delta.evictions)
+    }
+    fn per_second(&self, count: u64) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            seconds if seconds > 0.0 => count as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+struct Window {
+    started: Instant,
+    stats: BrainStats,
+}
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            stats: BrainStats::default(),
+        }
+    }
+}
+
+/// Retrievals of a single key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HotKey {
+    pub key: String,
+    /// Retrievals finding a value since it was memoized.
+    pub hits: u64,
+    /// Time since the value was memoized.
+    pub age: Duration,
+}
+
+#[derive(Default)]
+pub(crate) struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    insertions: AtomicU64,
+    evictions: AtomicU64,
+    capacity_evictions: AtomicU64,
+    expired_on_read: AtomicU64,
+    sweeps: [AtomicU64; SWEEP_BUCKETS.len()],
+    sweep_count: AtomicU64,
+    sweep_nanos: AtomicU64,
+    window: Mutex<Window>,
+}
+impl Stats {
+    pub(crate) fn inserted(&self) {
+        self.insertions.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Counts a retrieval, `None` being a miss and `Some(true)` an expired hit.
+    pub(crate) fn retrieved(&self, expired: Option<bool>) {
+        match expired {
+            Some(expired) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                if expired {
+                    self.expired_on_read.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+    pub(crate) fn evicted_for_capacity(&self) {
+        self.capacity_evictions.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Counts engrams forgotten outside of sweeps.
+    pub(crate) fn expired(&self, removed: usize) {
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+    }
+    pub(crate) fn swept(&self, removed: usize, elapsed: std::time::Duration) {
+        self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = SWEEP_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.sweeps[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sweep_count.fetch_add(1, Ordering::Relaxed);
+        self.sweep_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+    fn snapshot(&self) -> BrainStats {
+        BrainStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            insertions: self.insertions.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            capacity_evictions: self.capacity_evictions.load(Ordering::Relaxed),
+            expired_on_read: self.expired_on_read.load(Ordering::Relaxed),
+        }
+    }
+    fn reset(&self) {
+        [
+            &self.hits,
+            &self.misses,
+            &self.insertions,
+            &self.evictions,
+            &self.capacity_evictions,
+            &self.expired_on_read,
+            &self.sweep_count,
+            &self.sweep_nanos,
+        ]
+        .into_iter()
+        .chain(&self.sweeps)
+        .for_each(|counter| counter.store(0, Ordering::Relaxed));
+        *self.window.lock() = Window::default();
+    }
+}
+
+/// Point-in-time values of a brain's counters and gauges.
+#[cfg(feature = "time")]
+pub(crate) struct Sample {
+    pub(crate) stats: BrainStats,
+    pub(crate) entries: usize,
+    pub(crate) bytes: usize,
+    /// Sweeps per duration bucket, not cumulative.
+    pub(crate) sweeps: [u64; SWEEP_BUCKETS.len()],
+    pub(crate) sweep_count: u64,
+    pub(crate) sweep_seconds: f64,
+}
+
+impl<T, S: StorageBackend<String, T>> Brain<T, S> {
+    pub fn stats(&self) -> BrainStats {
+        self.stats.snapshot()
+    }
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+    /// Gives the changes of the counters since the previous call, or since
+    /// creation or the last reset for the first one.
+    pub fn stats_window(&self) -> StatsWindow {
+        let mut window = self.stats.window.lock();
+        let stats = self.stats.snapshot();
+        let started = Instant::now();
+        let previous = std::mem::replace(&mut *window, Window { started, stats });
+        StatsWindow {
+            delta: stats.since(&previous.stats),
+            elapsed: started - previous.started,
+        }
+    }
+    /// Lists the `n` most retrieved keys, the most retrieved first.
+    ///
+    /// Hits are counted per memoized value, so memoizing a key again starts
+    /// its count over.
+    pub fn top_keys(&self, n: usize) -> Vec<HotKey> {
+        let now = wall_clock::now();
+        let mut keys = self
+            .memory
+            .read()
+            .scan()
+            .filter(|(_, engram)| self.is_context_active(engram))
+            .map(|(key, engram)| HotKey {
+                key: key.clone(),
+                hits: engram.hits.load(Ordering::Relaxed),
+                age: now - engram.memoized,
+            })
+            .collect::<Vec<_>>();
+        keys.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.key.cmp(&b.key)));
+        keys.truncate(n);
+        keys
+    }
+    #[cfg(feature = "time")]
+    pub(crate) fn sample(&self) -> Sample {
+        let (entries, bytes) = {
+            let memory = self.memory.read();
+            let keys = memory.scan().map(|(key, _)| key.capacity()).sum::<usize>();
+            (
+                memory.len(),
+                memory.len() * std::mem::size_of::<(String, crate::Engram<T>)>() + keys,
+            )
+        };
+        let stats = &self.stats;
+        Sample {
+            stats: stats.snapshot(),
+            entries,
+            bytes,
+            sweeps: std::array::from_fn(|bucket| stats.sweeps[bucket].load(Ordering::Relaxed)),
+            sweep_count: stats.sweep_count.load(Ordering::Relaxed),
+            sweep_seconds: stats.sweep_nanos.load(Ordering::Relaxed) as f64 / 1e9,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn stats() {
+        let memory = Brain::new(3.milliseconds());
+
+        memory.memoize("a", 3);
+        memory.memoize("a", 4);
+        memory.retrieve("a");
+        memory.retrieve("b");
+        std::thread::sleep(std::time::Duration::from_millis(4));
+        memory.retrieve("a");
+        memory.forget();
+
+        let stats = memory.stats();
+        assert_eq!(
+            stats,
+            BrainStats {
+                hits: 2,
+                misses: 1,
+                insertions: 2,
+                evictions: 1,
+                capacity_evictions: 0,
+                expired_on_read: 1,
+            }
+        );
+        assert!((stats.hit_ratio() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        memory.reset_stats();
+        assert_eq!(memory.stats(), BrainStats::default());
+        assert_eq!(memory.stats().hit_ratio(), 0.0);
+    }
+
+    #[test]
+    fn stats_window() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 1);
+        memory.retrieve("a");
+
+        let window = memory.stats_window();
+        assert_eq!(window.delta.insertions, 1);
+        assert_eq!(window.delta.hits, 1);
+
+        memory.retrieve("a");
+        memory.retrieve("a");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let window = memory.stats_window();
+        assert_eq!(window.delta.insertions, 0);
+        assert_eq!(window.delta.hits, 2);
+        assert!(window.elapsed >= std::time::Duration::from_millis(2));
+        assert!(window.hits_per_second() > 0.0);
+        assert_eq!(window.misses_per_second(), 0.0);
+    }
+
+    #[test]
+    fn top_keys() {
+        let memory = Brain::new(1.minutes());
+        memory.memoize("a", 1);
+        memory.memoize("b", 2);
+        memory.memoize("c", 3);
+        for key in ["b", "b", "c", "b", "c", "a", "d"] {
+            memory.retrieve(key);
+        }
+
+        let top = memory.top_keys(2);
+        assert_eq!(
+            top.iter()
+                .map(|hot| (hot.key.as_str(), hot.hits))
+                .collect::<Vec<_>>(),
+            vec![("b", 3), ("c", 2)]
+        );
+        assert!(top[0].age >= Duration::ZERO);
+
+        memory.memoize("b", 4);
+        assert_eq!(memory.top_keys(1)[0].key, "c");
+    }
+}
@@ -0,0 +1,273 @@
+use crate::Engram;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+/// Stores the engrams of a brain, a `HashMap` by default.
+///
+/// The brain locks its storage, so implementations don't synchronize
+/// themselves.
+pub trait StorageBackend<K, T> {
+    fn get(&self, key: &str) -> Option<&Engram<T>>;
+    /// Stores an engram, giving back the one it replaces.
+    fn put(&mut self, key: K, engram: Engram<T>) -> Option<Engram<T>>;
+    fn remove(&mut self, key: &str) -> Option<(K, Engram<T>)>;
+    /// Visits all engrams, in no particular order.
+    fn scan(&self) -> Box<dyn Iterator<Item = (&K, &Engram<T>)> + '_>;
+    /// Removes the engrams the predicate holds for.
+    fn sweep(&mut self, expired: &mut dyn FnMut(&K, &Engram<T>) -> bool) -> Vec<(K, Engram<T>)>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Releases memory kept for engrams removed before, if the storage
+    /// holds on to it.
+    fn compact(&mut self) {}
+    /// Changes the engram for the key in place, telling whether there was
+    /// one.
+    ///
+    /// The default takes it out and puts it back, storages able to change
+    /// it in place should do so.
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Engram<T>)) -> bool {
+        let Some((key, mut engram)) = self.remove(key) else {
+            return false;
+        };
+        update(&mut engram);
+        self.put(key, engram);
+        true
+    }
+}
+
+impl<T, H: BuildHasher> StorageBackend<String, T> for HashMap<String, Engram<T>, H> {
+    fn get(&self, key: &str) -> Option<&Engram<T>> {
+        HashMap::get(self, key)
+    }
+    fn put(&mut self, key: String, engram: Engram<T>) -> Option<Engram<T>> {
+        self.insert(key, engram)
+    }
+    fn remove(&mut self, key: &str) -> Option<(String, Engram<T>)> {
+        self.remove_entry(key)
+    }
+    fn scan(&self) -> Box<dyn Iterator<Item = (&String, &Engram<T>)> + '_> {
+        Box::new(self.iter())
+    }
+    fn sweep(
+        &mut self,
+        expired: &mut dyn FnMut(&String, &Engram<T>) -> bool,
+    ) -> Vec<(String, Engram<T>)> {
+        let keys = self
+            .iter()
+            .filter(|(key, engram)| expired(key, engram))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| self.remove_entry(&key))
+            .collect()
+    }
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+    fn compact(&mut self) {
+        self.shrink_to_fit();
+    }
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Engram<T>)) -> bool {
+        self.get_mut(key).map(update).is_some()
+    }
+}
+
+/// Keeps the engrams in the order their keys were first memoized, so
+/// exports and snapshots come out the same on every run. Snapshots loaded
+/// with `Brain::load_into` and logs recovered with `WalBrain::recover_into`
+/// keep their order.
+///
+/// ```
+/// # use memory::{Brain, Duration, InsertionOrdered, Memory};
+/// let brain = Brain::builder()
+///     .retention(Duration::new(60, 0))
+///     .storage(InsertionOrdered::default())
+///     .build();
+/// brain.memoize("a", 1);
+/// ```
+pub struct InsertionOrdered<T> {
+    positions: HashMap<String, u64>,
+    engrams: BTreeMap<u64, (String, Engram<T>)>,
+    next: u64,
+}
+impl<T> Default for InsertionOrdered<T> {
+    fn default() -> Self {
+        Self {
+            positions: HashMap::new(),
+            engrams: BTreeMap::new(),
+            next: 0,
+        }
+    }
+}
+impl<T> InsertionOrdered<T> {
+    /// Takes the engrams out in the order their keys were inserted.
+    #[cfg(feature = "serde")]
+    pub(crate) fn into_entries(self) -> impl Iterator<Item = (String, Engram<T>)> {
+        self.engrams.into_values()
+    }
+}
+impl<T> StorageBackend<String, T> for InsertionOrdered<T> {
+    fn get(&self, key: &str) -> Option<&Engram<T>> {
+        let position = self.positions.get(key)?;
+        self.engrams.get(position).map(|(_, engram)| engram)
+    }
+    /// Replacing an engram keeps the position of its key.
+    fn put(&mut self, key: String, engram: Engram<T>) -> Option<Engram<T>> {
+        if let Some(position) = self.positions.get(&key) {
+            let entry = self.engrams.get_mut(position)?;
+            return Some(std::mem::replace(&mut entry.1, engram));
+        }
+        self.positions.insert(key.clone(), self.next);
+        self.engrams.insert(self.next, (key, engram));
+        self.next += 1;
+        None
+    }
+    fn remove(&mut self, key: &str) -> Option<(String, Engram<T>)> {
+        let position = self.positions.remove(key)?;
+        self.engrams.remove(&position)
+    }
+    fn scan(&self) -> Box<dyn Iterator<Item = (&String, &Engram<T>)> + '_> {
+        Box::new(self.engrams.values().map(|(key, engram)| (key, engram)))
+    }
+    fn sweep(
+        &mut self,
+        expired: &mut dyn FnMut(&String, &Engram<T>) -> bool,
+    ) -> Vec<(String, Engram<T>)> {
+        let positions = self
+            .engrams
+            .iter()
+            .filter(|(_, (key, engram))| expired(key, engram))
+            .map(|(position, _)| *position)
+            .collect::<Vec<_>>();
+        positions
+            .into_iter()
+            .filter_map(|position| {
+                let (key, engram) = self.engrams.remove(&position)?;
+                self.positions.remove(&key);
+                Some((key, engram))
+            })
+            .collect()
+    }
+    fn len(&self) -> usize {
+        self.engrams.len()
+    }
+    fn compact(&mut self) {
+        self.positions.shrink_to_fit();
+    }
+    fn update(&mut self, key: &str, update: &mut dyn FnMut(&mut Engram<T>)) -> bool {
+        let Some(position) = self.positions.get(key) else {
+            return false;
+        };
+        self.engrams
+            .get_mut(position)
+            .map(|(_, engram)| update(engram))
+            .is_some()
+    }
+}
+
+#[cfg(feature = "testing")]
+impl<T> crate::testing::CheckStorage for InsertionOrdered<T> {
+    fn check(&self) -> Result<(), crate::testing::InvariantViolation> {
+        use crate::testing::InvariantViolation;
+        if self.positions.len() != self.engrams.len() {
+            return Err(InvariantViolation(format!(
+                "{} positions for {} engrams",
+                self.positions.len(),
+                self.engrams.len()
+            )));
+        }
+        for (position, (key, _)) in &self.engrams {
+            if self.positions.get(key) != Some(position) {
+                return Err(InvariantViolation(format!(
+                    "{key:?} at {position} isn't indexed there"
+                )));
+            }
+            if *position >= self.next {
+                return Err(InvariantViolation(format!(
+                    "{key:?} at {position}, beyond the next position {}",
+                    self.next
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Brain, Memory, NumericalDuration};
+    use std::collections::BTreeMap;
+
+    /// Keeps the engrams ordered by key.
+    #[derive(Default)]
+    struct Ordered(BTreeMap<String, Engram<i32>>);
+    impl StorageBackend<String, i32> for Ordered {
+        fn get(&self, key: &str) -> Option<&Engram<i32>> {
+            self.0.get(key)
+        }
+        fn put(&mut self, key: String, engram: Engram<i32>) -> Option<Engram<i32>> {
+            self.0.insert(key, engram)
+        }
+        fn remove(&mut self, key: &str) -> Option<(String, Engram<i32>)> {
+            self.0.remove_entry(key)
+        }
+        fn scan(&self) -> Box<dyn Iterator<Item = (&String, &Engram<i32>)> + '_> {
+            Box::new(self.0.iter())
+        }
+        fn sweep(
+            &mut self,
+            expired: &mut dyn FnMut(&String, &Engram<i32>) -> bool,
+        ) -> Vec<(String, Engram<i32>)> {
+            let (gone, kept) = std::mem::take(&mut self.0)
+                .into_iter()
+                .partition(|(key, engram)| expired(key, engram));
+            self.0 = kept;
+            gone.into_iter().collect()
+        }
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn storage() {
+        let memory = Brain::with_storage(1.milliseconds(), Ordered::default());
+        memory.memoize("b", 2);
+        memory.memoize_for("a", 1, 1.minutes());
+        assert_eq!(memory.retrieve("b"), Some(2));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.forget();
+        assert!(!memory.is_cached("b"));
+        assert_eq!(memory.top_keys(1)[0].key, "a");
+        assert_eq!(memory.inspect().entries, 1);
+    }
+
+    #[test]
+    fn insertion_ordered() {
+        let memory = Brain::with_storage(1.milliseconds(), InsertionOrdered::default());
+        for (key, value) in [("c", 1), ("a", 2), ("b", 3)] {
+            memory.memoize(key, value);
+        }
+        memory.memoize_for("a", 4, 1.minutes());
+        memory.remove("c");
+        memory.memoize("c", 5);
+        let keys = |memory: &Brain<i32, InsertionOrdered<i32>>| {
+            let storage = memory.memory.read();
+            storage
+                .scan()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&memory), vec!["a", "b", "c"]);
+        assert_eq!(memory.retrieve("a"), Some(4));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.forget();
+        assert_eq!(keys(&memory), vec!["a"]);
+        assert_eq!(memory.inspect().entries, 1);
+    }
+}
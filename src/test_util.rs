@@ -0,0 +1,193 @@
+//! Test doubles for code taking a [`Memory`] or [`AsyncMemory`].
+
+use crate::wall_clock;
+use crate::{AsyncMemory, BoxFuture, Memory, MemoryDefaultRetrieval};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use time::OffsetDateTime;
+
+/// A call made on a [`RecordingMemory`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call<T> {
+    Memoize {
+        key: String,
+        value: T,
+    },
+    /// A retrieval with the value it found.
+    Retrieve {
+        key: String,
+        found: Option<T>,
+    },
+    Forget,
+}
+
+/// A [`Call`] with the time it was made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recorded<T> {
+    pub at: OffsetDateTime,
+    pub call: Call<T>,
+}
+
+/// A memory recording every call made on it, to assert on them in tests.
+///
+/// Values are kept until forgotten, and forgetting forgets all of them.
+pub struct RecordingMemory<T> {
+    values: Mutex<HashMap<String, T>>,
+    calls: Mutex<Vec<Recorded<T>>>,
+}
+impl<T> Default for RecordingMemory<T> {
+    fn default() -> Self {
+        Self {
+            values: Default::default(),
+            calls: Default::default(),
+        }
+    }
+}
+impl<T: Clone> RecordingMemory<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Memoizes values without recording it, e.g. to prepare a test.
+    pub fn with(self, entries: impl IntoIterator<Item = (String, T)>) -> Self {
+        self.values.lock().extend(entries);
+        self
+    }
+    /// The calls made so far, in order.
+    pub fn calls(&self) -> Vec<Recorded<T>> {
+        self.calls.lock().clone()
+    }
+    /// Forgets the calls made so far, keeping the values.
+    pub fn clear_calls(&self) {
+        self.calls.lock().clear();
+    }
+    /// The values memoized for the key, in order.
+    pub fn memoized(&self, key: &str) -> Vec<T> {
+        self.calls
+            .lock()
+            .iter()
+            .filter_map(|recorded| match &recorded.call {
+                Call::Memoize {
+                    key: memoized,
+                    value,
+                } if memoized == key => Some(value.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+    /// How often the key was retrieved, found or not.
+    pub fn retrievals(&self, key: &str) -> usize {
+        self.calls
+            .lock()
+            .iter()
+            .filter(|recorded| matches!(&recorded.call, Call::Retrieve { key: retrieved, .. } if retrieved == key))
+            .count()
+    }
+    #[track_caller]
+    pub fn assert_memoized(&self, key: &str) {
+        assert!(!self.memoized(key).is_empty(), "{key:?} was never memoized");
+    }
+    #[track_caller]
+    pub fn assert_not_memoized(&self, key: &str) {
+        let memoized = self.memoized(key);
+        assert!(
+            memoized.is_empty(),
+            "{key:?} was memoized {} times",
+            memoized.len()
+        );
+    }
+    /// Asserts the value was the last one memoized for the key.
+    #[track_caller]
+    pub fn assert_memoized_with(&self, key: &str, value: &T)
+    where
+        T: PartialEq + Debug,
+    {
+        match self.memoized(key).last() {
+            Some(last) => assert_eq!(last, value, "{key:?} was memoized with another value"),
+            None => panic!("{key:?} was never memoized"),
+        }
+    }
+    #[track_caller]
+    pub fn assert_retrieved(&self, key: &str) {
+        assert!(self.retrievals(key) > 0, "{key:?} was never retrieved");
+    }
+    fn record(&self, call: Call<T>) {
+        self.calls.lock().push(Recorded {
+            at: wall_clock::now(),
+            call,
+        });
+    }
+}
+impl<T: Clone> Memory<T> for RecordingMemory<T> {
+    fn memoize(&self, key: &str, value: T) {
+        self.values.lock().insert(key.to_string(), value.clone());
+        self.record(Call::Memoize {
+            key: key.to_string(),
+            value,
+        });
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        let found = self.values.lock().get(key).cloned();
+        self.record(Call::Retrieve {
+            key: key.to_string(),
+            found: found.clone(),
+        });
+        found
+    }
+    fn forget(&self) {
+        self.values.lock().clear();
+        self.record(Call::Forget);
+    }
+}
+impl<T: Default + Clone> MemoryDefaultRetrieval<T> for RecordingMemory<T> {
+    fn retrieve_or_default(&self, key: &str) -> T {
+        self.retrieve(key).unwrap_or(T::default())
+    }
+}
+impl<T: Clone + Send + 'static> AsyncMemory<T> for RecordingMemory<T> {
+    fn memoize<'a>(&'a self, key: &'a str, value: T) -> BoxFuture<'a, ()> {
+        Box::pin(async move { Memory::memoize(self, key, value) })
+    }
+    fn retrieve<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<T>> {
+        Box::pin(async move { Memory::retrieve(self, key) })
+    }
+    fn forget(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move { Memory::forget(self) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Call, RecordingMemory};
+    use crate::{Memory, MemoryDefaultRetrieval};
+
+    #[test]
+    fn recording() {
+        let memory = RecordingMemory::new().with([("ready".to_string(), 0)]);
+        memory.memoize("a", 1);
+        memory.memoize("a", 2);
+        assert_eq!(memory.retrieve("a"), Some(2));
+        assert_eq!(memory.retrieve("ready"), Some(0));
+        assert_eq!(memory.retrieve_or_default("b"), 0);
+        memory.assert_memoized("a");
+        memory.assert_memoized_with("a", &2);
+        memory.assert_not_memoized("ready");
+        memory.assert_retrieved("b");
+        assert_eq!(memory.memoized("a"), vec![1, 2]);
+
+        memory.forget();
+        let calls = memory.calls();
+        assert_eq!(calls.len(), 6);
+        assert_eq!(calls.last().unwrap().call, Call::Forget);
+        assert!(calls.windows(2).all(|pair| pair[0].at <= pair[1].at));
+        memory.clear_calls();
+        assert!(memory.calls().is_empty());
+        assert_eq!(memory.retrieve("a"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "\"b\" was never memoized")]
+    fn assert_memoized() {
+        RecordingMemory::<i32>::new().assert_memoized("b");
+    }
+}
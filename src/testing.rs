@@ -0,0 +1,161 @@
+//! Support for testing code built on brains, and brains themselves: random
+//! sequences of operations and checks of the brains' internal consistency.
+//!
+//! ```
+//! # use memory::{testing, Brain, NumericalDuration};
+//! let brain = Brain::builder().retention(1.minutes()).max_entries(4).build();
+//! for op in testing::ops(7, 10).take(1000) {
+//!     op.apply(&brain);
+//!     brain.check_invariants().unwrap();
+//! }
+//! ```
+
+use crate::{Brain, Engram, Memory, StorageBackend};
+use std::fmt;
+
+/// An operation on a memory, see [`ops`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op<T> {
+    Memoize(String, T),
+    Retrieve(String),
+    Remove(String),
+    Forget,
+}
+impl<T: Clone> Op<T> {
+    /// Applies the operation to the brain, giving what it retrieved or
+    /// removed.
+    pub fn apply<S: StorageBackend<String, T>>(&self, brain: &Brain<T, S>) -> Option<T> {
+        match self {
+            Op::Memoize(key, value) => {
+                brain.memoize(key, value.clone());
+                None
+            }
+            Op::Retrieve(key) => brain.retrieve(key),
+            Op::Remove(key) => brain.remove(key),
+            Op::Forget => {
+                brain.forget();
+                None
+            }
+        }
+    }
+}
+
+/// Endless random operations on `keys` keys with the numbers of their
+/// draws as values, the same for the same seed.
+///
+/// Keys are few compared to the operations so they collide, and memoizing
+/// is the most frequent operation so brains fill up.
+pub fn ops(seed: u64, keys: usize) -> impl Iterator<Item = Op<u64>> {
+    ops_with(seed, keys, |draw| draw)
+}
+
+/// Like [`ops`], making the values from the numbers of their draws.
+pub fn ops_with<T>(
+    seed: u64,
+    keys: usize,
+    mut value: impl FnMut(u64) -> T,
+) -> impl Iterator<Item = Op<T>> {
+    let mut state = seed | 1;
+    let mut draws = 0;
+    std::iter::from_fn(move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        draws += 1;
+        let key = format!("key-{}", (state >> 8) as usize % keys.max(1));
+        Some(match state % 8 {
+            0..=3 => Op::Memoize(key, value(draws)),
+            4 | 5 => Op::Retrieve(key),
+            6 => Op::Remove(key),
+            _ => Op::Forget,
+        })
+    })
+}
+
+/// An internal inconsistency [`Brain::check_invariants`] found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantViolation(pub String);
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invariant violated: {}", self.0)
+    }
+}
+impl std::error::Error for InvariantViolation {}
+
+/// Checks the indexes of a storage against its engrams.
+pub trait CheckStorage {
+    fn check(&self) -> Result<(), InvariantViolation> {
+        Ok(())
+    }
+}
+impl<T, H> CheckStorage for std::collections::HashMap<String, Engram<T>, H> {}
+
+impl<T, S: StorageBackend<String, T> + CheckStorage> Brain<T, S> {
+    /// Checks the brain's internal consistency: the storage's indexes, its
+    /// length, the entry limit and the links between keys.
+    ///
+    /// Meant for tests and debug builds, e.g. after every operation of
+    /// [`ops`]: it holds the brain's locks while scanning all of it.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let memory = self.memory.read();
+        memory.check()?;
+        let scanned = memory.scan().count();
+        if scanned != memory.len() {
+            return Err(InvariantViolation(format!(
+                "{scanned} engrams scanned but a length of {}",
+                memory.len()
+            )));
+        }
+        if let Some(capacity) = &self.capacity {
+            if memory.len() > capacity.max_entries {
+                return Err(InvariantViolation(format!(
+                    "{} entries beyond the limit of {}",
+                    memory.len(),
+                    capacity.max_entries
+                )));
+            }
+        }
+        self.associations.check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InsertionOrdered, NumericalDuration};
+    use std::collections::HashMap;
+
+    #[test]
+    fn ops_keep_invariants() {
+        assert_eq!(
+            ops(3, 4).take(50).collect::<Vec<_>>(),
+            ops(3, 4).take(50).collect::<Vec<_>>()
+        );
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(3)
+            .storage(InsertionOrdered::default())
+            .build();
+        let mut model = HashMap::new();
+        for op in ops(42, 5).take(2000) {
+            let applied = op.apply(&brain);
+            brain.check_invariants().unwrap();
+            if let Op::Memoize(key, _) = &op {
+                brain.associate(key, "key-0");
+            }
+            match op {
+                Op::Memoize(key, value) => {
+                    model.insert(key, value);
+                }
+                Op::Retrieve(key) | Op::Remove(key) => {
+                    if let Some(value) = applied {
+                        assert_eq!(model.get(&key), Some(&value));
+                    }
+                }
+                Op::Forget => {}
+            }
+            brain.check_invariants().unwrap();
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::wall_clock::Instant;
+use crate::Memory;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Limits how often each key is memoized, e.g. against a producer
+/// overwriting a hot key thousands of times a second.
+///
+/// Every key has a token bucket holding up to `burst` writes, refilled at
+/// `per_second`. Writes finding it empty are dropped, or with
+/// [`ThrottledMemory::coalesce`], kept until the next write the bucket
+/// allows, which memoizes the latest value instead. Reads always go
+/// through.
+pub struct ThrottledMemory<T, M> {
+    memory: M,
+    per_second: f64,
+    burst: f64,
+    coalesce: bool,
+    keys: Mutex<HashMap<String, Bucket<T>>>,
+    throttled: AtomicU64,
+}
+struct Bucket<T> {
+    tokens: f64,
+    refilled: Instant,
+    pending: Option<T>,
+}
+impl<T, M> ThrottledMemory<T, M> {
+    pub fn new(memory: M, per_second: f64, burst: u32) -> Self {
+        Self {
+            memory,
+            per_second,
+            burst: burst.max(1) as f64,
+            coalesce: false,
+            keys: Default::default(),
+            throttled: AtomicU64::new(0),
+        }
+    }
+    /// Keeps the latest throttled value of a key instead of dropping it, to
+    /// memoize it with the next write allowed or by [`ThrottledMemory::flush`].
+    ///
+    /// Retrieving the key gives the kept value meanwhile.
+    pub fn coalesce(mut self) -> Self {
+        self.coalesce = true;
+        self
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// Writes dropped or held back so far.
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+}
+impl<T: Clone, M: Memory<T>> ThrottledMemory<T, M> {
+    /// Memoizes the values held back, whatever the rate, and forgets the
+    /// buckets which refilled.
+    pub fn flush(&self) {
+        let pending = {
+            let mut keys = self.keys.lock();
+            let now = Instant::now();
+            let pending = keys
+                .iter_mut()
+                .filter_map(|(key, bucket)| Some((key.clone(), bucket.pending.take()?)))
+                .collect::<Vec<_>>();
+            keys.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+            pending
+        };
+        for (key, value) in pending {
+            self.memory.memoize(&key, value);
+        }
+    }
+    fn refill(&self, bucket: &mut Bucket<T>, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.refilled = now;
+        bucket.tokens
+    }
+}
+impl<T: Clone, M: Memory<T>> Memory<T> for ThrottledMemory<T, M> {
+    fn memoize(&self, key: &str, value: T) {
+        {
+            let mut keys = self.keys.lock();
+            let now = Instant::now();
+            let bucket = keys.entry(key.to_string()).or_insert_with(|| Bucket {
+                tokens: self.burst,
+                refilled: now,
+                pending: None,
+            });
+            if self.refill(bucket, now) < 1.0 {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+                if self.coalesce {
+                    bucket.pending = Some(value);
+                }
+                return;
+            }
+            bucket.tokens -= 1.0;
+            bucket.pending = None;
+        }
+        self.memory.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        let pending = self
+            .keys
+            .lock()
+            .get(key)
+            .and_then(|bucket| bucket.pending.clone());
+        pending.or_else(|| self.memory.retrieve(key))
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::RecordingMemory;
+
+    #[test]
+    fn throttle() {
+        let memory = ThrottledMemory::new(RecordingMemory::new(), 1.0, 2);
+        for value in 0..5 {
+            memory.memoize("hot", value);
+        }
+        memory.memoize("cold", 0);
+        assert_eq!(memory.memory().memoized("hot"), vec![0, 1]);
+        assert_eq!(memory.throttled(), 3);
+        assert_eq!(memory.retrieve("hot"), Some(1));
+        memory.flush();
+        assert_eq!(memory.memory().memoized("hot"), vec![0, 1]);
+
+        let memory = ThrottledMemory::new(RecordingMemory::new(), 1.0, 1).coalesce();
+        for value in 0..5 {
+            memory.memoize("hot", value);
+        }
+        assert_eq!(memory.retrieve("hot"), Some(4));
+        memory.flush();
+        assert_eq!(memory.memory().memoized("hot"), vec![0, 4]);
+        assert_eq!(memory.retrieve("hot"), Some(4));
+    }
+}
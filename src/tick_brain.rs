@@ -0,0 +1,176 @@
+use crate::Memory;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// The entries of a [`TickBrain`], each value with the tick it was
+/// memoized at.
+pub type TickEntries<T> = BTreeMap<String, (T, u64)>;
+
+/// Sources of the time of a [`TickBrain`], counting ticks, e.g. of a
+/// hardware timer.
+pub trait Ticks {
+    fn now(&self) -> u64;
+}
+impl<F: Fn() -> u64> Ticks for F {
+    fn now(&self) -> u64 {
+        self()
+    }
+}
+
+/// Locks guarding the entries of a [`TickBrain`], e.g. an RTOS mutex.
+pub trait Lock<T> {
+    fn new(value: T) -> Self;
+    /// Runs the function on the value while holding the lock.
+    fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R;
+}
+
+/// A lock spinning while taken, for targets without another.
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+// SAFETY: the value is only accessed while holding the lock
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+impl<T> Lock<T> for SpinLock<T> {
+    fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+    fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        struct Unlock<'a>(&'a AtomicBool);
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Release);
+            }
+        }
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let _unlock = Unlock(&self.locked);
+        // SAFETY: holding the lock, nothing else accesses the value until
+        // it's released, even if the function panics
+        f(unsafe { &mut *self.value.get() })
+    }
+}
+/// Milliseconds by the monotonic system clock since first asked, a source
+/// for [`TickBrain`] and [`StaticBrain`] on `std`.
+///
+/// [`StaticBrain`]: crate::StaticBrain
+#[cfg(feature = "std")]
+pub fn monotonic_millis() -> u64 {
+    static STARTED: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    let started = STARTED.get_or_init(std::time::Instant::now);
+    started.elapsed().as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(feature = "std")]
+impl<T> Lock<T> for parking_lot::Mutex<T> {
+    fn new(value: T) -> Self {
+        parking_lot::Mutex::new(value)
+    }
+    fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.lock())
+    }
+}
+
+/// A brain for targets without `std`, e.g. firmware, timing retention by
+/// ticks of a source of its own rather than the system clock.
+///
+/// Ticks may wrap around, as long as the retention is shorter than the
+/// period they wrap at. Like a [`Brain`], it retrieves expired values until
+/// forgetting them.
+///
+/// [`Brain`]: crate::Brain
+pub struct TickBrain<T, C, L = SpinLock<TickEntries<T>>> {
+    retention: u64,
+    ticks: C,
+    entries: L,
+    value: PhantomData<T>,
+}
+impl<T, C: Ticks, L: Lock<TickEntries<T>>> TickBrain<T, C, L> {
+    /// A brain keeping values for the retention in ticks of the source.
+    pub fn new(retention: u64, ticks: C) -> Self {
+        Self {
+            retention,
+            ticks,
+            entries: L::new(BTreeMap::new()),
+            value: PhantomData,
+        }
+    }
+    pub fn retention(&self) -> u64 {
+        self.retention
+    }
+    pub fn len(&self) -> usize {
+        self.entries.with(|entries| entries.len())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn remove(&self, key: &str) -> Option<T> {
+        self.entries
+            .with(|entries| entries.remove(key))
+            .map(|(value, _)| value)
+    }
+}
+impl<T: Clone, C: Ticks, L: Lock<TickEntries<T>>> Memory<T> for TickBrain<T, C, L> {
+    fn memoize(&self, key: &str, value: T) {
+        let now = self.ticks.now();
+        self.entries.with(|entries| {
+            entries.insert(key.to_string(), (value, now));
+        });
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.entries
+            .with(|entries| entries.get(key).map(|(value, _)| value.clone()))
+    }
+    fn forget(&self) {
+        let now = self.ticks.now();
+        self.entries.with(|entries| {
+            entries.retain(|_, (_, memoized)| now.wrapping_sub(*memoized) <= self.retention)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn tick_brain() {
+        let now = Cell::new(u64::MAX - 1);
+        let brain = TickBrain::<_, _>::new(10, || now.get());
+        brain.memoize("a", 1);
+        now.set(5);
+        brain.memoize("b", 2);
+        // wrapped around, 7 ticks after a was memoized
+        brain.forget();
+        assert_eq!(brain.retrieve("a"), Some(1));
+        now.set(12);
+        brain.forget();
+        assert_eq!(brain.retrieve("a"), None);
+        assert_eq!(brain.len(), 1);
+        assert_eq!(brain.remove("b"), Some(2));
+        assert!(brain.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn monotonic_millis() {
+        let brain = TickBrain::<_, _>::new(60_000, super::monotonic_millis);
+        brain.memoize("a", 1);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        brain.forget();
+        assert_eq!(brain.retrieve("a"), Some(1));
+        assert!(super::monotonic_millis() >= 2);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{Brain, Engram, Memory, StorageBackend};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+/// A small fast tier in front of a large slow one, each a brain with its
+/// own capacity and retention, see [`Brain::builder`].
+///
+/// Values are memoized into the slow tier and promoted into the fast one
+/// once retrieved often enough. When the fast tier is full, the entries it
+/// evicts to make room are demoted into the slow tier instead of being
+/// forgotten, so a fast tier evicting by [`Eviction::LeastHits`] keeps the
+/// hottest keys. Every key lives in one tier at a time, and moving between
+/// them keeps when its value was memoized, its own retention and attributes.
+///
+/// [`Eviction::LeastHits`]: crate::Eviction::LeastHits
+pub struct TieredMemory<T, F = HashMap<String, Engram<T>>, S = HashMap<String, Engram<T>>> {
+    fast: Brain<T, F>,
+    slow: Brain<T, S>,
+    promote_after: u64,
+}
+impl<T, F, S> TieredMemory<T, F, S> {
+    pub fn new(fast: Brain<T, F>, slow: Brain<T, S>) -> Self {
+        Self {
+            fast,
+            slow,
+            promote_after: 2,
+        }
+    }
+    /// Promotes values retrieved from the slow tier this often since they
+    /// were memoized there, twice by default.
+    pub fn promote_after(mut self, hits: u64) -> Self {
+        self.promote_after = hits.max(1);
+        self
+    }
+    pub fn fast(&self) -> &Brain<T, F> {
+        &self.fast
+    }
+    pub fn slow(&self) -> &Brain<T, S> {
+        &self.slow
+    }
+}
+impl<T: Clone, F: StorageBackend<String, T>, S: StorageBackend<String, T>> TieredMemory<T, F, S> {
+    fn memoize_fast(&self, key: &str, engram: Engram<T>) {
+        let evicted = self
+            .fast
+            .store_evicting(key, engram, |_| true)
+            .unwrap_or_default();
+        for (key, engram) in evicted {
+            self.slow.store_if(&key, moved(engram), |_| true);
+        }
+    }
+}
+/// An engram to store in the other tier, keeping when it was memoized, its
+/// own retention, attributes and hits, but versioned by that tier.
+fn moved<T>(engram: Engram<T>) -> Engram<T> {
+    Engram {
+        version: 0,
+        ..engram
+    }
+}
+impl<T: Clone, F: StorageBackend<String, T>, S: StorageBackend<String, T>> Memory<T>
+    for TieredMemory<T, F, S>
+{
+    fn memoize(&self, key: &str, value: T) {
+        if self.fast.is_cached(key) {
+            self.memoize_fast(key, Engram::new(value, None));
+        } else {
+            self.slow.memoize(key, value);
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        if let Some(value) = self.fast.retrieve(key) {
+            return Some(value);
+        }
+        let value = self.slow.retrieve(key)?;
+        let promoted = self.slow.remove_engram_if(key, |engram| {
+            engram.hits.load(Ordering::Relaxed) >= self.promote_after
+        });
+        if let Some(promoted) = promoted {
+            self.memoize_fast(key, moved(promoted));
+        }
+        Some(value)
+    }
+    fn forget(&self) {
+        self.fast.forget();
+        self.slow.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eviction, NumericalDuration};
+
+    #[test]
+    fn tiers() {
+        let fast = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(1)
+            .eviction(Eviction::LeastHits)
+            .build();
+        let memory = TieredMemory::new(fast, Brain::new(1.minutes()));
+        memory.memoize("a", 1);
+        memory.memoize("b", 2);
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert!(!memory.fast().is_cached("a"));
+        assert_eq!(memory.retrieve("a"), Some(1));
+        assert!(memory.fast().is_cached("a") && !memory.slow().is_cached("a"));
+
+        // updates stay in the tier of the key
+        memory.memoize("a", 3);
+        assert_eq!(memory.fast().retrieve("a"), Some(3));
+
+        // promoting b demotes a to make room
+        memory.retrieve("b");
+        memory.retrieve("b");
+        assert!(memory.fast().is_cached("b"));
+        assert_eq!(memory.slow().retrieve("a"), Some(3));
+        assert_eq!(memory.retrieve("a"), Some(3));
+    }
+
+    #[test]
+    fn moves_keep_engrams() {
+        let fast = Brain::builder()
+            .retention(1.minutes())
+            .max_entries(1)
+            .build();
+        let memory = TieredMemory::new(fast, Brain::new(1.minutes())).promote_after(1);
+        memory.slow().memoize_for("a", 1, 10.seconds());
+        memory
+            .slow()
+            .memoize_with_attributes("b", 2, [("importance", "high")]);
+        let memoized = memory.slow().memory.read().get("a").unwrap().memoized();
+
+        memory.retrieve("a");
+        let ttl = memory.fast().time_to_live("a").unwrap();
+        assert!(ttl <= 10.seconds());
+        // promoting b demotes a
+        memory.retrieve("b");
+        let b = memory
+            .fast()
+            .memory
+            .read()
+            .get("b")
+            .map(|b| b.attribute("importance").cloned());
+        assert_eq!(b, Some(Some("high".into())));
+        let slow = memory.slow().memory.read();
+        let a = slow.get("a").unwrap();
+        assert_eq!(
+            (a.memoized(), a.retention()),
+            (memoized, Some(10.seconds()))
+        );
+    }
+}
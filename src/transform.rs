@@ -0,0 +1,66 @@
+use crate::Memory;
+
+type Encode<A, B> = Box<dyn Fn(B) -> A + Send + Sync>;
+type Decode<A, B> = Box<dyn Fn(A) -> Option<B> + Send + Sync>;
+
+/// A memory of values of type `B` kept in a memory of values of type `A`,
+/// e.g. a typed view of a memory of bytes.
+///
+/// Values which don't convert back are retrieved as misses.
+pub struct TransformMemory<A, B, M> {
+    memory: M,
+    encode: Encode<A, B>,
+    decode: Decode<A, B>,
+}
+impl<A, B, M: Memory<A>> TransformMemory<A, B, M> {
+    pub fn new(
+        memory: M,
+        encode: impl Fn(B) -> A + Send + Sync + 'static,
+        decode: impl Fn(A) -> Option<B> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            memory,
+            encode: Box::new(encode),
+            decode: Box::new(decode),
+        }
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+}
+impl<A, B, M: Memory<A>> Memory<B> for TransformMemory<A, B, M> {
+    fn memoize(&self, key: &str, value: B) {
+        self.memory.memoize(key, (self.encode)(value));
+    }
+    fn retrieve(&self, key: &str) -> Option<B> {
+        self.memory.retrieve(key).and_then(&self.decode)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn transform() {
+        let bytes = Brain::<Vec<u8>>::new(1.minutes());
+        let numbers = TransformMemory::new(
+            bytes.clone(),
+            |n: u32| n.to_be_bytes().to_vec(),
+            |bytes: Vec<u8>| Some(u32::from_be_bytes(bytes.try_into().ok()?)),
+        );
+        let texts = TransformMemory::new(bytes.clone(), String::into_bytes, |bytes| {
+            String::from_utf8(bytes).ok()
+        });
+        numbers.memoize("n", 7);
+        texts.memoize("s", "seven".to_string());
+        assert_eq!(bytes.retrieve("n"), Some(vec![0, 0, 0, 7]));
+        assert_eq!(numbers.retrieve("n"), Some(7));
+        assert_eq!(texts.retrieve("s").as_deref(), Some("seven"));
+        assert_eq!(numbers.retrieve("s"), None);
+    }
+}
@@ -0,0 +1,137 @@
+use parking_lot::Mutex;
+use std::io::{self, Read, Write};
+#[cfg(feature = "server")]
+use std::net::TcpListener;
+use std::net::TcpStream;
+#[cfg(all(unix, feature = "server"))]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A stream carried over a TCP connection, such as a TLS session.
+pub trait Duplex: Read + Write + Send {}
+impl<S: Read + Write + Send> Duplex for S {}
+
+/// Carries a TCP connection in another stream, such as a TLS session.
+pub(crate) type Wrap = Arc<dyn Fn(TcpStream) -> io::Result<Box<dyn Duplex>> + Send + Sync>;
+
+/// A connection over TCP or a Unix domain socket.
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// A TCP connection carried in another stream, the socket kept for its
+    /// timeouts.
+    Wrapped {
+        socket: TcpStream,
+        stream: Arc<Mutex<Box<dyn Duplex>>>,
+    },
+}
+impl Stream {
+    /// Carries a TCP connection in the stream the hook makes of it, Unix
+    /// domain sockets are left as they are.
+    pub(crate) fn wrap(self, wrap: &Wrap) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => Ok(Stream::Wrapped {
+                socket: stream.try_clone()?,
+                stream: Arc::new(Mutex::new(wrap(stream)?)),
+            }),
+            stream => Ok(stream),
+        }
+    }
+    #[cfg(feature = "server")]
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+            Stream::Wrapped { socket, stream } => Ok(Stream::Wrapped {
+                socket: socket.try_clone()?,
+                stream: stream.clone(),
+            }),
+        }
+    }
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(timeout),
+            Stream::Wrapped { socket, .. } => socket.set_read_timeout(timeout),
+        }
+    }
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_write_timeout(timeout),
+            Stream::Wrapped { socket, .. } => socket.set_write_timeout(timeout),
+        }
+    }
+    #[cfg(all(test, feature = "client", feature = "server"))]
+    pub(crate) fn shutdown(&self, how: std::net::Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(how),
+            Stream::Wrapped { socket, .. } => socket.shutdown(how),
+        }
+    }
+    /// Sends small writes right away, sockets which don't buffer them anyway
+    /// are left as they are.
+    pub(crate) fn set_nodelay(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nodelay(true),
+            #[cfg(unix)]
+            Stream::Unix(_) => Ok(()),
+            Stream::Wrapped { socket, .. } => socket.set_nodelay(true),
+        }
+    }
+}
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+            Stream::Wrapped { stream, .. } => stream.lock().read(buf),
+        }
+    }
+}
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+            Stream::Wrapped { stream, .. } => stream.lock().write(buf),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+            Stream::Wrapped { stream, .. } => stream.lock().flush(),
+        }
+    }
+}
+
+/// Accepts connections over TCP or a Unix domain socket.
+#[cfg(feature = "server")]
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+#[cfg(feature = "server")]
+impl Listener {
+    pub(crate) fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.accept().map(|(stream, _)| Stream::Unix(stream)),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{AnyBrain, Memory};
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A key of values of a type, so memoizing or retrieving it as another
+/// type doesn't compile.
+///
+/// ```
+/// # use memory::{AnyBrain, NumericalDuration, TypedKey};
+/// const VISITS: TypedKey<u64> = TypedKey::constant("visits");
+///
+/// let brain = AnyBrain::new(1.minutes());
+/// brain.memoize_typed(&VISITS, 3);
+/// assert_eq!(brain.retrieve_typed(&VISITS), Some(3));
+/// ```
+pub struct TypedKey<T> {
+    key: Cow<'static, str>,
+    value: PhantomData<fn() -> T>,
+}
+impl<T> TypedKey<T> {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: Cow::Owned(key.into()),
+            value: PhantomData,
+        }
+    }
+    /// A key known at compile time, e.g. for a `const`.
+    pub const fn constant(key: &'static str) -> Self {
+        Self {
+            key: Cow::Borrowed(key),
+            value: PhantomData,
+        }
+    }
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+}
+impl<T> Clone for TypedKey<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            value: PhantomData,
+        }
+    }
+}
+impl<T> fmt::Debug for TypedKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "TypedKey<{}>({:?})",
+            std::any::type_name::<T>(),
+            self.key
+        )
+    }
+}
+impl<T> PartialEq for TypedKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl<T> Eq for TypedKey<T> {}
+impl<T> Hash for TypedKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl AnyBrain {
+    pub fn memoize_typed<T: Clone + Send + Sync + 'static>(&self, key: &TypedKey<T>, value: T) {
+        self.memoize(key.as_str(), value);
+    }
+    pub fn retrieve_typed<T: Clone + Send + Sync + 'static>(&self, key: &TypedKey<T>) -> Option<T> {
+        self.retrieve(key.as_str())
+    }
+    pub fn remove_typed<T: Clone + Send + Sync + 'static>(&self, key: &TypedKey<T>) -> Option<T> {
+        self.remove(key.as_str())
+    }
+}
+
+/// Memories of values of any types shared by convention, memoizing and
+/// retrieving them by typed keys.
+///
+/// Values memoized under the same key by its name as another type are
+/// retrieved as misses.
+pub trait TypedMemory: Memory<Arc<dyn Any + Send + Sync>> {
+    fn memoize_typed<T: Send + Sync + 'static>(&self, key: &TypedKey<T>, value: T) {
+        self.memoize(key.as_str(), Arc::new(value));
+    }
+    fn retrieve_typed<T: Send + Sync + 'static>(&self, key: &TypedKey<T>) -> Option<Arc<T>> {
+        self.retrieve(key.as_str())?.downcast().ok()
+    }
+}
+impl<M: Memory<Arc<dyn Any + Send + Sync>> + ?Sized> TypedMemory for M {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    const NAME: TypedKey<String> = TypedKey::constant("name");
+
+    #[test]
+    fn typed_keys() {
+        let brain = AnyBrain::new(1.minutes());
+        brain.memoize_typed(&NAME, "ada".to_string());
+        let age = TypedKey::<u32>::new("age");
+        brain.memoize_typed(&age, 36);
+        assert_eq!(brain.retrieve_typed(&NAME).as_deref(), Some("ada"));
+        assert_eq!(brain.remove_typed(&age), Some(36));
+        assert_eq!(brain.retrieve_typed(&age), None);
+
+        let shared = Brain::<Arc<dyn Any + Send + Sync>>::new(1.minutes());
+        shared.memoize_typed(&NAME, "grace".to_string());
+        assert_eq!(
+            shared.retrieve_typed(&NAME).as_deref().map(String::as_str),
+            Some("grace")
+        );
+        // memoized by name as another type
+        shared.memoize("age", Arc::new("old"));
+        assert_eq!(shared.retrieve_typed(&age), None);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{Brain, Engram, StorageBackend};
+use std::sync::Arc;
+
+impl<T: ?Sized, S: StorageBackend<String, Arc<T>>> Brain<Arc<T>, S> {
+    /// Memoizes anything turning into a shared value, e.g. a `&str` or a
+    /// `String` for a `Brain<Arc<str>>`, a `Vec<u8>` for a `Brain<Arc<[u8]>>`
+    /// or a boxed trait object for a `Brain<Arc<dyn Trait>>`.
+    pub fn memoize_unsized(&self, key: &str, value: impl Into<Arc<T>>) {
+        self.store(key, Engram::new(value.into(), None));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+    use std::fmt::Display;
+
+    #[test]
+    fn memoize_unsized() {
+        let brain = Brain::<Arc<str>>::new(1.minutes());
+        brain.memoize_unsized("a", "text");
+        brain.memoize_unsized("b", String::from("owned"));
+        assert_eq!(brain.retrieve("a").as_deref(), Some("text"));
+        assert_eq!(brain.retrieve("b").as_deref(), Some("owned"));
+
+        let brain = Brain::<Arc<[u8]>>::new(1.minutes());
+        brain.memoize_unsized("a", vec![1, 2]);
+        assert_eq!(brain.retrieve("a").as_deref(), Some(&[1, 2][..]));
+
+        let brain = Brain::<Arc<dyn Display + Send + Sync>>::new(1.minutes());
+        brain.memoize_unsized("a", Box::new(7) as Box<dyn Display + Send + Sync>);
+        assert_eq!(brain.retrieve("a").unwrap().to_string(), "7");
+    }
+}
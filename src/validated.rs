@@ -0,0 +1,98 @@
+use crate::Memory;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+type Validate<T, E> = Box<dyn Fn(&str, &T) -> Result<(), E> + Send + Sync>;
+type OnReject<E> = Box<dyn Fn(&str, &E) + Send + Sync>;
+
+/// A memory vetting values before memoizing them, e.g. for their size or
+/// invariants, so malformed values aren't served until they expire.
+///
+/// Rejected values aren't memoized, a value memoized before for the key
+/// stays. Use [`ValidatedMemory::try_memoize`] to learn about rejections, or
+/// [`ValidatedMemory::on_reject`] to hear of those [`Memory::memoize`]
+/// drops.
+pub struct ValidatedMemory<T, E, M> {
+    memory: M,
+    validate: Validate<T, E>,
+    on_reject: Option<OnReject<E>>,
+    rejected: AtomicU64,
+}
+impl<T, E, M: Memory<T>> ValidatedMemory<T, E, M> {
+    pub fn new(
+        memory: M,
+        validate: impl Fn(&str, &T) -> Result<(), E> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            memory,
+            validate: Box::new(validate),
+            on_reject: None,
+            rejected: AtomicU64::new(0),
+        }
+    }
+    /// Calls back with the key and the error of every rejected value.
+    pub fn on_reject(mut self, on_reject: impl Fn(&str, &E) + Send + Sync + 'static) -> Self {
+        self.on_reject = Some(Box::new(on_reject));
+        self
+    }
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+    /// Values rejected so far.
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+    /// Memoizes the value unless it's rejected.
+    pub fn try_memoize(&self, key: &str, value: T) -> Result<(), E> {
+        if let Err(error) = (self.validate)(key, &value) {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            if let Some(on_reject) = &self.on_reject {
+                on_reject(key, &error);
+            }
+            return Err(error);
+        }
+        self.memory.memoize(key, value);
+        Ok(())
+    }
+}
+impl<T, E, M: Memory<T>> Memory<T> for ValidatedMemory<T, E, M> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.memory.retrieve(key)
+    }
+    fn forget(&self) {
+        self.memory.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn validated() {
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let memory = ValidatedMemory::new(Brain::new(1.minutes()), |_, value: &String| match value
+            .len()
+        {
+            0 => Err("empty"),
+            1..=8 => Ok(()),
+            _ => Err("too long"),
+        })
+        .on_reject({
+            let rejections = rejections.clone();
+            move |key, error| rejections.lock().push(format!("{key}: {error}"))
+        });
+        assert_eq!(memory.try_memoize("a", "valid".into()), Ok(()));
+        assert_eq!(memory.try_memoize("a", "".into()), Err("empty"));
+        memory.memoize("b", "much too long".into());
+        assert_eq!(memory.retrieve("a").as_deref(), Some("valid"));
+        assert_eq!(memory.retrieve("b"), None);
+        assert_eq!(memory.rejected(), 2);
+        assert_eq!(*rejections.lock(), ["a: empty", "b: too long"]);
+    }
+}
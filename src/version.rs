@@ -0,0 +1,160 @@
+use crate::wall_clock;
+use crate::{Brain, Engram, StorageBackend};
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+/// Given instead of memoizing a value whose entry changed since it was
+/// read, see [`Brain::memoize_if_version`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionConflict {
+    /// Version of the entry as found, `None` if there was none.
+    pub current: Option<u64>,
+}
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.current {
+            Some(version) => write!(f, "entry changed to version {version}"),
+            None => f.write_str("entry gone"),
+        }
+    }
+}
+impl std::error::Error for VersionConflict {}
+
+/// Given instead of memoizing a value with [`Brain::memoize_if_version`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionError {
+    /// The entry changed since it was read.
+    Conflict(VersionConflict),
+    /// The write would have grown the brain beyond its hard limit, see
+    /// [`Brain::shed_writes`].
+    OutOfMemory,
+}
+impl From<VersionConflict> for VersionError {
+    fn from(conflict: VersionConflict) -> Self {
+        Self::Conflict(conflict)
+    }
+}
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conflict(conflict) => conflict.fmt(f),
+            Self::OutOfMemory => f.write_str("brain at its hard limit"),
+        }
+    }
+}
+impl std::error::Error for VersionError {}
+
+impl<T, S> Brain<T, S> {
+    /// A version no engram of the brain had before.
+    pub(crate) fn next_version(&self) -> u64 {
+        self.versions.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Retrieves the value with its version, which grows with every write
+    /// to the brain, e.g. to write it back with
+    /// [`Brain::memoize_if_version`] after changing it.
+    ///
+    /// Values restored from elsewhere, e.g. snapshots or peers, are at
+    /// version 0 until memoized again.
+    pub fn retrieve_versioned(&self, key: &str) -> Option<(T, u64)> {
+        let (value, expired) = if self.surely_missing(key) {
+            (None, None)
+        } else {
+            let memory = self.memory.read();
+            let engram = memory
+                .get(key)
+                .filter(|engram| self.is_context_active(engram));
+            let expired = engram.map(|engram| engram.is_expired(self.retention, wall_clock::now()));
+            let value = engram.map(|engram| {
+                engram.hits.fetch_add(1, Ordering::Relaxed);
+                (engram.value.clone(), engram.version)
+            });
+            (value, expired)
+        };
+        self.stats.retrieved(expired);
+        self.record_retrieval(key);
+        if expired == Some(false) {
+            self.rehearse(key);
+        }
+        if let Some(hooks) = self.hooks() {
+            match &value {
+                Some((value, _)) => hooks.on_hit(key, value),
+                None => hooks.on_miss(key),
+            }
+        }
+        value
+    }
+    /// Memoizes the value unless the entry's version is no longer the one
+    /// expected, `None` expecting no entry, giving the new version.
+    ///
+    /// Fails with [`VersionError::OutOfMemory`] for a new key the hard limit
+    /// sheds, whatever its version.
+    pub fn memoize_if_version(
+        &self,
+        key: &str,
+        value: T,
+        expected: Option<u64>,
+    ) -> Result<u64, VersionError> {
+        let version = self.next_version();
+        let engram = Engram {
+            version,
+            ..Engram::new(value, None)
+        };
+        let (mut current, mut allowed) = (None, false);
+        let stored = self.store_if(key, engram, |engram| {
+            current = engram.map(|engram| engram.version);
+            allowed = current == expected;
+            allowed
+        });
+        match (stored, allowed) {
+            (true, _) => Ok(version),
+            (false, true) => Err(VersionError::OutOfMemory),
+            (false, false) => Err(VersionConflict { current }.into()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn versions() {
+        let brain = Brain::new(1.minutes());
+        let first = brain.memoize_if_version("n", 1, None).unwrap();
+        assert_eq!(
+            brain.memoize_if_version("n", 1, None),
+            Err(VersionError::Conflict(VersionConflict {
+                current: Some(first)
+            }))
+        );
+        let (n, version) = brain.retrieve_versioned("n").unwrap();
+        assert_eq!(version, first);
+
+        // someone else writes in between
+        brain.memoize("n", 5);
+        assert!(brain.memoize_if_version("n", n + 1, Some(version)).is_err());
+        let (n, version) = brain.retrieve_versioned("n").unwrap();
+        let next = brain.memoize_if_version("n", n + 1, Some(version)).unwrap();
+        assert!(next > version);
+        assert_eq!(brain.retrieve("n"), Some(6));
+        assert_eq!(brain.retrieve_versioned("missing"), None);
+    }
+
+    #[test]
+    fn hard_limit() {
+        let brain = Brain::builder()
+            .retention(1.minutes())
+            .hard_limit(1)
+            .build();
+        let version = brain.memoize_if_version("a", 1, None).unwrap();
+        assert_eq!(
+            brain.memoize_if_version("b", 2, None),
+            Err(VersionError::OutOfMemory)
+        );
+        assert!(brain.memoize_if_version("a", 3, Some(version)).is_ok());
+        assert_eq!(brain.shed_writes(), 1);
+    }
+}
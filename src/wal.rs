@@ -0,0 +1,303 @@
+use crate::crc::crc32;
+use crate::snapshot::write_atomically;
+use crate::wall_clock;
+use crate::{json, Brain, Duration, Engram, Memory, StorageBackend, TryMemoize};
+use parking_lot::Mutex;
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, SeqAccess, Visitor};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Records per live engram above which [`WalBrain::forget`] compacts the log.
+const COMPACTION_RATIO: usize = 4;
+/// Records below which the log is never compacted automatically.
+const COMPACTION_MINIMUM: usize = 1024;
+
+/// A [`Brain`] logging every memoize and forget to an append-only file.
+///
+/// After a crash, [`WalBrain::recover`] rebuilds the brain from the log.
+pub struct WalBrain<T, S = HashMap<String, Engram<T>>> {
+    brain: Brain<T, S>,
+    log: Mutex<Log>,
+}
+
+struct Log {
+    path: PathBuf,
+    file: File,
+    records: usize,
+}
+impl Log {
+    fn append(&mut self, record: String) -> io::Result<()> {
+        self.file.write_all(line(&record).as_bytes())?;
+        self.records += 1;
+        Ok(())
+    }
+}
+
+pub(crate) enum Record<T> {
+    Memoize(String, Engram<T>),
+    Forget(OffsetDateTime),
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> WalBrain<T> {
+    /// Rebuilds the brain from the log at the path, or starts an empty one.
+    ///
+    /// The log is compacted to the engrams still alive and then appended to.
+    pub fn recover(path: impl Into<PathBuf>, retention: Duration) -> io::Result<Self> {
+        Self::recover_into(path, retention, HashMap::new())
+    }
+}
+impl<T: Clone + Serialize + DeserializeOwned, S: StorageBackend<String, T>> WalBrain<T, S> {
+    /// Like [`WalBrain::recover`], replaying the log into the storage, so an
+    /// [`InsertionOrdered`] one keeps the order keys were first memoized in.
+    ///
+    /// [`InsertionOrdered`]: crate::InsertionOrdered
+    pub fn recover_into(
+        path: impl Into<PathBuf>,
+        retention: Duration,
+        storage: S,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        let brain = Brain::with_storage(retention, storage);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => replay(&brain, &text)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let wal = Self {
+            brain,
+            log: Mutex::new(Log {
+                path,
+                file,
+                records: 0,
+            }),
+        };
+        wal.compact()?;
+        Ok(wal)
+    }
+    /// Memoizes a value, failing if it couldn't be logged.
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.log_and_store(key, Engram::new(value, None))
+    }
+    /// Memoizes a value with its own retention, failing if it couldn't be logged.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        self.log_and_store(key, Engram::new(value, Some(retention)))
+    }
+    /// Rewrites the log to hold only the engrams still alive.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut log = self.log.lock();
+        let now = wall_clock::now();
+        let mut text = String::new();
+        let mut records = 0;
+        for (key, engram) in self.brain.memory.read().scan() {
+            if !engram.is_expired(self.brain.retention, now) {
+                let record = json::to_string(&("memoize", key, engram)).map_err(invalid_data)?;
+                text += &line(&record);
+                records += 1;
+            }
+        }
+        write_atomically(&log.path, text.as_bytes(), self.brain.durability)?;
+        log.file = OpenOptions::new().append(true).open(&log.path)?;
+        log.records = records;
+        Ok(())
+    }
+    pub fn brain(&self) -> &Brain<T, S> {
+        &self.brain
+    }
+    fn log_and_store(&self, key: &str, engram: Engram<T>) -> io::Result<()> {
+        let record = json::to_string(&("memoize", key, &engram)).map_err(invalid_data)?;
+        let mut log = self.log.lock();
+        log.append(record)?;
+        self.brain.store(key, engram);
+        Ok(())
+    }
+}
+
+impl<T: Clone + Serialize + DeserializeOwned, S: StorageBackend<String, T>> TryMemoize<T>
+    for WalBrain<T, S>
+{
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+
+/// Memoizing keeps the value in memory even if it couldn't be logged, use
+/// [`WalBrain::try_memoize`] to learn about it.
+impl<T: Clone + Serialize + DeserializeOwned, S: StorageBackend<String, T>> Memory<T>
+    for WalBrain<T, S>
+{
+    fn memoize(&self, key: &str, value: T) {
+        if self.try_memoize(key, value.clone()).is_err() {
+            self.brain.memoize(key, value);
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.brain.retrieve(key)
+    }
+    fn forget(&self) {
+        let compact = {
+            let mut log = self.log.lock();
+            let record = json::to_string(&("forget", wall_clock::now()));
+            let _ = record
+                .map_err(invalid_data)
+                .and_then(|record| log.append(record));
+            self.brain.forget();
+            let entries = self.brain.memory.read().len();
+            log.records >= COMPACTION_MINIMUM && log.records > entries * COMPACTION_RATIO
+        };
+        if compact {
+            let _ = self.compact();
+        }
+    }
+}
+
+fn replay<T: DeserializeOwned, S: StorageBackend<String, T>>(
+    brain: &Brain<T, S>,
+    text: &str,
+) -> io::Result<()> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut memory = brain.memory.write();
+    for (index, line) in lines.iter().enumerate() {
+        let record = verified(line).ok_or_else(|| "log record checksum mismatch".to_string());
+        match record.and_then(|record| {
+            json::from_str::<Record<T>>(record).map_err(|error| error.to_string())
+        }) {
+            Ok(Record::Memoize(key, engram)) => {
+                memory.put(key, engram);
+            }
+            Ok(Record::Forget(now)) => {
+                memory.sweep(&mut |_, engram| engram.is_expired(brain.retention, now));
+            }
+            // a crash while appending leaves a torn last record
+            Err(_) if index + 1 == lines.len() => {}
+            Err(error) => return Err(invalid_data(error)),
+        }
+    }
+    Ok(())
+}
+
+/// Prefixes a record with its checksum.
+pub(crate) fn line(record: &str) -> String {
+    format!("{:08x} {record}\n", crc32(record.as_bytes()))
+}
+
+/// Strips the checksum off a line, `None` if it doesn't match.
+pub(crate) fn verified(line: &str) -> Option<&str> {
+    let (checksum, record) = line.split_once(' ')?;
+    (u32::from_str_radix(checksum, 16).ok()? == crc32(record.as_bytes())).then_some(record)
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Record<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RecordVisitor<T>(PhantomData<T>);
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for RecordVisitor<T> {
+            type Value = Record<T>;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a log record")
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let missing = |index| de::Error::invalid_length(index, &"a complete record");
+                let operation: String = seq.next_element()?.ok_or_else(|| missing(0))?;
+                match operation.as_str() {
+                    "memoize" => Ok(Record::Memoize(
+                        seq.next_element()?.ok_or_else(|| missing(1))?,
+                        seq.next_element()?.ok_or_else(|| missing(2))?,
+                    )),
+                    "forget" => Ok(Record::Forget(
+                        seq.next_element()?.ok_or_else(|| missing(1))?,
+                    )),
+                    _ => Err(de::Error::unknown_variant(
+                        &operation,
+                        &["memoize", "forget"],
+                    )),
+                }
+            }
+        }
+        deserializer.deserialize_seq(RecordVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InsertionOrdered, NumericalDuration};
+
+    #[test]
+    fn recover() {
+        let path = std::env::temp_dir().join(format!("brain-{}.wal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let memory = WalBrain::recover(&path, 1.minutes()).unwrap();
+            memory.memoize("a", 3);
+            memory.try_memoize_for("b", 6, 1.milliseconds()).unwrap();
+            memory.memoize("a", 4);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            memory.forget();
+        }
+        // a torn record from a crash while appending
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"00000000 ["memoize","c",{"val"#).unwrap();
+
+        let memory = WalBrain::<i32>::recover(&path, 1.minutes()).unwrap();
+        assert_eq!(memory.retrieve("a"), Some(4));
+        assert!(!memory.brain().is_cached("b"));
+        assert!(!memory.brain().is_cached("c"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn insertion_order() {
+        let path = std::env::temp_dir().join(format!("brain-{}-ordered.wal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let keys = (0..50)
+            .map(|n| format!("k{}", (n * 7) % 50))
+            .collect::<Vec<_>>();
+        {
+            let storage = InsertionOrdered::default();
+            let memory = WalBrain::recover_into(&path, 1.minutes(), storage).unwrap();
+            for key in &keys {
+                memory.memoize(key, 1);
+            }
+        }
+        let storage = InsertionOrdered::default();
+        let memory = WalBrain::<i32, _>::recover_into(&path, 1.minutes(), storage).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let recovered = memory.brain().memory.read();
+        let order = recovered
+            .scan()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(order, keys);
+    }
+
+    #[test]
+    fn corruption() {
+        let path = std::env::temp_dir().join(format!("brain-{}-corrupt.wal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let memory = WalBrain::recover(&path, 1.minutes()).unwrap();
+            memory.memoize("a", 3);
+            memory.memoize("b", 6);
+        }
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, log.replacen(r#""a""#, r#""x""#, 1)).unwrap();
+
+        let recovered = WalBrain::<i32>::recover(&path, 1.minutes());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            recovered.err().unwrap().to_string(),
+            "log record checksum mismatch"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::Duration;
+#[cfg(feature = "time")]
+use std::sync::OnceLock;
+#[cfg(feature = "time")]
+use time::OffsetDateTime;
+
+/// When values were memoized, by the wall clock with the `time` feature,
+/// else by the monotonic clock.
+#[cfg(feature = "time")]
+pub(crate) type Timestamp = OffsetDateTime;
+#[cfg(not(feature = "time"))]
+pub(crate) type Timestamp = std::time::Instant;
+
+#[cfg(feature = "time")]
+static CLOCK: OnceLock<fn() -> OffsetDateTime> = OnceLock::new();
+
+/// Tells brains the time from now on, instead of the system clock, telling
+/// whether it did: only the first call sets the clock.
+///
+/// Targets without a system clock need one before using brains, e.g.
+/// `wasm32-unknown-unknown` in a browser, with the time from JavaScript:
+///
+/// ```ignore
+/// memory::set_clock(|| {
+///     let millis = js_sys::Date::now() as i128;
+///     time::OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).unwrap()
+/// });
+/// ```
+#[cfg(feature = "time")]
+pub fn set_clock(now: fn() -> OffsetDateTime) -> bool {
+    CLOCK.set(now).is_ok()
+}
+
+/// The time by the clock set, or else the system clock.
+#[cfg(feature = "time")]
+pub(crate) fn now() -> Timestamp {
+    CLOCK
+        .get()
+        .map_or_else(OffsetDateTime::now_utc, |now| now())
+}
+#[cfg(not(feature = "time"))]
+pub(crate) fn now() -> Timestamp {
+    std::time::Instant::now()
+}
+
+/// The duration in seconds.
+#[cfg(feature = "time")]
+pub(crate) fn seconds(duration: Duration) -> f64 {
+    duration.as_seconds_f64()
+}
+#[cfg(not(feature = "time"))]
+pub(crate) fn seconds(duration: Duration) -> f64 {
+    duration.as_secs_f64()
+}
+
+/// The duration times the factor, zero for negative factors without the
+/// `time` feature.
+#[cfg(feature = "time")]
+pub(crate) fn scaled(duration: Duration, factor: f64) -> Duration {
+    duration * factor
+}
+#[cfg(not(feature = "time"))]
+pub(crate) fn scaled(duration: Duration, factor: f64) -> Duration {
+    let seconds = duration.as_secs_f64() * factor;
+    Duration::try_from_secs_f64(seconds).unwrap_or(if seconds > 0.0 {
+        Duration::MAX
+    } else {
+        Duration::ZERO
+    })
+}
+
+/// The length of the duration, for timers and sleeps.
+#[cfg(feature = "time")]
+pub(crate) fn unsigned(duration: Duration) -> std::time::Duration {
+    duration.unsigned_abs()
+}
+#[cfg(not(feature = "time"))]
+pub(crate) fn unsigned(duration: Duration) -> std::time::Duration {
+    duration
+}
+
+/// Monotonic time, for timing sweeps and intervals.
+#[cfg(not(all(target_arch = "wasm32", feature = "time")))]
+pub(crate) type Instant = std::time::Instant;
+#[cfg(all(target_arch = "wasm32", feature = "time"))]
+pub(crate) type Instant = WallInstant;
+
+/// Time by the clock standing in for [`std::time::Instant`], which wasm32
+/// lacks.
+#[cfg(all(feature = "time", any(target_arch = "wasm32", test)))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct WallInstant(OffsetDateTime);
+#[cfg(all(feature = "time", any(target_arch = "wasm32", test)))]
+impl WallInstant {
+    pub(crate) fn now() -> Self {
+        Self(now())
+    }
+    pub(crate) fn elapsed(&self) -> std::time::Duration {
+        Self::now() - *self
+    }
+    pub(crate) fn duration_since(&self, earlier: Self) -> std::time::Duration {
+        *self - earlier
+    }
+    pub(crate) fn saturating_duration_since(&self, earlier: Self) -> std::time::Duration {
+        *self - earlier
+    }
+}
+#[cfg(all(feature = "time", any(target_arch = "wasm32", test)))]
+impl std::ops::Add<std::time::Duration> for WallInstant {
+    type Output = Self;
+
+    fn add(self, duration: std::time::Duration) -> Self {
+        Self(self.0 + duration)
+    }
+}
+#[cfg(all(feature = "time", any(target_arch = "wasm32", test)))]
+impl std::ops::Sub for WallInstant {
+    type Output = std::time::Duration;
+
+    /// Zero if the clock went back.
+    fn sub(self, earlier: Self) -> std::time::Duration {
+        (self.0 - earlier.0).try_into().unwrap_or_default()
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn wall_instant() {
+        let started = WallInstant::now();
+        let later = started + std::time::Duration::from_secs(5);
+        assert_eq!(later.0, started.0 + 5.seconds());
+        assert_eq!(later - started, std::time::Duration::from_secs(5));
+        assert_eq!(
+            later.duration_since(started),
+            std::time::Duration::from_secs(5)
+        );
+        assert_eq!(
+            started.saturating_duration_since(later),
+            std::time::Duration::ZERO
+        );
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{Brain, StorageBackend};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+
+/// Channels of the values memoized for watched keys.
+pub(crate) struct ValueWatchers<T> {
+    /// Number of keys watched, for brains nobody watches to skip the lock.
+    watched: AtomicUsize,
+    senders: Mutex<HashMap<String, Vec<Sender<T>>>>,
+}
+impl<T> Default for ValueWatchers<T> {
+    fn default() -> Self {
+        Self {
+            watched: AtomicUsize::new(0),
+            senders: Mutex::default(),
+        }
+    }
+}
+impl<T: Clone> ValueWatchers<T> {
+    /// Sends the value memoized for the key to its watchers, with the brain
+    /// write-locked so they get the values in the order memoized.
+    pub(crate) fn send(&self, key: &str, value: &T) {
+        if self.watched.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut senders = self.senders.lock();
+        let Some(watching) = senders.get_mut(key) else {
+            return;
+        };
+        watching.retain(|sender| sender.send(value.clone()).is_ok());
+        if watching.is_empty() {
+            senders.remove(key);
+            self.watched.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+impl<T> ValueWatchers<T> {
+    /// Ends the watches of the key, whose entry is gone.
+    pub(crate) fn end(&self, key: &str) {
+        if self.watched.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        if self.senders.lock().remove(key).is_some() {
+            self.watched.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T: Clone, S: StorageBackend<String, T>> Brain<T, S> {
+    /// Watches the key, getting the value memoized for it now if any, and
+    /// then every value memoized for it, e.g. for parts of an app to follow
+    /// shared config.
+    ///
+    /// Values memoized in inactive contexts aren't sent, see
+    /// [`Brain::activate_context`]. The watch ends once the entry for the
+    /// key leaves the brain, see
+    /// [`Brain::on_expire`], or when the handle is dropped.
+    pub fn watch(&self, key: &str) -> WatchHandle<T> {
+        let (sender, receiver) = mpsc::channel();
+        let memory = self.memory.read();
+        if let Some(engram) = memory
+            .get(key)
+            .filter(|engram| self.is_context_active(engram))
+        {
+            let _ = sender.send(engram.value.clone());
+        }
+        let mut senders = self.value_watchers.senders.lock();
+        let watching = senders.entry(key.to_string()).or_default();
+        if watching.is_empty() {
+            self.value_watchers.watched.fetch_add(1, Ordering::Relaxed);
+        }
+        watching.push(sender);
+        WatchHandle { receiver }
+    }
+}
+
+/// The values memoized for a key, see [`Brain::watch`].
+///
+/// Iterating waits for the next value, and ends with the watch.
+pub struct WatchHandle<T> {
+    receiver: Receiver<T>,
+}
+impl<T> WatchHandle<T> {
+    /// Waits for the next value, `None` once the watch ended.
+    pub fn recv(&self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+    /// Waits up to the timeout for the next value.
+    pub fn recv_timeout(&self, timeout: std::time::Duration) -> Result<T, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+    /// The next value, unless there's none yet.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.receiver.try_recv()
+    }
+    /// The last value memoized, skipping those before it, unless there's
+    /// no new one.
+    pub fn latest(&self) -> Option<T> {
+        self.receiver.try_iter().last()
+    }
+}
+impl<T> Iterator for WatchHandle<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.recv()
+    }
+}
+
+#[cfg(all(test, feature = "time"))]
+mod tests {
+    use super::*;
+    use crate::{Memory, NumericalDuration};
+
+    #[test]
+    fn watch() {
+        let brain = Brain::new(1.minutes());
+        brain.memoize("config", "a");
+        let watch = brain.watch("config");
+        let other = brain.watch("config");
+        brain.memoize("config", "b");
+        brain.memoize("unwatched", "x");
+        brain.memoize("config", "c");
+        assert_eq!(watch.try_recv(), Ok("a"));
+        assert_eq!(other.latest(), Some("c"));
+        drop(other);
+
+        brain.remove("config");
+        assert_eq!(watch.collect::<Vec<_>>(), ["b", "c"]);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::wall_clock::Instant;
+use crate::{Brain, Duration, EvictionCause, Memory};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak};
+
+/// A brain holding its values weakly, so it never keeps alive what the
+/// application already dropped, e.g. a canonicalizing cache of large
+/// parsed documents.
+///
+/// Values are held strongly for the grace period after they're memoized,
+/// so a value dropped right away is still retrieved meanwhile. After that,
+/// a value is retrieved as long as the application holds it somewhere,
+/// and it expires for the retention as in any brain. Entries of dropped
+/// values are removed when retrieved and when forgetting.
+pub struct WeakBrain<T> {
+    brain: Brain<Weak<T>>,
+    grace: std::time::Duration,
+    held: Mutex<VecDeque<(Instant, Arc<T>)>>,
+}
+impl<T> WeakBrain<T> {
+    pub fn new(retention: Duration, grace: Duration) -> Self {
+        Self {
+            brain: Brain::new(retention),
+            grace: grace.unsigned_abs(),
+            held: Mutex::default(),
+        }
+    }
+    /// The brain of the weak values, e.g. for its stats.
+    pub fn brain(&self) -> &Brain<Weak<T>> {
+        &self.brain
+    }
+    /// Releases the values held past their grace period and removes the
+    /// entries of dropped values, telling how many.
+    pub fn prune(&self) -> usize {
+        let released = {
+            let mut held = self.held.lock();
+            let now = Instant::now();
+            let past = held
+                .iter()
+                .take_while(|(since, _)| now.saturating_duration_since(*since) >= self.grace)
+                .count();
+            held.drain(..past).collect::<Vec<_>>()
+        };
+        // dropped outside the lock, the values may be large
+        drop(released);
+        self.brain
+            .evict_where(EvictionCause::Removed, &mut |_, engram| {
+                engram.value().strong_count() == 0
+            })
+    }
+}
+impl<T: Send + Sync> Memory<Arc<T>> for WeakBrain<T> {
+    fn memoize(&self, key: &str, value: Arc<T>) {
+        self.brain.memoize(key, Arc::downgrade(&value));
+        if !self.grace.is_zero() {
+            self.held.lock().push_back((Instant::now(), value));
+        }
+    }
+    fn retrieve(&self, key: &str) -> Option<Arc<T>> {
+        let value = self.brain.retrieve(key)?;
+        if let Some(value) = value.upgrade() {
+            return Some(value);
+        }
+        self.brain
+            .remove_if(key, |engram| Weak::ptr_eq(engram.value(), &value));
+        None
+    }
+    fn forget(&self) {
+        self.prune();
+        self.brain.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn weak_brain() {
+        let brain = WeakBrain::new(1.minutes(), 2.milliseconds());
+        let kept = Arc::new("kept".to_string());
+        brain.memoize("kept", kept.clone());
+        brain.memoize("dropped", Arc::new("dropped".to_string()));
+        assert_eq!(
+            brain.retrieve("dropped").as_deref().map(String::as_str),
+            Some("dropped")
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(3));
+        assert_eq!(brain.prune(), 1);
+        assert_eq!(brain.retrieve("dropped"), None);
+        assert!(Arc::ptr_eq(&brain.retrieve("kept").unwrap(), &kept));
+        drop(kept);
+        assert_eq!(brain.retrieve("kept"), None);
+        assert!(!brain.brain().is_cached("kept"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::wall_clock;
+use crate::{json, Duration, Engram, Memory, TryMemoize};
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::marker::PhantomData;
+
+/// Stores of text items by key like the browser's `localStorage`, see
+/// [`WebStorageBrain`].
+///
+/// Bindings such as `web_sys::Storage` report failures as JavaScript
+/// values, which implementations turn into I/O errors.
+pub trait WebStorage {
+    fn get_item(&self, key: &str) -> io::Result<Option<String>>;
+    /// Fails e.g. when the storage's quota is exceeded.
+    fn set_item(&self, key: &str, value: &str) -> io::Result<()>;
+    fn remove_item(&self, key: &str) -> io::Result<()>;
+    /// The keys of all items, in any order.
+    fn keys(&self) -> io::Result<Vec<String>>;
+}
+/// Items kept in memory, e.g. for tests or native builds of a web app.
+impl WebStorage for RwLock<HashMap<String, String>> {
+    fn get_item(&self, key: &str) -> io::Result<Option<String>> {
+        Ok(self.read().get(key).cloned())
+    }
+    fn set_item(&self, key: &str, value: &str) -> io::Result<()> {
+        self.write().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+    fn remove_item(&self, key: &str) -> io::Result<()> {
+        self.write().remove(key);
+        Ok(())
+    }
+    fn keys(&self) -> io::Result<Vec<String>> {
+        Ok(self.read().keys().cloned().collect())
+    }
+}
+
+/// A brain keeping its values in a [`WebStorage`], e.g. `localStorage` so
+/// a web app's cache outlives reloads.
+///
+/// Each engram is an item of JSON with its value, when it was memoized and
+/// its retention, under the key after a prefix, so the brain shares the
+/// storage with other items. Storages like `localStorage` are small and
+/// synchronous, so suit small values.
+pub struct WebStorageBrain<T, W> {
+    storage: W,
+    prefix: String,
+    retention: Duration,
+    values: PhantomData<fn() -> T>,
+}
+impl<T: Serialize + DeserializeOwned, W: WebStorage> WebStorageBrain<T, W> {
+    pub fn new(storage: W, prefix: &str, retention: Duration) -> Self {
+        Self {
+            storage,
+            prefix: prefix.to_string(),
+            retention,
+            values: PhantomData,
+        }
+    }
+    pub fn storage(&self) -> &W {
+        &self.storage
+    }
+    /// Memoizes a value, failing if it couldn't be stored.
+    pub fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.put(key, Engram::new(value, None))
+    }
+    /// Memoizes a value with its own retention, failing if it couldn't be stored.
+    pub fn try_memoize_for(&self, key: &str, value: T, retention: Duration) -> io::Result<()> {
+        self.put(key, Engram::new(value, Some(retention)))
+    }
+    /// Retrieves a value, failing if it couldn't be read. Expired values
+    /// are misses.
+    pub fn try_retrieve(&self, key: &str) -> io::Result<Option<T>> {
+        let Some(engram) = self.get(&self.item(key))? else {
+            return Ok(None);
+        };
+        Ok((!engram.is_expired(self.retention, wall_clock::now())).then_some(engram.value))
+    }
+    /// Removes the expired items of the brain, and those it can't parse,
+    /// telling how many.
+    pub fn try_forget(&self) -> io::Result<usize> {
+        let now = wall_clock::now();
+        let mut removed = 0;
+        for item in self.storage.keys()? {
+            if !item.starts_with(&self.prefix) {
+                continue;
+            }
+            let Some(text) = self.storage.get_item(&item)? else {
+                continue;
+            };
+            let expired = json::from_str::<Engram<T>>(&text)
+                .map_or(true, |engram| engram.is_expired(self.retention, now));
+            if expired {
+                self.storage.remove_item(&item)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+    fn item(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+    fn get(&self, item: &str) -> io::Result<Option<Engram<T>>> {
+        let Some(text) = self.storage.get_item(item)? else {
+            return Ok(None);
+        };
+        json::from_str(&text)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+    fn put(&self, key: &str, engram: Engram<T>) -> io::Result<()> {
+        let text = json::to_string(&engram)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        self.storage.set_item(&self.item(key), &text)
+    }
+}
+impl<T: Serialize + DeserializeOwned, W: WebStorage> TryMemoize<T> for WebStorageBrain<T, W> {
+    fn try_memoize(&self, key: &str, value: T) -> io::Result<()> {
+        self.try_memoize(key, value)
+    }
+}
+
+/// Storage errors are swallowed, memoizing fails silently and retrieving
+/// misses, use the `try_` methods to learn about them.
+impl<T: Serialize + DeserializeOwned, W: WebStorage> Memory<T> for WebStorageBrain<T, W> {
+    fn memoize(&self, key: &str, value: T) {
+        let _ = self.try_memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.try_retrieve(key).ok().flatten()
+    }
+    fn forget(&self) {
+        let _ = self.try_forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NumericalDuration;
+
+    #[test]
+    fn web_storage() {
+        let storage = RwLock::new(HashMap::from([("theme".to_string(), "dark".to_string())]));
+        let brain = WebStorageBrain::new(storage, "cache:", 1.minutes());
+        brain.memoize("a", vec![1, 2]);
+        brain
+            .try_memoize_for("b", vec![3], 1.milliseconds())
+            .unwrap();
+        assert!(brain.storage().read().contains_key("cache:a"));
+        assert_eq!(brain.retrieve("a"), Some(vec![1, 2]));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(brain.retrieve("b"), None);
+        brain.storage().set_item("cache:c", "not json").unwrap();
+        assert_eq!(brain.try_forget().unwrap(), 2);
+        // items of others are left alone
+        let storage = brain.storage().read();
+        let mut keys = storage.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["cache:a", "theme"]);
+    }
+}
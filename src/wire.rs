@@ -0,0 +1,58 @@
+use crate::{binary, json, ValueCodec};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+
+/// The compact binary format of snapshots, the default of clients and
+/// [`Spillover`].
+///
+/// [`Spillover`]: crate::Spillover
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryCodec;
+impl<T: Serialize + DeserializeOwned> ValueCodec<T> for BinaryCodec {
+    fn encode(&self, value: &T) -> io::Result<Vec<u8>> {
+        binary::to_vec(value).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+    fn decode(&self, bytes: &[u8]) -> io::Result<T> {
+        binary::from_slice(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// JSON, readable by any client and the `http` facade.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+impl<T: Serialize + DeserializeOwned> ValueCodec<T> for JsonCodec {
+    fn encode(&self, value: &T) -> io::Result<Vec<u8>> {
+        json::to_string(value)
+            .map(String::into_bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+    fn decode(&self, bytes: &[u8]) -> io::Result<T> {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        json::from_str(text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn codecs() {
+        let value = vec![(1, "one".to_string())];
+        let json = JsonCodec.encode(&value).unwrap();
+        assert_eq!(json, br#"[[1,"one"]]"#);
+        assert_eq!(
+            ValueCodec::<Vec<(i32, String)>>::decode(&JsonCodec, &json).unwrap(),
+            value
+        );
+
+        let shared: Arc<dyn ValueCodec<Vec<(i32, String)>>> = Arc::new(BinaryCodec);
+        let binary = shared.encode(&value).unwrap();
+        assert_eq!(shared.decode(&binary).unwrap(), value);
+        let error = shared.decode(&json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::Memory;
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Describes the queue of a [`WriteBehind`].
+#[derive(Clone, Copy, Debug)]
+pub struct WriteBehindConfig {
+    capacity: usize,
+    batch_size: usize,
+}
+impl Default for WriteBehindConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            batch_size: 64,
+        }
+    }
+}
+impl WriteBehindConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Values waiting for the store, memoizing blocks while the queue is full.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+    /// Values taken from the queue at once.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+struct Queue<T> {
+    pending: VecDeque<(String, T)>,
+    flushing: usize,
+    closed: bool,
+}
+
+struct Shared<T, M2> {
+    store: M2,
+    queue: Mutex<Queue<T>>,
+    changed: Condvar,
+    config: WriteBehindConfig,
+}
+impl<T, M2: Memory<T>> Shared<T, M2> {
+    fn run(&self) {
+        let mut queue = self.queue.lock();
+        loop {
+            while queue.pending.is_empty() && !queue.closed {
+                self.changed.wait(&mut queue);
+            }
+            if queue.pending.is_empty() {
+                return;
+            }
+            let len = queue.pending.len().min(self.config.batch_size);
+            let batch = queue.pending.drain(..len).collect::<Vec<_>>();
+            queue.flushing = batch.len();
+            self.changed.notify_all();
+            drop(queue);
+            for (key, value) in batch {
+                self.store.memoize(&key, value);
+            }
+            queue = self.queue.lock();
+            queue.flushing = 0;
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// Memoizes into a fast memory and later, in the background, into a durable store.
+///
+/// Memoizing returns once the value is in the memory and queued for the
+/// store. Retrieval looks into the memory first and falls back to the store.
+/// Dropping the wrapper drains the queue like [`WriteBehind::shutdown`].
+pub struct WriteBehind<T, M1, M2: Memory<T>> {
+    memory: M1,
+    shared: Arc<Shared<T, M2>>,
+    worker: Option<JoinHandle<()>>,
+}
+impl<T, M1, M2> WriteBehind<T, M1, M2>
+where
+    T: Send + 'static,
+    M2: Memory<T> + Send + Sync + 'static,
+{
+    pub fn new(memory: M1, store: M2, config: WriteBehindConfig) -> Self {
+        let shared = Arc::new(Shared {
+            store,
+            queue: Mutex::new(Queue {
+                pending: VecDeque::new(),
+                flushing: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            config,
+        });
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || shared.run())
+        };
+        Self {
+            memory,
+            shared,
+            worker: Some(worker),
+        }
+    }
+}
+impl<T, M1, M2: Memory<T>> WriteBehind<T, M1, M2> {
+    pub fn memory(&self) -> &M1 {
+        &self.memory
+    }
+    pub fn store(&self) -> &M2 {
+        &self.shared.store
+    }
+    /// Waits until every queued value is in the store.
+    pub fn flush(&self) {
+        let mut queue = self.shared.queue.lock();
+        while !queue.pending.is_empty() || queue.flushing > 0 {
+            self.shared.changed.wait(&mut queue);
+        }
+    }
+    /// Drains the queue into the store and stops the background thread.
+    pub fn shutdown(mut self) -> thread::Result<()> {
+        self.stop()
+    }
+    fn stop(&mut self) -> thread::Result<()> {
+        self.shared.queue.lock().closed = true;
+        self.shared.changed.notify_all();
+        match self.worker.take() {
+            Some(worker) => worker.join(),
+            None => Ok(()),
+        }
+    }
+}
+impl<T, M1, M2: Memory<T>> Drop for WriteBehind<T, M1, M2> {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+impl<T: Clone, M1: Memory<T>, M2: Memory<T>> Memory<T> for WriteBehind<T, M1, M2> {
+    fn memoize(&self, key: &str, value: T) {
+        self.memory.memoize(key, value.clone());
+        let mut queue = self.shared.queue.lock();
+        while queue.pending.len() >= self.shared.config.capacity {
+            self.shared.changed.wait(&mut queue);
+        }
+        queue.pending.push_back((key.to_string(), value));
+        self.shared.changed.notify_all();
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.memory
+            .retrieve(key)
+            .or_else(|| self.shared.store.retrieve(key))
+    }
+    fn forget(&self) {
+        self.memory.forget();
+        self.shared.store.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn write_behind() {
+        let store = Brain::new(1.minutes());
+        let memory = WriteBehind::new(
+            Brain::new(1.minutes()),
+            store.clone(),
+            WriteBehindConfig::new().capacity(2).batch_size(2),
+        );
+        for i in 0..10 {
+            memory.memoize(&i.to_string(), i);
+        }
+        assert_eq!(memory.retrieve("9"), Some(9));
+
+        memory.flush();
+        assert_eq!(store.retrieve("9"), Some(9));
+
+        memory.memoize("last", 10);
+        memory.shutdown().unwrap();
+        assert_eq!(store.retrieve("last"), Some(10));
+    }
+}
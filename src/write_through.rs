@@ -0,0 +1,58 @@
+use crate::Memory;
+
+/// Memoizes into a fast memory and synchronously into a durable store.
+///
+/// Retrieval looks into the memory first and falls back to the store,
+/// memoizing what it finds there into the memory again.
+pub struct WriteThrough<M1, M2> {
+    memory: M1,
+    store: M2,
+}
+impl<M1, M2> WriteThrough<M1, M2> {
+    pub fn new(memory: M1, store: M2) -> Self {
+        Self { memory, store }
+    }
+    pub fn memory(&self) -> &M1 {
+        &self.memory
+    }
+    pub fn store(&self) -> &M2 {
+        &self.store
+    }
+}
+impl<T: Clone, M1: Memory<T>, M2: Memory<T>> Memory<T> for WriteThrough<M1, M2> {
+    fn memoize(&self, key: &str, value: T) {
+        self.store.memoize(key, value.clone());
+        self.memory.memoize(key, value);
+    }
+    fn retrieve(&self, key: &str) -> Option<T> {
+        self.memory.retrieve(key).or_else(|| {
+            let value = self.store.retrieve(key)?;
+            self.memory.memoize(key, value.clone());
+            Some(value)
+        })
+    }
+    fn forget(&self) {
+        self.memory.forget();
+        self.store.forget();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brain, NumericalDuration};
+
+    #[test]
+    fn write_through() {
+        let memory = WriteThrough::new(Brain::new(1.milliseconds()), Brain::new(1.minutes()));
+        memory.memoize("a", 3);
+        assert_eq!(memory.store().retrieve("a"), Some(3));
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        memory.memory().forget();
+        assert!(!memory.memory().is_cached("a"));
+
+        assert_eq!(memory.retrieve("a"), Some(3));
+        assert_eq!(memory.memory().retrieve("a"), Some(3));
+    }
+}